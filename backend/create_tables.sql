@@ -1,16 +1,71 @@
 -- Extensão para UUIDs
 CREATE EXTENSION IF NOT EXISTS "uuid-ossp";
 
+-- Extensão para busca por trechos de texto (ILIKE indexado)
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+-- Busca textual em português ignorando acentos ("cafe" encontra "Café")
+CREATE EXTENSION IF NOT EXISTS unaccent;
+
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_ts_config WHERE cfgname = 'portuguese_unaccent') THEN
+        CREATE TEXT SEARCH CONFIGURATION portuguese_unaccent (COPY = portuguese);
+        ALTER TEXT SEARCH CONFIGURATION portuguese_unaccent
+            ALTER MAPPING FOR hword, hword_part, word WITH unaccent, portuguese_stem;
+    END IF;
+END
+$$;
+
 -- Tabela de Usuários
 CREATE TABLE IF NOT EXISTS users (
     id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
     email VARCHAR(255) UNIQUE NOT NULL,
     full_name VARCHAR(255) NOT NULL,
-    password_hash VARCHAR(255) NOT NULL,
+    password_hash VARCHAR(255), -- NULL para usuários que só entram via provedor OIDC
+    preferred_currency VARCHAR(10) NOT NULL DEFAULT 'BRL',
+    locale VARCHAR(20) NOT NULL DEFAULT 'pt-BR',
+    timezone VARCHAR(64) NOT NULL DEFAULT 'America/Sao_Paulo',
+    -- Troca de email pendente de confirmação
+    pending_email VARCHAR(255),
+    email_verification_token_hash VARCHAR(64),
+    email_verification_expires_at TIMESTAMP WITH TIME ZONE,
     created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
     updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
 );
 
+-- Identidades externas (OIDC) vinculadas a usuários
+CREATE TABLE IF NOT EXISTS user_identities (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    issuer VARCHAR(255) NOT NULL,
+    subject VARCHAR(255) NOT NULL,
+    email VARCHAR(255),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(issuer, subject)
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_identities_user_id ON user_identities(user_id);
+
+-- Estado temporário do fluxo OIDC (authorization code + PKCE)
+CREATE TABLE IF NOT EXISTS oidc_login_states (
+    state VARCHAR(64) PRIMARY KEY,
+    code_verifier VARCHAR(128) NOT NULL,
+    nonce VARCHAR(64) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Chaves de assinatura dos JWTs (EdDSA), rotacionadas periodicamente
+CREATE TABLE IF NOT EXISTS jwt_signing_keys (
+    kid VARCHAR(64) PRIMARY KEY,
+    algorithm VARCHAR(20) NOT NULL,
+    private_key TEXT NOT NULL, -- PKCS#8 DER em base64, cifrada com SECRETS_KEY quando configurada
+    public_key TEXT NOT NULL, -- chave pública em base64url (campo "x" do JWK)
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    retired_at TIMESTAMP WITH TIME ZONE -- aposentada: não assina mais, só valida até os tokens expirarem
+);
+
 -- Tabela de Items (Conexões Bancárias)
 CREATE TABLE IF NOT EXISTS items (
     id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
@@ -53,6 +108,157 @@ CREATE TABLE IF NOT EXISTS accounts (
 CREATE INDEX IF NOT EXISTS idx_accounts_item_id ON accounts(item_id);
 CREATE INDEX IF NOT EXISTS idx_accounts_pluggy_account_id ON accounts(pluggy_account_id);
 
+-- Saldo diário de cada conta (histórico do patrimônio). Atualizado a cada mudança de saldo da conta
+-- e registrado uma vez por dia pelo agendador, mesmo sem sincronização
+CREATE TABLE IF NOT EXISTS account_snapshots (
+    account_id UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    date DATE NOT NULL,
+    balance DECIMAL(19, 4) NOT NULL,
+    currency VARCHAR(10),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (account_id, date)
+);
+
+-- Tabela de Investimentos (renda fixa, fundos, ações etc.), sincronizados da Pluggy com o item
+CREATE TABLE IF NOT EXISTS investments (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    pluggy_investment_id VARCHAR(255) NOT NULL,
+    item_id UUID NOT NULL REFERENCES items(id) ON DELETE CASCADE,
+    name VARCHAR(255),
+    number VARCHAR(255),
+    type VARCHAR(50),
+    subtype VARCHAR(50),
+    balance DECIMAL(19, 4),
+    currency VARCHAR(10),
+    status VARCHAR(50),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(pluggy_investment_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_investments_item_id ON investments(item_id);
+
+-- Saldo diário de cada investimento, nos mesmos moldes de account_snapshots
+CREATE TABLE IF NOT EXISTS investment_snapshots (
+    investment_id UUID NOT NULL REFERENCES investments(id) ON DELETE CASCADE,
+    date DATE NOT NULL,
+    balance DECIMAL(19, 4) NOT NULL,
+    currency VARCHAR(10),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (investment_id, date)
+);
+
+-- Tabela de Importações de extratos (CSV/OFX)
+CREATE TABLE IF NOT EXISTS import_batches (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    format VARCHAR(10) NOT NULL, -- 'csv' ou 'ofx'
+    file_name VARCHAR(255),
+    account_id UUID REFERENCES accounts(id) ON DELETE SET NULL,
+    imported_count INTEGER NOT NULL DEFAULT 0,
+    duplicate_count INTEGER NOT NULL DEFAULT 0,
+    error_count INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_import_batches_user_id ON import_batches(user_id);
+
+-- Tabela de Regras de categorização, avaliadas em ordem de prioridade sobre cada transação sincronizada
+CREATE TABLE IF NOT EXISTS categorization_rules (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    priority INTEGER NOT NULL DEFAULT 0, -- Menor valor é avaliado primeiro
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    -- Condições (todas as preenchidas precisam casar)
+    description_contains VARCHAR(255), -- Sem diferenciar maiúsculas e acentos
+    original_category VARCHAR(255),
+    direction VARCHAR(10), -- 'expense' ou 'income'
+    min_amount DECIMAL(19, 4), -- Sobre o valor absoluto
+    max_amount DECIMAL(19, 4),
+    account_id UUID REFERENCES accounts(id) ON DELETE CASCADE,
+    -- Ações
+    set_category VARCHAR(255),
+    set_subcategory VARCHAR(255),
+    rename_to TEXT,
+    add_tag VARCHAR(50),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_categorization_rules_user_priority ON categorization_rules(user_id, priority);
+
+-- Lançamentos recorrentes definidos pelo usuário; o agendador cria as transações nas datas previstas
+CREATE TABLE IF NOT EXISTS recurring_transactions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    account_id UUID REFERENCES accounts(id) ON DELETE SET NULL,
+    description TEXT NOT NULL,
+    amount DECIMAL(19, 4) NOT NULL,
+    currency VARCHAR(3) NOT NULL DEFAULT 'BRL',
+    category VARCHAR(255),
+    frequency VARCHAR(10) NOT NULL, -- 'weekly', 'biweekly', 'monthly', 'quarterly' ou 'yearly'
+    start_date DATE NOT NULL,
+    end_date DATE,
+    occurrence_count INTEGER NOT NULL DEFAULT 0, -- Ocorrências já criadas
+    next_date DATE, -- Nula quando a recorrência terminou
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_recurring_transactions_user_id ON recurring_transactions(user_id);
+CREATE INDEX IF NOT EXISTS idx_recurring_transactions_next_date ON recurring_transactions(next_date) WHERE enabled;
+
+-- Compras parceladas no cartão; cada parcela chega como uma transação separada
+CREATE TABLE IF NOT EXISTS installment_purchases (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    account_id UUID REFERENCES accounts(id) ON DELETE CASCADE,
+    description TEXT NOT NULL, -- Sem a indicação da parcela
+    description_key VARCHAR(255) NOT NULL, -- Descrição normalizada, usada para agrupar as parcelas
+    currency VARCHAR(10) NOT NULL,
+    installment_count INTEGER NOT NULL,
+    installment_amount DECIMAL(19, 4) NOT NULL, -- Valores absolutos
+    total_amount DECIMAL(19, 4) NOT NULL,
+    purchase_date DATE, -- Informada nos metadados do cartão
+    first_installment_date DATE NOT NULL, -- Estimada a partir da primeira parcela recebida
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_installment_purchases_user_key ON installment_purchases(user_id, description_key);
+
+-- Estabelecimentos do usuário, com nome normalizado e CNPJ quando a Pluggy informa
+CREATE TABLE IF NOT EXISTS merchants (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    name_key VARCHAR(255) NOT NULL, -- Nome normalizado (sem acentos, números e prefixos de pagamento)
+    legal_name VARCHAR(255), -- Razão social
+    cnpj VARCHAR(14), -- Apenas dígitos
+    category VARCHAR(255),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(user_id, name_key)
+);
+
+CREATE INDEX IF NOT EXISTS idx_merchants_user_cnpj ON merchants(user_id, cnpj) WHERE cnpj IS NOT NULL;
+
+-- Descrições normalizadas que identificam um estabelecimento, aprendidas das transações ou cadastradas pelo usuário
+CREATE TABLE IF NOT EXISTS merchant_aliases (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    merchant_id UUID NOT NULL REFERENCES merchants(id) ON DELETE CASCADE,
+    alias VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(user_id, alias)
+);
+
+CREATE INDEX IF NOT EXISTS idx_merchant_aliases_merchant_id ON merchant_aliases(merchant_id);
+
 -- Tabela de Transações
 CREATE TABLE IF NOT EXISTS transactions (
     id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
@@ -69,6 +275,21 @@ CREATE TABLE IF NOT EXISTS transactions (
     status VARCHAR(50),
     merchant JSONB,
     balance DECIMAL(19, 4),
+    notes TEXT, -- Anotações do usuário
+    search_vector TSVECTOR, -- Mantido pelo trigger transactions_search_vector_update
+    import_batch_id UUID REFERENCES import_batches(id) ON DELETE CASCADE, -- Desfazer a importação remove as transações
+    import_external_id VARCHAR(255), -- FITID do OFX, usado para detectar duplicatas
+    user_modified_fields TEXT[] NOT NULL DEFAULT '{}', -- Campos editados pelo usuário que a sincronização preserva
+    rule_id UUID REFERENCES categorization_rules(id) ON DELETE SET NULL, -- Regra de categorização aplicada
+    recurring_transaction_id UUID REFERENCES recurring_transactions(id) ON DELETE SET NULL, -- Lançamento recorrente que criou a transação
+    installment_purchase_id UUID REFERENCES installment_purchases(id) ON DELETE SET NULL, -- Compra parcelada da qual é parcela
+    installment_number INTEGER,
+    installment_total INTEGER,
+    merchant_id UUID REFERENCES merchants(id) ON DELETE SET NULL, -- Estabelecimento normalizado
+    merchant_key VARCHAR(255), -- Descrição original normalizada, o alias pelo qual a transação é reassociada
+    predicted_category VARCHAR(255), -- Sugestão do classificador local na sincronização
+    prediction_confidence REAL,
+    category_changed_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP, -- Mantido pelo trigger transactions_category_changed
     created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
     updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
     UNIQUE(pluggy_transaction_id)
@@ -78,6 +299,261 @@ CREATE INDEX IF NOT EXISTS idx_transactions_account_id ON transactions(account_i
 CREATE INDEX IF NOT EXISTS idx_transactions_item_id ON transactions(item_id);
 CREATE INDEX IF NOT EXISTS idx_transactions_user_id ON transactions(user_id);
 CREATE INDEX IF NOT EXISTS idx_transactions_date ON transactions(date);
+-- Índices da listagem paginada (ordenação por data ou valor com desempate por id)
+CREATE INDEX IF NOT EXISTS idx_transactions_user_date ON transactions(user_id, date DESC, id DESC);
+CREATE INDEX IF NOT EXISTS idx_transactions_user_amount ON transactions(user_id, amount, id);
+CREATE INDEX IF NOT EXISTS idx_transactions_user_category ON transactions(user_id, category);
+CREATE INDEX IF NOT EXISTS idx_transactions_description_trgm ON transactions USING GIN (description gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS idx_transactions_import_batch_id ON transactions(import_batch_id);
+CREATE INDEX IF NOT EXISTS idx_transactions_import_external_id ON transactions(user_id, import_external_id);
+CREATE INDEX IF NOT EXISTS idx_transactions_search_vector ON transactions USING GIN (search_vector);
+CREATE INDEX IF NOT EXISTS idx_transactions_merchant_name_trgm ON transactions USING GIN ((merchant->>'name') gin_trgm_ops);
+-- Uma transação por data de cada lançamento recorrente
+CREATE UNIQUE INDEX IF NOT EXISTS idx_transactions_recurring_date ON transactions(recurring_transaction_id, date) WHERE recurring_transaction_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_transactions_installment_purchase_id ON transactions(installment_purchase_id);
+CREATE INDEX IF NOT EXISTS idx_transactions_merchant_id ON transactions(merchant_id);
+CREATE INDEX IF NOT EXISTS idx_transactions_user_merchant_key ON transactions(user_id, merchant_key);
+
+-- Tabela de Divisões de transações (uma compra dividida entre várias categorias)
+CREATE TABLE IF NOT EXISTS transaction_splits (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    transaction_id UUID NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    amount DECIMAL(19, 4) NOT NULL, -- A soma das partes é igual ao valor da transação
+    category VARCHAR(255),
+    notes TEXT,
+    position INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_transaction_splits_transaction_id ON transaction_splits(transaction_id);
+CREATE INDEX IF NOT EXISTS idx_transaction_splits_user_category ON transaction_splits(user_id, category);
+
+-- Tabela de Tags (marcadores livres por usuário)
+CREATE TABLE IF NOT EXISTS tags (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(50) NOT NULL, -- Sempre em minúsculas
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(user_id, name)
+);
+
+CREATE TABLE IF NOT EXISTS transaction_tags (
+    transaction_id UUID NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+    tag_id UUID NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+    PRIMARY KEY (transaction_id, tag_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_transaction_tags_tag_id ON transaction_tags(tag_id);
+
+-- Tabela de Anexos (comprovantes e notas fiscais); o conteúdo fica no armazenamento configurado
+CREATE TABLE IF NOT EXISTS attachments (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    transaction_id UUID NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+    file_name VARCHAR(255) NOT NULL,
+    content_type VARCHAR(100) NOT NULL,
+    size_bytes BIGINT NOT NULL,
+    storage_key TEXT NOT NULL,
+    thumbnail_key TEXT, -- Miniatura JPEG, apenas para imagens
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_attachments_transaction_id ON attachments(transaction_id);
+CREATE INDEX IF NOT EXISTS idx_attachments_user_id ON attachments(user_id);
+
+-- Arquivos de anexos excluídos aguardando remoção do armazenamento (preenchida por trigger)
+CREATE TABLE IF NOT EXISTS attachment_deletions (
+    storage_key TEXT PRIMARY KEY,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Classificador de categorias (naive Bayes) treinado com as correções de cada usuário
+CREATE TABLE IF NOT EXISTS category_classifiers (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    model JSONB NOT NULL,
+    sample_count INTEGER NOT NULL,
+    trained_through TIMESTAMP WITH TIME ZONE, -- Última mudança de categoria no conjunto de treino; retreina quando muda
+    trained_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Categorias do usuário (categoria principal e subcategoria). As transações guardam os nomes
+-- em category/subcategory; renomear ou mesclar uma categoria reescreve as transações afetadas
+CREATE TABLE IF NOT EXISTS categories (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    parent_id UUID REFERENCES categories(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    kind VARCHAR(10) NOT NULL DEFAULT 'expense', -- 'expense', 'income' ou 'transfer'
+    icon VARCHAR(50),
+    color VARCHAR(7), -- #RRGGBB
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_categories_user_parent_name
+    ON categories(user_id, COALESCE(parent_id, '00000000-0000-0000-0000-000000000000'::uuid), LOWER(name));
+CREATE INDEX IF NOT EXISTS idx_categories_parent_id ON categories(parent_id);
+
+-- Associação entre os ids de categoria da Pluggy e as categorias do usuário
+CREATE TABLE IF NOT EXISTS category_mappings (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    pluggy_category_id VARCHAR(20) NOT NULL,
+    category_id UUID NOT NULL REFERENCES categories(id) ON DELETE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, pluggy_category_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_category_mappings_category_id ON category_mappings(category_id);
+
+-- Orçamentos mensais por categoria principal de despesa
+CREATE TABLE IF NOT EXISTS budgets (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    category_id UUID NOT NULL REFERENCES categories(id) ON DELETE CASCADE,
+    month DATE NOT NULL, -- Primeiro dia do mês
+    kind VARCHAR(20) NOT NULL DEFAULT 'fixed', -- 'fixed' ou 'percent_of_income'
+    amount DECIMAL(19, 4) NOT NULL, -- Valor fixo ou percentual da receita do mês
+    rollover BOOLEAN NOT NULL DEFAULT FALSE, -- Saldo não gasto passa para o mês seguinte
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(user_id, category_id, month)
+);
+
+CREATE INDEX IF NOT EXISTS idx_budgets_user_month ON budgets(user_id, month);
+CREATE INDEX IF NOT EXISTS idx_budgets_category_id ON budgets(category_id);
+
+-- Meses em que os orçamentos do mês anterior já foram copiados, para não recriar os que o usuário removeu
+CREATE TABLE IF NOT EXISTS budget_copies (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    month DATE NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, month)
+);
+
+-- Metas de economia com valor e data alvo
+CREATE TABLE IF NOT EXISTS goals (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    target_amount DECIMAL(19, 4) NOT NULL,
+    target_date DATE NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_goals_user_id ON goals(user_id);
+
+-- Contas cujo saldo conta para o progresso da meta; cada conta financia uma única meta
+CREATE TABLE IF NOT EXISTS goal_accounts (
+    goal_id UUID NOT NULL REFERENCES goals(id) ON DELETE CASCADE,
+    account_id UUID NOT NULL UNIQUE REFERENCES accounts(id) ON DELETE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (goal_id, account_id)
+);
+
+-- Aportes manuais nas metas; valores negativos são retiradas
+CREATE TABLE IF NOT EXISTS goal_contributions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    goal_id UUID NOT NULL REFERENCES goals(id) ON DELETE CASCADE,
+    amount DECIMAL(19, 4) NOT NULL,
+    date DATE NOT NULL,
+    note TEXT,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_goal_contributions_goal_date ON goal_contributions(goal_id, date);
+
+-- Notificações exibidas no aplicativo e enviadas pelos canais configurados
+CREATE TABLE IF NOT EXISTS notifications (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    kind VARCHAR(40) NOT NULL, -- 'budget_warning', 'budget_exceeded', 'large_transaction', 'connection_error', 'low_balance', 'test'
+    title VARCHAR(255) NOT NULL,
+    body TEXT NOT NULL,
+    data JSONB NOT NULL DEFAULT '{}', -- Identificadores do orçamento, transação, item ou conta que gerou o alerta
+    dedup_key VARCHAR(255), -- Impede alertas repetidos da mesma situação; liberada quando a situação se resolve
+    read_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(user_id, dedup_key)
+);
+
+CREATE INDEX IF NOT EXISTS idx_notifications_user_created ON notifications(user_id, created_at DESC);
+
+-- Preferências de alertas e canais de entrega do usuário
+CREATE TABLE IF NOT EXISTS notification_settings (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    budget_alerts BOOLEAN NOT NULL DEFAULT TRUE, -- Orçamento passou de 80% e de 100%
+    connection_alerts BOOLEAN NOT NULL DEFAULT TRUE, -- Conexão bancária exige ação
+    large_transaction_threshold DECIMAL(19, 4), -- Despesa única a partir deste valor; nulo desativa
+    low_balance_threshold DECIMAL(19, 4), -- Saldo de conta corrente abaixo deste valor; nulo desativa
+    email_enabled BOOLEAN NOT NULL DEFAULT FALSE,
+    webhook_url TEXT, -- Recebe um POST JSON assinado a cada notificação
+    webhook_secret TEXT, -- Chave HMAC-SHA256 da assinatura do webhook, cifrada com SECRETS_KEY
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Inscrições de web push dos navegadores do usuário
+CREATE TABLE IF NOT EXISTS push_subscriptions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    endpoint TEXT NOT NULL UNIQUE,
+    p256dh VARCHAR(255) NOT NULL,
+    auth VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_push_subscriptions_user_id ON push_subscriptions(user_id);
+
+-- Transferências entre contas do próprio usuário: a saída de uma conta pareada com a entrada em outra.
+-- As transações pareadas ficam fora dos totais de despesas e receitas
+CREATE TABLE IF NOT EXISTS transfers (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    outgoing_transaction_id UUID NOT NULL UNIQUE REFERENCES transactions(id) ON DELETE CASCADE,
+    incoming_transaction_id UUID NOT NULL UNIQUE REFERENCES transactions(id) ON DELETE CASCADE,
+    detection VARCHAR(10) NOT NULL DEFAULT 'auto', -- 'auto' ou 'manual'
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    CHECK (outgoing_transaction_id <> incoming_transaction_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_transfers_user_id ON transfers(user_id);
+
+-- Pares desfeitos pelo usuário, que a detecção automática não volta a parear
+CREATE TABLE IF NOT EXISTS transfer_dismissals (
+    outgoing_transaction_id UUID NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+    incoming_transaction_id UUID NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (outgoing_transaction_id, incoming_transaction_id)
+);
+
+-- Lançamentos para relatórios e orçamentos: as partes de transações divididas
+-- substituem a transação original, as demais entram inteiras.
+-- is_transfer indica transações pareadas como transferência entre contas do usuário
+CREATE OR REPLACE VIEW transaction_entries AS
+SELECT
+    t.id AS transaction_id, NULL::UUID AS split_id, t.user_id, t.account_id, t.item_id,
+    t.date, t.amount, t.category, t.currency, t.description,
+    EXISTS (
+        SELECT 1 FROM transfers tr WHERE t.id IN (tr.outgoing_transaction_id, tr.incoming_transaction_id)
+    ) AS is_transfer,
+    t.merchant_id, t.subcategory
+FROM transactions t
+WHERE NOT EXISTS (SELECT 1 FROM transaction_splits s WHERE s.transaction_id = t.id)
+UNION ALL
+SELECT
+    t.id AS transaction_id, s.id AS split_id, t.user_id, t.account_id, t.item_id,
+    t.date, s.amount, s.category, t.currency, t.description,
+    EXISTS (
+        SELECT 1 FROM transfers tr WHERE t.id IN (tr.outgoing_transaction_id, tr.incoming_transaction_id)
+    ) AS is_transfer,
+    -- As partes só têm categoria
+    t.merchant_id, NULL::VARCHAR(255) AS subcategory
+FROM transaction_splits s
+INNER JOIN transactions t ON s.transaction_id = t.id;
 
 -- Tabela de Saldos (Histórico ou Snapshot)
 CREATE TABLE IF NOT EXISTS balances (
@@ -95,6 +571,41 @@ CREATE TABLE IF NOT EXISTS balances (
 
 CREATE INDEX IF NOT EXISTS idx_balances_account_id ON balances(account_id);
 
+-- Tabela de Tokens de Acesso Pessoal (para scripts e integrações)
+CREATE TABLE IF NOT EXISTS api_tokens (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    token_prefix VARCHAR(16) NOT NULL,
+    token_hash VARCHAR(64) NOT NULL, -- SHA-256 do token; o valor em texto puro nunca é armazenado
+    scope VARCHAR(20) NOT NULL DEFAULT 'read', -- 'read' ou 'read_write'
+    expires_at TIMESTAMP WITH TIME ZONE,
+    last_used_at TIMESTAMP WITH TIME ZONE,
+    revoked_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(token_hash)
+);
+
+CREATE INDEX IF NOT EXISTS idx_api_tokens_user_id ON api_tokens(user_id);
+
+-- Tabela de Exportações de Dados (portabilidade LGPD)
+CREATE TABLE IF NOT EXISTS data_exports (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    status VARCHAR(20) NOT NULL DEFAULT 'PENDING', -- PENDING, READY, FAILED, EXPIRED
+    file_path TEXT,
+    download_token VARCHAR(64),
+    error TEXT,
+    expires_at TIMESTAMP WITH TIME ZONE,
+    completed_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(download_token)
+);
+
+CREATE INDEX IF NOT EXISTS idx_data_exports_user_id ON data_exports(user_id);
+
 -- Função para atualizar updated_at automaticamente
 CREATE OR REPLACE FUNCTION update_updated_at_column()
 RETURNS TRIGGER AS $$
@@ -104,9 +615,122 @@ BEGIN
 END;
 $$ language 'plpgsql';
 
+-- Índice de busca textual de uma transação
+-- Descrição e nome do estabelecimento pesam mais que as anotações e os demais campos do merchant
+CREATE OR REPLACE FUNCTION transactions_search_vector(description TEXT, merchant JSONB, notes TEXT)
+RETURNS TSVECTOR AS $$
+    SELECT
+        setweight(to_tsvector('portuguese_unaccent', COALESCE(description, '')), 'A') ||
+        setweight(to_tsvector('portuguese_unaccent',
+            COALESCE(merchant->>'name', '') || ' ' || COALESCE(merchant->>'businessName', '')), 'A') ||
+        setweight(to_tsvector('portuguese_unaccent', COALESCE(notes, '')), 'B') ||
+        setweight(jsonb_to_tsvector('portuguese_unaccent', COALESCE(merchant, '{}'::jsonb), '["string"]'), 'C');
+$$ language 'sql' STABLE;
+
+-- Função para manter o índice de busca textual das transações
+CREATE OR REPLACE FUNCTION transactions_search_vector_update()
+RETURNS TRIGGER AS $$
+BEGIN
+    NEW.search_vector = transactions_search_vector(NEW.description, NEW.merchant, NEW.notes);
+    RETURN NEW;
+END;
+$$ language 'plpgsql';
+
+-- Função que desfaz a divisão quando o valor da transação muda (ex: sincronização da Pluggy),
+-- já que as partes deixariam de somar o total
+CREATE OR REPLACE FUNCTION transactions_reset_splits()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.amount <> OLD.amount THEN
+        DELETE FROM transaction_splits WHERE transaction_id = NEW.id;
+    END IF;
+    RETURN NEW;
+END;
+$$ language 'plpgsql';
+
+-- Função que registra quando a categoria da transação mudou (a sincronização atualiza updated_at sempre)
+CREATE OR REPLACE FUNCTION transactions_category_changed()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.category IS DISTINCT FROM OLD.category THEN
+        NEW.category_changed_at = CURRENT_TIMESTAMP;
+    END IF;
+    RETURN NEW;
+END;
+$$ language 'plpgsql';
+
+-- Função que enfileira a remoção dos arquivos de um anexo excluído, inclusive em cascata
+CREATE OR REPLACE FUNCTION attachments_queue_deletion()
+RETURNS TRIGGER AS $$
+BEGIN
+    INSERT INTO attachment_deletions (storage_key) VALUES (OLD.storage_key) ON CONFLICT DO NOTHING;
+    IF OLD.thumbnail_key IS NOT NULL THEN
+        INSERT INTO attachment_deletions (storage_key) VALUES (OLD.thumbnail_key) ON CONFLICT DO NOTHING;
+    END IF;
+    RETURN OLD;
+END;
+$$ language 'plpgsql';
+
+-- Função que registra o saldo do dia quando o saldo da conta muda
+CREATE OR REPLACE FUNCTION accounts_snapshot_balance()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.balance IS NOT NULL THEN
+        INSERT INTO account_snapshots (account_id, date, balance, currency)
+        VALUES (NEW.id, CURRENT_DATE, NEW.balance, NEW.currency)
+        ON CONFLICT (account_id, date) DO UPDATE
+        SET balance = EXCLUDED.balance, currency = EXCLUDED.currency, updated_at = CURRENT_TIMESTAMP;
+    END IF;
+    RETURN NEW;
+END;
+$$ language 'plpgsql';
+
+-- Função que registra o saldo do dia quando o saldo do investimento muda
+CREATE OR REPLACE FUNCTION investments_snapshot_balance()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.balance IS NOT NULL THEN
+        INSERT INTO investment_snapshots (investment_id, date, balance, currency)
+        VALUES (NEW.id, CURRENT_DATE, NEW.balance, NEW.currency)
+        ON CONFLICT (investment_id, date) DO UPDATE
+        SET balance = EXCLUDED.balance, currency = EXCLUDED.currency, updated_at = CURRENT_TIMESTAMP;
+    END IF;
+    RETURN NEW;
+END;
+$$ language 'plpgsql';
+
 -- Triggers para atualizar updated_at
 CREATE TRIGGER update_users_updated_at BEFORE UPDATE ON users FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+CREATE TRIGGER update_user_identities_updated_at BEFORE UPDATE ON user_identities FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
 CREATE TRIGGER update_items_updated_at BEFORE UPDATE ON items FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
 CREATE TRIGGER update_accounts_updated_at BEFORE UPDATE ON accounts FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+CREATE TRIGGER update_investments_updated_at BEFORE UPDATE ON investments FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
 CREATE TRIGGER update_transactions_updated_at BEFORE UPDATE ON transactions FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+CREATE TRIGGER update_transaction_splits_updated_at BEFORE UPDATE ON transaction_splits FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+CREATE TRIGGER update_categorization_rules_updated_at BEFORE UPDATE ON categorization_rules FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+CREATE TRIGGER update_categories_updated_at BEFORE UPDATE ON categories FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+CREATE TRIGGER update_budgets_updated_at BEFORE UPDATE ON budgets FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+CREATE TRIGGER update_recurring_transactions_updated_at BEFORE UPDATE ON recurring_transactions FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+CREATE TRIGGER update_installment_purchases_updated_at BEFORE UPDATE ON installment_purchases FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+CREATE TRIGGER update_merchants_updated_at BEFORE UPDATE ON merchants FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+CREATE TRIGGER update_goals_updated_at BEFORE UPDATE ON goals FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+CREATE TRIGGER update_notification_settings_updated_at BEFORE UPDATE ON notification_settings FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
 CREATE TRIGGER update_balances_updated_at BEFORE UPDATE ON balances FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+CREATE TRIGGER update_api_tokens_updated_at BEFORE UPDATE ON api_tokens FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+CREATE TRIGGER update_data_exports_updated_at BEFORE UPDATE ON data_exports FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+-- Trigger do índice de busca textual
+CREATE TRIGGER transactions_search_vector_update BEFORE INSERT OR UPDATE OF description, merchant, notes ON transactions FOR EACH ROW EXECUTE FUNCTION transactions_search_vector_update();
+
+-- Trigger da data de mudança de categoria, usada pelo classificador
+CREATE TRIGGER transactions_category_changed BEFORE UPDATE OF category ON transactions FOR EACH ROW EXECUTE FUNCTION transactions_category_changed();
+
+-- Trigger que remove divisões inconsistentes
+CREATE TRIGGER transactions_reset_splits AFTER UPDATE OF amount ON transactions FOR EACH ROW EXECUTE FUNCTION transactions_reset_splits();
+
+-- Trigger que enfileira a remoção dos arquivos de anexos
+CREATE TRIGGER attachments_queue_deletion AFTER DELETE ON attachments FOR EACH ROW EXECUTE FUNCTION attachments_queue_deletion();
+
+-- Trigger do histórico de saldos das contas
+CREATE TRIGGER accounts_snapshot_balance AFTER INSERT OR UPDATE OF balance ON accounts FOR EACH ROW EXECUTE FUNCTION accounts_snapshot_balance();
+CREATE TRIGGER investments_snapshot_balance AFTER INSERT OR UPDATE OF balance ON investments FOR EACH ROW EXECUTE FUNCTION investments_snapshot_balance();
@@ -0,0 +1,297 @@
+use crate::budget_planning;
+use crate::models::notification::{
+    Notification, BUDGET_WARNING_PERCENT, KIND_BUDGET_EXCEEDED, KIND_BUDGET_WARNING, KIND_CONNECTION_ERROR,
+    KIND_LARGE_TRANSACTION, KIND_LOW_BALANCE,
+};
+use crate::notifier::{self, Notifier};
+use chrono::Utc;
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+// Status de item da Pluggy em que a conexão deixou de atualizar e precisa do usuário
+const BROKEN_ITEM_STATUSES: &[&str] = &["LOGIN_ERROR", "OUTDATED", "WAITING_USER_INPUT"];
+// Só alerta despesas grandes recentes, para a primeira sincronização não avisar sobre o histórico todo
+const LARGE_TRANSACTION_MAX_AGE_DAYS: i32 = 7;
+
+/// Alerta a registrar, identificado pela situação que o gerou
+struct Alert {
+    kind: &'static str,
+    title: String,
+    body: String,
+    data: serde_json::Value,
+    dedup_key: String,
+}
+
+/// Avalia as condições de alerta do usuário após uma sincronização e entrega as notificações novas.
+///
+/// Cada situação (orçamento do mês, transação, conexão, conta) gera uma única notificação. Quando a situação
+/// se resolve, a chave é liberada e uma nova ocorrência volta a notificar. Retorna quantas notificações foram criadas.
+pub async fn evaluate(pool: &PgPool, notifier: &Arc<Notifier>, user_id: Uuid) -> anyhow::Result<usize> {
+    let Some(recipient) = notifier::recipient(pool, user_id).await? else {
+        return Ok(0);
+    };
+
+    let mut alerts = Vec::new();
+    let mut resolved = Vec::new();
+
+    if recipient.budget_alerts {
+        budget_alerts(pool, user_id, &recipient.preferred_currency, &mut alerts, &mut resolved).await?;
+    }
+    if let Some(threshold) = recipient.large_transaction_threshold {
+        large_transaction_alerts(pool, user_id, threshold, &mut alerts).await?;
+    }
+    if recipient.connection_alerts {
+        connection_alerts(pool, user_id, &mut alerts, &mut resolved).await?;
+    }
+    if let Some(threshold) = recipient.low_balance_threshold {
+        low_balance_alerts(pool, user_id, threshold, &mut alerts, &mut resolved).await?;
+    }
+
+    if !resolved.is_empty() {
+        sqlx::query!(
+            "UPDATE notifications SET dedup_key = NULL WHERE user_id = $1 AND dedup_key = ANY($2)",
+            user_id,
+            &resolved
+        )
+        .execute(pool)
+        .await?;
+    }
+
+    let mut created = Vec::new();
+    for alert in alerts {
+        if let Some(notification) = insert(pool, user_id, alert).await? {
+            created.push(notification);
+        }
+    }
+
+    let count = created.len();
+    notifier.dispatch(pool, recipient, created);
+    Ok(count)
+}
+
+async fn insert(pool: &PgPool, user_id: Uuid, alert: Alert) -> sqlx::Result<Option<Notification>> {
+    sqlx::query_as!(
+        Notification,
+        r#"
+        INSERT INTO notifications (user_id, kind, title, body, data, dedup_key)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (user_id, dedup_key) DO NOTHING
+        RETURNING id, kind, title, body, data, read_at, created_at
+        "#,
+        user_id,
+        alert.kind,
+        alert.title,
+        alert.body,
+        alert.data,
+        alert.dedup_key
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+// Orçamentos do mês atual que passaram de 80% ou de 100% do disponível
+async fn budget_alerts(
+    pool: &PgPool,
+    user_id: Uuid,
+    currency: &str,
+    alerts: &mut Vec<Alert>,
+    resolved: &mut Vec<String>,
+) -> sqlx::Result<()> {
+    let month = budget_planning::first_day(Utc::now().date_naive());
+    let progress = budget_planning::month_progress(pool, user_id, month).await?;
+
+    for budget in progress.budgets {
+        let warning_key = format!("{}:{}", KIND_BUDGET_WARNING, budget.budget.id);
+        let exceeded_key = format!("{}:{}", KIND_BUDGET_EXCEEDED, budget.budget.id);
+        let data = serde_json::json!({
+            "budget_id": budget.budget.id,
+            "category_id": budget.budget.category_id,
+            "month": progress.month,
+        });
+
+        let percent = budget.percent_used.unwrap_or(Decimal::ZERO);
+        if percent >= Decimal::from(100) {
+            alerts.push(Alert {
+                kind: KIND_BUDGET_EXCEEDED,
+                title: format!("Orçamento de {} estourado", budget.category),
+                body: format!(
+                    "Você gastou {} de {} disponíveis em {} neste mês ({}%).",
+                    format_amount(budget.actual, currency),
+                    format_amount(budget.available, currency),
+                    budget.category,
+                    percent.round()
+                ),
+                data,
+                dedup_key: exceeded_key,
+            });
+        } else if percent >= Decimal::from(BUDGET_WARNING_PERCENT) {
+            resolved.push(exceeded_key);
+            alerts.push(Alert {
+                kind: KIND_BUDGET_WARNING,
+                title: format!("Orçamento de {} em {}%", budget.category, percent.round()),
+                body: format!(
+                    "Restam {} dos {} disponíveis em {} neste mês.",
+                    format_amount(budget.remaining, currency),
+                    format_amount(budget.available, currency),
+                    budget.category
+                ),
+                data,
+                dedup_key: warning_key,
+            });
+        } else {
+            // Orçamento aumentado ou gasto estornado: os alertas podem voltar a acontecer
+            resolved.push(warning_key);
+            resolved.push(exceeded_key);
+        }
+    }
+
+    Ok(())
+}
+
+// Despesas recém-sincronizadas a partir do limite, sem contar transferências entre contas próprias
+async fn large_transaction_alerts(
+    pool: &PgPool,
+    user_id: Uuid,
+    threshold: Decimal,
+    alerts: &mut Vec<Alert>,
+) -> sqlx::Result<()> {
+    let transactions = sqlx::query!(
+        r#"
+        SELECT t.id, t.account_id, t.description, t.amount, t.currency, t.date
+        FROM transactions t
+        WHERE t.user_id = $1
+          AND t.amount <= -$2::DECIMAL
+          AND t.date >= CURRENT_DATE - $3::INTEGER
+          AND t.created_at >= CURRENT_TIMESTAMP - INTERVAL '1 day'
+          AND NOT EXISTS (
+              SELECT 1 FROM transfers tr WHERE t.id IN (tr.outgoing_transaction_id, tr.incoming_transaction_id)
+          )
+        ORDER BY t.date, t.created_at
+        "#,
+        user_id,
+        threshold,
+        LARGE_TRANSACTION_MAX_AGE_DAYS
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for transaction in transactions {
+        let description = transaction.description.unwrap_or_else(|| "Sem descrição".to_string());
+        alerts.push(Alert {
+            kind: KIND_LARGE_TRANSACTION,
+            title: format!("Despesa de {}", format_amount(-transaction.amount, &transaction.currency)),
+            body: format!("{} em {}.", description, transaction.date.format("%d/%m/%Y")),
+            data: serde_json::json!({
+                "transaction_id": transaction.id,
+                "account_id": transaction.account_id,
+            }),
+            dedup_key: format!("{}:{}", KIND_LARGE_TRANSACTION, transaction.id),
+        });
+    }
+
+    Ok(())
+}
+
+// Conexões bancárias com erro de login, desatualizadas ou aguardando o usuário
+async fn connection_alerts(
+    pool: &PgPool,
+    user_id: Uuid,
+    alerts: &mut Vec<Alert>,
+    resolved: &mut Vec<String>,
+) -> sqlx::Result<()> {
+    let items = sqlx::query!(
+        r#"SELECT id, status, connector->>'name' AS "connector_name?" FROM items WHERE user_id = $1"#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for item in items {
+        let key = format!("{}:{}", KIND_CONNECTION_ERROR, item.id);
+        if !BROKEN_ITEM_STATUSES.contains(&item.status.as_str()) {
+            resolved.push(key);
+            continue;
+        }
+
+        let connector = item.connector_name.unwrap_or_else(|| "banco".to_string());
+        let body = match item.status.as_str() {
+            "LOGIN_ERROR" => format!("As credenciais de acesso ao {} não são mais aceitas. Reconecte a conta.", connector),
+            "WAITING_USER_INPUT" => format!("A conexão com {} aguarda uma confirmação sua (ex: token ou MFA).", connector),
+            _ => format!("A conexão com {} não está conseguindo atualizar os dados. Tente reconectar.", connector),
+        };
+        alerts.push(Alert {
+            kind: KIND_CONNECTION_ERROR,
+            title: format!("Conexão com {} precisa de atenção", connector),
+            body,
+            data: serde_json::json!({ "item_id": item.id, "status": item.status }),
+            dedup_key: key,
+        });
+    }
+
+    Ok(())
+}
+
+// Contas correntes com saldo abaixo do mínimo
+async fn low_balance_alerts(
+    pool: &PgPool,
+    user_id: Uuid,
+    threshold: Decimal,
+    alerts: &mut Vec<Alert>,
+    resolved: &mut Vec<String>,
+) -> sqlx::Result<()> {
+    let accounts = sqlx::query!(
+        r#"
+        SELECT a.id, a.name, a.balance AS "balance!", COALESCE(a.currency, 'BRL') AS "currency!"
+        FROM accounts a
+        INNER JOIN items i ON a.item_id = i.id
+        WHERE i.user_id = $1 AND a.type = 'BANK' AND a.balance IS NOT NULL
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for account in accounts {
+        let key = format!("{}:{}", KIND_LOW_BALANCE, account.id);
+        if account.balance >= threshold {
+            resolved.push(key);
+            continue;
+        }
+
+        let name = account.name.unwrap_or_else(|| "conta".to_string());
+        alerts.push(Alert {
+            kind: KIND_LOW_BALANCE,
+            title: format!("Saldo baixo em {}", name),
+            body: format!(
+                "O saldo de {} está em {}, abaixo do mínimo de {}.",
+                name,
+                format_amount(account.balance, &account.currency),
+                format_amount(threshold, &account.currency)
+            ),
+            data: serde_json::json!({ "account_id": account.id }),
+            dedup_key: key,
+        });
+    }
+
+    Ok(())
+}
+
+/// Valor no formato brasileiro: "R$ 1.234,56", "USD -10,00"
+fn format_amount(amount: Decimal, currency: &str) -> String {
+    let fixed = format!("{:.2}", amount.abs().round_dp(2));
+    let (integer, cents) = fixed.split_once('.').unwrap_or((&fixed, "00"));
+
+    let mut grouped = String::new();
+    for (i, digit) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 {
+            grouped.push('.');
+        }
+        grouped.push(digit);
+    }
+
+    let symbol = if currency == "BRL" { "R$" } else { currency };
+    let sign = if amount < Decimal::ZERO { "-" } else { "" };
+    format!("{} {}{},{}", symbol, sign, grouped, cents)
+}
@@ -0,0 +1,159 @@
+use crate::models::net_worth::{NetWorthPoint, LIABILITY_ACCOUNT_TYPES};
+use chrono::NaiveDate;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Registra o saldo do dia das contas e investimentos que ainda não têm registro hoje, para que o histórico
+/// tenha um ponto diário mesmo sem sincronização. Retorna quantos registros foram criados.
+pub async fn snapshot_balances(pool: &PgPool) -> sqlx::Result<u64> {
+    let accounts = sqlx::query!(
+        r#"
+        INSERT INTO account_snapshots (account_id, date, balance, currency)
+        SELECT id, CURRENT_DATE, balance, currency FROM accounts WHERE balance IS NOT NULL
+        ON CONFLICT (account_id, date) DO NOTHING
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    let investments = sqlx::query!(
+        r#"
+        INSERT INTO investment_snapshots (investment_id, date, balance, currency)
+        SELECT id, CURRENT_DATE, balance, currency FROM investments WHERE balance IS NOT NULL
+        ON CONFLICT (investment_id, date) DO NOTHING
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(accounts.rows_affected() + investments.rows_affected())
+}
+
+/// Reconstrói o saldo da conta antes do primeiro registro, descontando as transações a partir dele:
+/// um ponto no fim de cada dia com transações e outro na véspera da primeira.
+///
+/// Só preenche datas anteriores ao registro mais antigo, então pode rodar a cada sincronização.
+/// Em cartões e empréstimos o saldo é a dívida, que cresce com as despesas (valores negativos).
+/// Retorna quantos registros foram criados.
+pub async fn backfill_account(pool: &PgPool, account_id: Uuid) -> sqlx::Result<u64> {
+    let liability_types: Vec<String> = LIABILITY_ACCOUNT_TYPES.iter().map(|t| t.to_string()).collect();
+
+    let result = sqlx::query!(
+        r#"
+        WITH anchor AS (
+            SELECT s.date, s.balance, s.currency,
+                   CASE WHEN a.type = ANY($2) THEN 1 ELSE -1 END AS direction
+            FROM account_snapshots s
+            INNER JOIN accounts a ON a.id = s.account_id
+            WHERE s.account_id = $1
+            ORDER BY s.date
+            LIMIT 1
+        ),
+        daily AS (
+            SELECT t.date, SUM(t.amount) AS amount
+            FROM transactions t, anchor
+            WHERE t.account_id = $1 AND t.date <= anchor.date
+            GROUP BY t.date
+        ),
+        points AS (
+            SELECT date,
+                   COALESCE(SUM(amount) OVER (ORDER BY date DESC ROWS BETWEEN UNBOUNDED PRECEDING AND 1 PRECEDING), 0) AS later
+            FROM daily
+            UNION ALL
+            SELECT MIN(date) - 1, SUM(amount) FROM daily HAVING COUNT(*) > 0
+        )
+        INSERT INTO account_snapshots (account_id, date, balance, currency)
+        SELECT $1, p.date, anchor.balance + anchor.direction * p.later, anchor.currency
+        FROM points p, anchor
+        WHERE p.date < anchor.date
+        ON CONFLICT (account_id, date) DO NOTHING
+        "#,
+        account_id,
+        &liability_types
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Ativos, passivos e patrimônio líquido no fim de cada período entre `from` e `to`.
+///
+/// `interval` é 'day', 'week' ou 'month'; os períodos começam na segunda-feira ou no dia 1º e o último termina em `to`.
+/// Cada conta e investimento entra com o último saldo registrado até a data; investimentos são ativos e
+/// contas de cartão e empréstimo são passivos.
+pub async fn net_worth(
+    pool: &PgPool,
+    user_id: Uuid,
+    from: NaiveDate,
+    to: NaiveDate,
+    interval: &str,
+) -> sqlx::Result<Vec<NetWorthPoint>> {
+    let liability_types: Vec<String> = LIABILITY_ACCOUNT_TYPES.iter().map(|t| t.to_string()).collect();
+
+    sqlx::query_as!(
+        NetWorthPoint,
+        r#"
+        WITH periods AS (
+            SELECT LEAST((start + ('1 ' || $4)::INTERVAL - INTERVAL '1 day')::DATE, $3) AS date
+            FROM generate_series(date_trunc($4, $2::DATE), $3::DATE, ('1 ' || $4)::INTERVAL) AS start
+        ),
+        user_accounts AS (
+            SELECT a.id, a.type
+            FROM accounts a
+            INNER JOIN items i ON a.item_id = i.id
+            WHERE i.user_id = $1
+        ),
+        balances AS (
+            SELECT p.date, ua.type, s.balance
+            FROM periods p
+            CROSS JOIN user_accounts ua
+            CROSS JOIN LATERAL (
+                SELECT balance FROM account_snapshots
+                WHERE account_id = ua.id AND date <= p.date
+                ORDER BY date DESC
+                LIMIT 1
+            ) s
+        ),
+        user_investments AS (
+            SELECT v.id
+            FROM investments v
+            INNER JOIN items i ON v.item_id = i.id
+            WHERE i.user_id = $1
+        ),
+        investment_totals AS (
+            SELECT p.date, SUM(s.balance) AS investments
+            FROM periods p
+            CROSS JOIN user_investments ui
+            CROSS JOIN LATERAL (
+                SELECT balance FROM investment_snapshots
+                WHERE investment_id = ui.id AND date <= p.date
+                ORDER BY date DESC
+                LIMIT 1
+            ) s
+            GROUP BY p.date
+        ),
+        totals AS (
+            SELECT p.date,
+                   COALESCE(SUM(b.balance) FILTER (WHERE b.type IS NULL OR b.type <> ALL($5)), 0) AS accounts,
+                   COALESCE(SUM(b.balance) FILTER (WHERE b.type = ANY($5)), 0) AS liabilities
+            FROM periods p
+            LEFT JOIN balances b ON b.date = p.date
+            GROUP BY p.date
+        )
+        SELECT t.date AS "date!", t.accounts + COALESCE(it.investments, 0) AS "assets!",
+               COALESCE(it.investments, 0) AS "investments!", t.liabilities AS "liabilities!",
+               t.accounts + COALESCE(it.investments, 0) - t.liabilities AS "net_worth!"
+        FROM totals t
+        LEFT JOIN investment_totals it ON it.date = t.date
+        ORDER BY t.date
+        "#,
+        user_id,
+        from,
+        to,
+        interval,
+        &liability_types
+    )
+    .fetch_all(pool)
+    .await
+}
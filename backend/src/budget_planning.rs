@@ -0,0 +1,185 @@
+use crate::models::budget::{Budget, BudgetMonth, BudgetProgress, KIND_PERCENT_OF_INCOME};
+use crate::models::category::KIND_EXPENSE;
+use chrono::{Datelike, Months, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+// Meses anteriores considerados no acúmulo de saldo não gasto
+const ROLLOVER_MONTHS: u32 = 24;
+
+/// Primeiro dia do mês informado como AAAA-MM
+pub fn parse_month(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(&format!("{}-01", value.trim()), "%Y-%m-%d").ok()
+}
+
+pub fn first_day(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).unwrap_or(date)
+}
+
+/// Planejado, realizado e saldo dos orçamentos do usuário no mês.
+///
+/// O realizado é o gasto líquido da categoria no mês (estornos descontam), sem transferências.
+/// Orçamentos com rollover passam o saldo não gasto para o orçamento da mesma categoria no mês seguinte;
+/// gastos acima do orçamento não são descontados do mês seguinte.
+pub async fn month_progress(pool: &PgPool, user_id: Uuid, month: NaiveDate) -> sqlx::Result<BudgetMonth> {
+    let start = month.checked_sub_months(Months::new(ROLLOVER_MONTHS)).unwrap_or(month);
+    let end = month.checked_add_months(Months::new(1)).unwrap_or(month);
+
+    let budgets = sqlx::query!(
+        r#"
+        SELECT b.id, b.category_id, c.name AS category, b.month, b.kind, b.amount, b.rollover,
+               b.created_at, b.updated_at
+        FROM budgets b
+        INNER JOIN categories c ON b.category_id = c.id
+        WHERE b.user_id = $1 AND b.month >= $2 AND b.month <= $3
+        ORDER BY b.month
+        "#,
+        user_id,
+        start,
+        month
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let spending: HashMap<(NaiveDate, String), Decimal> = sqlx::query!(
+        r#"
+        SELECT date_trunc('month', date)::DATE AS "month!", LOWER(category) AS "category!", -SUM(amount) AS "spent!"
+        FROM transaction_entries
+        WHERE user_id = $1 AND NOT is_transfer AND category IS NOT NULL AND date >= $2 AND date < $3
+        GROUP BY 1, 2
+        "#,
+        user_id,
+        start,
+        end
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|row| ((row.month, row.category), row.spent))
+    .collect();
+
+    let income: HashMap<NaiveDate, Decimal> = sqlx::query!(
+        r#"
+        SELECT date_trunc('month', date)::DATE AS "month!", SUM(amount) AS "income!"
+        FROM transaction_entries e
+        WHERE user_id = $1 AND NOT is_transfer AND amount > 0 AND date >= $2 AND date < $3
+          -- Estornos em categorias de despesa não são receita
+          AND NOT EXISTS (
+              SELECT 1 FROM categories c
+              WHERE c.user_id = $1 AND c.parent_id IS NULL AND c.kind = $4 AND LOWER(c.name) = LOWER(e.category)
+          )
+        GROUP BY 1
+        "#,
+        user_id,
+        start,
+        end,
+        KIND_EXPENSE
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|row| (row.month, row.income))
+    .collect();
+
+    // Saldo que cada categoria leva para o mês seguinte
+    let mut carry: HashMap<Uuid, (NaiveDate, Decimal)> = HashMap::new();
+    let mut progress = Vec::new();
+
+    for row in budgets {
+        let planned = if row.kind == KIND_PERCENT_OF_INCOME {
+            let income = income.get(&row.month).copied().unwrap_or(Decimal::ZERO);
+            (income * row.amount / Decimal::from(100)).round_dp(2)
+        } else {
+            row.amount
+        };
+
+        let previous_month = row.month.checked_sub_months(Months::new(1));
+        let carried_over = match carry.remove(&row.category_id) {
+            Some((carried_month, amount)) if Some(carried_month) == previous_month => amount,
+            _ => Decimal::ZERO,
+        };
+
+        let available = planned + carried_over;
+        let actual = spending
+            .get(&(row.month, row.category.to_lowercase()))
+            .copied()
+            .unwrap_or(Decimal::ZERO);
+        let remaining = available - actual;
+
+        if row.rollover {
+            carry.insert(row.category_id, (row.month, remaining.max(Decimal::ZERO)));
+        }
+
+        if row.month == month {
+            let percent_used =
+                (available > Decimal::ZERO).then(|| (actual * Decimal::from(100) / available).round_dp(1));
+            progress.push(BudgetProgress {
+                budget: Budget {
+                    id: row.id,
+                    category_id: row.category_id,
+                    month: row.month,
+                    kind: row.kind,
+                    amount: row.amount,
+                    rollover: row.rollover,
+                    created_at: row.created_at,
+                    updated_at: row.updated_at,
+                },
+                category: row.category,
+                planned,
+                carried_over,
+                available,
+                actual,
+                remaining,
+                percent_used,
+            });
+        }
+    }
+
+    progress.sort_by_key(|p| p.category.to_lowercase());
+
+    Ok(BudgetMonth {
+        month: month.format("%Y-%m").to_string(),
+        income: income.get(&month).copied().unwrap_or(Decimal::ZERO),
+        total_available: progress.iter().map(|p| p.available).sum(),
+        total_actual: progress.iter().map(|p| p.actual).sum(),
+        total_remaining: progress.iter().map(|p| p.remaining).sum(),
+        budgets: progress,
+    })
+}
+
+/// Copia os orçamentos do mês anterior para o mês atual, uma única vez por usuário,
+/// para quem ainda não definiu orçamentos no mês. Retorna quantos orçamentos foram criados.
+pub async fn copy_forward(pool: &PgPool) -> sqlx::Result<u64> {
+    let month = first_day(Utc::now().date_naive());
+    let Some(previous) = month.checked_sub_months(Months::new(1)) else {
+        return Ok(0);
+    };
+
+    let result = sqlx::query!(
+        r#"
+        WITH copied AS (
+            INSERT INTO budget_copies (user_id, month)
+            SELECT DISTINCT b.user_id, $1::DATE
+            FROM budgets b
+            WHERE b.month = $2
+              AND NOT EXISTS (SELECT 1 FROM budgets n WHERE n.user_id = b.user_id AND n.month = $1)
+            ON CONFLICT DO NOTHING
+            RETURNING user_id
+        )
+        INSERT INTO budgets (user_id, category_id, month, kind, amount, rollover)
+        SELECT b.user_id, b.category_id, $1, b.kind, b.amount, b.rollover
+        FROM budgets b
+        INNER JOIN copied c ON b.user_id = c.user_id
+        WHERE b.month = $2
+        ON CONFLICT (user_id, category_id, month) DO NOTHING
+        "#,
+        month,
+        previous
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
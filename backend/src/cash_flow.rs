@@ -0,0 +1,313 @@
+use crate::installment_tracking::installment_date;
+use crate::models::forecast::{
+    AccountForecast, ForecastDay, ForecastEvent, EVENT_CREDIT_CARD_BILL, EVENT_INSTALLMENT, EVENT_RECURRING,
+};
+use crate::models::recurring::{RecurringSeries, SOURCE_MANUAL};
+use crate::recurrence;
+use chrono::{Days, Months, NaiveDate};
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+// Fechamento da fatura assumido quando a Pluggy não informa a data
+const DEFAULT_CLOSING_DAYS_BEFORE_DUE: u64 = 7;
+
+struct AccountRow {
+    id: Uuid,
+    item_id: Option<Uuid>,
+    name: Option<String>,
+    account_type: Option<String>,
+    subtype: Option<String>,
+    balance: Option<Decimal>,
+    currency: Option<String>,
+    due_date: Option<String>,
+    close_date: Option<String>,
+}
+
+impl AccountRow {
+    fn is_type(&self, account_type: &str) -> bool {
+        self.account_type.as_deref() == Some(account_type)
+    }
+
+    fn is_checking(&self) -> bool {
+        self.subtype.as_deref() == Some("CHECKING_ACCOUNT")
+    }
+}
+
+/// Cartão com as datas de vencimento e fechamento das faturas dentro do período
+struct CardBills {
+    paying_account_id: Uuid,
+    name: String,
+    // Valor da fatura atual (positivo quando há dívida)
+    balance: Decimal,
+    // (fechamento, vencimento) de cada fatura, em ordem
+    cycles: Vec<(NaiveDate, NaiveDate)>,
+    // Compras previstas no cartão (valor positivo aumenta a fatura)
+    charges: Vec<(NaiveDate, Decimal)>,
+}
+
+// Datas da Pluggy chegam como "2026-11-10T00:00:00.000Z"
+fn parse_pluggy_date(value: Option<&str>) -> Option<NaiveDate> {
+    value.and_then(|v| NaiveDate::parse_from_str(v.get(..10)?, "%Y-%m-%d").ok())
+}
+
+/// Faturas do cartão que vencem entre `today` e `end`, repetindo mensalmente o último vencimento informado
+fn bill_cycles(account: &AccountRow, today: NaiveDate, end: NaiveDate) -> Vec<(NaiveDate, NaiveDate)> {
+    let Some(due) = parse_pluggy_date(account.due_date.as_deref()) else {
+        return Vec::new();
+    };
+    let closing_days = parse_pluggy_date(account.close_date.as_deref())
+        .map(|close| (due - close).num_days())
+        .filter(|days| (1..=31).contains(days))
+        .map(|days| days as u64)
+        .unwrap_or(DEFAULT_CLOSING_DAYS_BEFORE_DUE);
+
+    let mut cycles = Vec::new();
+    let mut index = 0;
+    while let Some(date) = due.checked_add_months(Months::new(index)) {
+        if date > end {
+            break;
+        }
+        if date >= today {
+            cycles.push((date - Days::new(closing_days), date));
+        }
+        index += 1;
+    }
+    cycles
+}
+
+/// Conta que paga a fatura do cartão: conta corrente da mesma instituição, se houver, senão a de maior saldo
+fn paying_account(banks: &[&AccountRow], item_id: Option<Uuid>) -> Option<Uuid> {
+    banks
+        .iter()
+        .max_by_key(|a| (item_id.is_some() && a.item_id == item_id, a.is_checking(), a.balance.unwrap_or_default()))
+        .map(|a| a.id)
+}
+
+/// Datas das ocorrências da série entre `today` e `end`
+fn series_dates(series: &RecurringSeries, today: NaiveDate, end: NaiveDate) -> Vec<NaiveDate> {
+    // Lançamentos cadastrados partem da próxima data; séries detectadas, da última ocorrência vista
+    let (start, first_index) = if series.source == SOURCE_MANUAL {
+        (series.next_expected_date, 0)
+    } else {
+        (series.last_date, 1)
+    };
+    let Some(start) = start else {
+        return Vec::new();
+    };
+
+    let mut dates = Vec::new();
+    let mut index = first_index;
+    while let Some(date) = recurrence::next_occurrence(start, &series.frequency, index, series.end_date) {
+        if date > end {
+            break;
+        }
+        if date >= today {
+            dates.push(date);
+        }
+        index += 1;
+    }
+    dates
+}
+
+/// Projeção diária do saldo das contas correntes e poupanças do usuário de `today` a `end`, a partir do saldo atual,
+/// das séries recorrentes, das parcelas a vencer e do pagamento das faturas dos cartões.
+///
+/// Recorrências e parcelas lançadas no cartão entram na fatura do ciclo em que caem; cartões sem data de
+/// vencimento informada pela Pluggy não são projetados. Lançamentos sem conta vão para a conta corrente de maior saldo.
+pub async fn forecast(pool: &PgPool, user_id: Uuid, today: NaiveDate, end: NaiveDate) -> sqlx::Result<Vec<AccountForecast>> {
+    let accounts = sqlx::query_as!(
+        AccountRow,
+        r#"
+        SELECT a.id, a.item_id, a.name, a.type AS account_type, a.subtype, a.balance, a.currency,
+               a.credit_data->>'balanceDueDate' AS due_date, a.credit_data->>'balanceCloseDate' AS close_date
+        FROM accounts a
+        INNER JOIN items i ON a.item_id = i.id
+        WHERE i.user_id = $1
+        ORDER BY a.name, a.id
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let banks: Vec<&AccountRow> = accounts.iter().filter(|a| a.is_type("BANK")).collect();
+    let default_account = paying_account(&banks, None);
+
+    let mut cards: HashMap<Uuid, CardBills> = HashMap::new();
+    for card in accounts.iter().filter(|a| a.is_type("CREDIT")) {
+        let Some(paying_account_id) = paying_account(&banks, card.item_id) else {
+            continue;
+        };
+        cards.insert(
+            card.id,
+            CardBills {
+                paying_account_id,
+                name: card.name.clone().unwrap_or_else(|| "Cartão de crédito".to_string()),
+                balance: card.balance.unwrap_or_default(),
+                cycles: bill_cycles(card, today, end),
+                charges: Vec::new(),
+            },
+        );
+    }
+
+    let mut events: HashMap<Uuid, Vec<ForecastEvent>> = HashMap::new();
+
+    // Conta que recebe o lançamento: a própria conta, se for corrente ou poupança, ou a padrão se não tiver conta.
+    // Lançamentos em cartões vão para a fatura; os de outros tipos de conta são ignorados.
+    let target = |account_id: Option<Uuid>| match account_id {
+        None => default_account.map(Ok),
+        Some(id) if cards.contains_key(&id) => Some(Err(id)),
+        Some(id) if banks.iter().any(|a| a.id == id) => Some(Ok(id)),
+        Some(_) => None,
+    };
+    let mut charges: Vec<(Uuid, NaiveDate, Decimal)> = Vec::new();
+
+    for series in recurrence::load_series(pool, user_id, today).await? {
+        let Some(target) = target(series.account_id) else {
+            continue;
+        };
+        for date in series_dates(&series, today, end) {
+            match target {
+                Ok(account_id) => events.entry(account_id).or_default().push(ForecastEvent {
+                    date,
+                    kind: EVENT_RECURRING.to_string(),
+                    description: series.name.clone(),
+                    amount: series.amount,
+                    credit_card_account_id: None,
+                    recurring_transaction_id: series.recurring_transaction_id,
+                    installment_purchase_id: None,
+                }),
+                Err(card_id) => charges.push((card_id, date, -series.amount)),
+            }
+        }
+    }
+
+    let purchases = sqlx::query!(
+        r#"
+        SELECT p.id, p.description, p.account_id, p.installment_count, p.installment_amount, p.first_installment_date,
+               COALESCE(MAX(t.installment_number), 0) AS "paid_installments!"
+        FROM installment_purchases p
+        LEFT JOIN transactions t ON t.installment_purchase_id = p.id
+        WHERE p.user_id = $1
+        GROUP BY p.id
+        HAVING COALESCE(MAX(t.installment_number), 0) < p.installment_count
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for purchase in purchases {
+        let Some(target) = target(purchase.account_id) else {
+            continue;
+        };
+        for number in (purchase.paid_installments + 1)..=purchase.installment_count {
+            let Some(date) = installment_date(purchase.first_installment_date, number) else {
+                continue;
+            };
+            if date > end {
+                break;
+            }
+            if date < today {
+                continue;
+            }
+            match target {
+                Ok(account_id) => events.entry(account_id).or_default().push(ForecastEvent {
+                    date,
+                    kind: EVENT_INSTALLMENT.to_string(),
+                    description: format!("{} ({}/{})", purchase.description, number, purchase.installment_count),
+                    amount: -purchase.installment_amount,
+                    credit_card_account_id: None,
+                    recurring_transaction_id: None,
+                    installment_purchase_id: Some(purchase.id),
+                }),
+                Err(card_id) => charges.push((card_id, date, purchase.installment_amount)),
+            }
+        }
+    }
+
+    for (card_id, date, amount) in charges {
+        if let Some(card) = cards.get_mut(&card_id) {
+            card.charges.push((date, amount));
+        }
+    }
+
+    // A primeira fatura é o saldo atual do cartão mais as compras previstas até o fechamento;
+    // as seguintes, as compras previstas entre um fechamento e outro
+    for (card_id, card) in &cards {
+        let mut previous_close: Option<NaiveDate> = None;
+        for (index, (close, due)) in card.cycles.iter().enumerate() {
+            let charged: Decimal = card
+                .charges
+                .iter()
+                .filter(|(date, _)| date <= close && previous_close.is_none_or(|p| *date > p))
+                .map(|(_, amount)| *amount)
+                .sum();
+            previous_close = Some(*close);
+
+            let bill = if index == 0 { card.balance.max(Decimal::ZERO) + charged } else { charged };
+            if bill <= Decimal::ZERO {
+                continue;
+            }
+            events.entry(card.paying_account_id).or_default().push(ForecastEvent {
+                date: *due,
+                kind: EVENT_CREDIT_CARD_BILL.to_string(),
+                description: format!("Fatura {}", card.name),
+                amount: -bill,
+                credit_card_account_id: Some(*card_id),
+                recurring_transaction_id: None,
+                installment_purchase_id: None,
+            });
+        }
+    }
+
+    Ok(banks
+        .iter()
+        .map(|account| {
+            let mut account_events = events.remove(&account.id).unwrap_or_default();
+            account_events.sort_by_key(|e| e.date);
+            project(account, account_events, today, end)
+        })
+        .collect())
+}
+
+fn project(account: &AccountRow, events: Vec<ForecastEvent>, today: NaiveDate, end: NaiveDate) -> AccountForecast {
+    let current_balance = account.balance.unwrap_or_default();
+    let mut balance = current_balance;
+    let mut lowest = (current_balance, today);
+    let mut negative_dates = Vec::new();
+    let mut days = Vec::new();
+    let mut events = events.into_iter().peekable();
+
+    for date in today.iter_days().take_while(|d| *d <= end) {
+        let mut day_events = Vec::new();
+        while let Some(event) = events.next_if(|e| e.date == date) {
+            balance += event.amount;
+            day_events.push(event);
+        }
+
+        if balance < lowest.0 {
+            lowest = (balance, date);
+        }
+        let negative = balance < Decimal::ZERO;
+        if negative {
+            negative_dates.push(date);
+        }
+        days.push(ForecastDay { date, balance, negative, events: day_events });
+    }
+
+    AccountForecast {
+        account_id: account.id,
+        name: account.name.clone(),
+        subtype: account.subtype.clone(),
+        currency: account.currency.clone(),
+        current_balance,
+        projected_balance: balance,
+        lowest_balance: lowest.0,
+        lowest_balance_date: lowest.1,
+        negative_dates,
+        days,
+    }
+}
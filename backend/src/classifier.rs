@@ -0,0 +1,256 @@
+use crate::models::categorization_rule::fold_text;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+/// Mínimo de transações categorizadas pelo usuário para treinar o classificador
+pub const MIN_TRAINING_SAMPLES: i64 = 10;
+/// Confiança mínima para atribuir a categoria automaticamente na sincronização
+pub const AUTO_ASSIGN_CONFIDENCE: f64 = 0.8;
+
+// Palavras comuns em descrições bancárias que não ajudam a distinguir categorias
+const STOP_WORDS: &[&str] = &[
+    "de", "da", "do", "das", "dos", "e", "em", "para", "com", "compra", "pagamento", "pag", "pgto", "cartao",
+    "debito", "credito", "transf", "transferencia", "ltda", "sa", "me", "eireli",
+];
+
+/// Tokens de uma descrição: minúsculas, sem acentos, sem números e palavras muito curtas.
+/// Tokens do estabelecimento recebem o prefixo "m:" para pesarem separadamente.
+pub fn tokenize(description: Option<&str>, merchant: Option<&str>) -> Vec<String> {
+    let split = |text: &str| -> Vec<String> {
+        fold_text(text)
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|t| t.chars().count() >= 2 && !t.chars().all(|c| c.is_ascii_digit()))
+            .filter(|t| !STOP_WORDS.contains(t))
+            .map(str::to_string)
+            .collect()
+    };
+
+    let mut tokens = split(description.unwrap_or_default());
+    if let Some(merchant) = merchant {
+        tokens.extend(split(merchant).into_iter().map(|t| format!("m:{}", t)));
+    }
+    tokens
+}
+
+/// Naive Bayes multinomial com suavização de Laplace, treinado por usuário
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct NaiveBayes {
+    // Transações por categoria
+    documents: HashMap<String, u32>,
+    // Ocorrências de cada token por categoria
+    tokens: HashMap<String, HashMap<String, u32>>,
+    // Total de tokens por categoria
+    token_totals: HashMap<String, u32>,
+    vocabulary: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CategoryPrediction {
+    pub category: String,
+    pub confidence: f64,
+}
+
+impl NaiveBayes {
+    pub fn train<'a>(samples: impl IntoIterator<Item = (Vec<String>, &'a str)>) -> Self {
+        let mut model = NaiveBayes::default();
+        let mut vocabulary = HashSet::new();
+
+        for (tokens, category) in samples {
+            *model.documents.entry(category.to_string()).or_default() += 1;
+            let counts = model.tokens.entry(category.to_string()).or_default();
+            for token in tokens {
+                *model.token_totals.entry(category.to_string()).or_default() += 1;
+                *counts.entry(token.clone()).or_default() += 1;
+                vocabulary.insert(token);
+            }
+        }
+
+        model.vocabulary = vocabulary.len() as u32;
+        model
+    }
+
+    pub fn categories(&self) -> usize {
+        self.documents.len()
+    }
+
+    /// Probabilidade de cada categoria, da mais provável para a menos provável
+    pub fn predict(&self, tokens: &[String]) -> Vec<CategoryPrediction> {
+        // Sem nenhum token conhecido a previsão seria só a categoria mais frequente
+        let known = tokens
+            .iter()
+            .any(|t| self.tokens.values().any(|counts| counts.contains_key(t)));
+        if !known {
+            return Vec::new();
+        }
+
+        let total_documents: u32 = self.documents.values().sum();
+        let vocabulary = self.vocabulary.max(1) as f64;
+
+        let scores: Vec<(String, f64)> = self
+            .documents
+            .iter()
+            .map(|(category, documents)| {
+                let counts = &self.tokens[category];
+                let total = self.token_totals.get(category).copied().unwrap_or(0) as f64;
+                let prior = (*documents as f64 / total_documents as f64).ln();
+                let likelihood: f64 = tokens
+                    .iter()
+                    .map(|t| ((counts.get(t).copied().unwrap_or(0) as f64 + 1.0) / (total + vocabulary)).ln())
+                    .sum();
+                (category.clone(), prior + likelihood)
+            })
+            .collect();
+
+        // Softmax sobre os log-scores para obter probabilidades
+        let max = scores.iter().map(|(_, s)| *s).fold(f64::NEG_INFINITY, f64::max);
+        let sum: f64 = scores.iter().map(|(_, s)| (s - max).exp()).sum();
+
+        let mut predictions: Vec<CategoryPrediction> = scores
+            .into_iter()
+            .map(|(category, score)| CategoryPrediction { category, confidence: (score - max).exp() / sum })
+            .collect();
+        predictions.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+        predictions
+    }
+}
+
+/// Situação do classificador de um usuário
+#[derive(Debug, Serialize)]
+pub struct ClassifierStatus {
+    pub sample_count: i64,
+    pub category_count: usize,
+    pub ready: bool,
+    pub trained_at: Option<DateTime<Utc>>,
+    // O modelo salvo não inclui as últimas categorizações; é treinado de novo na próxima sincronização
+    // ou importação, ou por POST /classifier/train
+    pub stale: bool,
+}
+
+// Quantidade e última mudança de categoria das transações de treino, que identificam o conjunto de treino atual.
+// `updated_at` não serve: a sincronização o atualiza em todas as transações a cada execução
+struct TrainingSet {
+    sample_count: i64,
+    last_change: Option<DateTime<Utc>>,
+}
+
+struct TrainingSample {
+    description: Option<String>,
+    merchant: Option<String>,
+    category: String,
+}
+
+// Transações categorizadas pelo próprio usuário: correções de transações sincronizadas
+// e transações manuais ou importadas
+async fn training_set(pool: &PgPool, user_id: Uuid) -> sqlx::Result<TrainingSet> {
+    sqlx::query_as!(
+        TrainingSet,
+        r#"
+        SELECT COUNT(*) AS "sample_count!", MAX(category_changed_at) AS last_change
+        FROM transactions
+        WHERE user_id = $1 AND NULLIF(BTRIM(category), '') IS NOT NULL
+          AND ('category' = ANY(user_modified_fields) OR pluggy_transaction_id IS NULL)
+        "#,
+        user_id
+    )
+    .fetch_one(pool)
+    .await
+}
+
+fn status_for(model: &NaiveBayes, set: &TrainingSet, trained_at: Option<DateTime<Utc>>, stale: bool) -> ClassifierStatus {
+    ClassifierStatus {
+        sample_count: set.sample_count,
+        category_count: model.categories(),
+        ready: model.categories() >= 2,
+        trained_at,
+        stale,
+    }
+}
+
+fn not_ready(set: &TrainingSet) -> ClassifierStatus {
+    ClassifierStatus { sample_count: set.sample_count, category_count: 0, ready: false, trained_at: None, stale: false }
+}
+
+/// Carrega o modelo salvo do usuário sem treinar, mesmo que esteja desatualizado (somente leitura).
+/// O modelo só é retornado quando está pronto para uso (exemplos suficientes e ao menos duas categorias).
+pub async fn load(pool: &PgPool, user_id: Uuid) -> anyhow::Result<(Option<NaiveBayes>, ClassifierStatus)> {
+    let set = training_set(pool, user_id).await?;
+    if set.sample_count < MIN_TRAINING_SAMPLES {
+        return Ok((None, not_ready(&set)));
+    }
+
+    let stored = sqlx::query!(
+        "SELECT model, sample_count, trained_through, trained_at FROM category_classifiers WHERE user_id = $1",
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    let Some(stored) = stored else {
+        return Ok((None, ClassifierStatus { stale: true, ..not_ready(&set) }));
+    };
+
+    let stale = stored.sample_count as i64 != set.sample_count || stored.trained_through != set.last_change;
+    let model: NaiveBayes = serde_json::from_value(stored.model)?;
+    let status = status_for(&model, &set, stored.trained_at, stale);
+    Ok((Some(model).filter(|_| status.ready), status))
+}
+
+/// Carrega o modelo do usuário, treinando de novo se as transações de treino mudaram.
+/// Usado na sincronização e na importação, que já gravam no banco.
+pub async fn load_or_train(pool: &PgPool, user_id: Uuid) -> anyhow::Result<(Option<NaiveBayes>, ClassifierStatus)> {
+    let (model, status) = load(pool, user_id).await?;
+    if !status.stale {
+        return Ok((model, status));
+    }
+    train(pool, user_id).await
+}
+
+/// Treina o modelo com as transações de treino atuais e salva
+pub async fn train(pool: &PgPool, user_id: Uuid) -> anyhow::Result<(Option<NaiveBayes>, ClassifierStatus)> {
+    let set = training_set(pool, user_id).await?;
+    if set.sample_count < MIN_TRAINING_SAMPLES {
+        return Ok((None, not_ready(&set)));
+    }
+
+    let samples = sqlx::query_as!(
+        TrainingSample,
+        r#"
+        SELECT description, merchant->>'name' AS merchant, category AS "category!"
+        FROM transactions
+        WHERE user_id = $1 AND NULLIF(BTRIM(category), '') IS NOT NULL
+          AND ('category' = ANY(user_modified_fields) OR pluggy_transaction_id IS NULL)
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let model = NaiveBayes::train(samples.iter().map(|sample| {
+        (tokenize(sample.description.as_deref(), sample.merchant.as_deref()), sample.category.as_str())
+    }));
+
+    let trained_at = sqlx::query_scalar!(
+        r#"
+        INSERT INTO category_classifiers (user_id, model, sample_count, trained_through, trained_at)
+        VALUES ($1, $2, $3, $4, CURRENT_TIMESTAMP)
+        ON CONFLICT (user_id) DO UPDATE SET
+            model = EXCLUDED.model,
+            sample_count = EXCLUDED.sample_count,
+            trained_through = EXCLUDED.trained_through,
+            trained_at = EXCLUDED.trained_at
+        RETURNING trained_at
+        "#,
+        user_id,
+        serde_json::to_value(&model)?,
+        set.sample_count as i32,
+        set.last_change
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let status = status_for(&model, &set, trained_at, false);
+    Ok((Some(model).filter(|_| status.ready), status))
+}
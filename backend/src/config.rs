@@ -1,15 +1,64 @@
 use std::env;
 
+#[derive(Clone)]
+pub struct OidcConfig {
+    pub issuer_url: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub redirect_uri: String,
+    pub scopes: String,
+}
+
+#[derive(Clone)]
+pub struct S3Config {
+    // Endpoint S3 ou compatível (MinIO, R2...); os objetos são acessados no estilo path
+    pub endpoint: String,
+    pub bucket: String,
+    pub region: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+}
+
+#[derive(Clone)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    // Remetente, ex: "Firebudget <alertas@exemplo.com>"
+    pub from: String,
+}
+
+#[derive(Clone)]
+pub struct VapidConfig {
+    // Chave privada P-256 em base64url (32 bytes), como gerada por `web-push generate-vapid-keys`
+    pub private_key: String,
+    // Contato do responsável pelo servidor (mailto: ou https:)
+    pub subject: String,
+}
+
+#[derive(Clone)]
 pub struct AppConfig {
     pub client_id: String,
     pub client_secret: String,
     pub environment: String,
     pub base_url: String,
     pub database_url: String,
-    pub jwt_secret: String,
+    // Segredo HS256 legado: apenas valida tokens emitidos antes das chaves assimétricas
+    pub jwt_secret: Option<String>,
+    pub jwt_key_rotation_days: i64,
     pub admin_email: Option<String>,
     pub admin_password: Option<String>,
     pub admin_name: Option<String>,
+    pub export_dir: String,
+    pub oidc: Option<OidcConfig>,
+    pub attachments_dir: String,
+    pub max_attachment_bytes: u64,
+    pub s3: Option<S3Config>,
+    pub smtp: Option<SmtpConfig>,
+    pub vapid: Option<VapidConfig>,
+    // Chave AES-256 (32 bytes em base64) que cifra os segredos guardados no banco, como o do webhook
+    pub secrets_key: Option<String>,
 }
 
 impl AppConfig {
@@ -31,13 +80,82 @@ impl AppConfig {
         let database_url = env::var("DATABASE_URL")
             .map_err(|_| anyhow::anyhow!("DATABASE_URL não encontrada"))?;
 
-        let jwt_secret = env::var("JWT_SECRET")
-            .map_err(|_| anyhow::anyhow!("JWT_SECRET não encontrada"))?;
+        let jwt_secret = env::var("JWT_SECRET").ok().filter(|v| !v.is_empty());
+
+        let jwt_key_rotation_days = env::var("JWT_KEY_ROTATION_DAYS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(30);
 
         let admin_email = env::var("ADMIN_EMAIL").ok();
         let admin_password = env::var("ADMIN_PASSWORD").ok();
         let admin_name = env::var("ADMIN_NAME").ok();
 
+        // Diretório onde os arquivos de exportação de dados (LGPD) são gerados
+        let export_dir = env::var("EXPORT_DIR").unwrap_or_else(|_| "exports".to_string());
+
+        // Login via OIDC é habilitado apenas quando o emissor está configurado
+        let oidc = match env::var("OIDC_ISSUER_URL").ok().filter(|v| !v.is_empty()) {
+            Some(issuer_url) => Some(OidcConfig {
+                issuer_url: issuer_url.trim_end_matches('/').to_string(),
+                client_id: env::var("OIDC_CLIENT_ID")
+                    .map_err(|_| anyhow::anyhow!("OIDC_CLIENT_ID não encontrada"))?,
+                client_secret: env::var("OIDC_CLIENT_SECRET").ok().filter(|v| !v.is_empty()),
+                redirect_uri: env::var("OIDC_REDIRECT_URI")
+                    .map_err(|_| anyhow::anyhow!("OIDC_REDIRECT_URI não encontrada"))?,
+                scopes: env::var("OIDC_SCOPES").unwrap_or_else(|_| "openid email profile".to_string()),
+            }),
+            None => None,
+        };
+
+        // Comprovantes ficam no disco local, a menos que um bucket S3 esteja configurado
+        let attachments_dir = env::var("ATTACHMENTS_DIR").unwrap_or_else(|_| "attachments".to_string());
+
+        let max_attachment_bytes = env::var("MAX_ATTACHMENT_BYTES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(10 * 1024 * 1024);
+
+        let s3 = match env::var("S3_BUCKET").ok().filter(|v| !v.is_empty()) {
+            Some(bucket) => Some(S3Config {
+                endpoint: env::var("S3_ENDPOINT")
+                    .ok()
+                    .filter(|v| !v.is_empty())
+                    .unwrap_or_else(|| "https://s3.amazonaws.com".to_string())
+                    .trim_end_matches('/')
+                    .to_string(),
+                bucket,
+                region: env::var("S3_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+                access_key_id: env::var("S3_ACCESS_KEY_ID")
+                    .map_err(|_| anyhow::anyhow!("S3_ACCESS_KEY_ID não encontrada"))?,
+                secret_access_key: env::var("S3_SECRET_ACCESS_KEY")
+                    .map_err(|_| anyhow::anyhow!("S3_SECRET_ACCESS_KEY não encontrada"))?,
+            }),
+            None => None,
+        };
+
+        // Canais de notificação opcionais: e-mail (SMTP) e web push (VAPID)
+        let smtp = match env::var("SMTP_HOST").ok().filter(|v| !v.is_empty()) {
+            Some(host) => Some(SmtpConfig {
+                host,
+                port: env::var("SMTP_PORT").ok().and_then(|v| v.parse().ok()).unwrap_or(587),
+                username: env::var("SMTP_USERNAME").ok().filter(|v| !v.is_empty()),
+                password: env::var("SMTP_PASSWORD").ok().filter(|v| !v.is_empty()),
+                from: env::var("SMTP_FROM").map_err(|_| anyhow::anyhow!("SMTP_FROM não encontrada"))?,
+            }),
+            None => None,
+        };
+
+        let vapid = match env::var("VAPID_PRIVATE_KEY").ok().filter(|v| !v.is_empty()) {
+            Some(private_key) => Some(VapidConfig {
+                private_key,
+                subject: env::var("VAPID_SUBJECT").map_err(|_| anyhow::anyhow!("VAPID_SUBJECT não encontrada"))?,
+            }),
+            None => None,
+        };
+
+        let secrets_key = env::var("SECRETS_KEY").ok().filter(|v| !v.is_empty());
+
         Ok(AppConfig {
             client_id,
             client_secret,
@@ -45,9 +163,18 @@ impl AppConfig {
             base_url: base_url.to_string(),
             database_url,
             jwt_secret,
+            jwt_key_rotation_days,
             admin_email,
             admin_password,
             admin_name,
+            export_dir,
+            oidc,
+            attachments_dir,
+            max_attachment_bytes,
+            s3,
+            smtp,
+            vapid,
+            secrets_key,
         })
     }
 }
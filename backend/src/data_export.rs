@@ -0,0 +1,660 @@
+use crate::config::AppConfig;
+use crate::models::data_export::{STATUS_EXPIRED, STATUS_FAILED, STATUS_PENDING, STATUS_READY};
+use crate::transaction_export::escape_formula;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use chrono::{Duration, Utc};
+use rand::RngCore;
+use sqlx::PgPool;
+use std::io::{Cursor, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+use uuid::Uuid;
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+/// Tempo de validade do link de download após a geração
+const EXPORT_TTL_HOURS: i64 = 24;
+
+/// Exportação pendente há mais tempo que isso é considerada perdida (ex.: servidor reiniciado durante a geração)
+const PENDING_TIMEOUT_MINUTES: i64 = 30;
+
+/// Seções exportadas como JSON: (arquivo, lista dos registros do usuário montada pelo banco)
+async fn json_sections(pool: &PgPool, user_id: Uuid) -> sqlx::Result<Vec<(&'static str, serde_json::Value)>> {
+    Ok(vec![
+        (
+            "user.json",
+            sqlx::query_scalar!(
+                r#"
+                SELECT COALESCE(json_agg(t), '[]'::json) AS "data!" FROM (
+                    SELECT id, email, full_name, preferred_currency, locale, timezone, created_at, updated_at FROM users WHERE id = $1
+                ) t
+                "#,
+                user_id
+            )
+            .fetch_one(pool)
+            .await?,
+        ),
+        (
+            "items.json",
+            sqlx::query_scalar!(
+                r#"
+                SELECT COALESCE(json_agg(t), '[]'::json) AS "data!" FROM (
+                    SELECT id, pluggy_item_id, connector, status, execution_status, error, client_user_id, created_at, updated_at FROM items WHERE user_id = $1
+                ) t
+                "#,
+                user_id
+            )
+            .fetch_one(pool)
+            .await?,
+        ),
+        (
+            "accounts.json",
+            sqlx::query_scalar!(
+                r#"
+                SELECT COALESCE(json_agg(t), '[]'::json) AS "data!" FROM (
+                    SELECT a.id, a.pluggy_account_id, a.item_id, a.name, a.number, a.balance, a.currency, a.type, a.subtype,
+                           a.bank_data, a.credit_data, a.loan_data, a.investment_data, a.created_at, a.updated_at
+                    FROM accounts a
+                    INNER JOIN items i ON a.item_id = i.id
+                    WHERE i.user_id = $1
+                ) t
+                "#,
+                user_id
+            )
+            .fetch_one(pool)
+            .await?,
+        ),
+        (
+            "account_snapshots.json",
+            sqlx::query_scalar!(
+                r#"
+                SELECT COALESCE(json_agg(t), '[]'::json) AS "data!" FROM (
+                    SELECT s.account_id, s.date, s.balance, s.currency
+                    FROM account_snapshots s
+                    INNER JOIN accounts a ON s.account_id = a.id
+                    INNER JOIN items i ON a.item_id = i.id
+                    WHERE i.user_id = $1
+                    ORDER BY s.account_id, s.date
+                ) t
+                "#,
+                user_id
+            )
+            .fetch_one(pool)
+            .await?,
+        ),
+        (
+            "investments.json",
+            sqlx::query_scalar!(
+                r#"
+                SELECT COALESCE(json_agg(t), '[]'::json) AS "data!" FROM (
+                    SELECT v.id, v.pluggy_investment_id, v.item_id, v.name, v.number, v.type, v.subtype, v.balance,
+                           v.currency, v.status, v.created_at, v.updated_at
+                    FROM investments v
+                    INNER JOIN items i ON v.item_id = i.id
+                    WHERE i.user_id = $1
+                ) t
+                "#,
+                user_id
+            )
+            .fetch_one(pool)
+            .await?,
+        ),
+        (
+            "investment_snapshots.json",
+            sqlx::query_scalar!(
+                r#"
+                SELECT COALESCE(json_agg(t), '[]'::json) AS "data!" FROM (
+                    SELECT s.investment_id, s.date, s.balance, s.currency
+                    FROM investment_snapshots s
+                    INNER JOIN investments v ON s.investment_id = v.id
+                    INNER JOIN items i ON v.item_id = i.id
+                    WHERE i.user_id = $1
+                    ORDER BY s.investment_id, s.date
+                ) t
+                "#,
+                user_id
+            )
+            .fetch_one(pool)
+            .await?,
+        ),
+        (
+            "api_tokens.json",
+            sqlx::query_scalar!(
+                r#"
+                SELECT COALESCE(json_agg(t), '[]'::json) AS "data!" FROM (
+                    SELECT id, name, token_prefix, scope, expires_at, last_used_at, revoked_at, created_at FROM api_tokens WHERE user_id = $1
+                ) t
+                "#,
+                user_id
+            )
+            .fetch_one(pool)
+            .await?,
+        ),
+        (
+            "import_batches.json",
+            sqlx::query_scalar!(
+                r#"
+                SELECT COALESCE(json_agg(t), '[]'::json) AS "data!" FROM (
+                    SELECT id, format, file_name, account_id, imported_count, duplicate_count, error_count, created_at FROM import_batches WHERE user_id = $1
+                ) t
+                "#,
+                user_id
+            )
+            .fetch_one(pool)
+            .await?,
+        ),
+        (
+            "categorization_rules.json",
+            sqlx::query_scalar!(
+                r#"
+                SELECT COALESCE(json_agg(t), '[]'::json) AS "data!" FROM (
+                    SELECT id, name, priority, enabled, description_contains, original_category, direction, min_amount, max_amount,
+                           account_id, set_category, set_subcategory, rename_to, add_tag, created_at, updated_at
+                    FROM categorization_rules WHERE user_id = $1
+                ) t
+                "#,
+                user_id
+            )
+            .fetch_one(pool)
+            .await?,
+        ),
+        (
+            "categories.json",
+            sqlx::query_scalar!(
+                r#"
+                SELECT COALESCE(json_agg(t), '[]'::json) AS "data!" FROM (
+                    SELECT id, parent_id, name, kind, icon, color, created_at, updated_at FROM categories WHERE user_id = $1
+                ) t
+                "#,
+                user_id
+            )
+            .fetch_one(pool)
+            .await?,
+        ),
+        (
+            "category_mappings.json",
+            sqlx::query_scalar!(
+                r#"
+                SELECT COALESCE(json_agg(t), '[]'::json) AS "data!" FROM (
+                    SELECT pluggy_category_id, category_id, created_at FROM category_mappings WHERE user_id = $1
+                ) t
+                "#,
+                user_id
+            )
+            .fetch_one(pool)
+            .await?,
+        ),
+        (
+            "recurring_transactions.json",
+            sqlx::query_scalar!(
+                r#"
+                SELECT COALESCE(json_agg(t), '[]'::json) AS "data!" FROM (
+                    SELECT id, account_id, description, amount, currency, category, frequency, start_date, end_date,
+                           occurrence_count, next_date, enabled, created_at, updated_at
+                    FROM recurring_transactions WHERE user_id = $1
+                ) t
+                "#,
+                user_id
+            )
+            .fetch_one(pool)
+            .await?,
+        ),
+        (
+            "installment_purchases.json",
+            sqlx::query_scalar!(
+                r#"
+                SELECT COALESCE(json_agg(t), '[]'::json) AS "data!" FROM (
+                    SELECT id, account_id, description, currency, installment_count, installment_amount, total_amount,
+                           purchase_date, first_installment_date, created_at, updated_at
+                    FROM installment_purchases WHERE user_id = $1
+                ) t
+                "#,
+                user_id
+            )
+            .fetch_one(pool)
+            .await?,
+        ),
+        (
+            "budgets.json",
+            sqlx::query_scalar!(
+                r#"
+                SELECT COALESCE(json_agg(t), '[]'::json) AS "data!" FROM (
+                    SELECT id, category_id, month, kind, amount, rollover, created_at, updated_at FROM budgets WHERE user_id = $1
+                ) t
+                "#,
+                user_id
+            )
+            .fetch_one(pool)
+            .await?,
+        ),
+        (
+            "goals.json",
+            sqlx::query_scalar!(
+                r#"
+                SELECT COALESCE(json_agg(t), '[]'::json) AS "data!" FROM (
+                    SELECT id, name, target_amount, target_date, created_at, updated_at FROM goals WHERE user_id = $1
+                ) t
+                "#,
+                user_id
+            )
+            .fetch_one(pool)
+            .await?,
+        ),
+        (
+            "goal_accounts.json",
+            sqlx::query_scalar!(
+                r#"
+                SELECT COALESCE(json_agg(t), '[]'::json) AS "data!" FROM (
+                    SELECT ga.goal_id, ga.account_id, ga.created_at
+                    FROM goal_accounts ga
+                    INNER JOIN goals g ON ga.goal_id = g.id
+                    WHERE g.user_id = $1
+                ) t
+                "#,
+                user_id
+            )
+            .fetch_one(pool)
+            .await?,
+        ),
+        (
+            "goal_contributions.json",
+            sqlx::query_scalar!(
+                r#"
+                SELECT COALESCE(json_agg(t), '[]'::json) AS "data!" FROM (
+                    SELECT id, goal_id, amount, date, note, created_at FROM goal_contributions WHERE user_id = $1
+                ) t
+                "#,
+                user_id
+            )
+            .fetch_one(pool)
+            .await?,
+        ),
+        (
+            "notifications.json",
+            sqlx::query_scalar!(
+                r#"
+                SELECT COALESCE(json_agg(t), '[]'::json) AS "data!" FROM (
+                    SELECT id, kind, title, body, data, read_at, created_at FROM notifications WHERE user_id = $1
+                ) t
+                "#,
+                user_id
+            )
+            .fetch_one(pool)
+            .await?,
+        ),
+        (
+            "notification_settings.json",
+            sqlx::query_scalar!(
+                r#"
+                SELECT COALESCE(json_agg(t), '[]'::json) AS "data!" FROM (
+                    SELECT budget_alerts, connection_alerts, large_transaction_threshold, low_balance_threshold, email_enabled,
+                           webhook_url, created_at, updated_at
+                    FROM notification_settings WHERE user_id = $1
+                ) t
+                "#,
+                user_id
+            )
+            .fetch_one(pool)
+            .await?,
+        ),
+        (
+            "push_subscriptions.json",
+            sqlx::query_scalar!(
+                r#"
+                SELECT COALESCE(json_agg(t), '[]'::json) AS "data!" FROM (
+                    SELECT id, endpoint, created_at FROM push_subscriptions WHERE user_id = $1
+                ) t
+                "#,
+                user_id
+            )
+            .fetch_one(pool)
+            .await?,
+        ),
+        (
+            "merchants.json",
+            sqlx::query_scalar!(
+                r#"
+                SELECT COALESCE(json_agg(t), '[]'::json) AS "data!" FROM (
+                    SELECT id, name, legal_name, cnpj, category, created_at, updated_at FROM merchants WHERE user_id = $1
+                ) t
+                "#,
+                user_id
+            )
+            .fetch_one(pool)
+            .await?,
+        ),
+        (
+            "merchant_aliases.json",
+            sqlx::query_scalar!(
+                r#"
+                SELECT COALESCE(json_agg(t), '[]'::json) AS "data!" FROM (
+                    SELECT id, merchant_id, alias, created_at FROM merchant_aliases WHERE user_id = $1
+                ) t
+                "#,
+                user_id
+            )
+            .fetch_one(pool)
+            .await?,
+        ),
+        (
+            "transfers.json",
+            sqlx::query_scalar!(
+                r#"
+                SELECT COALESCE(json_agg(t), '[]'::json) AS "data!" FROM (
+                    SELECT id, outgoing_transaction_id, incoming_transaction_id, detection, created_at FROM transfers WHERE user_id = $1
+                ) t
+                "#,
+                user_id
+            )
+            .fetch_one(pool)
+            .await?,
+        ),
+        (
+            "attachments.json",
+            sqlx::query_scalar!(
+                r#"
+                SELECT COALESCE(json_agg(t), '[]'::json) AS "data!" FROM (
+                    SELECT id, transaction_id, file_name, content_type, size_bytes, created_at FROM attachments WHERE user_id = $1
+                ) t
+                "#,
+                user_id
+            )
+            .fetch_one(pool)
+            .await?,
+        ),
+    ])
+}
+
+// Colunas dos arquivos CSV, na ordem das consultas
+const BALANCE_COLUMNS: &[&str] = &["id", "account_id", "account_name", "balance", "currency", "created_at", "updated_at"];
+const TRANSACTION_COLUMNS: &[&str] = &[
+    "id",
+    "date",
+    "amount",
+    "currency",
+    "description",
+    "category",
+    "subcategory",
+    "status",
+    "notes",
+    "tags",
+    "account_id",
+    "account_name",
+    "item_id",
+    "pluggy_transaction_id",
+    "import_batch_id",
+    "created_at",
+    "updated_at",
+];
+const SPLIT_COLUMNS: &[&str] =
+    &["id", "transaction_id", "amount", "category", "notes", "position", "created_at", "updated_at"];
+
+/// Seções exportadas como CSV: (arquivo, conteúdo), com todas as colunas convertidas para texto
+async fn csv_sections(pool: &PgPool, user_id: Uuid) -> anyhow::Result<Vec<(&'static str, Vec<u8>)>> {
+    let balances = sqlx::query!(
+        r#"
+        SELECT b.id::text AS id, b.account_id::text AS account_id, a.name AS account_name, b.balance::text AS balance,
+               b.currency, b.created_at::text AS created_at, b.updated_at::text AS updated_at
+        FROM balances b
+        INNER JOIN accounts a ON b.account_id = a.id
+        INNER JOIN items i ON a.item_id = i.id
+        WHERE i.user_id = $1
+        ORDER BY b.updated_at
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+    let balances = csv_file(
+        BALANCE_COLUMNS,
+        balances.into_iter().map(|r| {
+            vec![r.id, r.account_id, r.account_name, r.balance, Some(r.currency), r.created_at, r.updated_at]
+        }),
+    )?;
+
+    let transactions = sqlx::query!(
+        r#"
+        SELECT t.id::text AS id, t.date::text AS date, t.amount::text AS amount, t.currency, t.description,
+               t.category, t.subcategory, t.status, t.notes,
+               (SELECT string_agg(g.name, ', ' ORDER BY g.name) FROM transaction_tags tt
+                INNER JOIN tags g ON tt.tag_id = g.id WHERE tt.transaction_id = t.id) AS tags,
+               t.account_id::text AS account_id, a.name AS "account_name?", t.item_id::text AS item_id,
+               t.pluggy_transaction_id, t.import_batch_id::text AS import_batch_id,
+               t.created_at::text AS created_at, t.updated_at::text AS updated_at
+        FROM transactions t
+        LEFT JOIN accounts a ON t.account_id = a.id
+        WHERE t.user_id = $1
+        ORDER BY t.date, t.created_at
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+    let transactions = csv_file(
+        TRANSACTION_COLUMNS,
+        transactions.into_iter().map(|r| {
+            vec![
+                r.id,
+                r.date,
+                r.amount,
+                Some(r.currency),
+                r.description,
+                r.category,
+                r.subcategory,
+                r.status,
+                r.notes,
+                r.tags,
+                r.account_id,
+                r.account_name,
+                r.item_id,
+                r.pluggy_transaction_id,
+                r.import_batch_id,
+                r.created_at,
+                r.updated_at,
+            ]
+        }),
+    )?;
+
+    let splits = sqlx::query!(
+        r#"
+        SELECT s.id::text AS id, s.transaction_id::text AS transaction_id, s.amount::text AS amount, s.category,
+               s.notes, s.position::text AS position, s.created_at::text AS created_at,
+               s.updated_at::text AS updated_at
+        FROM transaction_splits s
+        WHERE s.user_id = $1
+        ORDER BY s.transaction_id, s.position
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+    let splits = csv_file(
+        SPLIT_COLUMNS,
+        splits.into_iter().map(|r| {
+            vec![r.id, r.transaction_id, r.amount, r.category, r.notes, r.position, r.created_at, r.updated_at]
+        }),
+    )?;
+
+    Ok(vec![("balances.csv", balances), ("transactions.csv", transactions), ("transaction_splits.csv", splits)])
+}
+
+/// Gera o arquivo de exportação em background e atualiza o registro em `data_exports`
+pub async fn run_export(pool: PgPool, config: Arc<AppConfig>, export_id: Uuid, user_id: Uuid) {
+    eprintln!("Gerando exportação de dados {} para usuário {}", export_id, user_id);
+
+    match build_and_store(&pool, &config, export_id, user_id).await {
+        Ok(file_path) => {
+            let mut bytes = [0u8; 32];
+            rand::thread_rng().fill_bytes(&mut bytes);
+            let download_token = URL_SAFE_NO_PAD.encode(bytes);
+            let expires_at = Utc::now() + Duration::hours(EXPORT_TTL_HOURS);
+
+            let result = sqlx::query!(
+                r#"
+                UPDATE data_exports
+                SET status = $1, file_path = $2, download_token = $3, expires_at = $4, completed_at = CURRENT_TIMESTAMP
+                WHERE id = $5
+                "#,
+                STATUS_READY,
+                file_path,
+                download_token,
+                expires_at,
+                export_id
+            )
+            .execute(&pool)
+            .await;
+
+            match result {
+                // O usuário foi removido durante a geração e o registro sumiu em cascata
+                Ok(r) if r.rows_affected() == 0 => remove_export_file(&file_path).await,
+                Ok(_) => eprintln!("✓ Exportação {} concluída", export_id),
+                Err(e) => eprintln!("✗ Erro ao atualizar exportação {}: {}", export_id, e),
+            }
+        }
+        Err(e) => {
+            eprintln!("✗ Erro ao gerar exportação {}: {}", export_id, e);
+            let _ = sqlx::query!(
+                "UPDATE data_exports SET status = $1, error = $2, completed_at = CURRENT_TIMESTAMP WHERE id = $3",
+                STATUS_FAILED,
+                e.to_string(),
+                export_id
+            )
+            .execute(&pool)
+            .await;
+        }
+    }
+}
+
+async fn build_and_store(
+    pool: &PgPool,
+    config: &AppConfig,
+    export_id: Uuid,
+    user_id: Uuid,
+) -> anyhow::Result<String> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    let mut files = Vec::new();
+
+    for (name, data) in json_sections(pool, user_id).await? {
+        // O registro do usuário é um objeto único, não uma lista
+        let data = if name == "user.json" {
+            data.get(0).cloned().unwrap_or(serde_json::Value::Null)
+        } else {
+            data
+        };
+        zip.start_file(name, options)?;
+        zip.write_all(&serde_json::to_vec_pretty(&data)?)?;
+        files.push(name);
+    }
+
+    for (name, data) in csv_sections(pool, user_id).await? {
+        zip.start_file(name, options)?;
+        zip.write_all(&data)?;
+        files.push(name);
+    }
+
+    let manifest = serde_json::json!({
+        "export_id": export_id,
+        "user_id": user_id,
+        "generated_at": Utc::now(),
+        "files": files,
+    });
+    zip.start_file("manifest.json", options)?;
+    zip.write_all(&serde_json::to_vec_pretty(&manifest)?)?;
+
+    let archive = zip.finish()?.into_inner();
+
+    let dir = PathBuf::from(&config.export_dir);
+    tokio::fs::create_dir_all(&dir).await?;
+    let path = dir.join(format!("{}.zip", export_id));
+    tokio::fs::write(&path, archive).await?;
+
+    Ok(path.to_string_lossy().to_string())
+}
+
+fn csv_file(columns: &[&str], rows: impl Iterator<Item = Vec<Option<String>>>) -> anyhow::Result<Vec<u8>> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    // Cabeçalho presente mesmo sem linhas
+    writer.write_record(columns)?;
+
+    for row in rows {
+        writer.write_record(row.into_iter().map(|value| escape_formula(&value.unwrap_or_default())))?;
+    }
+
+    Ok(writer.into_inner()?)
+}
+
+/// Marca como falhas as exportações pendentes há mais de `PENDING_TIMEOUT_MINUTES`, liberando um novo pedido
+pub async fn fail_stale_exports(pool: &PgPool, user_id: Uuid) -> sqlx::Result<()> {
+    let stale_before = Utc::now() - Duration::minutes(PENDING_TIMEOUT_MINUTES);
+    let failed = sqlx::query!(
+        r#"
+        UPDATE data_exports
+        SET status = $1, error = 'Tempo esgotado na geração da exportação', completed_at = CURRENT_TIMESTAMP
+        WHERE user_id = $2 AND status = $3 AND created_at < $4
+        "#,
+        STATUS_FAILED,
+        user_id,
+        STATUS_PENDING,
+        stale_before
+    )
+    .execute(pool)
+    .await?;
+
+    if failed.rows_affected() > 0 {
+        eprintln!("Exportações pendentes do usuário {} expiraram sem concluir", user_id);
+    }
+    Ok(())
+}
+
+async fn remove_export_file(path: &str) {
+    match tokio::fs::remove_file(path).await {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => eprintln!("Erro ao remover arquivo de exportação {}: {}", path, e),
+    }
+}
+
+/// Remove os arquivos de todas as exportações do usuário, antes de apagar a conta
+pub async fn delete_user_exports(pool: &PgPool, user_id: Uuid) -> sqlx::Result<()> {
+    let paths = sqlx::query_scalar!(
+        "SELECT file_path FROM data_exports WHERE user_id = $1 AND file_path IS NOT NULL",
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for path in paths.into_iter().flatten() {
+        remove_export_file(&path).await;
+    }
+
+    Ok(())
+}
+
+/// Remove arquivos de exportações cujo link expirou
+pub async fn cleanup_expired_exports(pool: &PgPool) -> anyhow::Result<()> {
+    let expired = sqlx::query!(
+        "SELECT id, file_path FROM data_exports WHERE status = $1 AND expires_at < CURRENT_TIMESTAMP",
+        STATUS_READY
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for export in expired {
+        if let Some(path) = &export.file_path {
+            remove_export_file(path).await;
+        }
+
+        sqlx::query!(
+            "UPDATE data_exports SET status = $1, file_path = NULL, download_token = NULL WHERE id = $2",
+            STATUS_EXPIRED,
+            export.id
+        )
+        .execute(pool)
+        .await?;
+
+        eprintln!("Exportação {} expirada e removida", export.id);
+    }
+
+    Ok(())
+}
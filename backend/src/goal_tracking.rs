@@ -0,0 +1,188 @@
+use crate::budget_planning::first_day;
+use crate::models::goal::{Goal, GoalProgress};
+use chrono::{Datelike, Months, NaiveDate, Utc};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+// Meses de histórico (incluindo o atual) usados na média de aportes
+const HISTORY_MONTHS: u32 = 6;
+
+/// Progresso das metas do usuário (ou só de `goal_id`), na ordem da data alvo.
+///
+/// O valor atual é o saldo das contas vinculadas mais os aportes manuais. A média mensal considera os aportes
+/// e as transferências entre as contas vinculadas e as demais contas do usuário (entradas somam, retiradas
+/// subtraem) nos últimos meses, a partir do primeiro mês com movimento. Gastos, salário e rendimentos nas
+/// contas vinculadas não são aportes.
+pub async fn progress(pool: &PgPool, user_id: Uuid, goal_id: Option<Uuid>) -> sqlx::Result<Vec<GoalProgress>> {
+    let today = Utc::now().date_naive();
+    let current_month = first_day(today);
+    let history_start = current_month
+        .checked_sub_months(Months::new(HISTORY_MONTHS - 1))
+        .unwrap_or(current_month);
+
+    let goals = sqlx::query_as!(
+        Goal,
+        r#"
+        SELECT id, name, target_amount, target_date, created_at, updated_at
+        FROM goals
+        WHERE user_id = $1 AND ($2::UUID IS NULL OR id = $2)
+        ORDER BY target_date, name
+        "#,
+        user_id,
+        goal_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut accounts: HashMap<Uuid, (Vec<Uuid>, Decimal)> = HashMap::new();
+    let linked = sqlx::query!(
+        r#"
+        SELECT ga.goal_id, ga.account_id, COALESCE(a.balance, 0) AS "balance!"
+        FROM goal_accounts ga
+        INNER JOIN goals g ON ga.goal_id = g.id
+        INNER JOIN accounts a ON ga.account_id = a.id
+        WHERE g.user_id = $1
+        ORDER BY ga.created_at
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+    for row in linked {
+        let entry = accounts.entry(row.goal_id).or_default();
+        entry.0.push(row.account_id);
+        entry.1 += row.balance;
+    }
+
+    let contributions: HashMap<Uuid, Decimal> = sqlx::query!(
+        r#"SELECT goal_id, SUM(amount) AS "total!" FROM goal_contributions WHERE user_id = $1 GROUP BY goal_id"#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|row| (row.goal_id, row.total))
+    .collect();
+
+    // Movimento de cada meta por mês na janela do histórico
+    let mut history: HashMap<Uuid, Vec<(NaiveDate, Decimal)>> = HashMap::new();
+    let rows = sqlx::query!(
+        r#"
+        SELECT goal_id AS "goal_id!", month AS "month!", SUM(amount) AS "amount!"
+        FROM (
+            SELECT c.goal_id, date_trunc('month', c.date)::DATE AS month, c.amount
+            FROM goal_contributions c
+            WHERE c.user_id = $1 AND c.date >= $2 AND c.date <= $3
+            UNION ALL
+            SELECT ga.goal_id, date_trunc('month', t.date)::DATE AS month, t.amount
+            FROM goal_accounts ga
+            INNER JOIN goals g ON ga.goal_id = g.id
+            INNER JOIN transactions t ON t.account_id = ga.account_id
+            INNER JOIN transfers tr ON t.id IN (tr.outgoing_transaction_id, tr.incoming_transaction_id)
+            INNER JOIN transactions other ON other.id = CASE
+                WHEN tr.outgoing_transaction_id = t.id THEN tr.incoming_transaction_id
+                ELSE tr.outgoing_transaction_id
+            END
+            WHERE g.user_id = $1 AND t.date >= $2 AND t.date <= $3
+              -- Movimentações entre as próprias contas da meta não mudam o valor guardado
+              AND NOT EXISTS (
+                  SELECT 1 FROM goal_accounts og WHERE og.goal_id = ga.goal_id AND og.account_id = other.account_id
+              )
+        ) movements
+        GROUP BY 1, 2
+        "#,
+        user_id,
+        history_start,
+        today
+    )
+    .fetch_all(pool)
+    .await?;
+    for row in rows {
+        history.entry(row.goal_id).or_default().push((row.month, row.amount));
+    }
+
+    Ok(goals
+        .into_iter()
+        .map(|goal| {
+            let (account_ids, accounts_balance) = accounts.remove(&goal.id).unwrap_or_default();
+            let contributions_total = contributions.get(&goal.id).copied().unwrap_or(Decimal::ZERO);
+            let movements = history.remove(&goal.id).unwrap_or_default();
+            build_progress(goal, account_ids, accounts_balance, contributions_total, &movements, today)
+        })
+        .collect())
+}
+
+fn build_progress(
+    goal: Goal,
+    account_ids: Vec<Uuid>,
+    accounts_balance: Decimal,
+    contributions_total: Decimal,
+    movements: &[(NaiveDate, Decimal)],
+    today: NaiveDate,
+) -> GoalProgress {
+    let current_amount = accounts_balance + contributions_total;
+    let remaining = (goal.target_amount - current_amount).max(Decimal::ZERO);
+    let completed = remaining.is_zero();
+    let percent = if goal.target_amount > Decimal::ZERO {
+        (current_amount.max(Decimal::ZERO) * Decimal::from(100) / goal.target_amount)
+            .min(Decimal::from(100))
+            .round_dp(1)
+    } else {
+        Decimal::from(100)
+    };
+
+    let months_remaining = months_between(today, goal.target_date).max(0);
+    let required_monthly_contribution = (remaining / Decimal::from(months_remaining.max(1))).round_dp(2);
+
+    // Média desde o primeiro mês com movimento na janela, contando meses sem movimento como zero
+    let average_monthly_contribution = match movements.iter().map(|(month, _)| *month).min() {
+        Some(first) => {
+            let months = months_between(first, first_day(today)) + 1;
+            let total: Decimal = movements.iter().map(|(_, amount)| *amount).sum();
+            (total / Decimal::from(months.max(1))).round_dp(2)
+        }
+        None => Decimal::ZERO,
+    };
+
+    let projected_completion_date = if completed {
+        Some(today)
+    } else if average_monthly_contribution > Decimal::ZERO {
+        (remaining / average_monthly_contribution)
+            .ceil()
+            .to_u32()
+            .and_then(|months| today.checked_add_months(Months::new(months)))
+    } else {
+        None
+    };
+
+    let on_track = projected_completion_date.is_some_and(|date| date <= goal.target_date);
+
+    GoalProgress {
+        goal,
+        account_ids,
+        accounts_balance,
+        contributions_total,
+        current_amount,
+        remaining,
+        percent,
+        completed,
+        months_remaining,
+        required_monthly_contribution,
+        average_monthly_contribution,
+        projected_completion_date,
+        on_track,
+    }
+}
+
+// Meses inteiros de `from` até `to` (negativo se `to` for anterior)
+fn months_between(from: NaiveDate, to: NaiveDate) -> i32 {
+    let months = (to.year() - from.year()) * 12 + to.month() as i32 - from.month() as i32;
+    if months > 0 && to.day() < from.day() {
+        months - 1
+    } else {
+        months
+    }
+}
@@ -0,0 +1,272 @@
+use crate::models::import_batch::{ColumnRef, CsvOptions, ImportRowError};
+use anyhow::Result;
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use std::str::FromStr;
+
+// Formatos tentados quando o usuário não informa `date_format`
+const DEFAULT_DATE_FORMATS: &[&str] = &["%d/%m/%Y", "%Y-%m-%d", "%d/%m/%y", "%d-%m-%Y"];
+
+// Nomes de cabeçalho reconhecidos automaticamente (comparados em minúsculas)
+const DATE_HEADERS: &[&str] = &["data", "date", "data lançamento", "data lancamento", "data da transação"];
+const AMOUNT_HEADERS: &[&str] = &["valor", "amount", "valor (r$)", "value", "quantia"];
+const DESCRIPTION_HEADERS: &[&str] = &[
+    "descrição", "descricao", "description", "histórico", "historico", "lançamento", "lancamento", "título", "titulo",
+];
+const CATEGORY_HEADERS: &[&str] = &["categoria", "category"];
+
+/// Transação lida de um extrato, antes da detecção de duplicatas
+#[derive(Debug)]
+pub struct ParsedTransaction {
+    pub line: usize,
+    pub date: NaiveDate,
+    pub amount: Decimal,
+    pub description: String,
+    pub category: Option<String>,
+    pub external_id: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct ParsedFile {
+    pub transactions: Vec<ParsedTransaction>,
+    pub errors: Vec<ImportRowError>,
+    // Moeda declarada no arquivo (CURDEF no OFX)
+    pub currency: Option<String>,
+}
+
+pub fn parse_date(value: &str, format: Option<&str>) -> Option<NaiveDate> {
+    let value = value.trim();
+    match format {
+        Some(format) => NaiveDate::parse_from_str(value, format).ok(),
+        None => DEFAULT_DATE_FORMATS
+            .iter()
+            .find_map(|format| NaiveDate::parse_from_str(value, format).ok()),
+    }
+}
+
+/// Converte valores como "1.234,56", "R$ -10,00", "(50,00)" ou "1.234,56-"
+pub fn parse_decimal(value: &str, decimal_separator: char) -> Option<Decimal> {
+    let mut value: String = value.replace("R$", "").chars().filter(|c| !c.is_whitespace()).collect();
+
+    let parenthesized = value.starts_with('(') && value.ends_with(')');
+    if parenthesized {
+        value = value[1..value.len() - 1].to_string();
+    }
+    let trailing_minus = value.len() > 1 && value.ends_with('-');
+    if trailing_minus {
+        value.pop();
+    }
+
+    let thousands_separator = if decimal_separator == ',' { '.' } else { ',' };
+    let normalized: String = value
+        .chars()
+        .filter(|c| *c != thousands_separator)
+        .map(|c| if c == decimal_separator { '.' } else { c })
+        .collect();
+
+    let amount = Decimal::from_str(&normalized).ok()?;
+    Some(if parenthesized || trailing_minus { -amount } else { amount })
+}
+
+/// Separador decimal de um valor: o último ',' ou '.' quando os dois aparecem ("1.234,56"),
+/// o outro quando o único separador se repete ("1.234.567"). Um separador único seguido de
+/// três dígitos ("1.234") tanto pode ser milhar quanto decimal e retorna `None`.
+pub fn detect_decimal_separator(value: &str) -> Option<char> {
+    let Some(last) = value.rfind([',', '.']) else {
+        return Some('.');
+    };
+    let separator = if value[last..].starts_with(',') { ',' } else { '.' };
+    let other = if separator == ',' { '.' } else { ',' };
+
+    if value.contains(other) {
+        return Some(separator);
+    }
+    if value.matches(separator).count() > 1 {
+        return Some(other);
+    }
+
+    // Grupos de milhar têm três dígitos e não seguem um zero à esquerda ("0,500")
+    let integer: String = value[..last].chars().filter(char::is_ascii_digit).collect();
+    let decimals = value[last + 1..].chars().filter(char::is_ascii_digit).count();
+    if decimals == 3 && (1..=3).contains(&integer.len()) && integer != "0" {
+        return None;
+    }
+    Some(separator)
+}
+
+fn detect_delimiter(content: &str) -> char {
+    let first_line = content.lines().next().unwrap_or_default();
+    if first_line.matches(';').count() >= first_line.matches(',').count() && first_line.contains(';') {
+        ';'
+    } else {
+        ','
+    }
+}
+
+fn resolve_column(headers: &[String], column: Option<&ColumnRef>, candidates: &[&str]) -> Result<Option<usize>> {
+    match column {
+        Some(ColumnRef::Index(index)) => Ok(Some(*index)),
+        Some(ColumnRef::Name(name)) => {
+            let name = name.trim().to_lowercase();
+            headers
+                .iter()
+                .position(|h| *h == name)
+                .map(Some)
+                .ok_or_else(|| anyhow::anyhow!("Coluna '{}' não encontrada no cabeçalho", name))
+        }
+        None => Ok(headers.iter().position(|h| candidates.contains(&h.as_str()))),
+    }
+}
+
+pub fn parse_csv(content: &str, options: &CsvOptions) -> Result<ParsedFile> {
+    let content = content.trim_start_matches('\u{feff}');
+    let delimiter = options.delimiter.unwrap_or_else(|| detect_delimiter(content));
+    if !delimiter.is_ascii() {
+        return Err(anyhow::anyhow!("Delimitador inválido"));
+    }
+    let has_header = options.has_header.unwrap_or(true);
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter as u8)
+        .has_headers(has_header)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(content.as_bytes());
+
+    let headers: Vec<String> = if has_header {
+        reader.headers()?.iter().map(|h| h.to_lowercase()).collect()
+    } else {
+        Vec::new()
+    };
+
+    let columns = &options.columns;
+    let date_column = resolve_column(&headers, columns.date.as_ref(), DATE_HEADERS)?
+        .ok_or_else(|| anyhow::anyhow!("Informe a coluna da data"))?;
+    let amount_column = resolve_column(&headers, columns.amount.as_ref(), AMOUNT_HEADERS)?
+        .ok_or_else(|| anyhow::anyhow!("Informe a coluna do valor"))?;
+    let description_column = resolve_column(&headers, columns.description.as_ref(), DESCRIPTION_HEADERS)?
+        .ok_or_else(|| anyhow::anyhow!("Informe a coluna da descrição"))?;
+    let category_column = resolve_column(&headers, columns.category.as_ref(), CATEGORY_HEADERS)?;
+
+    let mut parsed = ParsedFile::default();
+
+    for record in reader.records() {
+        let record = record?;
+        if record.iter().all(str::is_empty) {
+            continue;
+        }
+        let line = record.position().map(|p| p.line() as usize).unwrap_or_default();
+        let field = |index: usize| record.get(index).unwrap_or_default();
+
+        let Some(date) = parse_date(field(date_column), options.date_format.as_deref()) else {
+            parsed.errors.push(ImportRowError { line, message: format!("Data inválida: '{}'", field(date_column)) });
+            continue;
+        };
+        let Some(decimal_separator) =
+            options.decimal_separator.or_else(|| detect_decimal_separator(field(amount_column)))
+        else {
+            parsed.errors.push(ImportRowError {
+                line,
+                message: format!("Valor ambíguo: '{}'. Informe o separador decimal", field(amount_column)),
+            });
+            continue;
+        };
+        let Some(amount) = parse_decimal(field(amount_column), decimal_separator) else {
+            parsed.errors.push(ImportRowError { line, message: format!("Valor inválido: '{}'", field(amount_column)) });
+            continue;
+        };
+        let description = field(description_column).to_string();
+        if description.is_empty() {
+            parsed.errors.push(ImportRowError { line, message: "Descrição vazia".to_string() });
+            continue;
+        }
+
+        parsed.transactions.push(ParsedTransaction {
+            line,
+            date,
+            amount: if options.invert_sign { -amount } else { amount },
+            description,
+            category: category_column.map(field).filter(|c| !c.is_empty()).map(str::to_string),
+            external_id: None,
+        });
+    }
+
+    Ok(parsed)
+}
+
+// Valor de um elemento OFX; no OFX 1.x (SGML) os elementos simples não têm tag de fechamento
+fn ofx_value(block: &str, block_upper: &str, tag: &str) -> Option<String> {
+    let open = format!("<{}>", tag);
+    let start = block_upper.find(&open)? + open.len();
+    let rest = &block[start..];
+    let value = rest[..rest.find('<').unwrap_or(rest.len())].trim();
+    if value.is_empty() {
+        return None;
+    }
+    Some(value.replace("&lt;", "<").replace("&gt;", ">").replace("&amp;", "&"))
+}
+
+pub fn parse_ofx(content: &str) -> Result<ParsedFile> {
+    // Mesmo tamanho em bytes que o original, então os índices valem para os dois
+    let upper = content.to_ascii_uppercase();
+    if !upper.contains("<OFX>") {
+        return Err(anyhow::anyhow!("Conteúdo não parece ser um arquivo OFX"));
+    }
+
+    let mut parsed = ParsedFile {
+        currency: ofx_value(content, &upper, "CURDEF"),
+        ..Default::default()
+    };
+
+    let mut position = 0;
+    while let Some(found) = upper[position..].find("<STMTTRN>") {
+        let start = position + found + "<STMTTRN>".len();
+        let end = upper[start..]
+            .find("</STMTTRN>")
+            .map(|e| start + e)
+            .ok_or_else(|| anyhow::anyhow!("Bloco STMTTRN sem fechamento"))?;
+        position = end;
+
+        let block = &content[start..end];
+        let block_upper = &upper[start..end];
+        let line = content[..start].matches('\n').count() + 1;
+
+        let Some(date) = ofx_value(block, block_upper, "DTPOSTED")
+            .and_then(|d| d.get(..8).and_then(|d| NaiveDate::parse_from_str(d, "%Y%m%d").ok()))
+        else {
+            parsed.errors.push(ImportRowError { line, message: "DTPOSTED ausente ou inválido".to_string() });
+            continue;
+        };
+
+        // Alguns bancos brasileiros exportam TRNAMT com vírgula decimal
+        let amount = ofx_value(block, block_upper, "TRNAMT")
+            .and_then(|a| parse_decimal(&a, detect_decimal_separator(&a)?));
+        let Some(amount) = amount else {
+            parsed.errors.push(ImportRowError { line, message: "TRNAMT ausente ou inválido".to_string() });
+            continue;
+        };
+
+        let name = ofx_value(block, block_upper, "NAME");
+        let memo = ofx_value(block, block_upper, "MEMO");
+        let description = match (name, memo) {
+            (Some(name), Some(memo)) if name != memo => format!("{} - {}", name, memo),
+            (Some(name), _) => name,
+            (None, Some(memo)) => memo,
+            (None, None) => {
+                parsed.errors.push(ImportRowError { line, message: "Transação sem NAME ou MEMO".to_string() });
+                continue;
+            }
+        };
+
+        parsed.transactions.push(ParsedTransaction {
+            line,
+            date,
+            amount,
+            description,
+            category: None,
+            external_id: ofx_value(block, block_upper, "FITID"),
+        });
+    }
+
+    Ok(parsed)
+}
@@ -0,0 +1,355 @@
+use crate::taxonomy::{self, MappedCategory};
+use crate::classifier::{self, NaiveBayes, AUTO_ASSIGN_CONFIDENCE};
+use crate::installment_tracking::{self, InstallmentTransaction};
+use crate::merchant_matching;
+use crate::models::categorization_rule::{CategorizationRule, RuleInput};
+use crate::models::category::CategoryPath;
+use crate::models::installment::InstallmentInfo;
+use crate::models::tag::Tag;
+use crate::pluggy::models::Transaction as PluggyTransaction;
+use chrono::NaiveDate;
+use rust_decimal::prelude::FromPrimitive;
+use rust_decimal::Decimal;
+use sqlx::{PgConnection, PgPool};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Transação recebida da Pluggy (sincronização do item ou webhook), já associada à conta local
+pub struct IncomingTransaction {
+    pub pluggy_transaction_id: String,
+    pub account_id: Uuid,
+    pub item_id: Uuid,
+    pub user_id: Uuid,
+    pub amount: Decimal,
+    pub date: NaiveDate,
+    pub description: Option<String>,
+    pub category: Option<String>,
+    pub pluggy_category_id: Option<String>,
+    pub subcategory: Option<String>,
+    pub currency: String,
+    pub status: String,
+    pub merchant: Option<serde_json::Value>,
+    pub balance: Option<Decimal>,
+    pub installment: Option<InstallmentInfo>,
+}
+
+impl IncomingTransaction {
+    pub fn from_pluggy(
+        tx: PluggyTransaction,
+        account_id: Uuid,
+        account_type: Option<&str>,
+        item_id: Uuid,
+        user_id: Uuid,
+    ) -> Self {
+        let date = tx
+            .date
+            .get(0..10)
+            .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+            .unwrap_or_else(|| chrono::Utc::now().date_naive());
+
+        let amount = Decimal::from_f64(tx.amount).unwrap_or(Decimal::ZERO);
+        let installment = installment_tracking::from_pluggy(
+            tx.description.as_deref(),
+            tx.credit_card_metadata.as_ref(),
+            installment_tracking::is_card_charge(account_type, amount),
+        );
+
+        IncomingTransaction {
+            pluggy_transaction_id: tx.id,
+            account_id,
+            item_id,
+            user_id,
+            amount,
+            date,
+            description: tx.description,
+            category: tx.category,
+            pluggy_category_id: tx.category_id,
+            subcategory: tx.subcategory,
+            currency: tx.currency_code,
+            status: tx.status.unwrap_or_else(|| "PENDING".to_string()),
+            merchant: tx.merchant,
+            balance: tx.balance.and_then(Decimal::from_f64),
+            installment,
+        }
+    }
+}
+
+/// Regras, classificador e categorias do usuário, carregados uma vez por sincronização
+pub struct IngestContext {
+    pub rules: Vec<CategorizationRule>,
+    pub classifier: Option<NaiveBayes>,
+    // Categorias associadas aos ids da Pluggy, incluindo as aprendidas durante a sincronização
+    pub categories: HashMap<String, MappedCategory>,
+}
+
+impl IngestContext {
+    pub async fn load(pool: &PgPool, user_id: Uuid) -> anyhow::Result<Self> {
+        let rules = load_rules(pool, user_id).await?;
+
+        // Falha no classificador não deve impedir a sincronização
+        let classifier = match classifier::load_or_train(pool, user_id).await {
+            Ok((model, _)) => model,
+            Err(e) => {
+                eprintln!("Erro ao carregar classificador de categorias: {}", e);
+                None
+            }
+        };
+
+        if let Err(e) = taxonomy::ensure_default_categories(pool, user_id).await {
+            eprintln!("Erro ao criar categorias padrão: {}", e);
+        }
+        let categories = taxonomy::load_mappings(pool, user_id).await?;
+
+        Ok(IngestContext { rules, classifier, categories })
+    }
+
+    /// Categoria do usuário para um id da Pluggy, aprendendo a associação na primeira vez que aparece
+    async fn map_category(&mut self, pool: &PgPool, tx: &IncomingTransaction) -> Option<CategoryPath> {
+        let pluggy_category_id = tx.pluggy_category_id.as_deref()?;
+        if let Some(mapped) = self.categories.get(pluggy_category_id) {
+            return Some(mapped.path.clone());
+        }
+
+        let name = tx.category.as_deref().map(str::trim).filter(|n| !n.is_empty())?;
+        match taxonomy::learn_mapping(pool, tx.user_id, &self.categories, pluggy_category_id, name, tx.amount).await {
+            Ok(mapped) => {
+                let path = mapped.path.clone();
+                self.categories.insert(pluggy_category_id.to_string(), mapped);
+                Some(path)
+            }
+            Err(e) => {
+                eprintln!("Erro ao associar categoria {} da Pluggy: {}", pluggy_category_id, e);
+                None
+            }
+        }
+    }
+}
+
+/// Regras ativas do usuário, na ordem em que devem ser avaliadas
+pub async fn load_rules(pool: &PgPool, user_id: Uuid) -> sqlx::Result<Vec<CategorizationRule>> {
+    sqlx::query_as!(
+        CategorizationRule,
+        r#"
+        SELECT id, name, priority, enabled, description_contains, original_category, direction,
+               min_amount, max_amount, account_id, set_category, set_subcategory, rename_to, add_tag,
+               created_at, updated_at
+        FROM categorization_rules
+        WHERE user_id = $1 AND enabled
+        ORDER BY priority, created_at
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Primeira regra (em ordem de prioridade) cujas condições casam com a transação
+pub fn first_match<'a>(rules: &'a [CategorizationRule], input: &RuleInput) -> Option<&'a CategorizationRule> {
+    rules.iter().find(|rule| rule.matches(input))
+}
+
+/// Insere ou atualiza uma transação vinda da Pluggy, aplicando as regras do usuário.
+/// A categoria da Pluggy é traduzida para a taxonomia do usuário; regras têm precedência sobre ela
+/// e, sem regra que defina a categoria, o classificador a sugere e, com confiança alta, a atribui.
+///
+/// Campos editados manualmente (`user_modified_fields`), anotações e tags existentes
+/// nunca são sobrescritos.
+pub async fn upsert_transaction(
+    pool: &PgPool,
+    context: &mut IngestContext,
+    tx: IncomingTransaction,
+) -> anyhow::Result<Uuid> {
+    let (base_category, base_subcategory) = match context.map_category(pool, &tx).await {
+        Some(path) => (Some(path.category), path.subcategory),
+        None => (tx.category.clone(), tx.subcategory.clone()),
+    };
+
+    let rule = first_match(
+        &context.rules,
+        &RuleInput {
+            description: tx.description.as_deref(),
+            category: tx.category.as_deref(),
+            amount: tx.amount,
+            account_id: Some(tx.account_id),
+        },
+    );
+
+    let prediction = match (&context.classifier, rule.and_then(|r| r.set_category.as_ref())) {
+        (Some(model), None) => {
+            let merchant = tx.merchant.as_ref().and_then(|m| m.get("name")).and_then(|n| n.as_str());
+            model.predict(&classifier::tokenize(tx.description.as_deref(), merchant)).into_iter().next()
+        }
+        _ => None,
+    };
+
+    let category = match (rule.and_then(|r| r.set_category.clone()), &prediction) {
+        (Some(category), _) => Some(category),
+        (None, Some(p)) if p.confidence >= AUTO_ASSIGN_CONFIDENCE => Some(p.category.clone()),
+        _ => base_category.clone(),
+    };
+
+    let mut conn = pool.acquire().await?;
+
+    // O estabelecimento segue a descrição original do banco, não a renomeada pela regra
+    let merchant_id =
+        merchant_matching::resolve(&mut conn, tx.user_id, tx.description.as_deref(), tx.merchant.as_ref()).await?;
+    let merchant_key = merchant_matching::alias_key(tx.description.as_deref());
+
+    let description = rule.and_then(|r| r.rename_to.clone()).or(tx.description);
+    // A subcategoria só vale dentro da categoria final: a da regra acompanha a categoria da regra,
+    // a do banco é descartada quando a regra ou o classificador trocam a categoria
+    let base_subcategory = base_subcategory.filter(|_| category == base_category);
+    let subcategory = match rule.filter(|r| r.set_category.is_some()) {
+        Some(rule) => rule.set_subcategory.clone().or(base_subcategory),
+        None => base_subcategory,
+    };
+
+    let row = sqlx::query!(
+        r#"
+        INSERT INTO transactions (
+            pluggy_transaction_id, account_id, item_id, user_id,
+            amount, date, description, category, subcategory, currency, status, merchant, balance, rule_id,
+            predicted_category, prediction_confidence, merchant_id, merchant_key
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18)
+        ON CONFLICT (pluggy_transaction_id) DO UPDATE SET
+            amount = EXCLUDED.amount,
+            date = EXCLUDED.date,
+            -- Notas, tags e campos editados pelo usuário são preservados
+            description = CASE WHEN 'description' = ANY(transactions.user_modified_fields)
+                THEN transactions.description ELSE EXCLUDED.description END,
+            -- Uma subcategoria escolhida pelo usuário prende também a categoria
+            category = CASE WHEN transactions.user_modified_fields && ARRAY['category', 'subcategory']
+                THEN transactions.category ELSE EXCLUDED.category END,
+            subcategory = CASE WHEN 'subcategory' = ANY(transactions.user_modified_fields)
+                    THEN transactions.subcategory
+                WHEN 'category' = ANY(transactions.user_modified_fields)
+                    AND transactions.category IS DISTINCT FROM EXCLUDED.category THEN NULL
+                ELSE EXCLUDED.subcategory END,
+            currency = EXCLUDED.currency,
+            status = EXCLUDED.status,
+            merchant = EXCLUDED.merchant,
+            merchant_id = CASE WHEN 'merchant_id' = ANY(transactions.user_modified_fields)
+                THEN transactions.merchant_id ELSE EXCLUDED.merchant_id END,
+            merchant_key = EXCLUDED.merchant_key,
+            balance = EXCLUDED.balance,
+            rule_id = EXCLUDED.rule_id,
+            predicted_category = EXCLUDED.predicted_category,
+            prediction_confidence = EXCLUDED.prediction_confidence,
+            updated_at = CURRENT_TIMESTAMP
+        RETURNING id, (xmax = 0) AS "inserted!"
+        "#,
+        tx.pluggy_transaction_id,
+        tx.account_id,
+        tx.item_id,
+        tx.user_id,
+        tx.amount,
+        tx.date,
+        description,
+        category,
+        subcategory,
+        tx.currency,
+        tx.status,
+        tx.merchant,
+        tx.balance,
+        rule.map(|r| r.id),
+        prediction.as_ref().map(|p| p.category.clone()),
+        prediction.as_ref().map(|p| p.confidence as f32),
+        merchant_id,
+        merchant_key
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    // A tag da regra só é adicionada na chegada da transação, para que o usuário possa removê-la
+    if let (true, Some(tag)) = (row.inserted, rule.and_then(|r| r.add_tag.as_deref())) {
+        add_tag(&mut conn, tx.user_id, row.id, tag).await?;
+    }
+
+    if let Some(info) = &tx.installment {
+        let installment = InstallmentTransaction {
+            id: row.id,
+            user_id: tx.user_id,
+            account_id: Some(tx.account_id),
+            amount: tx.amount,
+            currency: &tx.currency,
+            date: tx.date,
+            info,
+        };
+        installment_tracking::assign(&mut conn, installment).await?;
+    }
+
+    Ok(row.id)
+}
+
+async fn add_tag(conn: &mut PgConnection, user_id: Uuid, transaction_id: Uuid, tag: &str) -> sqlx::Result<()> {
+    let Some(name) = Tag::normalize_name(tag) else {
+        return Ok(());
+    };
+
+    sqlx::query!(
+        r#"
+        WITH tag AS (
+            INSERT INTO tags (user_id, name) VALUES ($1, $2)
+            ON CONFLICT (user_id, name) DO UPDATE SET name = EXCLUDED.name
+            RETURNING id
+        )
+        INSERT INTO transaction_tags (transaction_id, tag_id)
+        SELECT $3, id FROM tag
+        ON CONFLICT DO NOTHING
+        "#,
+        user_id,
+        name,
+        transaction_id
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Aplica as ações de uma regra a uma transação já existente.
+/// Retorna se algum campo da transação mudou.
+pub async fn apply_rule(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    transaction_id: Uuid,
+    rule: &CategorizationRule,
+) -> sqlx::Result<bool> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE transactions
+        SET description = CASE WHEN $1::TEXT IS NULL OR 'description' = ANY(user_modified_fields)
+                THEN description ELSE $1::TEXT END,
+            category = CASE WHEN $2::VARCHAR IS NULL OR user_modified_fields && ARRAY['category', 'subcategory']
+                THEN category ELSE $2::VARCHAR END,
+            -- A subcategoria só muda junto com a categoria e é limpa quando não pertence à nova
+            subcategory = CASE WHEN $2::VARCHAR IS NULL OR user_modified_fields && ARRAY['category', 'subcategory']
+                    THEN subcategory
+                WHEN $3::VARCHAR IS NOT NULL THEN $3::VARCHAR
+                WHEN category IS NOT DISTINCT FROM $2::VARCHAR THEN subcategory
+                ELSE NULL END,
+            rule_id = $4
+        WHERE id = $5 AND user_id = $6
+          AND (rule_id IS DISTINCT FROM $4
+               OR (description IS DISTINCT FROM COALESCE($1::TEXT, description) AND NOT 'description' = ANY(user_modified_fields))
+               OR ($2::VARCHAR IS NOT NULL AND NOT user_modified_fields && ARRAY['category', 'subcategory']
+                   AND (category IS DISTINCT FROM $2::VARCHAR
+                        OR ($3::VARCHAR IS NOT NULL AND subcategory IS DISTINCT FROM $3::VARCHAR))))
+        "#,
+        rule.rename_to,
+        rule.set_category,
+        rule.set_subcategory,
+        rule.id,
+        transaction_id,
+        user_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    if let Some(tag) = rule.add_tag.as_deref() {
+        add_tag(conn, user_id, transaction_id, tag).await?;
+    }
+
+    Ok(result.rows_affected() > 0)
+}
@@ -0,0 +1,311 @@
+use crate::models::installment::{InstallmentInfo, InstallmentPurchase};
+use crate::pluggy::models::CreditCardMetadata;
+use crate::recurrence::series_key;
+use chrono::{DateTime, Months, NaiveDate, Utc};
+use rust_decimal::prelude::FromPrimitive;
+use rust_decimal::Decimal;
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+// Palavras que antecedem a indicação da parcela ("PARC 03/10", "PARCELA 3/10")
+const KEYWORDS: &[&str] = &["parcela", "parc.", "parc", "prc", "pc"];
+
+/// Lê a indicação de parcela de uma descrição: "LOJA PARC 03/10", "LOJA PARCELA 3/10" ou "LOJA 03/10" no final.
+/// Sem a palavra-chave ("UBER 03/10", "PIX ENVIADO 05/12"), o número só é aceito no fim da descrição
+/// de uma compra no cartão (`card_charge`), onde a fatura costuma omitir o "PARC".
+pub fn parse_description(description: &str, card_charge: bool) -> Option<InstallmentInfo> {
+    let bytes = description.as_bytes();
+
+    for (slash, _) in description.match_indices('/') {
+        // Número antes da barra, ignorando espaços
+        let mut left_end = slash;
+        while left_end > 0 && bytes[left_end - 1] == b' ' {
+            left_end -= 1;
+        }
+        let mut left_start = left_end;
+        while left_start > 0 && bytes[left_start - 1].is_ascii_digit() {
+            left_start -= 1;
+        }
+
+        // Número depois da barra
+        let mut right_start = slash + 1;
+        while right_start < bytes.len() && bytes[right_start] == b' ' {
+            right_start += 1;
+        }
+        let mut right_end = right_start;
+        while right_end < bytes.len() && bytes[right_end].is_ascii_digit() {
+            right_end += 1;
+        }
+
+        let (left_len, right_len) = (left_end - left_start, right_end - right_start);
+        if !(1..=2).contains(&left_len) || !(1..=2).contains(&right_len) {
+            continue;
+        }
+        // Datas como 10/03/2024
+        if (left_start > 0 && bytes[left_start - 1] == b'/') || bytes.get(right_end) == Some(&b'/') {
+            continue;
+        }
+
+        let number: i32 = description[left_start..left_end].parse().ok()?;
+        let total: i32 = description[right_start..right_end].parse().ok()?;
+        if number < 1 || total < 2 || number > total {
+            continue;
+        }
+
+        let before = description[..left_start].trim_end();
+        let keyword = KEYWORDS.iter().find(|k| {
+            let Some(start) = before.len().checked_sub(k.len()).filter(|s| before.is_char_boundary(*s)) else {
+                return false;
+            };
+            before[start..].eq_ignore_ascii_case(k) && before[..start].chars().last().is_none_or(|c| !c.is_alphanumeric())
+        });
+        let after = description[right_end..].trim_start_matches([')', ' ']);
+
+        let prefix = match keyword {
+            Some(keyword) => &before[..before.len() - keyword.len()],
+            None if card_charge && after.is_empty() => before,
+            None => continue,
+        };
+
+        let cleaned = format!("{} {}", prefix.trim_end_matches(['(', '-', ' ']), after);
+        let description = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+
+        return Some(InstallmentInfo { number, total, description, total_amount: None, purchase_date: None });
+    }
+
+    None
+}
+
+/// Compra no cartão de crédito: valor negativo em conta do tipo CREDIT
+pub fn is_card_charge(account_type: Option<&str>, amount: Decimal) -> bool {
+    account_type == Some("CREDIT") && amount < Decimal::ZERO
+}
+
+/// Parcela de uma transação da Pluggy: metadados do cartão quando existirem, senão a descrição
+pub fn from_pluggy(
+    description: Option<&str>,
+    metadata: Option<&CreditCardMetadata>,
+    card_charge: bool,
+) -> Option<InstallmentInfo> {
+    let parsed = description.and_then(|d| parse_description(d, card_charge));
+
+    let Some(metadata) = metadata else {
+        return parsed;
+    };
+    let (Some(number), Some(total)) = (metadata.installment_number, metadata.total_installments) else {
+        return parsed;
+    };
+    if number < 1 || total < 2 || number > total {
+        return parsed;
+    }
+
+    let description = parsed
+        .map(|p| p.description)
+        .or_else(|| description.map(str::to_string))
+        .unwrap_or_default();
+    let purchase_date = metadata
+        .purchase_date
+        .as_deref()
+        .and_then(|d| d.get(0..10))
+        .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok());
+
+    Some(InstallmentInfo {
+        number,
+        total,
+        description,
+        total_amount: metadata.total_amount.and_then(Decimal::from_f64).map(|a| a.abs()),
+        purchase_date,
+    })
+}
+
+/// Data da parcela `number` de uma compra cuja primeira parcela é `first`
+pub fn installment_date(first: NaiveDate, number: i32) -> Option<NaiveDate> {
+    first.checked_add_months(Months::new((number - 1).max(0) as u32))
+}
+
+/// Transação recebida que contém uma parcela
+pub struct InstallmentTransaction<'a> {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub account_id: Option<Uuid>,
+    pub amount: Decimal,
+    pub currency: &'a str,
+    pub date: NaiveDate,
+    pub info: &'a InstallmentInfo,
+}
+
+/// Associa a parcela à compra parcelada correspondente, criando a compra na primeira parcela vista.
+/// Transações já associadas não mudam.
+pub async fn assign(conn: &mut PgConnection, tx: InstallmentTransaction<'_>) -> sqlx::Result<()> {
+    let assigned = sqlx::query_scalar!(
+        "SELECT installment_purchase_id FROM transactions WHERE id = $1",
+        tx.id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    if assigned.is_some() {
+        return Ok(());
+    }
+
+    let info = tx.info;
+    let installment_amount = tx.amount.abs();
+    let description_key = series_key(Some(&info.description), None).unwrap_or_default();
+    // Estimativa: uma parcela por mês a partir da primeira
+    let first_installment_date = tx
+        .date
+        .checked_sub_months(Months::new((info.number - 1) as u32))
+        .unwrap_or(tx.date);
+
+    // Mesma compra: mesma conta, descrição, quantidade e valor de parcela, com data da compra igual
+    // ou primeira parcela próxima, e que ainda não tenha esta parcela
+    let existing = sqlx::query_scalar!(
+        r#"
+        SELECT p.id
+        FROM installment_purchases p
+        WHERE p.user_id = $1 AND p.account_id IS NOT DISTINCT FROM $2 AND p.description_key = $3
+          AND p.installment_count = $4 AND ABS(p.installment_amount - $5) <= 0.05
+          AND (($6::DATE IS NOT NULL AND p.purchase_date = $6) OR ABS(p.first_installment_date - $7) <= 5)
+          AND NOT EXISTS (
+              SELECT 1 FROM transactions t WHERE t.installment_purchase_id = p.id AND t.installment_number = $8
+          )
+        ORDER BY ABS(p.first_installment_date - $7)
+        LIMIT 1
+        "#,
+        tx.user_id,
+        tx.account_id,
+        description_key,
+        info.total,
+        installment_amount,
+        info.purchase_date,
+        first_installment_date,
+        info.number
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let purchase_id = match existing {
+        Some(id) => id,
+        None => {
+            let total_amount = info.total_amount.unwrap_or(installment_amount * Decimal::from(info.total));
+            sqlx::query_scalar!(
+                r#"
+                INSERT INTO installment_purchases (
+                    user_id, account_id, description, description_key, currency, installment_count,
+                    installment_amount, total_amount, purchase_date, first_installment_date
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                RETURNING id
+                "#,
+                tx.user_id,
+                tx.account_id,
+                info.description,
+                description_key,
+                tx.currency,
+                info.total,
+                installment_amount,
+                total_amount,
+                info.purchase_date,
+                first_installment_date
+            )
+            .fetch_one(&mut *conn)
+            .await?
+        }
+    };
+
+    sqlx::query!(
+        r#"
+        UPDATE transactions
+        SET installment_purchase_id = $1, installment_number = $2, installment_total = $3
+        WHERE id = $4
+        "#,
+        purchase_id,
+        info.number,
+        info.total,
+        tx.id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Linha de `installment_purchases` com a maior parcela já recebida
+pub struct PurchaseRow {
+    pub id: Uuid,
+    pub description: String,
+    pub account_id: Option<Uuid>,
+    pub currency: String,
+    pub installment_count: i32,
+    pub installment_amount: Decimal,
+    pub total_amount: Decimal,
+    pub purchase_date: Option<NaiveDate>,
+    pub first_installment_date: NaiveDate,
+    pub paid_installments: i32,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl From<PurchaseRow> for InstallmentPurchase {
+    fn from(row: PurchaseRow) -> Self {
+        let remaining_installments = (row.installment_count - row.paid_installments).max(0);
+        let next_installment_date = (remaining_installments > 0)
+            .then(|| installment_date(row.first_installment_date, row.paid_installments + 1))
+            .flatten();
+
+        InstallmentPurchase {
+            id: row.id,
+            description: row.description,
+            account_id: row.account_id,
+            currency: row.currency,
+            installment_count: row.installment_count,
+            installment_amount: row.installment_amount,
+            total_amount: row.total_amount,
+            purchase_date: row.purchase_date,
+            first_installment_date: row.first_installment_date,
+            last_installment_date: installment_date(row.first_installment_date, row.installment_count)
+                .unwrap_or(row.first_installment_date),
+            paid_installments: row.paid_installments,
+            remaining_installments,
+            remaining_amount: row.installment_amount * Decimal::from(remaining_installments),
+            next_installment_date,
+            created_at: row.created_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_description;
+
+    fn parsed(description: &str, card_charge: bool) -> Option<(i32, i32, String)> {
+        parse_description(description, card_charge).map(|i| (i.number, i.total, i.description))
+    }
+
+    #[test]
+    fn keyword_is_required_outside_card_charges() {
+        assert_eq!(parsed("UBER 03/10", false), None);
+        assert_eq!(parsed("PIX ENVIADO 05/12", false), None);
+        assert_eq!(parsed("PAG*JOSEDASILVA 03/12", false), None);
+    }
+
+    #[test]
+    fn keyword_marks_installment() {
+        assert_eq!(parsed("LOJA PARC 03/10", false), Some((3, 10, "LOJA".to_string())));
+        assert_eq!(parsed("LOJA PARCELA 3/10", false), Some((3, 10, "LOJA".to_string())));
+        assert_eq!(parsed("LOJA (PARC 03/10) SP", true), Some((3, 10, "LOJA SP".to_string())));
+    }
+
+    #[test]
+    fn bare_number_only_at_end_of_card_charge() {
+        assert_eq!(parsed("LOJA 03/10", true), Some((3, 10, "LOJA".to_string())));
+        assert_eq!(parsed("LOJA 03/10 SP", true), None);
+    }
+
+    #[test]
+    fn dates_are_not_installments() {
+        assert_eq!(parsed("PAGAMENTO 10/03/2024", true), None);
+        assert_eq!(parsed("COMPRA 2024/03/10", true), None);
+        assert_eq!(parsed("LOJA PARC 10/03/2024", false), None);
+        assert_eq!(parsed("VENCIMENTO 15/03", false), None);
+        assert_eq!(parsed("LOJA 12/03", true), None);
+    }
+}
@@ -0,0 +1,307 @@
+use crate::secrets::SecretCipher;
+use anyhow::{Context, Result};
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine as _,
+};
+use chrono::{DateTime, Duration, Utc};
+use ed25519_dalek::pkcs8::EncodePrivateKey;
+use ed25519_dalek::SigningKey;
+use jsonwebtoken::{decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::time::{Duration as StdDuration, Instant};
+use tokio::sync::{Mutex, RwLock};
+use uuid::Uuid;
+
+/// Validade dos JWTs de sessão. Uma chave aposentada continua validando por esse período.
+pub const TOKEN_LIFETIME_HOURS: i64 = 24;
+
+// Intervalo mínimo entre recargas das chaves motivadas por um `kid` desconhecido,
+// para que tokens forjados com `kid` aleatório não gerem uma consulta ao banco por requisição
+const UNKNOWN_KID_RELOAD_INTERVAL: StdDuration = StdDuration::from_secs(30);
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: String,
+    pub exp: usize,
+    pub iat: usize,
+}
+
+struct LoadedKey {
+    kid: String,
+    public_key: String,
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    created_at: DateTime<Utc>,
+    retired_at: Option<DateTime<Utc>>,
+}
+
+/// Chaves EdDSA (Ed25519) de assinatura dos JWTs, identificadas por `kid`.
+///
+/// As chaves ficam na tabela `jwt_signing_keys` para serem compartilhadas entre instâncias.
+/// A chave ativa mais recente assina novos tokens; ao rotacionar, a anterior é aposentada
+/// e continua aceita até que os tokens assinados por ela expirem.
+/// Com `SECRETS_KEY` configurada, as chaves privadas ficam cifradas no banco.
+pub struct KeyStore {
+    keys: RwLock<Vec<LoadedKey>>,
+    rotation_days: i64,
+    legacy_secret: Option<String>,
+    secrets: Option<SecretCipher>,
+    // Última recarga por `kid` desconhecido
+    unknown_kid_reload: Mutex<Option<Instant>>,
+}
+
+impl KeyStore {
+    pub async fn load(
+        pool: &PgPool,
+        rotation_days: i64,
+        legacy_secret: Option<String>,
+        secrets_key: Option<&str>,
+    ) -> Result<Self> {
+        let secrets = match secrets_key {
+            Some(key) => Some(SecretCipher::from_base64(key).context("SECRETS_KEY inválida")?),
+            None => {
+                eprintln!("SECRETS_KEY não configurada: chaves privadas JWT serão guardadas sem cifra");
+                None
+            }
+        };
+
+        let store = KeyStore {
+            keys: RwLock::new(Vec::new()),
+            rotation_days,
+            legacy_secret,
+            secrets,
+            unknown_kid_reload: Mutex::new(None),
+        };
+        store.encrypt_stored_keys(pool).await?;
+        store.rotate_if_needed(pool).await?;
+        Ok(store)
+    }
+
+    // Cifra as chaves gravadas antes de `SECRETS_KEY` ser configurada
+    async fn encrypt_stored_keys(&self, pool: &PgPool) -> Result<()> {
+        let Some(secrets) = &self.secrets else {
+            return Ok(());
+        };
+
+        let rows = sqlx::query!("SELECT kid, private_key FROM jwt_signing_keys").fetch_all(pool).await?;
+        for row in rows.into_iter().filter(|row| !SecretCipher::is_encrypted(&row.private_key)) {
+            sqlx::query!(
+                "UPDATE jwt_signing_keys SET private_key = $1 WHERE kid = $2 AND private_key = $3",
+                secrets.encrypt(&row.private_key)?,
+                row.kid,
+                row.private_key
+            )
+            .execute(pool)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    fn seal_private_key(&self, der: &[u8]) -> Result<String> {
+        let encoded = STANDARD.encode(der);
+        match &self.secrets {
+            Some(secrets) => secrets.encrypt(&encoded),
+            None => Ok(encoded),
+        }
+    }
+
+    fn open_private_key(&self, stored: &str) -> Result<Vec<u8>> {
+        let encoded = match (&self.secrets, SecretCipher::is_encrypted(stored)) {
+            (Some(secrets), true) => secrets.decrypt(stored)?,
+            (None, true) => return Err(anyhow::anyhow!("chave cifrada e SECRETS_KEY não configurada")),
+            (_, false) => stored.to_string(),
+        };
+        Ok(STANDARD.decode(encoded)?)
+    }
+
+    /// Recarrega as chaves do banco, gera uma nova chave ativa quando a atual passou do
+    /// prazo de rotação e remove chaves aposentadas que não validam mais nenhum token
+    pub async fn rotate_if_needed(&self, pool: &PgPool) -> Result<()> {
+        sqlx::query!(
+            "DELETE FROM jwt_signing_keys WHERE retired_at < $1",
+            Utc::now() - Duration::hours(TOKEN_LIFETIME_HOURS)
+        )
+        .execute(pool)
+        .await?;
+
+        self.reload(pool).await?;
+
+        let needs_rotation = {
+            let keys = self.keys.read().await;
+            match keys.iter().find(|k| k.retired_at.is_none()) {
+                Some(active) => active.created_at < Utc::now() - Duration::days(self.rotation_days),
+                None => true,
+            }
+        };
+
+        if needs_rotation {
+            self.rotate(pool).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Gera uma nova chave ativa e aposenta as anteriores
+    pub async fn rotate(&self, pool: &PgPool) -> Result<()> {
+        let signing_key = SigningKey::generate(&mut OsRng);
+        let private_der = signing_key.to_pkcs8_der()?;
+        let kid = Uuid::new_v4().simple().to_string();
+
+        let mut tx = pool.begin().await?;
+
+        sqlx::query!(
+            "UPDATE jwt_signing_keys SET retired_at = CURRENT_TIMESTAMP WHERE retired_at IS NULL"
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            "INSERT INTO jwt_signing_keys (kid, algorithm, private_key, public_key) VALUES ($1, 'EdDSA', $2, $3)",
+            kid,
+            self.seal_private_key(private_der.as_bytes())?,
+            URL_SAFE_NO_PAD.encode(signing_key.verifying_key().as_bytes())
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        println!("✓ Nova chave de assinatura JWT gerada (kid: {})", kid);
+        self.reload(pool).await
+    }
+
+    async fn reload(&self, pool: &PgPool) -> Result<()> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT kid, private_key, public_key, created_at as "created_at!", retired_at
+            FROM jwt_signing_keys
+            ORDER BY created_at DESC
+            "#
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let mut loaded = Vec::with_capacity(rows.len());
+        for row in rows {
+            // Uma chave que não pode ser aberta (ex.: `SECRETS_KEY` trocada) é ignorada; sem chave ativa,
+            // a rotação gera outra
+            let private_der = match self.open_private_key(&row.private_key) {
+                Ok(der) => der,
+                Err(e) => {
+                    eprintln!("✗ Chave de assinatura JWT {} ignorada: {}", row.kid, e);
+                    continue;
+                }
+            };
+
+            loaded.push(LoadedKey {
+                encoding_key: EncodingKey::from_ed_der(&private_der),
+                decoding_key: DecodingKey::from_ed_components(&row.public_key)?,
+                kid: row.kid,
+                public_key: row.public_key,
+                created_at: row.created_at,
+                retired_at: row.retired_at,
+            });
+        }
+
+        *self.keys.write().await = loaded;
+        Ok(())
+    }
+
+    /// Gera o JWT de sessão assinado com a chave ativa
+    pub async fn issue_token(&self, user_id: Uuid) -> Result<String> {
+        let now = Utc::now();
+        let claims = Claims {
+            sub: user_id.to_string(),
+            exp: (now + Duration::hours(TOKEN_LIFETIME_HOURS)).timestamp() as usize,
+            iat: now.timestamp() as usize,
+        };
+
+        let keys = self.keys.read().await;
+        let active = keys
+            .iter()
+            .find(|k| k.retired_at.is_none())
+            .ok_or_else(|| anyhow::anyhow!("Nenhuma chave de assinatura ativa"))?;
+
+        let mut header = Header::new(Algorithm::EdDSA);
+        header.kid = Some(active.kid.clone());
+
+        Ok(encode(&header, &claims, &active.encoding_key)?)
+    }
+
+    /// Valida um JWT de sessão pela chave indicada no `kid`
+    pub async fn verify(&self, pool: &PgPool, token: &str) -> Result<Claims> {
+        let header = decode_header(token)?;
+
+        let Some(kid) = header.kid else {
+            return self.verify_legacy(token);
+        };
+
+        // Chave desconhecida pode ter sido criada por outra instância
+        if !self.is_known(&kid).await {
+            self.reload_for_unknown_kid(pool, &kid).await?;
+        }
+
+        let keys = self.keys.read().await;
+        let key = keys
+            .iter()
+            .find(|k| k.kid == kid)
+            .ok_or_else(|| anyhow::anyhow!("Chave {} desconhecida", kid))?;
+
+        Ok(decode::<Claims>(token, &key.decoding_key, &Validation::new(Algorithm::EdDSA))?.claims)
+    }
+
+    async fn is_known(&self, kid: &str) -> bool {
+        self.keys.read().await.iter().any(|k| k.kid == kid)
+    }
+
+    // Recarrega as chaves no máximo uma vez a cada `UNKNOWN_KID_RELOAD_INTERVAL`; requisições
+    // concorrentes esperam a recarga em andamento em vez de repeti-la
+    async fn reload_for_unknown_kid(&self, pool: &PgPool, kid: &str) -> Result<()> {
+        let mut last_reload = self.unknown_kid_reload.lock().await;
+        if self.is_known(kid).await || last_reload.is_some_and(|at| at.elapsed() < UNKNOWN_KID_RELOAD_INTERVAL) {
+            return Ok(());
+        }
+
+        *last_reload = Some(Instant::now());
+        self.reload(pool).await
+    }
+
+    // Tokens HS256 emitidos antes da migração para chaves assimétricas (sem `kid`)
+    fn verify_legacy(&self, token: &str) -> Result<Claims> {
+        let secret = self
+            .legacy_secret
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Token sem kid e JWT_SECRET não configurado"))?;
+
+        Ok(decode::<Claims>(
+            token,
+            &DecodingKey::from_secret(secret.as_bytes()),
+            &Validation::new(Algorithm::HS256),
+        )?
+        .claims)
+    }
+
+    /// Chaves públicas em formato JWKS para que outros serviços validem os tokens
+    pub async fn jwks(&self) -> serde_json::Value {
+        let keys = self.keys.read().await;
+        let jwks: Vec<_> = keys
+            .iter()
+            .map(|k| {
+                serde_json::json!({
+                    "kty": "OKP",
+                    "crv": "Ed25519",
+                    "alg": "EdDSA",
+                    "use": "sig",
+                    "kid": k.kid,
+                    "x": k.public_key,
+                })
+            })
+            .collect();
+
+        serde_json::json!({ "keys": jwks })
+    }
+}
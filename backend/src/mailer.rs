@@ -0,0 +1,52 @@
+use crate::config::SmtpConfig;
+use anyhow::Result;
+use lettre::message::{header::ContentType, Mailbox};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::sync::Arc;
+
+/// Envio de e-mails em texto simples via SMTP (STARTTLS, ou TLS direto na porta 465).
+/// Usado pelas notificações e pela confirmação de troca de e-mail.
+pub struct Mailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl Mailer {
+    pub fn new(config: &SmtpConfig) -> Result<Self> {
+        let builder = if config.port == 465 {
+            AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host)?
+        } else {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)?
+        };
+        let mut builder = builder.port(config.port);
+        if let (Some(username), Some(password)) = (&config.username, &config.password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        Ok(Mailer { transport: builder.build(), from: config.from.parse()? })
+    }
+
+    pub async fn send(&self, name: &str, email: &str, subject: &str, body: String) -> Result<()> {
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(Mailbox::new(Some(name.to_string()), email.parse()?))
+            .subject(subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(body)?;
+
+        self.transport.send(message).await?;
+        Ok(())
+    }
+}
+
+/// Mailer configurado pelo SMTP do ambiente; sem SMTP (ou com configuração inválida), não há envio de e-mails
+pub fn from_config(config: Option<&SmtpConfig>) -> Option<Arc<Mailer>> {
+    match Mailer::new(config?) {
+        Ok(mailer) => Some(Arc::new(mailer)),
+        Err(e) => {
+            eprintln!("✗ Configuração de SMTP inválida, envio de e-mails desativado: {}", e);
+            None
+        }
+    }
+}
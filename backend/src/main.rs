@@ -1,13 +1,36 @@
+mod alerts;
+mod balance_history;
+mod budget_planning;
+mod cash_flow;
+mod classifier;
 mod config;
+mod data_export;
+mod goal_tracking;
+mod import;
+mod ingest;
+mod installment_tracking;
+mod jwt_keys;
+mod mailer;
+mod merchant_matching;
 mod models;
+mod notifier;
+mod oidc;
 mod pluggy;
+mod recurrence;
+mod reporting;
 mod routes;
 mod scheduler;
+mod secrets;
+mod storage;
+mod taxonomy;
+mod transaction_export;
+mod transfer_detection;
 
 use config::AppConfig;
+use jwt_keys::KeyStore;
 use pluggy::client::PluggyClient;
 use pluggy::models::ConnectTokenResponse;
-use routes::{auth, transactions, items, accounts, webhooks};
+use routes::{auth, transactions, items, accounts, webhooks, tokens, profile, imports, splits, tags, attachments, rules, category_suggestions, categories, transfers, recurring, installments, merchants, budgets, goals, net_worth, forecast, reports, notifications};
 use dotenvy::dotenv;
 use rocket::{get, post, routes, State, serde::json::Json, http::Status};
 use rocket_cors::{CorsOptions, AllowedOrigins};
@@ -21,17 +44,7 @@ fn health() -> &'static str {
 
 #[post("/pluggy/connect-token")]
 async fn create_connect_token(config: &State<Arc<AppConfig>>) -> Result<Json<ConnectTokenResponse>, (Status, String)> {
-    let pluggy_config = AppConfig {
-        client_id: config.client_id.clone(),
-        client_secret: config.client_secret.clone(),
-        environment: config.environment.clone(),
-        base_url: config.base_url.clone(),
-        database_url: config.database_url.clone(),
-        jwt_secret: config.jwt_secret.clone(),
-        admin_email: None,
-        admin_password: None,
-        admin_name: None,
-    };
+    let pluggy_config = config.inner().as_ref().clone();
     
     let mut pluggy_client = PluggyClient::new(pluggy_config);
     
@@ -80,6 +93,41 @@ async fn main() -> Result<(), rocket::Error> {
         }
     };
 
+    // Carrega (ou gera) as chaves de assinatura dos JWTs
+    let key_store = match KeyStore::load(
+        &pool,
+        app_config.jwt_key_rotation_days,
+        app_config.jwt_secret.clone(),
+        app_config.secrets_key.as_deref(),
+    )
+    .await
+    {
+        Ok(keys) => {
+            println!("✓ Chaves de assinatura JWT carregadas");
+            Arc::new(keys)
+        }
+        Err(e) => {
+            eprintln!("✗ Erro ao carregar chaves de assinatura JWT: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // Armazenamento dos anexos das transações
+    let storage = storage::from_config(&app_config);
+    match &app_config.s3 {
+        Some(s3) => println!("✓ Anexos armazenados no bucket S3 {} ({})", s3.bucket, s3.endpoint),
+        None => println!("✓ Anexos armazenados em {}", app_config.attachments_dir),
+    }
+
+    // Envio de e-mails (notificações e confirmação de troca de e-mail), quando o SMTP está configurado
+    let mailer = mailer::from_config(app_config.smtp.as_ref());
+
+    // Canais de entrega das notificações
+    let notifier = notifier::from_config(&app_config, mailer.clone());
+
+    // Cliente do provedor OIDC, com metadados e chaves em cache entre as requisições
+    let oidc_client = app_config.oidc.clone().map(|config| Arc::new(oidc::OidcClient::new(config)));
+
     // Seed Admin User se credenciais estiverem presentes
     if let (Some(email), Some(password), Some(name)) = (
         &app_config.admin_email,
@@ -120,20 +168,10 @@ async fn main() -> Result<(), rocket::Error> {
     }
 
     // Testa conexão com Pluggy
-    let test_config = AppConfig {
-        client_id: app_config.client_id.clone(),
-        client_secret: app_config.client_secret.clone(),
-        environment: app_config.environment.clone(),
-        base_url: app_config.base_url.clone(),
-        database_url: app_config.database_url.clone(),
-        jwt_secret: app_config.jwt_secret.clone(),
-        admin_email: None,
-        admin_password: None,
-        admin_name: None,
-    };
+    let test_config = app_config.as_ref().clone();
     let mut test_client = PluggyClient::new(test_config);
     
-    println!("Testando conexão com Pluggy API...");
+    println!("Testando conexão com Pluggy API (ambiente: {})...", app_config.environment);
     match test_client.test_connection().await {
         Ok(message) => {
             println!("✓ {}", message);
@@ -152,6 +190,7 @@ async fn main() -> Result<(), rocket::Error> {
                 rocket::http::Method::Get, 
                 rocket::http::Method::Post, 
                 rocket::http::Method::Put, 
+                rocket::http::Method::Patch,
                 rocket::http::Method::Delete,
                 rocket::http::Method::Options
             ]
@@ -167,29 +206,125 @@ async fn main() -> Result<(), rocket::Error> {
         });
 
     // Iniciar scheduler
-    scheduler::start_scheduler(pool.clone(), app_config.clone());
+    scheduler::start_scheduler(pool.clone(), app_config.clone(), key_store.clone(), storage.clone(), notifier.clone());
 
     println!("\nIniciando servidor Rocket na porta 8000...");
     
     let _rocket = rocket::build()
         .manage(app_config)
         .manage(pool)
+        .manage(key_store)
+        .manage(storage)
+        .manage(notifier)
+        .manage(mailer)
+        .manage(oidc_client)
         .attach(cors)
         .mount("/api", routes![
             health, 
             create_connect_token, 
             auth::register, 
             auth::login,
+            auth::jwks,
+            auth::oidc_authorize,
+            auth::oidc_callback,
             transactions::get_transactions,
+            transactions::search_transactions,
+            transactions::export_transactions,
             transactions::create_transaction,
             transactions::delete_transaction,
             transactions::update_transaction,
+            transactions::annotate_transaction,
+            tags::get_tags,
+            tags::delete_tag,
+            splits::get_splits,
+            splits::replace_splits,
+            splits::delete_splits,
+            attachments::upload_attachment,
+            attachments::get_attachments,
+            attachments::download_attachment,
+            attachments::download_thumbnail,
+            attachments::delete_attachment,
+            rules::get_rules,
+            rules::create_rule,
+            rules::update_rule,
+            rules::delete_rule,
+            rules::apply_rule,
+            rules::apply_all_rules,
+            category_suggestions::get_classifier_status,
+            category_suggestions::train_classifier,
+            category_suggestions::get_category_suggestions,
+            categories::get_categories,
+            categories::create_category,
+            categories::update_category,
+            categories::delete_category,
+            categories::merge_category,
+            categories::get_category_mappings,
+            categories::set_category_mapping,
+            categories::delete_category_mapping,
+            transfers::get_transfers,
+            transfers::create_transfer,
+            transfers::delete_transfer,
+            transfers::detect_transfers,
+            recurring::get_recurring,
+            recurring::get_recurring_transactions,
+            recurring::create_recurring_transaction,
+            recurring::update_recurring_transaction,
+            recurring::delete_recurring_transaction,
+            installments::get_installments,
+            installments::get_installment,
+            installments::get_installment_projection,
+            merchants::get_merchants,
+            merchants::get_merchant,
+            merchants::update_merchant,
+            merchants::add_merchant_alias,
+            merchants::delete_merchant_alias,
+            merchants::merge_merchant,
+            merchants::link_merchants,
+            budgets::get_budget_month,
+            budgets::create_budget,
+            budgets::update_budget,
+            budgets::delete_budget,
+            goals::get_goals,
+            goals::get_goal,
+            goals::create_goal,
+            goals::update_goal,
+            goals::delete_goal,
+            goals::get_goal_contributions,
+            goals::create_goal_contribution,
+            goals::delete_goal_contribution,
+            notifications::get_notifications,
+            notifications::mark_notification_read,
+            notifications::mark_all_notifications_read,
+            notifications::delete_notification,
+            notifications::get_notification_settings,
+            notifications::update_notification_settings,
+            notifications::send_test_notification,
+            notifications::get_push_public_key,
+            notifications::create_push_subscription,
+            notifications::delete_push_subscription,
+            imports::import_transactions,
+            imports::get_import_batches,
+            imports::undo_import,
             items::create_item,
             accounts::get_total_balance,
             accounts::get_total_expenses,
             accounts::get_monthly_expenses,
             accounts::get_accounts,
-            webhooks::handle_pluggy_webhook
+            net_worth::get_net_worth,
+            forecast::get_forecast,
+            reports::get_report,
+            webhooks::handle_pluggy_webhook,
+            tokens::create_api_token,
+            tokens::get_api_tokens,
+            tokens::revoke_api_token,
+            profile::get_me,
+            profile::update_me,
+            profile::verify_email,
+            profile::change_password,
+            profile::delete_me,
+            profile::get_my_export,
+            profile::create_my_export,
+            profile::download_export
         ])
         .launch()
         .await?;
@@ -0,0 +1,249 @@
+use crate::models::categorization_rule::fold_text;
+use crate::pluggy::models::Merchant as PluggyMerchant;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+// Intermediadores de pagamento e tipos de lançamento que antecedem o nome do estabelecimento
+// ("PAG*JOSEDASILVA", "PIX ENVIADO FULANO", "COMPRA NO DEBITO PADARIA")
+const PREFIX_WORDS: &[&str] = &[
+    "pag", "pagseguro", "pg", "mp", "mercadopago", "mercpago", "paypal", "ebanx", "ebn", "sumup", "picpay", "ifd",
+    "sq", "pix", "ted", "doc", "tef", "compra", "compras", "debito", "credito", "cartao", "pagamento", "pagto",
+    "enviado", "enviada", "recebido", "recebida", "transf", "transferencia", "no", "na", "em", "com",
+];
+// Indicação de parcela: o que vem depois não faz parte do nome
+const STOP_WORDS: &[&str] = &["parc", "parcela", "prc"];
+const MAX_NAME_WORDS: usize = 4;
+
+/// Nome de estabelecimento extraído de uma descrição
+#[derive(Debug, Clone, PartialEq)]
+pub struct NormalizedName {
+    // Para exibição ("Padaria São João")
+    pub display: String,
+    // Sem acentos e em minúsculas, usado como alias e para comparar nomes
+    pub key: String,
+}
+
+/// Extrai o nome do estabelecimento de uma descrição do extrato, sem prefixos de pagamento,
+/// datas, números e indicação de parcela: "PAG*JOSEDASILVA 12/03" → "Josedasilva"
+pub fn normalize(description: &str) -> Option<NormalizedName> {
+    let words: Vec<&str> = description.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()).collect();
+
+    let prefix = words.iter().take_while(|w| PREFIX_WORDS.contains(&fold_text(w).as_str())).count();
+    // Descrição só com prefixos ("PAGAMENTO") é usada inteira
+    let words = if prefix < words.len() { &words[prefix..] } else { &words[..] };
+
+    let kept: Vec<&str> = words
+        .iter()
+        .copied()
+        .take_while(|w| !STOP_WORDS.contains(&fold_text(w).as_str()))
+        .filter(|w| w.chars().count() >= 2 && !w.chars().any(|c| c.is_ascii_digit()))
+        .take(MAX_NAME_WORDS)
+        .collect();
+
+    if kept.is_empty() {
+        return None;
+    }
+
+    Some(NormalizedName {
+        display: kept.iter().map(|w| title_case(w)).collect::<Vec<_>>().join(" "),
+        key: kept.iter().map(|w| fold_text(w)).collect::<Vec<_>>().join(" "),
+    })
+}
+
+/// Chave do alias da descrição ("PAG*JOSEDASILVA 12/03" → "josedasilva"), guardada na transação
+/// para reassociá-la quando o alias muda de estabelecimento
+pub fn alias_key(description: Option<&str>) -> Option<String> {
+    description.and_then(normalize).map(|name| name.key)
+}
+
+// Palavras em maiúsculas viram "Capitalizadas"; as demais ficam como estão ("iFood")
+fn title_case(word: &str) -> String {
+    if word.chars().any(|c| c.is_lowercase()) {
+        return word.to_string();
+    }
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect(),
+        None => String::new(),
+    }
+}
+
+/// CNPJ apenas com dígitos, se tiver 14
+pub fn normalize_cnpj(cnpj: &str) -> Option<String> {
+    let digits: String = cnpj.chars().filter(|c| c.is_ascii_digit()).collect();
+    (digits.len() == 14).then_some(digits)
+}
+
+async fn find_alias(conn: &mut PgConnection, user_id: Uuid, alias: &str) -> sqlx::Result<Option<Uuid>> {
+    sqlx::query_scalar!(
+        "SELECT merchant_id FROM merchant_aliases WHERE user_id = $1 AND alias = $2",
+        user_id,
+        alias
+    )
+    .fetch_optional(conn)
+    .await
+}
+
+/// Estabelecimento da transação, criando-o na primeira vez que aparece.
+///
+/// A descrição normalizada e o nome informado pela Pluggy são procurados nos aliases do usuário;
+/// sem alias, vale o CNPJ da Pluggy e depois o nome (da Pluggy ou da descrição). A descrição é registrada como alias
+/// do estabelecimento encontrado, para que as próximas transações iguais caiam nele.
+pub async fn resolve(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    description: Option<&str>,
+    merchant: Option<&serde_json::Value>,
+) -> sqlx::Result<Option<Uuid>> {
+    let pluggy: Option<PluggyMerchant> = merchant.and_then(|m| serde_json::from_value(m.clone()).ok());
+    let pluggy_name = pluggy
+        .as_ref()
+        .and_then(|m| m.name.as_deref().or(m.business_name.as_deref()))
+        .and_then(normalize);
+    let cnpj = pluggy.as_ref().and_then(|m| m.cnpj.as_deref()).and_then(normalize_cnpj);
+    let alias = description.and_then(normalize);
+
+    let mut merchant_id = None;
+    for name in [&alias, &pluggy_name].into_iter().flatten() {
+        merchant_id = find_alias(&mut *conn, user_id, &name.key).await?;
+        if merchant_id.is_some() {
+            break;
+        }
+    }
+
+    if let (None, Some(cnpj)) = (merchant_id, &cnpj) {
+        merchant_id = sqlx::query_scalar!(
+            "SELECT id FROM merchants WHERE user_id = $1 AND cnpj = $2 ORDER BY created_at LIMIT 1",
+            user_id,
+            cnpj
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+    }
+
+    let merchant_id = match (merchant_id, pluggy_name.as_ref().or(alias.as_ref())) {
+        (Some(id), _) => id,
+        (None, None) => return Ok(None),
+        (None, Some(name)) => {
+            sqlx::query_scalar!(
+                r#"
+                INSERT INTO merchants (user_id, name, name_key)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (user_id, name_key) DO UPDATE SET name_key = EXCLUDED.name_key
+                RETURNING id
+                "#,
+                user_id,
+                name.display,
+                name.key
+            )
+            .fetch_one(&mut *conn)
+            .await?
+        }
+    };
+
+    // Dados da Pluggy completam o estabelecimento, sem sobrescrever o que já existe
+    if let Some(pluggy) = &pluggy {
+        sqlx::query!(
+            r#"
+            UPDATE merchants
+            SET legal_name = COALESCE(legal_name, $1), cnpj = COALESCE(cnpj, $2), category = COALESCE(category, $3)
+            WHERE id = $4
+              AND ((legal_name IS NULL AND $1::VARCHAR IS NOT NULL)
+                   OR (cnpj IS NULL AND $2::VARCHAR IS NOT NULL)
+                   OR (category IS NULL AND $3::VARCHAR IS NOT NULL))
+            "#,
+            pluggy.business_name,
+            cnpj,
+            pluggy.category,
+            merchant_id
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    if let Some(alias) = &alias {
+        add_alias(&mut *conn, user_id, merchant_id, &alias.key, false).await?;
+    }
+
+    Ok(Some(merchant_id))
+}
+
+/// Registra um alias do estabelecimento. Com `replace`, um alias de outro estabelecimento passa para este.
+pub async fn add_alias(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    merchant_id: Uuid,
+    alias: &str,
+    replace: bool,
+) -> sqlx::Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO merchant_aliases (user_id, merchant_id, alias)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (user_id, alias) DO UPDATE SET merchant_id = EXCLUDED.merchant_id
+        WHERE $4
+        "#,
+        user_id,
+        merchant_id,
+        alias,
+        replace
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Associa ao estabelecimento as transações do usuário cuja descrição normalizada é `alias`,
+/// marcando o estabelecimento como escolhido pelo usuário. Retorna quantas transações mudaram.
+pub async fn relink_alias(conn: &mut PgConnection, user_id: Uuid, merchant_id: Uuid, alias: &str) -> sqlx::Result<u64> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE transactions
+        SET merchant_id = $2,
+            user_modified_fields = ARRAY(SELECT DISTINCT field FROM unnest(user_modified_fields || ARRAY['merchant_id']) AS field)
+        WHERE user_id = $1 AND merchant_key = $3 AND merchant_id IS DISTINCT FROM $2
+        "#,
+        user_id,
+        merchant_id,
+        alias
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Associa a estabelecimentos as transações do usuário que ainda não têm um.
+/// Retorna quantas foram associadas.
+pub async fn link_unlinked(pool: &PgPool, user_id: Uuid) -> sqlx::Result<u64> {
+    let mut conn = pool.acquire().await?;
+
+    let unlinked = sqlx::query!(
+        "SELECT id, description, merchant FROM transactions WHERE user_id = $1 AND merchant_id IS NULL",
+        user_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut linked = 0;
+    for transaction in unlinked {
+        let Some(merchant_id) =
+            resolve(&mut conn, user_id, transaction.description.as_deref(), transaction.merchant.as_ref()).await?
+        else {
+            continue;
+        };
+
+        sqlx::query!(
+            "UPDATE transactions SET merchant_id = $1, merchant_key = $2 WHERE id = $3",
+            merchant_id,
+            alias_key(transaction.description.as_deref()),
+            transaction.id
+        )
+        .execute(&mut *conn)
+        .await?;
+        linked += 1;
+    }
+
+    Ok(linked)
+}
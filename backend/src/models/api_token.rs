@@ -0,0 +1,60 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// Prefixo que identifica tokens de acesso pessoal no header Authorization
+pub const TOKEN_PREFIX: &str = "fbt_";
+
+pub const SCOPE_READ: &str = "read";
+pub const SCOPE_READ_WRITE: &str = "read_write";
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ApiToken {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub token_prefix: String,
+    pub scope: String,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewApiToken {
+    pub name: String,
+    #[serde(default = "default_scope")]
+    pub scope: String,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+fn default_scope() -> String {
+    SCOPE_READ.to_string()
+}
+
+impl ApiToken {
+    /// Gera um novo token em texto puro. Retorna (token, prefixo exibível, hash).
+    pub fn generate() -> (String, String, String) {
+        let mut bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let token = format!("{}{}", TOKEN_PREFIX, URL_SAFE_NO_PAD.encode(bytes));
+        let prefix = token[..12].to_string();
+        let hash = Self::hash(&token);
+        (token, prefix, hash)
+    }
+
+    /// Hash SHA-256 (hex) usado para armazenar e buscar o token
+    pub fn hash(token: &str) -> String {
+        format!("{:x}", Sha256::digest(token.as_bytes()))
+    }
+
+    pub fn is_valid_scope(scope: &str) -> bool {
+        scope == SCOPE_READ || scope == SCOPE_READ_WRITE
+    }
+}
@@ -0,0 +1,70 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+pub const CONTENT_TYPE_JPEG: &str = "image/jpeg";
+pub const CONTENT_TYPE_PNG: &str = "image/png";
+pub const CONTENT_TYPE_WEBP: &str = "image/webp";
+pub const CONTENT_TYPE_PDF: &str = "application/pdf";
+
+pub const MAX_ATTACHMENTS_PER_TRANSACTION: i64 = 20;
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct Attachment {
+    pub id: Uuid,
+    pub transaction_id: Uuid,
+    pub file_name: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    #[serde(skip)]
+    pub storage_key: String,
+    #[serde(skip)]
+    pub thumbnail_key: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl Attachment {
+    /// Identifica o tipo pelo conteúdo (assinatura do arquivo), não pela extensão
+    pub fn detect_content_type(bytes: &[u8]) -> Option<&'static str> {
+        if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Some(CONTENT_TYPE_JPEG)
+        } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(CONTENT_TYPE_PNG)
+        } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+            Some(CONTENT_TYPE_WEBP)
+        } else if bytes.starts_with(b"%PDF-") {
+            Some(CONTENT_TYPE_PDF)
+        } else {
+            None
+        }
+    }
+
+    pub fn extension(content_type: &str) -> &'static str {
+        match content_type {
+            CONTENT_TYPE_JPEG => "jpg",
+            CONTENT_TYPE_PNG => "png",
+            CONTENT_TYPE_WEBP => "webp",
+            _ => "pdf",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AttachmentResponse {
+    #[serde(flatten)]
+    pub attachment: Attachment,
+    pub download_url: String,
+    pub thumbnail_url: Option<String>,
+}
+
+impl From<Attachment> for AttachmentResponse {
+    fn from(attachment: Attachment) -> Self {
+        let download_url = format!("/api/attachments/{}", attachment.id);
+        let thumbnail_url = attachment
+            .thumbnail_key
+            .as_ref()
+            .map(|_| format!("/api/attachments/{}/thumbnail", attachment.id));
+        AttachmentResponse { attachment, download_url, thumbnail_url }
+    }
+}
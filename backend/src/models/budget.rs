@@ -0,0 +1,78 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub const KIND_FIXED: &str = "fixed";
+pub const KIND_PERCENT_OF_INCOME: &str = "percent_of_income";
+
+pub const MAX_BUDGETS_PER_MONTH: i64 = 200;
+
+pub fn is_valid_kind(kind: &str) -> bool {
+    matches!(kind, KIND_FIXED | KIND_PERCENT_OF_INCOME)
+}
+
+/// Orçamento mensal de uma categoria principal de despesa
+#[derive(Debug, Serialize)]
+pub struct Budget {
+    pub id: Uuid,
+    pub category_id: Uuid,
+    // Primeiro dia do mês
+    pub month: NaiveDate,
+    // 'fixed' ou 'percent_of_income'
+    pub kind: String,
+    // Valor fixo ou percentual da receita do mês (0 a 100)
+    pub amount: Decimal,
+    // O saldo não gasto passa para o mês seguinte
+    pub rollover: bool,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewBudget {
+    pub category_id: Uuid,
+    // AAAA-MM
+    pub month: String,
+    pub kind: Option<String>,
+    pub amount: Decimal,
+    #[serde(default)]
+    pub rollover: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateBudget {
+    pub kind: Option<String>,
+    pub amount: Option<Decimal>,
+    pub rollover: Option<bool>,
+}
+
+/// Planejado x realizado de um orçamento no mês. Gastos em valor absoluto; estornos reduzem o realizado.
+#[derive(Debug, Serialize)]
+pub struct BudgetProgress {
+    #[serde(flatten)]
+    pub budget: Budget,
+    pub category: String,
+    // Valor do mês (fixo ou calculado sobre a receita)
+    pub planned: Decimal,
+    // Saldo não gasto trazido do mês anterior
+    pub carried_over: Decimal,
+    pub available: Decimal,
+    pub actual: Decimal,
+    // Negativo quando o orçamento estourou
+    pub remaining: Decimal,
+    // Percentual do disponível já gasto; nulo quando nada está disponível
+    pub percent_used: Option<Decimal>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BudgetMonth {
+    // AAAA-MM
+    pub month: String,
+    // Entradas do mês que não são transferências nem estornos de despesas, base dos orçamentos percentuais
+    pub income: Decimal,
+    pub total_available: Decimal,
+    pub total_actual: Decimal,
+    pub total_remaining: Decimal,
+    pub budgets: Vec<BudgetProgress>,
+}
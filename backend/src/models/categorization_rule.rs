@@ -0,0 +1,121 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+pub const DIRECTION_EXPENSE: &str = "expense";
+pub const DIRECTION_INCOME: &str = "income";
+
+pub const MAX_RULES_PER_USER: i64 = 500;
+
+/// Regra de categorização: condições (todas precisam casar) e ações aplicadas à transação
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct CategorizationRule {
+    pub id: Uuid,
+    pub name: String,
+    // Menor valor é avaliado primeiro; a primeira regra que casa é aplicada
+    pub priority: i32,
+    pub enabled: bool,
+    // Condições
+    pub description_contains: Option<String>,
+    pub original_category: Option<String>,
+    pub direction: Option<String>,
+    // Limites sobre o valor absoluto da transação
+    pub min_amount: Option<Decimal>,
+    pub max_amount: Option<Decimal>,
+    pub account_id: Option<Uuid>,
+    // Ações
+    pub set_category: Option<String>,
+    pub set_subcategory: Option<String>,
+    pub rename_to: Option<String>,
+    pub add_tag: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewCategorizationRule {
+    pub name: String,
+    pub priority: Option<i32>,
+    pub enabled: Option<bool>,
+    pub description_contains: Option<String>,
+    pub original_category: Option<String>,
+    pub direction: Option<String>,
+    pub min_amount: Option<Decimal>,
+    pub max_amount: Option<Decimal>,
+    pub account_id: Option<Uuid>,
+    pub set_category: Option<String>,
+    pub set_subcategory: Option<String>,
+    pub rename_to: Option<String>,
+    pub add_tag: Option<String>,
+}
+
+/// Campos de uma transação considerados pelas condições das regras
+pub struct RuleInput<'a> {
+    pub description: Option<&'a str>,
+    pub category: Option<&'a str>,
+    pub amount: Decimal,
+    pub account_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApplyRulesResult {
+    pub matched: u64,
+    pub updated: u64,
+}
+
+/// Minúsculas e sem acentos, para comparar textos digitados de formas diferentes
+pub fn fold_text(text: &str) -> String {
+    text.to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'á' | 'à' | 'â' | 'ã' | 'ä' => 'a',
+            'é' | 'è' | 'ê' | 'ë' => 'e',
+            'í' | 'ì' | 'î' | 'ï' => 'i',
+            'ó' | 'ò' | 'ô' | 'õ' | 'ö' => 'o',
+            'ú' | 'ù' | 'û' | 'ü' => 'u',
+            'ç' => 'c',
+            'ñ' => 'n',
+            other => other,
+        })
+        .collect()
+}
+
+impl CategorizationRule {
+    pub fn matches(&self, input: &RuleInput) -> bool {
+        if !self.enabled {
+            return false;
+        }
+
+        if let Some(pattern) = &self.description_contains {
+            let description = fold_text(input.description.unwrap_or_default());
+            if !description.contains(&fold_text(pattern)) {
+                return false;
+            }
+        }
+
+        if let Some(category) = &self.original_category {
+            if !input.category.is_some_and(|c| c.eq_ignore_ascii_case(category)) {
+                return false;
+            }
+        }
+
+        match self.direction.as_deref() {
+            Some(DIRECTION_EXPENSE) if input.amount >= Decimal::ZERO => return false,
+            Some(DIRECTION_INCOME) if input.amount <= Decimal::ZERO => return false,
+            _ => {}
+        }
+
+        let amount = input.amount.abs();
+        if self.min_amount.is_some_and(|min| amount < min) || self.max_amount.is_some_and(|max| amount > max) {
+            return false;
+        }
+
+        if self.account_id.is_some() && self.account_id != input.account_id {
+            return false;
+        }
+
+        true
+    }
+}
@@ -0,0 +1,90 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+pub const KIND_EXPENSE: &str = "expense";
+pub const KIND_INCOME: &str = "income";
+pub const KIND_TRANSFER: &str = "transfer";
+
+pub const MAX_CATEGORIES_PER_USER: i64 = 500;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Category {
+    pub id: Uuid,
+    // Apenas dois níveis: categoria principal e subcategoria
+    pub parent_id: Option<Uuid>,
+    pub name: String,
+    pub kind: String,
+    pub icon: Option<String>,
+    pub color: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl Category {
+    pub fn is_valid_kind(kind: &str) -> bool {
+        matches!(kind, KIND_EXPENSE | KIND_INCOME | KIND_TRANSFER)
+    }
+
+    /// Cor no formato #RRGGBB
+    pub fn is_valid_color(color: &str) -> bool {
+        color.len() == 7 && color.starts_with('#') && color[1..].chars().all(|c| c.is_ascii_hexdigit())
+    }
+}
+
+/// Categoria principal com suas subcategorias
+#[derive(Debug, Serialize)]
+pub struct CategoryNode {
+    #[serde(flatten)]
+    pub category: Category,
+    pub children: Vec<Category>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewCategory {
+    pub name: String,
+    pub parent_id: Option<Uuid>,
+    pub kind: Option<String>,
+    pub icon: Option<String>,
+    pub color: Option<String>,
+}
+
+/// Alteração de uma categoria; mudar o nome reescreve as transações
+#[derive(Debug, Deserialize)]
+pub struct UpdateCategory {
+    pub name: Option<String>,
+    pub kind: Option<String>,
+    pub icon: Option<String>,
+    pub color: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MergeCategory {
+    pub into: Uuid,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CategoryRewriteResult {
+    pub transactions_updated: u64,
+}
+
+/// Associação entre o id de categoria da Pluggy (ex: "05010000") e uma categoria do usuário
+#[derive(Debug, Serialize, FromRow)]
+pub struct CategoryMapping {
+    pub pluggy_category_id: String,
+    pub category_id: Uuid,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewCategoryMapping {
+    pub category_id: Uuid,
+}
+
+/// Como a categoria aparece nas transações: (category, subcategory)
+#[derive(Debug, Clone, PartialEq)]
+pub struct CategoryPath {
+    pub category: String,
+    pub subcategory: Option<String>,
+}
@@ -0,0 +1,41 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+pub const STATUS_PENDING: &str = "PENDING";
+pub const STATUS_READY: &str = "READY";
+pub const STATUS_FAILED: &str = "FAILED";
+pub const STATUS_EXPIRED: &str = "EXPIRED";
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct DataExport {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub status: String,
+    #[serde(skip)]
+    pub download_token: Option<String>,
+    pub error: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DataExportResponse {
+    #[serde(flatten)]
+    pub export: DataExport,
+    // Link público (sem autenticação) válido até `expires_at`
+    pub download_url: Option<String>,
+}
+
+impl From<DataExport> for DataExportResponse {
+    fn from(export: DataExport) -> Self {
+        let download_url = match (&export.status[..], &export.download_token) {
+            (STATUS_READY, Some(token)) => Some(format!("/api/exports/{}", token)),
+            _ => None,
+        };
+        DataExportResponse { export, download_url }
+    }
+}
@@ -0,0 +1,58 @@
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use uuid::Uuid;
+
+pub const DEFAULT_FORECAST_DAYS: i64 = 30;
+pub const MAX_FORECAST_DAYS: i64 = 90;
+
+pub const EVENT_RECURRING: &str = "recurring";
+pub const EVENT_INSTALLMENT: &str = "installment";
+pub const EVENT_CREDIT_CARD_BILL: &str = "credit_card_bill";
+
+/// Lançamento previsto na conta: ocorrência recorrente, parcela debitada na conta ou pagamento de fatura
+#[derive(Debug, Serialize)]
+pub struct ForecastEvent {
+    pub date: NaiveDate,
+    // 'recurring', 'installment' ou 'credit_card_bill'
+    pub kind: String,
+    pub description: String,
+    // Negativo para saídas
+    pub amount: Decimal,
+    // Cartão da fatura
+    pub credit_card_account_id: Option<Uuid>,
+    pub recurring_transaction_id: Option<Uuid>,
+    pub installment_purchase_id: Option<Uuid>,
+}
+
+/// Saldo previsto no fim do dia
+#[derive(Debug, Serialize)]
+pub struct ForecastDay {
+    pub date: NaiveDate,
+    pub balance: Decimal,
+    pub negative: bool,
+    pub events: Vec<ForecastEvent>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AccountForecast {
+    pub account_id: Uuid,
+    pub name: Option<String>,
+    pub subtype: Option<String>,
+    pub currency: Option<String>,
+    pub current_balance: Decimal,
+    pub projected_balance: Decimal,
+    pub lowest_balance: Decimal,
+    pub lowest_balance_date: NaiveDate,
+    // Dias em que o saldo previsto fica negativo
+    pub negative_dates: Vec<NaiveDate>,
+    pub days: Vec<ForecastDay>,
+}
+
+/// Projeção diária do saldo das contas correntes e poupanças
+#[derive(Debug, Serialize)]
+pub struct CashFlowForecast {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub accounts: Vec<AccountForecast>,
+}
@@ -0,0 +1,84 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub const MAX_GOALS_PER_USER: i64 = 100;
+
+// Tipos de conta cujo saldo pode financiar uma meta: contas correntes e poupanças (cartões e empréstimos são dívidas)
+pub const LINKABLE_ACCOUNT_TYPES: &[&str] = &["BANK"];
+
+/// Meta de economia ("Viagem: R$ 10.000 até dez/2027")
+#[derive(Debug, Serialize)]
+pub struct Goal {
+    pub id: Uuid,
+    pub name: String,
+    pub target_amount: Decimal,
+    pub target_date: NaiveDate,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewGoal {
+    pub name: String,
+    pub target_amount: Decimal,
+    pub target_date: NaiveDate,
+    // Contas cujo saldo conta para a meta
+    #[serde(default)]
+    pub account_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateGoal {
+    pub name: Option<String>,
+    pub target_amount: Option<Decimal>,
+    pub target_date: Option<NaiveDate>,
+    // Substitui as contas vinculadas; lista vazia desvincula todas
+    pub account_ids: Option<Vec<Uuid>>,
+}
+
+/// Aporte manual na meta; valores negativos são retiradas
+#[derive(Debug, Serialize)]
+pub struct GoalContribution {
+    pub id: Uuid,
+    pub goal_id: Uuid,
+    pub amount: Decimal,
+    pub date: NaiveDate,
+    pub note: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewGoalContribution {
+    pub amount: Decimal,
+    // Padrão: hoje
+    pub date: Option<NaiveDate>,
+    pub note: Option<String>,
+}
+
+/// Progresso da meta e projeção de conclusão
+#[derive(Debug, Serialize)]
+pub struct GoalProgress {
+    #[serde(flatten)]
+    pub goal: Goal,
+    pub account_ids: Vec<Uuid>,
+    // Soma dos saldos atuais das contas vinculadas
+    pub accounts_balance: Decimal,
+    // Soma dos aportes manuais
+    pub contributions_total: Decimal,
+    pub current_amount: Decimal,
+    pub remaining: Decimal,
+    pub percent: Decimal,
+    pub completed: bool,
+    // Meses inteiros até a data alvo; zero quando ela já passou
+    pub months_remaining: i32,
+    // Aporte mensal necessário para chegar ao valor alvo na data
+    pub required_monthly_contribution: Decimal,
+    // Média mensal dos aportes manuais e das transferências de outras contas para as vinculadas (menos as
+    // retiradas) nos últimos meses
+    pub average_monthly_contribution: Decimal,
+    // Mantida a média, quando o valor alvo será atingido (hoje, se já foi); nulo se a média não for positiva
+    pub projected_completion_date: Option<NaiveDate>,
+    pub on_track: bool,
+}
@@ -0,0 +1,98 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+pub const FORMAT_CSV: &str = "csv";
+pub const FORMAT_OFX: &str = "ofx";
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ImportBatch {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub format: String,
+    pub file_name: Option<String>,
+    pub account_id: Option<Uuid>,
+    pub imported_count: i32,
+    pub duplicate_count: i32,
+    pub error_count: i32,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportRequest {
+    // "csv" ou "ofx"
+    pub format: String,
+    // Conteúdo do arquivo em texto
+    pub content: String,
+    pub file_name: Option<String>,
+    pub account_id: Option<Uuid>,
+    // Moeda das transações quando o arquivo não informa (padrão BRL)
+    pub currency: Option<String>,
+    // Apenas valida e retorna a pré-visualização, sem gravar nada
+    #[serde(default)]
+    pub dry_run: bool,
+    #[serde(default)]
+    pub csv: CsvOptions,
+}
+
+/// Coluna do CSV pelo nome no cabeçalho ou pela posição (a partir de 0)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum ColumnRef {
+    Index(usize),
+    Name(String),
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct CsvColumns {
+    pub date: Option<ColumnRef>,
+    pub amount: Option<ColumnRef>,
+    pub description: Option<ColumnRef>,
+    pub category: Option<ColumnRef>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct CsvOptions {
+    // Detectado pela primeira linha quando omitido (';' ou ',')
+    pub delimiter: Option<char>,
+    pub has_header: Option<bool>,
+    #[serde(default)]
+    pub columns: CsvColumns,
+    // Formato chrono da data (ex: "%d/%m/%Y"); por padrão tenta DD/MM/AAAA e AAAA-MM-DD
+    pub date_format: Option<String>,
+    // Separador decimal: ',' ("1.234,56") ou '.' ("1,234.56"); detectado em cada valor quando omitido
+    pub decimal_separator: Option<char>,
+    // Inverte o sinal dos valores (faturas que listam gastos como positivos)
+    #[serde(default)]
+    pub invert_sign: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportPreviewRow {
+    pub line: usize,
+    pub date: NaiveDate,
+    pub amount: Decimal,
+    pub description: String,
+    pub category: Option<String>,
+    // Já existe uma transação igual (mesma data, valor e descrição ou mesmo FITID)
+    pub duplicate: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportRowError {
+    pub line: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportResult {
+    pub dry_run: bool,
+    // Ausente no modo de pré-visualização
+    pub batch: Option<ImportBatch>,
+    pub imported: usize,
+    pub duplicates: usize,
+    pub rows: Vec<ImportPreviewRow>,
+    pub errors: Vec<ImportRowError>,
+}
@@ -0,0 +1,63 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use uuid::Uuid;
+
+/// Parcela identificada em uma transação ("PARC 03/10" ou metadados do cartão na Pluggy)
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstallmentInfo {
+    pub number: i32,
+    pub total: i32,
+    // Descrição sem a indicação da parcela
+    pub description: String,
+    // Informados apenas nos metadados de cartão da Pluggy
+    pub total_amount: Option<Decimal>,
+    pub purchase_date: Option<NaiveDate>,
+}
+
+/// Compra parcelada: as parcelas chegam como transações separadas e são agrupadas aqui.
+/// Valores em absoluto.
+#[derive(Debug, Serialize)]
+pub struct InstallmentPurchase {
+    pub id: Uuid,
+    pub description: String,
+    pub account_id: Option<Uuid>,
+    pub currency: String,
+    pub installment_count: i32,
+    pub installment_amount: Decimal,
+    pub total_amount: Decimal,
+    pub purchase_date: Option<NaiveDate>,
+    // Data (estimada) da primeira parcela
+    pub first_installment_date: NaiveDate,
+    pub last_installment_date: NaiveDate,
+    // Maior parcela já recebida
+    pub paid_installments: i32,
+    pub remaining_installments: i32,
+    pub remaining_amount: Decimal,
+    pub next_installment_date: Option<NaiveDate>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InstallmentEntry {
+    pub transaction_id: Uuid,
+    pub number: i32,
+    pub date: NaiveDate,
+    pub amount: Decimal,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InstallmentPurchaseDetail {
+    #[serde(flatten)]
+    pub purchase: InstallmentPurchase,
+    pub installments: Vec<InstallmentEntry>,
+}
+
+/// Parcelas futuras já comprometidas em um mês
+#[derive(Debug, Serialize)]
+pub struct InstallmentProjectionMonth {
+    pub year: i32,
+    pub month: u32,
+    pub total: Decimal,
+    pub installments: usize,
+}
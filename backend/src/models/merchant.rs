@@ -0,0 +1,67 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Estabelecimento normalizado; as transações apontam para ele por `merchant_id`
+#[derive(Debug, Serialize)]
+pub struct Merchant {
+    pub id: Uuid,
+    pub name: String,
+    // Razão social e CNPJ (apenas dígitos), quando a Pluggy informa
+    pub legal_name: Option<String>,
+    pub cnpj: Option<String>,
+    pub category: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Estabelecimento com o resumo das transações associadas
+#[derive(Debug, Serialize)]
+pub struct MerchantSummary {
+    #[serde(flatten)]
+    pub merchant: Merchant,
+    pub transaction_count: i64,
+    // Soma dos valores (negativa para gastos)
+    pub total_amount: Decimal,
+    pub last_transaction_date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MerchantAlias {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    // Descrição normalizada
+    pub alias: String,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MerchantDetail {
+    #[serde(flatten)]
+    pub merchant: Merchant,
+    pub aliases: Vec<MerchantAlias>,
+}
+
+/// Alteração de um estabelecimento; categoria vazia remove o valor atual
+#[derive(Debug, Deserialize)]
+pub struct UpdateMerchant {
+    pub name: Option<String>,
+    pub category: Option<String>,
+}
+
+/// Descrição (como aparece no extrato) que deve ser associada ao estabelecimento
+#[derive(Debug, Deserialize)]
+pub struct NewMerchantAlias {
+    pub description: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MergeMerchant {
+    pub into: Uuid,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MerchantLinkResult {
+    pub transactions_updated: u64,
+}
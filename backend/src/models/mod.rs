@@ -1,3 +1,21 @@
 pub mod user;
 pub mod transaction;
 pub mod balance;
+pub mod api_token;
+pub mod data_export;
+pub mod import_batch;
+pub mod transaction_split;
+pub mod tag;
+pub mod attachment;
+pub mod categorization_rule;
+pub mod category;
+pub mod transfer;
+pub mod recurring;
+pub mod installment;
+pub mod merchant;
+pub mod budget;
+pub mod forecast;
+pub mod goal;
+pub mod net_worth;
+pub mod notification;
+pub mod report;
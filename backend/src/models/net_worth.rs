@@ -0,0 +1,36 @@
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+pub const INTERVAL_DAY: &str = "day";
+pub const INTERVAL_WEEK: &str = "week";
+pub const INTERVAL_MONTH: &str = "month";
+
+// Tipos de conta cujo saldo é dívida (fatura do cartão, saldo devedor do empréstimo)
+pub const LIABILITY_ACCOUNT_TYPES: &[&str] = &["CREDIT", "LOAN"];
+
+pub const MAX_NET_WORTH_POINTS: i64 = 1000;
+
+pub fn is_valid_interval(interval: &str) -> bool {
+    matches!(interval, INTERVAL_DAY | INTERVAL_WEEK | INTERVAL_MONTH)
+}
+
+/// Patrimônio no fim de um período, com o último saldo conhecido de cada conta até a data
+#[derive(Debug, Serialize)]
+pub struct NetWorthPoint {
+    pub date: NaiveDate,
+    // Inclui os investimentos
+    pub assets: Decimal,
+    pub investments: Decimal,
+    // Valor positivo das dívidas
+    pub liabilities: Decimal,
+    pub net_worth: Decimal,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NetWorthHistory {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub interval: String,
+    pub points: Vec<NetWorthPoint>,
+}
@@ -0,0 +1,101 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+pub const KIND_BUDGET_WARNING: &str = "budget_warning";
+pub const KIND_BUDGET_EXCEEDED: &str = "budget_exceeded";
+pub const KIND_LARGE_TRANSACTION: &str = "large_transaction";
+pub const KIND_CONNECTION_ERROR: &str = "connection_error";
+pub const KIND_LOW_BALANCE: &str = "low_balance";
+pub const KIND_TEST: &str = "test";
+
+// Percentual do orçamento que dispara o aviso antes de estourar
+pub const BUDGET_WARNING_PERCENT: i64 = 80;
+
+pub const DEFAULT_FEED_LIMIT: i64 = 50;
+pub const MAX_FEED_LIMIT: i64 = 200;
+pub const MAX_PUSH_SUBSCRIPTIONS: i64 = 20;
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct Notification {
+    pub id: Uuid,
+    pub kind: String,
+    pub title: String,
+    pub body: String,
+    // Identificadores do que gerou o alerta (budget_id, transaction_id, item_id, account_id)
+    pub data: serde_json::Value,
+    pub read_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NotificationFeed {
+    pub unread_count: i64,
+    pub notifications: Vec<Notification>,
+}
+
+/// Preferências de alertas. O segredo do webhook só é exibido na resposta que o gera (ao configurar
+/// o webhook ou pedir `rotate_webhook_secret`), para que o destino valide a assinatura.
+#[derive(Debug, Serialize)]
+pub struct NotificationSettings {
+    pub budget_alerts: bool,
+    pub connection_alerts: bool,
+    pub large_transaction_threshold: Option<Decimal>,
+    pub low_balance_threshold: Option<Decimal>,
+    pub email_enabled: bool,
+    pub webhook_url: Option<String>,
+    pub webhook_secret: Option<String>,
+    // Canais disponíveis no servidor
+    pub webhook_available: bool,
+    pub email_available: bool,
+    pub push_available: bool,
+}
+
+/// Substitui as preferências; campos ausentes voltam ao padrão
+#[derive(Debug, Deserialize)]
+pub struct UpdateNotificationSettings {
+    #[serde(default = "enabled")]
+    pub budget_alerts: bool,
+    #[serde(default = "enabled")]
+    pub connection_alerts: bool,
+    pub large_transaction_threshold: Option<Decimal>,
+    pub low_balance_threshold: Option<Decimal>,
+    #[serde(default)]
+    pub email_enabled: bool,
+    pub webhook_url: Option<String>,
+    // Gera um novo segredo para o webhook
+    #[serde(default)]
+    pub rotate_webhook_secret: bool,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PushSubscriptionKeys {
+    pub p256dh: String,
+    pub auth: String,
+}
+
+/// Formato de `PushSubscription.toJSON()` do navegador
+#[derive(Debug, Deserialize)]
+pub struct NewPushSubscription {
+    pub endpoint: String,
+    pub keys: PushSubscriptionKeys,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PushSubscription {
+    pub id: Uuid,
+    pub endpoint: String,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PushPublicKey {
+    // Chave pública VAPID (ponto P-256 não comprimido em base64url), usada como applicationServerKey
+    pub public_key: String,
+}
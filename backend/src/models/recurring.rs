@@ -0,0 +1,91 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub const FREQUENCY_WEEKLY: &str = "weekly";
+pub const FREQUENCY_BIWEEKLY: &str = "biweekly";
+pub const FREQUENCY_MONTHLY: &str = "monthly";
+pub const FREQUENCY_QUARTERLY: &str = "quarterly";
+pub const FREQUENCY_YEARLY: &str = "yearly";
+
+pub const SOURCE_DETECTED: &str = "detected";
+pub const SOURCE_MANUAL: &str = "manual";
+
+pub const MAX_RECURRING_PER_USER: i64 = 200;
+
+pub fn is_valid_frequency(frequency: &str) -> bool {
+    matches!(
+        frequency,
+        FREQUENCY_WEEKLY | FREQUENCY_BIWEEKLY | FREQUENCY_MONTHLY | FREQUENCY_QUARTERLY | FREQUENCY_YEARLY
+    )
+}
+
+/// Lançamento recorrente definido pelo usuário; o agendador cria as transações nas datas previstas
+#[derive(Debug, Serialize)]
+pub struct RecurringTransaction {
+    pub id: Uuid,
+    pub description: String,
+    pub amount: Decimal,
+    pub currency: String,
+    pub category: Option<String>,
+    pub account_id: Option<Uuid>,
+    pub frequency: String,
+    pub start_date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
+    // Próxima data a ser criada; nula quando a recorrência terminou
+    pub next_date: Option<NaiveDate>,
+    pub occurrence_count: i32,
+    pub enabled: bool,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewRecurringTransaction {
+    pub description: String,
+    pub amount: Decimal,
+    pub currency: Option<String>,
+    pub category: Option<String>,
+    pub account_id: Option<Uuid>,
+    pub frequency: String,
+    pub start_date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
+}
+
+/// Alteração de um lançamento recorrente; vale para as próximas ocorrências.
+/// Frequência e data inicial não mudam: para isso, crie outro lançamento.
+#[derive(Debug, Deserialize)]
+pub struct UpdateRecurringTransaction {
+    pub description: Option<String>,
+    pub amount: Option<Decimal>,
+    // String vazia remove a categoria
+    pub category: Option<String>,
+    pub end_date: Option<NaiveDate>,
+    pub enabled: Option<bool>,
+}
+
+/// Série recorrente, detectada no histórico ou definida pelo usuário
+#[derive(Debug, Serialize)]
+pub struct RecurringSeries {
+    // 'detected' ou 'manual'
+    pub source: String,
+    pub recurring_transaction_id: Option<Uuid>,
+    pub name: String,
+    pub category: Option<String>,
+    pub account_id: Option<Uuid>,
+    pub frequency: String,
+    // Valor esperado da próxima ocorrência (o da última)
+    pub amount: Decimal,
+    pub currency: String,
+    pub occurrences: usize,
+    pub last_date: Option<NaiveDate>,
+    pub next_expected_date: Option<NaiveDate>,
+    // Data final dos lançamentos cadastrados
+    pub end_date: Option<NaiveDate>,
+    // Valor anterior à última mudança de preço e a data da primeira ocorrência com o valor atual
+    pub previous_amount: Option<Decimal>,
+    pub price_changed_on: Option<NaiveDate>,
+    // Ocorrências esperadas que não aconteceram desde a última
+    pub missed_occurrences: u32,
+}
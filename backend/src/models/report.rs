@@ -0,0 +1,69 @@
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+pub const METRIC_INCOME: &str = "income";
+pub const METRIC_EXPENSE: &str = "expense";
+pub const METRIC_NET: &str = "net";
+pub const METRICS: &[&str] = &[METRIC_INCOME, METRIC_EXPENSE, METRIC_NET];
+
+pub const COMPARE_PREVIOUS_PERIOD: &str = "previous_period";
+pub const COMPARE_PREVIOUS_YEAR: &str = "previous_year";
+
+// Agrupamentos combinados em um mesmo relatório (ex.: mês e categoria)
+pub const MAX_REPORT_DIMENSIONS: usize = 2;
+
+/// Parâmetros do relatório (todos opcionais)
+#[derive(Debug, Default, rocket::FromForm)]
+pub struct ReportQuery {
+    // Período no formato YYYY-MM-DD (inclusivo); padrão: do dia 1º do mês até hoje
+    pub from: Option<String>,
+    pub to: Option<String>,
+    // income, expense e/ou net (?metric=income&metric=expense); padrão: todas
+    pub metric: Vec<String>,
+    // category, subcategory, account, merchant, tag, day, week, month ou year
+    pub group_by: Vec<String>,
+    // previous_period ou previous_year
+    pub compare: Option<String>,
+    pub account_id: Option<Uuid>,
+    pub category: Option<String>,
+    // Lançamentos que têm todas as tags informadas
+    pub tag: Vec<String>,
+}
+
+/// Valor de um agrupamento na linha; `key` nulo reúne os lançamentos sem o campo (ex.: sem categoria)
+#[derive(Debug, Serialize)]
+pub struct ReportGroup {
+    pub dimension: String,
+    pub key: Option<String>,
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReportRow {
+    pub groups: Vec<ReportGroup>,
+    // Métrica → valor; receitas e despesas em valor positivo
+    pub values: BTreeMap<String, Decimal>,
+    // Presentes apenas com comparação
+    pub previous: Option<BTreeMap<String, Decimal>>,
+    pub change: Option<BTreeMap<String, Decimal>>,
+    // Variação percentual; métricas sem valor no período anterior ficam de fora
+    pub change_percent: Option<BTreeMap<String, Decimal>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub metrics: Vec<String>,
+    pub group_by: Vec<String>,
+    pub compare: Option<String>,
+    // Período usado na comparação
+    pub previous_from: Option<NaiveDate>,
+    pub previous_to: Option<NaiveDate>,
+    // Totais do período (sem agrupamento)
+    pub totals: ReportRow,
+    pub rows: Vec<ReportRow>,
+}
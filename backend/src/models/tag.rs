@@ -0,0 +1,27 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+pub const MAX_TAG_LENGTH: usize = 50;
+pub const MAX_TAGS_PER_TRANSACTION: usize = 20;
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Tag {
+    pub id: Uuid,
+    pub name: String,
+    // Quantidade de transações marcadas
+    pub transaction_count: i64,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl Tag {
+    /// Normaliza o nome digitado: sem espaços nas pontas, minúsculas e espaços internos viram hífen
+    pub fn normalize_name(name: &str) -> Option<String> {
+        let name = name.split_whitespace().collect::<Vec<_>>().join("-").to_lowercase();
+        if name.is_empty() || name.chars().count() > MAX_TAG_LENGTH {
+            return None;
+        }
+        Some(name)
+    }
+}
@@ -19,6 +19,23 @@ pub struct Transaction {
     pub status: Option<String>,
     pub merchant: Option<serde_json::Value>,
     pub balance: Option<rust_decimal::Decimal>,
+    pub notes: Option<String>,
+    pub tags: Vec<String>,
+    // Regra de categorização que alterou a transação na sincronização
+    pub rule_id: Option<Uuid>,
+    // Lançamento recorrente que criou a transação
+    pub recurring_transaction_id: Option<Uuid>,
+    // Compra parcelada e número da parcela ("3 de 10")
+    pub installment_purchase_id: Option<Uuid>,
+    pub installment_number: Option<i32>,
+    pub installment_total: Option<i32>,
+    // Estabelecimento normalizado
+    pub merchant_id: Option<Uuid>,
+    // Sugestão do classificador local e sua confiança (0 a 1)
+    pub predicted_category: Option<String>,
+    pub prediction_confidence: Option<f32>,
+    // Transferência entre contas do usuário da qual a transação faz parte
+    pub transfer_id: Option<Uuid>,
     pub created_at: Option<DateTime<Utc>>,
     pub updated_at: Option<DateTime<Utc>>,
     // user_id isn't in the table schema provided earlier for transactions directly, 
@@ -33,7 +50,58 @@ pub struct NewTransaction {
     pub date: NaiveDate,
     pub description: String,
     pub category: Option<String>,
+    // Na edição, ausente mantém a atual (ou nenhuma, se a categoria mudar); string vazia remove
+    pub subcategory: Option<String>,
     pub currency: String,
 }
 
+/// Campos do usuário em uma transação; nunca sobrescritos pela sincronização com a Pluggy
+#[derive(Debug, Deserialize)]
+pub struct TransactionAnnotations {
+    // String vazia remove a anotação
+    pub notes: Option<String>,
+    // Substitui todas as tags da transação
+    pub tags: Option<Vec<String>>,
+}
+
+/// Filtros, busca e paginação da listagem de transações (todos opcionais)
+#[derive(Debug, Default, rocket::FromForm)]
+pub struct TransactionFilter {
+    // Período no formato YYYY-MM-DD (inclusivo)
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub account_id: Option<Uuid>,
+    pub item_id: Option<Uuid>,
+    pub merchant_id: Option<Uuid>,
+    pub category: Option<String>,
+    pub min_amount: Option<String>,
+    pub max_amount: Option<String>,
+    pub status: Option<String>,
+    // Transações que têm todas as tags informadas (?tag=a&tag=b)
+    pub tag: Vec<String>,
+    // "manual" ou "synced"
+    pub source: Option<String>,
+    // Busca livre na descrição e no nome do estabelecimento
+    pub q: Option<String>,
+    // date_desc (padrão), date_asc, amount_desc ou amount_asc
+    pub sort: Option<String>,
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TransactionPage {
+    pub items: Vec<Transaction>,
+    // Cursor para a próxima página; ausente quando não há mais resultados
+    pub next_cursor: Option<String>,
+}
 
+#[derive(Debug, Serialize, FromRow)]
+pub struct TransactionSearchResult {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub transaction: Transaction,
+    pub rank: f32,
+    // Trecho da descrição, estabelecimento e anotações em HTML escapado, com os termos entre <mark> e </mark>
+    pub snippet: Option<String>,
+}
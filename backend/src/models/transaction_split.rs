@@ -0,0 +1,30 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct TransactionSplit {
+    pub id: Uuid,
+    pub transaction_id: Uuid,
+    pub amount: Decimal,
+    pub category: Option<String>,
+    pub notes: Option<String>,
+    pub position: i32,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewTransactionSplit {
+    pub amount: Decimal,
+    pub category: Option<String>,
+    pub notes: Option<String>,
+}
+
+/// Substitui todas as partes de uma transação
+#[derive(Debug, Deserialize)]
+pub struct ReplaceSplits {
+    pub splits: Vec<NewTransactionSplit>,
+}
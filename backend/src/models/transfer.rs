@@ -0,0 +1,39 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub const DETECTION_AUTO: &str = "auto";
+pub const DETECTION_MANUAL: &str = "manual";
+
+/// Transferência entre contas do usuário: a saída de uma conta e a entrada correspondente em outra
+#[derive(Debug, Serialize)]
+pub struct Transfer {
+    pub id: Uuid,
+    // 'auto' (detectada na sincronização) ou 'manual'
+    pub detection: String,
+    // Valor que saiu da conta de origem
+    pub amount: Decimal,
+    pub currency: String,
+    pub outgoing_transaction_id: Uuid,
+    pub outgoing_account_id: Option<Uuid>,
+    pub outgoing_date: NaiveDate,
+    pub outgoing_description: Option<String>,
+    pub incoming_transaction_id: Uuid,
+    pub incoming_account_id: Option<Uuid>,
+    pub incoming_date: NaiveDate,
+    pub incoming_description: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// Pareamento manual; os valores podem diferir (ex: tarifa cobrada na origem)
+#[derive(Debug, Deserialize)]
+pub struct NewTransfer {
+    pub outgoing_transaction_id: Uuid,
+    pub incoming_transaction_id: Uuid,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DetectTransfersResult {
+    pub detected: u64,
+}
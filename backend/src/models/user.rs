@@ -1,9 +1,12 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
 use chrono::{DateTime, Utc};
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use sqlx::FromRow;
 use uuid::Uuid;
 use argon2::{
-    password_hash::{rand_core::OsRng, PasswordHasher, SaltString},
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
     Argon2,
 };
 
@@ -13,7 +16,11 @@ pub struct User {
     pub email: String,
     pub full_name: String,
     #[serde(skip)]
-    pub password_hash: String,
+    pub password_hash: Option<String>,
+    pub preferred_currency: String,
+    pub locale: String,
+    pub timezone: String,
+    pub pending_email: Option<String>,
     pub created_at: Option<DateTime<Utc>>,
     pub updated_at: Option<DateTime<Utc>>,
 }
@@ -27,6 +34,30 @@ impl User {
             .map(|hash| hash.to_string())
             .map_err(|e| e.to_string())
     }
+
+    /// Usuários criados via OIDC podem não ter senha; nesse caso a verificação sempre falha
+    pub fn verify_password(&self, password: &str) -> Result<bool, String> {
+        let Some(password_hash) = &self.password_hash else {
+            return Ok(false);
+        };
+        let parsed_hash = PasswordHash::new(password_hash).map_err(|e| e.to_string())?;
+        Ok(Argon2::default()
+            .verify_password(password.as_bytes(), &parsed_hash)
+            .is_ok())
+    }
+
+    /// Gera um token de confirmação de email. Retorna (token, hash SHA-256 em hex).
+    pub fn generate_email_token() -> (String, String) {
+        let mut bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let token = URL_SAFE_NO_PAD.encode(bytes);
+        let hash = Self::hash_email_token(&token);
+        (token, hash)
+    }
+
+    pub fn hash_email_token(token: &str) -> String {
+        format!("{:x}", Sha256::digest(token.as_bytes()))
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -43,3 +74,36 @@ pub struct LoginUser {
     pub password: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct UpdateUser {
+    #[serde(rename = "fullName")]
+    pub full_name: Option<String>,
+    pub email: Option<String>,
+    pub preferred_currency: Option<String>,
+    pub locale: Option<String>,
+    pub timezone: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangePassword {
+    #[serde(default)]
+    pub current_password: String,
+    pub new_password: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmail {
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeleteUser {
+    #[serde(default)]
+    pub password: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OidcCallback {
+    pub code: String,
+    pub state: String,
+}
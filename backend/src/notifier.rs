@@ -0,0 +1,383 @@
+use crate::config::{AppConfig, VapidConfig};
+use crate::mailer::Mailer;
+use crate::models::notification::Notification;
+use crate::secrets::SecretCipher;
+use crate::storage::{hex, hmac_sha256};
+use anyhow::anyhow;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use chrono::{Duration, Utc};
+use p256::ecdsa::{signature::Signer, Signature, SigningKey};
+use reqwest::redirect::Policy;
+use rocket::futures::future::join_all;
+use reqwest::{Client, StatusCode, Url};
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use uuid::Uuid;
+
+// Tempo máximo de espera por um webhook ou serviço de push
+const DELIVERY_TIMEOUT_SECS: u64 = 10;
+// Por quanto tempo o serviço de push guarda a mensagem de um navegador offline
+const PUSH_TTL_SECS: u32 = 24 * 60 * 60;
+
+/// Destino aceito para webhooks e web push: https, em um host que resolve apenas para endereços públicos
+pub struct PublicEndpoint {
+    pub url: Url,
+    addrs: Vec<SocketAddr>,
+}
+
+impl PublicEndpoint {
+    // Conecta apenas aos endereços verificados, sem consultar o DNS de novo, e não segue redirecionamentos
+    fn client(&self) -> anyhow::Result<Client> {
+        let mut builder = Client::builder()
+            .timeout(std::time::Duration::from_secs(DELIVERY_TIMEOUT_SECS))
+            .redirect(Policy::none());
+        if let Some(domain) = self.url.host_str().filter(|host| ip_literal(host).is_none()) {
+            builder = builder.resolve_to_addrs(domain, &self.addrs);
+        }
+        Ok(builder.build()?)
+    }
+}
+
+// Host escrito como IP ("10.0.0.1" ou "[::1]")
+fn ip_literal(host: &str) -> Option<IpAddr> {
+    host.trim_start_matches('[').trim_end_matches(']').parse().ok()
+}
+
+// Endereços fora da internet pública: loopback, redes privadas, link-local (inclui 169.254.169.254),
+// CGNAT, documentação, multicast e faixas reservadas
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, _] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || a == 0
+                || (a == 100 && (64..128).contains(&b))
+                || (a == 192 && b == 0 && c == 0)
+                || (a == 198 && (18..20).contains(&b))
+                || a >= 240)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(v4) = ip.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(v4));
+            }
+            let [first, second, ..] = ip.segments();
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80
+                || (first == 0x2001 && second == 0x0db8)
+                || (first == 0x0064 && second == 0xff9b))
+        }
+    }
+}
+
+/// Valida a URL de um webhook ou endpoint de push, resolvendo o host. Usada ao salvar e a cada entrega,
+/// já que o DNS do host pode mudar depois de salvo.
+pub async fn resolve_public_endpoint(value: &str) -> anyhow::Result<PublicEndpoint> {
+    let url = Url::parse(value.trim())?;
+    if url.scheme() != "https" {
+        return Err(anyhow!("use https://"));
+    }
+    let host = url.host_str().ok_or_else(|| anyhow!("informe o host"))?;
+    let port = url.port_or_known_default().unwrap_or(443);
+    let addrs: Vec<SocketAddr> = match ip_literal(host) {
+        Some(ip) => vec![SocketAddr::new(ip, port)],
+        None => tokio::net::lookup_host((host, port)).await?.collect(),
+    };
+
+    if addrs.is_empty() {
+        return Err(anyhow!("o host não tem endereço"));
+    }
+    if let Some(addr) = addrs.iter().find(|a| !is_public_ip(a.ip())) {
+        return Err(anyhow!("o endereço {} não é público", addr.ip()));
+    }
+    Ok(PublicEndpoint { url, addrs })
+}
+
+/// Destinatário de uma notificação, com as preferências de alertas e canais
+#[derive(Debug)]
+pub struct Recipient {
+    pub user_id: Uuid,
+    pub email: String,
+    pub full_name: String,
+    pub preferred_currency: String,
+    pub budget_alerts: bool,
+    pub connection_alerts: bool,
+    pub large_transaction_threshold: Option<Decimal>,
+    pub low_balance_threshold: Option<Decimal>,
+    pub email_enabled: bool,
+    pub webhook_url: Option<String>,
+    pub webhook_secret: Option<String>,
+}
+
+/// Usuário e preferências de notificação; sem preferências salvas valem os padrões
+pub async fn recipient(pool: &PgPool, user_id: Uuid) -> sqlx::Result<Option<Recipient>> {
+    sqlx::query_as!(
+        Recipient,
+        r#"
+        SELECT u.id AS user_id, u.email, u.full_name, u.preferred_currency,
+               COALESCE(s.budget_alerts, TRUE) AS "budget_alerts!",
+               COALESCE(s.connection_alerts, TRUE) AS "connection_alerts!",
+               s.large_transaction_threshold AS "large_transaction_threshold?",
+               s.low_balance_threshold AS "low_balance_threshold?",
+               COALESCE(s.email_enabled, FALSE) AS "email_enabled!",
+               s.webhook_url AS "webhook_url?",
+               s.webhook_secret AS "webhook_secret?"
+        FROM users u
+        LEFT JOIN notification_settings s ON s.user_id = u.id
+        WHERE u.id = $1
+        "#,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Canal de entrega das notificações. O registro no feed do aplicativo sempre existe;
+/// os canais apenas avisam o usuário fora dele.
+#[rocket::async_trait]
+pub trait NotificationChannel: Send + Sync {
+    fn name(&self) -> &'static str;
+    /// Entrega a notificação; canais que o usuário não configurou não fazem nada
+    async fn deliver(&self, pool: &PgPool, recipient: &Recipient, notification: &Notification) -> anyhow::Result<()>;
+}
+
+pub struct Notifier {
+    channels: Vec<Box<dyn NotificationChannel>>,
+    push_public_key: Option<String>,
+    secrets: Option<Arc<SecretCipher>>,
+}
+
+/// Webhook quando `SECRETS_KEY` está configurada (o segredo da assinatura fica cifrado no banco);
+/// e-mail e web push quando SMTP e VAPID estão configurados
+pub fn from_config(config: &AppConfig, mailer: Option<Arc<Mailer>>) -> Arc<Notifier> {
+    let mut channels: Vec<Box<dyn NotificationChannel>> = Vec::new();
+    let mut push_public_key = None;
+    let mut secrets = None;
+
+    if let Some(key) = &config.secrets_key {
+        match SecretCipher::from_base64(key) {
+            Ok(cipher) => {
+                let cipher = Arc::new(cipher);
+                channels.push(Box::new(WebhookChannel { secrets: cipher.clone() }));
+                secrets = Some(cipher);
+            }
+            Err(e) => eprintln!("✗ SECRETS_KEY inválida, webhooks desativados: {}", e),
+        }
+    }
+
+    if let Some(mailer) = mailer {
+        channels.push(Box::new(EmailChannel { mailer }));
+    }
+
+    if let Some(vapid) = &config.vapid {
+        match WebPushChannel::new(vapid) {
+            Ok(channel) => {
+                push_public_key = Some(channel.public_key.clone());
+                channels.push(Box::new(channel));
+            }
+            Err(e) => eprintln!("✗ Chave VAPID inválida, web push desativado: {}", e),
+        }
+    }
+
+    Arc::new(Notifier { channels, push_public_key, secrets })
+}
+
+impl Notifier {
+    /// Cifra dos segredos dos webhooks; ausente quando o canal está desativado
+    pub fn secrets(&self) -> Option<&SecretCipher> {
+        self.secrets.as_deref()
+    }
+
+    pub fn email_available(&self) -> bool {
+        self.channels.iter().any(|c| c.name() == EmailChannel::NAME)
+    }
+
+    pub fn push_public_key(&self) -> Option<&str> {
+        self.push_public_key.as_deref()
+    }
+
+    /// Envia as notificações por todos os canais em segundo plano, sem atrasar a sincronização ou a requisição.
+    /// Os canais são acionados em paralelo; falhas de um canal são registradas e não impedem os demais.
+    pub fn dispatch(self: &Arc<Self>, pool: &PgPool, recipient: Recipient, notifications: Vec<Notification>) {
+        if notifications.is_empty() {
+            return;
+        }
+
+        let notifier = Arc::clone(self);
+        let pool = pool.clone();
+        tokio::spawn(async move {
+            for notification in &notifications {
+                let deliveries = notifier.channels.iter().map(|channel| async {
+                    if let Err(e) = channel.deliver(&pool, &recipient, notification).await {
+                        eprintln!(
+                            "Erro ao enviar notificação {} por {} para o usuário {}: {}",
+                            notification.id,
+                            channel.name(),
+                            recipient.user_id,
+                            e
+                        );
+                    }
+                });
+                join_all(deliveries).await;
+            }
+        });
+    }
+}
+
+/// E-mail em texto simples para o endereço do usuário, quando ele habilitou o canal
+pub struct EmailChannel {
+    mailer: Arc<Mailer>,
+}
+
+impl EmailChannel {
+    const NAME: &'static str = "email";
+}
+
+#[rocket::async_trait]
+impl NotificationChannel for EmailChannel {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    async fn deliver(&self, _pool: &PgPool, recipient: &Recipient, notification: &Notification) -> anyhow::Result<()> {
+        if !recipient.email_enabled {
+            return Ok(());
+        }
+
+        self.mailer
+            .send(&recipient.full_name, &recipient.email, &notification.title, notification.body.clone())
+            .await
+    }
+}
+
+/// POST com a notificação em JSON para a URL do usuário. O corpo é assinado com HMAC-SHA256
+/// no cabeçalho `X-Firebudget-Signature: sha256=<hex>`, usando o segredo exibido nas preferências.
+pub struct WebhookChannel {
+    secrets: Arc<SecretCipher>,
+}
+
+#[rocket::async_trait]
+impl NotificationChannel for WebhookChannel {
+    fn name(&self) -> &'static str {
+        "webhook"
+    }
+
+    async fn deliver(&self, _pool: &PgPool, recipient: &Recipient, notification: &Notification) -> anyhow::Result<()> {
+        let (Some(url), Some(secret)) = (&recipient.webhook_url, &recipient.webhook_secret) else {
+            return Ok(());
+        };
+
+        let secret = self.secrets.decrypt(secret).map_err(|e| anyhow!("Segredo do webhook {} inválido: {}", url, e))?;
+        let endpoint = resolve_public_endpoint(url).await.map_err(|e| anyhow!("Webhook {} recusado: {}", url, e))?;
+        let body = serde_json::to_vec(notification)?;
+        let signature = hex(&hmac_sha256(secret.as_bytes(), &body));
+
+        let response = endpoint
+            .client()?
+            .post(endpoint.url.clone())
+            .header("Content-Type", "application/json")
+            .header("X-Firebudget-Signature", format!("sha256={}", signature))
+            .body(body)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!("Webhook {} retornou {}", url, response.status()));
+        }
+        Ok(())
+    }
+}
+
+/// Web push sem conteúdo, autenticado com VAPID (RFC 8292). Ao receber o push, o service worker
+/// busca as notificações não lidas em `/api/notifications?unread=true` para exibi-las.
+pub struct WebPushChannel {
+    key: SigningKey,
+    public_key: String,
+    subject: String,
+}
+
+impl WebPushChannel {
+    pub fn new(config: &VapidConfig) -> anyhow::Result<Self> {
+        let bytes = URL_SAFE_NO_PAD.decode(config.private_key.trim().trim_end_matches('='))?;
+        let key = SigningKey::from_slice(&bytes)?;
+        let public_key = URL_SAFE_NO_PAD.encode(key.verifying_key().to_encoded_point(false).as_bytes());
+        Ok(WebPushChannel { key, public_key, subject: config.subject.clone() })
+    }
+
+    // JWT ES256 com o serviço de push (origem do endpoint) como audiência
+    fn vapid_token(&self, endpoint: &str) -> anyhow::Result<String> {
+        let url = reqwest::Url::parse(endpoint)?;
+        let claims = serde_json::json!({
+            "aud": url.origin().ascii_serialization(),
+            "exp": (Utc::now() + Duration::hours(12)).timestamp(),
+            "sub": self.subject,
+        });
+        let signing_input = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(br#"{"typ":"JWT","alg":"ES256"}"#),
+            URL_SAFE_NO_PAD.encode(serde_json::to_vec(&claims)?)
+        );
+        let signature: Signature = self.key.sign(signing_input.as_bytes());
+        Ok(format!("{}.{}", signing_input, URL_SAFE_NO_PAD.encode(signature.to_bytes())))
+    }
+}
+
+#[rocket::async_trait]
+impl NotificationChannel for WebPushChannel {
+    fn name(&self) -> &'static str {
+        "push"
+    }
+
+    async fn deliver(&self, pool: &PgPool, recipient: &Recipient, _notification: &Notification) -> anyhow::Result<()> {
+        let subscriptions = sqlx::query!(
+            "SELECT id, endpoint FROM push_subscriptions WHERE user_id = $1",
+            recipient.user_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        for subscription in subscriptions {
+            let endpoint = match resolve_public_endpoint(&subscription.endpoint).await {
+                Ok(endpoint) => endpoint,
+                Err(e) => {
+                    eprintln!("Endpoint de push {} recusado: {}", subscription.endpoint, e);
+                    continue;
+                }
+            };
+            let token = self.vapid_token(&subscription.endpoint)?;
+            let response = endpoint
+                .client()?
+                .post(endpoint.url.clone())
+                .header("TTL", PUSH_TTL_SECS)
+                .header("Authorization", format!("vapid t={}, k={}", token, self.public_key))
+                .header("Content-Length", 0)
+                .send()
+                .await?;
+
+            match response.status() {
+                // Inscrição expirada ou cancelada pelo navegador
+                StatusCode::NOT_FOUND | StatusCode::GONE => {
+                    sqlx::query!("DELETE FROM push_subscriptions WHERE id = $1", subscription.id)
+                        .execute(pool)
+                        .await?;
+                }
+                status if !status.is_success() => {
+                    eprintln!("Serviço de push {} retornou {}", subscription.endpoint, status);
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+}
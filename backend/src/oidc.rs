@@ -0,0 +1,279 @@
+use crate::config::OidcConfig;
+use anyhow::Result;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use jsonwebtoken::jwk::{AlgorithmParameters, Jwk, JwkSet};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use rand::RngCore;
+use reqwest::{Client, Url};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+// Por quanto tempo os metadados e as chaves do provedor são reaproveitados
+const METADATA_TTL: Duration = Duration::from_secs(60 * 60);
+const JWKS_TTL: Duration = Duration::from_secs(60 * 60);
+// Intervalo mínimo entre buscas do JWKS motivadas por um `kid` desconhecido (rotação no provedor)
+const JWKS_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Metadados publicados pelo provedor em /.well-known/openid-configuration
+#[derive(Debug, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+    // Sem a informação, vale o RS256 exigido pela especificação
+    #[serde(default = "default_signing_algs")]
+    pub id_token_signing_alg_values_supported: Vec<String>,
+}
+
+fn default_signing_algs() -> Vec<String> {
+    vec!["RS256".to_string()]
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IdTokenClaims {
+    pub sub: String,
+    pub email: Option<String>,
+    #[serde(default)]
+    pub email_verified: bool,
+    pub name: Option<String>,
+    pub nonce: Option<String>,
+}
+
+struct Cached<T> {
+    value: Arc<T>,
+    fetched_at: Instant,
+}
+
+impl<T> Cached<T> {
+    fn new(value: T) -> Self {
+        Cached { value: Arc::new(value), fetched_at: Instant::now() }
+    }
+
+    fn fresh(&self, ttl: Duration) -> Option<Arc<T>> {
+        (self.fetched_at.elapsed() < ttl).then(|| self.value.clone())
+    }
+}
+
+/// Cliente do provedor OIDC, compartilhado entre as requisições para reaproveitar
+/// os metadados de descoberta e o JWKS em cache
+pub struct OidcClient {
+    client: Client,
+    config: OidcConfig,
+    metadata: RwLock<Option<Cached<ProviderMetadata>>>,
+    jwks: RwLock<Option<Cached<JwkSet>>>,
+}
+
+/// Valor aleatório em base64url, usado para state, nonce e code_verifier
+pub fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// code_challenge do PKCE (método S256)
+pub fn pkce_challenge(code_verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()))
+}
+
+impl OidcClient {
+    pub fn new(config: OidcConfig) -> Self {
+        OidcClient {
+            client: Client::new(),
+            config,
+            metadata: RwLock::new(None),
+            jwks: RwLock::new(None),
+        }
+    }
+
+    /// Metadados de descoberta do provedor, buscados novamente após `METADATA_TTL`
+    pub async fn metadata(&self) -> Result<Arc<ProviderMetadata>> {
+        if let Some(metadata) = self.metadata.read().await.as_ref().and_then(|m| m.fresh(METADATA_TTL)) {
+            return Ok(metadata);
+        }
+
+        let mut cached = self.metadata.write().await;
+        // Outra requisição pode ter atualizado enquanto esperávamos o lock
+        if let Some(metadata) = cached.as_ref().and_then(|m| m.fresh(METADATA_TTL)) {
+            return Ok(metadata);
+        }
+
+        let metadata = Cached::new(self.discover().await?);
+        let value = metadata.value.clone();
+        *cached = Some(metadata);
+        Ok(value)
+    }
+
+    async fn discover(&self) -> Result<ProviderMetadata> {
+        let url = format!("{}/.well-known/openid-configuration", self.config.issuer_url);
+
+        let response = self.client.get(&url).send().await?;
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow::anyhow!("Erro ao buscar configuração OIDC: {}", error_text));
+        }
+
+        let metadata: ProviderMetadata = response.json().await?;
+
+        if metadata.issuer.trim_end_matches('/') != self.config.issuer_url {
+            return Err(anyhow::anyhow!(
+                "Emissor OIDC divergente: esperado {}, recebido {}",
+                self.config.issuer_url,
+                metadata.issuer
+            ));
+        }
+
+        Ok(metadata)
+    }
+
+    /// Chaves do provedor, buscadas novamente após `JWKS_TTL` ou, quando `kid` não está no cache,
+    /// no máximo uma vez a cada `JWKS_REFRESH_INTERVAL`
+    async fn jwks(&self, metadata: &ProviderMetadata, kid: Option<&str>) -> Result<Arc<JwkSet>> {
+        let usable = |cached: &Cached<JwkSet>| {
+            let known = kid.is_none_or(|kid| cached.value.find(kid).is_some());
+            let ttl = if known { JWKS_TTL } else { JWKS_REFRESH_INTERVAL };
+            cached.fresh(ttl)
+        };
+
+        if let Some(jwks) = self.jwks.read().await.as_ref().and_then(usable) {
+            return Ok(jwks);
+        }
+
+        let mut cached = self.jwks.write().await;
+        if let Some(jwks) = cached.as_ref().and_then(usable) {
+            return Ok(jwks);
+        }
+
+        let jwks: JwkSet = self
+            .client
+            .get(&metadata.jwks_uri)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let jwks = Cached::new(jwks);
+        let value = jwks.value.clone();
+        *cached = Some(jwks);
+        Ok(value)
+    }
+
+    pub async fn issuer(&self) -> Result<String> {
+        Ok(self.metadata().await?.issuer.clone())
+    }
+
+    pub async fn authorization_url(&self, state: &str, nonce: &str, code_verifier: &str) -> Result<String> {
+        let metadata = self.metadata().await?;
+        let url = Url::parse_with_params(
+            &metadata.authorization_endpoint,
+            &[
+                ("response_type", "code"),
+                ("client_id", self.config.client_id.as_str()),
+                ("redirect_uri", self.config.redirect_uri.as_str()),
+                ("scope", self.config.scopes.as_str()),
+                ("state", state),
+                ("nonce", nonce),
+                ("code_challenge", &pkce_challenge(code_verifier)),
+                ("code_challenge_method", "S256"),
+            ],
+        )?;
+        Ok(url.to_string())
+    }
+
+    /// Troca o authorization code pelo id_token e valida suas claims
+    pub async fn exchange_code(&self, code: &str, code_verifier: &str, nonce: &str) -> Result<IdTokenClaims> {
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", self.config.redirect_uri.as_str()),
+            ("client_id", self.config.client_id.as_str()),
+            ("code_verifier", code_verifier),
+        ];
+        if let Some(secret) = &self.config.client_secret {
+            form.push(("client_secret", secret.as_str()));
+        }
+
+        let metadata = self.metadata().await?;
+        let response = self
+            .client
+            .post(&metadata.token_endpoint)
+            .form(&form)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow::anyhow!("Erro ao trocar código OIDC: {}", error_text));
+        }
+
+        let tokens: TokenResponse = response.json().await?;
+        self.verify_id_token(&metadata, &tokens.id_token, nonce).await
+    }
+
+    /// Algoritmos aceitos para a chave: o `alg` declarado no JWK ou, sem ele, os anunciados pelo provedor.
+    /// Só entram algoritmos da família da chave (RSA, EC ou Ed25519); HMAC nunca é aceito.
+    fn allowed_algorithms(metadata: &ProviderMetadata, jwk: &Jwk) -> Vec<Algorithm> {
+        let names = match jwk.common.key_algorithm {
+            Some(alg) => vec![alg.to_string()],
+            None => metadata.id_token_signing_alg_values_supported.clone(),
+        };
+
+        names
+            .iter()
+            .filter_map(|name| Algorithm::from_str(name).ok())
+            .filter(|alg| match &jwk.algorithm {
+                AlgorithmParameters::RSA(_) => matches!(
+                    alg,
+                    Algorithm::RS256
+                        | Algorithm::RS384
+                        | Algorithm::RS512
+                        | Algorithm::PS256
+                        | Algorithm::PS384
+                        | Algorithm::PS512
+                ),
+                AlgorithmParameters::EllipticCurve(_) => matches!(alg, Algorithm::ES256 | Algorithm::ES384),
+                AlgorithmParameters::OctetKeyPair(_) => *alg == Algorithm::EdDSA,
+                AlgorithmParameters::OctetKey(_) => false,
+            })
+            .collect()
+    }
+
+    async fn verify_id_token(&self, metadata: &ProviderMetadata, id_token: &str, nonce: &str) -> Result<IdTokenClaims> {
+        let header = decode_header(id_token)?;
+        let jwks = self.jwks(metadata, header.kid.as_deref()).await?;
+
+        let jwk = match &header.kid {
+            Some(kid) => jwks.find(kid),
+            None => jwks.keys.first(),
+        }
+        .ok_or_else(|| anyhow::anyhow!("Chave do id_token não encontrada no JWKS"))?;
+
+        if !Self::allowed_algorithms(metadata, jwk).contains(&header.alg) {
+            return Err(anyhow::anyhow!("Algoritmo do id_token não permitido: {:?}", header.alg));
+        }
+
+        // A validação aceita apenas o algoritmo já conferido, nunca a lista inteira
+        let mut validation = Validation::new(header.alg);
+        validation.algorithms = vec![header.alg];
+        validation.set_audience(&[&self.config.client_id]);
+        validation.set_issuer(&[&metadata.issuer]);
+
+        let claims = decode::<IdTokenClaims>(id_token, &DecodingKey::from_jwk(jwk)?, &validation)?.claims;
+
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err(anyhow::anyhow!("Nonce do id_token inválido"));
+        }
+
+        Ok(claims)
+    }
+}
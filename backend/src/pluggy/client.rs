@@ -148,6 +148,30 @@ impl PluggyClient {
         Ok(item)
     }
 
+    pub async fn delete_item(&mut self, item_id: &str) -> Result<()> {
+        let api_key = self.get_api_key_header().await?;
+        let url = format!("{}/items/{}", self.config.base_url, item_id);
+
+        let response = self
+            .client
+            .delete(&url)
+            .header("X-API-KEY", &api_key)
+            .send()
+            .await?;
+
+        // Item já removido na Pluggy: nada a fazer
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(());
+        }
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow::anyhow!("Erro ao deletar item: {}", error_text));
+        }
+
+        Ok(())
+    }
+
     pub async fn get_accounts(&mut self, item_id: Option<&str>) -> Result<Vec<Account>> {
         let api_key = self.get_api_key_header().await?;
         let mut url = format!("{}/accounts", self.config.base_url);
@@ -264,6 +288,26 @@ impl PluggyClient {
         Ok(balances)
     }
 
+    pub async fn get_investments(&mut self, item_id: &str) -> Result<Vec<Investment>> {
+        let api_key = self.get_api_key_header().await?;
+        let url = format!("{}/investments?itemId={}", self.config.base_url, item_id);
+
+        let response = self
+            .client
+            .get(&url)
+            .header("X-API-KEY", &api_key)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow::anyhow!("Erro ao buscar investimentos: {}", error_text));
+        }
+
+        let page: PageResponse<Investment> = response.json().await?;
+        Ok(page.results)
+    }
+
     pub async fn test_connection(&mut self) -> Result<String> {
         let api_key = self.authenticate().await?;
         Ok(format!("Conexão com Pluggy API estabelecida com sucesso! API Key obtido: {}...", &api_key[..20.min(api_key.len())]))
@@ -72,18 +72,45 @@ pub struct Transaction {
     pub date: String,
     pub description: Option<String>,
     pub category: Option<String>,
+    #[serde(rename = "categoryId")]
+    pub category_id: Option<String>,
     pub subcategory: Option<String>,
     #[serde(rename = "currencyCode")]
     pub currency_code: String,
     pub merchant: Option<serde_json::Value>,
     pub balance: Option<f64>,
     pub status: Option<String>,
+    #[serde(rename = "creditCardMetadata")]
+    pub credit_card_metadata: Option<CreditCardMetadata>,
     #[serde(rename = "createdAt")]
     pub created_at: String,
     #[serde(rename = "updatedAt")]
     pub updated_at: Option<String>,
 }
 
+/// Metadados de transações de cartão de crédito (parcelamento)
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreditCardMetadata {
+    #[serde(rename = "installmentNumber")]
+    pub installment_number: Option<i32>,
+    #[serde(rename = "totalInstallments")]
+    pub total_installments: Option<i32>,
+    #[serde(rename = "totalAmount")]
+    pub total_amount: Option<f64>,
+    #[serde(rename = "purchaseDate")]
+    pub purchase_date: Option<String>,
+}
+
+/// Estabelecimento da transação (campo `merchant`, guardado como JSON na transação)
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Merchant {
+    pub name: Option<String>,
+    #[serde(rename = "businessName")]
+    pub business_name: Option<String>,
+    pub cnpj: Option<String>,
+    pub category: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Balance {
     pub id: String,
@@ -99,6 +126,23 @@ pub struct Balance {
     pub updated_at: Option<String>,
 }
 
+/// Investimento do item (GET /investments); `balance` é o valor bruto atual da posição
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Investment {
+    pub id: String,
+    #[serde(rename = "itemId")]
+    pub item_id: Option<String>,
+    pub name: Option<String>,
+    pub number: Option<String>,
+    #[serde(rename = "type")]
+    pub type_field: Option<String>,
+    pub subtype: Option<String>,
+    pub balance: Option<f64>,
+    #[serde(rename = "currencyCode")]
+    pub currency_code: Option<String>,
+    pub status: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ErrorResponse {
     pub detail: Option<String>,
@@ -0,0 +1,355 @@
+use crate::merchant_matching;
+use crate::models::categorization_rule::fold_text;
+use crate::models::recurring::{
+    RecurringSeries, FREQUENCY_BIWEEKLY, FREQUENCY_MONTHLY, FREQUENCY_QUARTERLY, FREQUENCY_WEEKLY, FREQUENCY_YEARLY,
+    SOURCE_DETECTED, SOURCE_MANUAL,
+};
+use crate::transfer_detection::looks_like_bill_payment;
+use chrono::{Days, Months, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Mínimo de ocorrências para considerar uma série recorrente
+pub const MIN_OCCURRENCES: usize = 3;
+/// Histórico analisado na detecção
+pub const HISTORY_DAYS: u64 = 400;
+// Séries com mais ocorrências perdidas que isso são consideradas canceladas
+const MAX_MISSED_OCCURRENCES: u32 = 2;
+// Ocorrências criadas por lançamento a cada execução do agendador
+const MAX_CATCH_UP: i32 = 60;
+
+const FREQUENCIES: &[&str] = &[FREQUENCY_WEEKLY, FREQUENCY_BIWEEKLY, FREQUENCY_MONTHLY, FREQUENCY_QUARTERLY, FREQUENCY_YEARLY];
+
+/// Data da ocorrência de número `index` (0 é a própria data inicial).
+/// Meses mais curtos usam o último dia (31/01 → 28/02).
+pub fn occurrence_date(start: NaiveDate, frequency: &str, index: u32) -> Option<NaiveDate> {
+    match frequency {
+        FREQUENCY_WEEKLY => start.checked_add_days(Days::new(7 * index as u64)),
+        FREQUENCY_BIWEEKLY => start.checked_add_days(Days::new(14 * index as u64)),
+        FREQUENCY_MONTHLY => start.checked_add_months(Months::new(index)),
+        FREQUENCY_QUARTERLY => start.checked_add_months(Months::new(3 * index)),
+        FREQUENCY_YEARLY => start.checked_add_months(Months::new(12 * index)),
+        _ => None,
+    }
+}
+
+// Duração aproximada de cada período e a variação aceita, em dias
+fn period_days(frequency: &str) -> (i64, i64) {
+    match frequency {
+        FREQUENCY_WEEKLY => (7, 1),
+        FREQUENCY_BIWEEKLY => (14, 2),
+        FREQUENCY_MONTHLY => (30, 4),
+        FREQUENCY_QUARTERLY => (91, 10),
+        _ => (365, 15),
+    }
+}
+
+fn classify_interval(days: i64) -> Option<&'static str> {
+    FREQUENCIES.iter().copied().find(|f| {
+        let (period, tolerance) = period_days(f);
+        (days - period).abs() <= tolerance
+    })
+}
+
+/// Chave que agrupa as ocorrências de uma mesma cobrança: estabelecimento (ou descrição)
+/// sem acentos, números e palavras muito curtas
+pub fn series_key(description: Option<&str>, merchant: Option<&str>) -> Option<String> {
+    let source = merchant.filter(|m| !m.trim().is_empty()).or(description)?;
+    let folded = fold_text(source);
+    let tokens: Vec<&str> = folded
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|t| t.chars().count() >= 2 && !t.chars().any(|c| c.is_ascii_digit()))
+        .take(4)
+        .collect();
+
+    if tokens.is_empty() {
+        None
+    } else {
+        Some(tokens.join(" "))
+    }
+}
+
+fn median(values: &mut [i64]) -> i64 {
+    values.sort_unstable();
+    values[values.len() / 2]
+}
+
+/// Transação do histórico considerada na detecção
+pub struct HistoryTransaction {
+    pub date: NaiveDate,
+    pub amount: Decimal,
+    pub currency: String,
+    pub description: Option<String>,
+    pub merchant: Option<String>,
+    pub category: Option<String>,
+    pub account_id: Option<Uuid>,
+}
+
+/// Transações dos últimos `HISTORY_DAYS` dias, sem transferências, parcelas e as criadas por lançamentos recorrentes.
+/// Pagamentos de fatura também ficam de fora mesmo sem par: a previsão já conta a fatura do cartão.
+pub async fn load_history(pool: &PgPool, user_id: Uuid, today: NaiveDate) -> sqlx::Result<Vec<HistoryTransaction>> {
+    let since = today - Days::new(HISTORY_DAYS);
+    let rows = sqlx::query!(
+        r#"
+        SELECT t.date, t.amount, t.currency, t.description, t.merchant->>'name' AS merchant, t.category, t.account_id
+        FROM transactions t
+        WHERE t.user_id = $1 AND t.date >= $2 AND t.recurring_transaction_id IS NULL
+          AND t.installment_purchase_id IS NULL
+          AND NOT EXISTS (
+              SELECT 1 FROM transfers tr WHERE t.id IN (tr.outgoing_transaction_id, tr.incoming_transaction_id)
+          )
+        ORDER BY t.date
+        "#,
+        user_id,
+        since
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .filter(|row| !looks_like_bill_payment(row.description.as_deref()))
+        .map(|row| HistoryTransaction {
+            date: row.date,
+            amount: row.amount,
+            currency: row.currency,
+            description: row.description,
+            merchant: row.merchant,
+            category: row.category,
+            account_id: row.account_id,
+        })
+        .collect())
+}
+
+/// Detecta cobranças e receitas recorrentes: ocorrências do mesmo estabelecimento ou descrição,
+/// com o mesmo sinal, intervalos regulares e valores parecidos.
+pub fn detect(history: Vec<HistoryTransaction>, today: NaiveDate) -> Vec<RecurringSeries> {
+    let mut groups: HashMap<(String, bool), Vec<HistoryTransaction>> = HashMap::new();
+    for transaction in history {
+        if transaction.amount.is_zero() {
+            continue;
+        }
+        if let Some(key) = series_key(transaction.description.as_deref(), transaction.merchant.as_deref()) {
+            groups.entry((key, transaction.amount.is_sign_negative())).or_default().push(transaction);
+        }
+    }
+
+    let mut series: Vec<RecurringSeries> = groups.into_values().filter_map(|group| analyze(group, today)).collect();
+    series.sort_by_key(|s| s.next_expected_date);
+    series
+}
+
+fn analyze(mut occurrences: Vec<HistoryTransaction>, today: NaiveDate) -> Option<RecurringSeries> {
+    if occurrences.len() < MIN_OCCURRENCES {
+        return None;
+    }
+    occurrences.sort_by_key(|o| o.date);
+
+    let intervals: Vec<i64> = occurrences.windows(2).map(|w| (w[1].date - w[0].date).num_days()).collect();
+    let frequency = classify_interval(median(&mut intervals.clone()))?;
+
+    // Ao menos 3/4 dos intervalos precisam seguir o período
+    let (period, tolerance) = period_days(frequency);
+    let regular = intervals.iter().filter(|d| (**d - period).abs() <= tolerance).count();
+    if regular * 4 < intervals.len() * 3 {
+        return None;
+    }
+
+    // Ao menos 2/3 dos valores a até 25% da mediana (reajustes e contas variáveis são aceitos)
+    let mut amounts: Vec<Decimal> = occurrences.iter().map(|o| o.amount.abs()).collect();
+    amounts.sort();
+    let typical = amounts[amounts.len() / 2];
+    let close = amounts.iter().filter(|a| (**a - typical).abs() * Decimal::from(4) <= typical).count();
+    if close * 3 < occurrences.len() * 2 {
+        return None;
+    }
+
+    let last = occurrences.last()?;
+
+    // Última mudança de preço: primeira ocorrência com o valor atual e o valor anterior a ela
+    let current_run = occurrences.iter().rev().take_while(|o| o.amount == last.amount).count();
+    let price_change = occurrences
+        .len()
+        .checked_sub(current_run + 1)
+        .map(|i| (occurrences[i].amount, occurrences[i + 1].date));
+
+    let next_expected_date = occurrence_date(last.date, frequency, 1)?;
+    let mut missed_occurrences = 0;
+    while let Some(expected) = occurrence_date(last.date, frequency, missed_occurrences + 1) {
+        if expected + Days::new(tolerance as u64) >= today {
+            break;
+        }
+        missed_occurrences += 1;
+        if missed_occurrences > MAX_MISSED_OCCURRENCES {
+            return None;
+        }
+    }
+
+    let name = last
+        .merchant
+        .clone()
+        .filter(|m| !m.trim().is_empty())
+        .or_else(|| last.description.clone())
+        .unwrap_or_default();
+
+    Some(RecurringSeries {
+        source: SOURCE_DETECTED.to_string(),
+        recurring_transaction_id: None,
+        name,
+        category: last.category.clone(),
+        account_id: last.account_id,
+        frequency: frequency.to_string(),
+        amount: last.amount,
+        currency: last.currency.clone(),
+        occurrences: occurrences.len(),
+        last_date: Some(last.date),
+        next_expected_date: Some(next_expected_date),
+        end_date: None,
+        previous_amount: price_change.map(|(amount, _)| amount),
+        price_changed_on: price_change.map(|(_, date)| date),
+        missed_occurrences,
+    })
+}
+
+/// Séries recorrentes do usuário: as detectadas no histórico e os lançamentos recorrentes ativos,
+/// ordenadas pela próxima data esperada
+pub async fn load_series(pool: &PgPool, user_id: Uuid, today: NaiveDate) -> sqlx::Result<Vec<RecurringSeries>> {
+    let history = load_history(pool, user_id, today).await?;
+    let mut series = detect(history, today);
+
+    let manual = sqlx::query!(
+        r#"
+        SELECT r.id, r.description, r.amount, r.currency, r.category, r.account_id, r.frequency, r.next_date,
+               r.end_date, r.occurrence_count, MAX(t.date) AS last_date
+        FROM recurring_transactions r
+        LEFT JOIN transactions t ON t.recurring_transaction_id = r.id
+        WHERE r.user_id = $1 AND r.enabled
+        GROUP BY r.id
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    series.extend(manual.into_iter().map(|r| RecurringSeries {
+        source: SOURCE_MANUAL.to_string(),
+        recurring_transaction_id: Some(r.id),
+        name: r.description,
+        category: r.category,
+        account_id: r.account_id,
+        frequency: r.frequency,
+        amount: r.amount,
+        currency: r.currency,
+        occurrences: r.occurrence_count.max(0) as usize,
+        last_date: r.last_date,
+        next_expected_date: r.next_date,
+        end_date: r.end_date,
+        previous_amount: None,
+        price_changed_on: None,
+        missed_occurrences: 0,
+    }));
+    series.sort_by_key(|s| (s.next_expected_date.is_none(), s.next_expected_date));
+
+    Ok(series)
+}
+
+/// Cria as transações dos lançamentos recorrentes cuja data chegou, inclusive as atrasadas.
+/// Um lançamento com erro é registrado no log e não impede os demais. Retorna quantas transações foram criadas.
+pub async fn materialize_due(pool: &PgPool) -> anyhow::Result<u64> {
+    let today = Utc::now().date_naive();
+    let due = sqlx::query_scalar!(
+        "SELECT id FROM recurring_transactions WHERE enabled AND next_date <= $1",
+        today
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut created = 0;
+    for id in due {
+        match materialize(pool, id, today).await {
+            Ok(count) => created += count,
+            Err(e) => eprintln!("Erro ao criar transações do lançamento recorrente {}: {}", id, e),
+        }
+    }
+    Ok(created)
+}
+
+/// Cria as ocorrências de um lançamento até `today`
+pub async fn materialize(pool: &PgPool, id: Uuid, today: NaiveDate) -> anyhow::Result<u64> {
+    let mut tx = pool.begin().await?;
+
+    let Some(recurring) = sqlx::query!(
+        r#"
+        SELECT user_id, account_id, description, amount, currency, category, frequency,
+               start_date, end_date, occurrence_count, next_date
+        FROM recurring_transactions
+        WHERE id = $1 AND enabled
+        FOR UPDATE
+        "#,
+        id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    else {
+        return Ok(0);
+    };
+
+    let merchant_id =
+        merchant_matching::resolve(&mut tx, recurring.user_id, Some(&recurring.description), None).await?;
+    let merchant_key = merchant_matching::alias_key(Some(&recurring.description));
+
+    let mut count = recurring.occurrence_count;
+    let mut next_date = recurring.next_date;
+    let mut created = 0;
+
+    while let Some(date) = next_date.filter(|d| *d <= today) {
+        if count - recurring.occurrence_count >= MAX_CATCH_UP {
+            break;
+        }
+
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO transactions
+                (user_id, account_id, amount, date, description, category, currency, recurring_transaction_id,
+                 merchant_id, merchant_key)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            ON CONFLICT (recurring_transaction_id, date) WHERE recurring_transaction_id IS NOT NULL DO NOTHING
+            "#,
+            recurring.user_id,
+            recurring.account_id,
+            recurring.amount,
+            date,
+            recurring.description,
+            recurring.category,
+            recurring.currency,
+            id,
+            merchant_id,
+            merchant_key
+        )
+        .execute(&mut *tx)
+        .await?;
+        created += result.rows_affected();
+
+        count += 1;
+        next_date = next_occurrence(recurring.start_date, &recurring.frequency, count, recurring.end_date);
+    }
+
+    sqlx::query!(
+        "UPDATE recurring_transactions SET occurrence_count = $1, next_date = $2 WHERE id = $3",
+        count,
+        next_date,
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(created)
+}
+
+/// Data da ocorrência `index`, ou nenhuma se passar da data final
+pub fn next_occurrence(start: NaiveDate, frequency: &str, index: i32, end_date: Option<NaiveDate>) -> Option<NaiveDate> {
+    occurrence_date(start, frequency, index.max(0) as u32).filter(|d| end_date.is_none_or(|end| *d <= end))
+}
@@ -0,0 +1,202 @@
+use crate::models::report::{COMPARE_PREVIOUS_PERIOD, COMPARE_PREVIOUS_YEAR};
+use chrono::{Days, Months, NaiveDate};
+use rust_decimal::Decimal;
+use sqlx::{PgPool, Postgres, QueryBuilder, Row};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReportDimension {
+    Category,
+    Subcategory,
+    Account,
+    Merchant,
+    Tag,
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+const DIMENSIONS: &[ReportDimension] = &[
+    ReportDimension::Category,
+    ReportDimension::Subcategory,
+    ReportDimension::Account,
+    ReportDimension::Merchant,
+    ReportDimension::Tag,
+    ReportDimension::Day,
+    ReportDimension::Week,
+    ReportDimension::Month,
+    ReportDimension::Year,
+];
+
+impl ReportDimension {
+    pub fn parse(value: &str) -> Option<Self> {
+        DIMENSIONS.iter().copied().find(|d| d.name() == value)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ReportDimension::Category => "category",
+            ReportDimension::Subcategory => "subcategory",
+            ReportDimension::Account => "account",
+            ReportDimension::Merchant => "merchant",
+            ReportDimension::Tag => "tag",
+            ReportDimension::Day => "day",
+            ReportDimension::Week => "week",
+            ReportDimension::Month => "month",
+            ReportDimension::Year => "year",
+        }
+    }
+
+    pub fn is_period(self) -> bool {
+        matches!(
+            self,
+            ReportDimension::Day | ReportDimension::Week | ReportDimension::Month | ReportDimension::Year
+        )
+    }
+
+    // Expressões da chave e do rótulo do grupo; `date` é a expressão da data do lançamento.
+    // Períodos usam a data de início ("2026-10-01" no mês, a segunda-feira na semana)
+    fn columns(self, date: &str) -> (String, String) {
+        match self {
+            ReportDimension::Category => ("e.category".to_string(), "e.category".to_string()),
+            ReportDimension::Subcategory => {
+                let path = "NULLIF(concat_ws(' > ', e.category, e.subcategory), '')".to_string();
+                (path.clone(), path)
+            }
+            ReportDimension::Account => ("e.account_id::TEXT".to_string(), "a.name".to_string()),
+            ReportDimension::Merchant => ("e.merchant_id::TEXT".to_string(), "m.name".to_string()),
+            ReportDimension::Tag => ("g.name".to_string(), "g.name".to_string()),
+            _ => {
+                let period = format!("date_trunc('{}', {})::DATE::TEXT", self.name(), date);
+                (period.clone(), period)
+            }
+        }
+    }
+
+    fn join(self) -> Option<&'static str> {
+        match self {
+            ReportDimension::Account => Some(" LEFT JOIN accounts a ON a.id = e.account_id"),
+            ReportDimension::Merchant => Some(" LEFT JOIN merchants m ON m.id = e.merchant_id"),
+            ReportDimension::Tag => Some(
+                " LEFT JOIN transaction_tags tt ON tt.transaction_id = e.transaction_id \
+                 LEFT JOIN tags g ON g.id = tt.tag_id",
+            ),
+            _ => None,
+        }
+    }
+}
+
+/// Período agregado. Na comparação, as datas dos lançamentos são deslocadas para o período do
+/// relatório, de modo que dias, semanas e meses coincidam com os da linha correspondente.
+pub struct ReportPeriod {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    date: String,
+}
+
+impl ReportPeriod {
+    pub fn new(from: NaiveDate, to: NaiveDate) -> Self {
+        ReportPeriod { from, to, date: "e.date".to_string() }
+    }
+
+    /// Período imediatamente anterior, com a mesma duração, ou o mesmo período do ano anterior
+    pub fn comparison(compare: &str, from: NaiveDate, to: NaiveDate) -> Option<Self> {
+        match compare {
+            COMPARE_PREVIOUS_PERIOD => {
+                let days = (to - from).num_days() + 1;
+                Some(ReportPeriod {
+                    from: from.checked_sub_days(Days::new(days as u64))?,
+                    to: from.pred_opt()?,
+                    date: format!("(e.date + {})", days),
+                })
+            }
+            COMPARE_PREVIOUS_YEAR => Some(ReportPeriod {
+                from: from.checked_sub_months(Months::new(12))?,
+                to: to.checked_sub_months(Months::new(12))?,
+                date: "(e.date + INTERVAL '1 year')::DATE".to_string(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Filtros aplicados aos lançamentos do relatório
+pub struct ReportFilter {
+    pub account_id: Option<Uuid>,
+    pub category: Option<String>,
+    // Nomes já normalizados; o lançamento precisa ter todas
+    pub tags: Vec<String>,
+}
+
+/// Receitas e despesas (em valor positivo) de um grupo
+pub struct Aggregate {
+    pub keys: Vec<Option<String>>,
+    pub labels: Vec<Option<String>>,
+    pub income: Decimal,
+    pub expense: Decimal,
+}
+
+/// Receitas e despesas do período agrupadas pelas dimensões, sem transferências entre contas do usuário.
+///
+/// Usa `transaction_entries`, então transações divididas entram pelas partes. No agrupamento por tag,
+/// um lançamento com várias tags entra em cada uma. Sem dimensões, retorna uma única linha com os totais.
+pub async fn aggregate(
+    pool: &PgPool,
+    user_id: Uuid,
+    period: &ReportPeriod,
+    dimensions: &[ReportDimension],
+    filter: &ReportFilter,
+) -> sqlx::Result<Vec<Aggregate>> {
+    let mut select = String::from("SELECT ");
+    for (index, dimension) in dimensions.iter().enumerate() {
+        let (key, label) = dimension.columns(&period.date);
+        select.push_str(&format!("{} AS key_{}, {} AS label_{}, ", key, index, label, index));
+    }
+    select.push_str(
+        "COALESCE(SUM(e.amount) FILTER (WHERE e.amount > 0), 0) AS income, \
+         COALESCE(-SUM(e.amount) FILTER (WHERE e.amount < 0), 0) AS expense \
+         FROM transaction_entries e",
+    );
+
+    let mut qb: QueryBuilder<'_, Postgres> = QueryBuilder::new(select);
+    for join in dimensions.iter().filter_map(|d| d.join()) {
+        qb.push(join);
+    }
+
+    qb.push(" WHERE e.user_id = ").push_bind(user_id);
+    qb.push(" AND NOT e.is_transfer AND e.date >= ").push_bind(period.from);
+    qb.push(" AND e.date <= ").push_bind(period.to);
+    if let Some(account_id) = filter.account_id {
+        qb.push(" AND e.account_id = ").push_bind(account_id);
+    }
+    if let Some(category) = &filter.category {
+        qb.push(" AND e.category = ").push_bind(category.clone());
+    }
+    for tag in &filter.tags {
+        qb.push(
+            " AND EXISTS (SELECT 1 FROM transaction_tags ft INNER JOIN tags fg ON ft.tag_id = fg.id \
+             WHERE ft.transaction_id = e.transaction_id AND fg.name = ",
+        )
+        .push_bind(tag.clone())
+        .push(")");
+    }
+
+    if !dimensions.is_empty() {
+        let positions: Vec<String> = (1..=dimensions.len() * 2).map(|p| p.to_string()).collect();
+        qb.push(format!(" GROUP BY {}", positions.join(", ")));
+    }
+
+    let rows = qb.build().fetch_all(pool).await?;
+    rows.into_iter()
+        .map(|row| {
+            let mut keys = Vec::with_capacity(dimensions.len());
+            let mut labels = Vec::with_capacity(dimensions.len());
+            for index in 0..dimensions.len() {
+                keys.push(row.try_get(format!("key_{}", index).as_str())?);
+                labels.push(row.try_get(format!("label_{}", index).as_str())?);
+            }
+            Ok(Aggregate { keys, labels, income: row.try_get("income")?, expense: row.try_get("expense")? })
+        })
+        .collect()
+}
@@ -1,3 +1,4 @@
+use crate::models::tag::Tag;
 use crate::routes::transactions::AuthenticatedUser;
 use chrono::{Datelike, Utc};
 use rocket::http::Status;
@@ -24,7 +25,7 @@ pub async fn get_total_balance(
         FROM accounts a
         INNER JOIN items i ON a.item_id = i.id
         WHERE i.user_id = $1
-        AND a.type ="BANK"
+        AND a.type = 'BANK'
         "#,
         user.id
     )
@@ -49,18 +50,26 @@ pub struct TotalExpensesResponse {
     pub currency: String,
 }
 
-#[get("/accounts/expenses/total")]
+#[get("/accounts/expenses/total?<tag>")]
 pub async fn get_total_expenses(
     user: AuthenticatedUser,
+    tag: Option<&str>,
     pool: &State<PgPool>,
 ) -> Result<Json<TotalExpensesResponse>, Status> {
+    let tag = tag.map(|t| Tag::normalize_name(t).ok_or(Status::BadRequest)).transpose()?;
+
     let result = sqlx::query!(
         r#"
         SELECT COALESCE(ABS(SUM(amount)), 0) as total_expenses
-        FROM transactions
-        WHERE user_id = $1 AND amount < 0
+        FROM transaction_entries e
+        WHERE user_id = $1 AND amount < 0 AND NOT is_transfer
+          AND ($2::TEXT IS NULL OR EXISTS (
+              SELECT 1 FROM transaction_tags tt INNER JOIN tags g ON tt.tag_id = g.id
+              WHERE tt.transaction_id = e.transaction_id AND g.name = $2
+          ))
         "#,
-        user.id
+        user.id,
+        tag
     )
     .fetch_one(pool.inner())
     .await
@@ -87,6 +96,7 @@ pub struct MonthlyExpense {
 #[derive(Debug, Deserialize, rocket::FromForm)]
 pub struct MonthlyExpensesQuery {
     pub year: Option<i32>,
+    pub tag: Option<String>,
 }
 
 #[get("/accounts/expenses/monthly?<query>")]
@@ -96,6 +106,11 @@ pub async fn get_monthly_expenses(
     pool: &State<PgPool>,
 ) -> Result<Json<Vec<MonthlyExpense>>, Status> {
     let year = query.year.unwrap_or_else(|| Utc::now().year() as i32);
+    let tag = query
+        .tag
+        .as_deref()
+        .map(|t| Tag::normalize_name(t).ok_or(Status::BadRequest))
+        .transpose()?;
     
     let results = sqlx::query!(
         r#"
@@ -103,15 +118,21 @@ pub async fn get_monthly_expenses(
             EXTRACT(MONTH FROM date)::INTEGER as month,
             EXTRACT(YEAR FROM date)::INTEGER as year,
             COALESCE(ABS(SUM(amount)), 0) as total
-        FROM transactions
+        FROM transaction_entries e
         WHERE user_id = $1 
           AND amount < 0
+          AND NOT is_transfer
           AND EXTRACT(YEAR FROM date)::INTEGER = $2
+          AND ($3::TEXT IS NULL OR EXISTS (
+              SELECT 1 FROM transaction_tags tt INNER JOIN tags g ON tt.tag_id = g.id
+              WHERE tt.transaction_id = e.transaction_id AND g.name = $3
+          ))
         GROUP BY EXTRACT(MONTH FROM date), EXTRACT(YEAR FROM date)
         ORDER BY month
         "#,
         user.id,
-        year
+        year,
+        tag
     )
     .fetch_all(pool.inner())
     .await
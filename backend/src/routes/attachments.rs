@@ -0,0 +1,352 @@
+use crate::config::AppConfig;
+use crate::models::attachment::{
+    Attachment, AttachmentResponse, CONTENT_TYPE_JPEG, CONTENT_TYPE_PDF, MAX_ATTACHMENTS_PER_TRANSACTION,
+};
+use crate::routes::transactions::AuthenticatedUser;
+use crate::storage::{self, Storage};
+use rocket::data::{Data, ToByteUnit};
+use rocket::http::{ContentType, Header, Status};
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, Responder, State};
+use sqlx::PgPool;
+use std::io::Cursor;
+use std::sync::Arc;
+use uuid::Uuid;
+
+type ApiError = (Status, String);
+
+const THUMBNAIL_SIZE: u32 = 256;
+// Limites da decodificação da imagem para a miniatura, contra arquivos pequenos que expandem para gigabytes
+const MAX_THUMBNAIL_SOURCE_DIMENSION: u32 = 10_000;
+const MAX_THUMBNAIL_DECODE_BYTES: u64 = 256 * 1024 * 1024;
+
+fn db_error(e: sqlx::Error) -> ApiError {
+    eprintln!("Erro de banco de dados nos anexos: {}", e);
+    (Status::InternalServerError, format!("Erro de banco de dados: {}", e))
+}
+
+fn limit_reached() -> ApiError {
+    (
+        Status::Conflict,
+        format!("Limite de {} anexos por transação atingido", MAX_ATTACHMENTS_PER_TRANSACTION),
+    )
+}
+
+fn storage_error(e: anyhow::Error) -> ApiError {
+    eprintln!("Erro no armazenamento de anexos: {}", e);
+    (Status::InternalServerError, "Erro ao acessar o armazenamento de anexos".to_string())
+}
+
+// Mantém só o nome do arquivo (sem diretórios) e limita o tamanho
+fn sanitize_file_name(file_name: Option<&str>, content_type: &str) -> String {
+    let name = file_name
+        .and_then(|n| n.rsplit(['/', '\\']).next())
+        .map(|n| n.trim().chars().filter(|c| !c.is_control()).take(255).collect::<String>())
+        .unwrap_or_default();
+
+    if name.is_empty() {
+        format!("comprovante.{}", Attachment::extension(content_type))
+    } else {
+        name
+    }
+}
+
+/// Miniatura JPEG da imagem; None se a imagem não puder ser decodificada
+fn make_thumbnail(bytes: &[u8]) -> Option<Vec<u8>> {
+    let mut reader = image::ImageReader::new(Cursor::new(bytes)).with_guessed_format().ok()?;
+    let mut limits = image::Limits::default();
+    limits.max_image_width = Some(MAX_THUMBNAIL_SOURCE_DIMENSION);
+    limits.max_image_height = Some(MAX_THUMBNAIL_SOURCE_DIMENSION);
+    limits.max_alloc = Some(MAX_THUMBNAIL_DECODE_BYTES);
+    reader.limits(limits);
+
+    let image = match reader.decode() {
+        Ok(image) => image,
+        Err(e) => {
+            eprintln!("Não foi possível gerar miniatura do anexo: {}", e);
+            return None;
+        }
+    };
+
+    // JPEG não tem canal alfa
+    let thumbnail = image::DynamicImage::ImageRgb8(image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE).to_rgb8());
+    let mut output = Cursor::new(Vec::new());
+    thumbnail.write_to(&mut output, image::ImageFormat::Jpeg).ok()?;
+    Some(output.into_inner())
+}
+
+async fn find_attachment(pool: &PgPool, user_id: Uuid, id: Uuid) -> Result<Attachment, ApiError> {
+    sqlx::query_as!(
+        Attachment,
+        r#"
+        SELECT id, transaction_id, file_name, content_type, size_bytes, storage_key, thumbnail_key, created_at
+        FROM attachments
+        WHERE id = $1 AND user_id = $2
+        "#,
+        id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(db_error)?
+    .ok_or_else(|| (Status::NotFound, "Anexo não encontrado".to_string()))
+}
+
+async fn ensure_transaction(pool: &PgPool, user_id: Uuid, transaction_id: Uuid) -> Result<(), ApiError> {
+    sqlx::query_scalar!(
+        "SELECT id FROM transactions WHERE id = $1 AND user_id = $2",
+        transaction_id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(db_error)?
+    .map(|_| ())
+    .ok_or_else(|| (Status::NotFound, "Transação não encontrada".to_string()))
+}
+
+/// Envia um comprovante: o corpo da requisição é o próprio arquivo (JPEG, PNG, WebP ou PDF).
+/// O tipo é detectado pelo conteúdo, independente do Content-Type informado.
+#[post("/transactions/<id>/attachments?<filename>", data = "<data>")]
+pub async fn upload_attachment(
+    user: AuthenticatedUser,
+    id: Uuid,
+    filename: Option<&str>,
+    data: Data<'_>,
+    pool: &State<PgPool>,
+    config: &State<Arc<AppConfig>>,
+    storage: &State<Arc<dyn Storage>>,
+) -> Result<(Status, Json<AttachmentResponse>), ApiError> {
+    ensure_transaction(pool.inner(), user.id, id).await?;
+
+    let count = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM attachments WHERE transaction_id = $1"#,
+        id
+    )
+    .fetch_one(pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    if count >= MAX_ATTACHMENTS_PER_TRANSACTION {
+        return Err(limit_reached());
+    }
+
+    let max_bytes = config.max_attachment_bytes;
+    let bytes = data
+        .open(max_bytes.bytes())
+        .into_bytes()
+        .await
+        .map_err(|e| (Status::BadRequest, format!("Erro ao ler o arquivo: {}", e)))?;
+
+    if !bytes.is_complete() {
+        return Err((
+            Status::PayloadTooLarge,
+            format!("Arquivo excede o limite de {} KB", max_bytes / 1024),
+        ));
+    }
+    let bytes = bytes.into_inner();
+    if bytes.is_empty() {
+        return Err((Status::BadRequest, "Arquivo vazio".to_string()));
+    }
+
+    let content_type = Attachment::detect_content_type(&bytes).ok_or_else(|| {
+        (
+            Status::UnsupportedMediaType,
+            "Tipo de arquivo não suportado. Envie JPEG, PNG, WebP ou PDF".to_string(),
+        )
+    })?;
+
+    let attachment_id = Uuid::new_v4();
+    let file_name = sanitize_file_name(filename, content_type);
+    let storage_key = format!("{}/{}.{}", user.id, attachment_id, Attachment::extension(content_type));
+    let size_bytes = bytes.len() as i64;
+
+    let thumbnail = if content_type == CONTENT_TYPE_PDF {
+        None
+    } else {
+        let source = bytes.clone();
+        rocket::tokio::task::spawn_blocking(move || make_thumbnail(&source))
+            .await
+            .unwrap_or(None)
+    };
+
+    storage.put(&storage_key, bytes, content_type).await.map_err(storage_error)?;
+
+    let thumbnail_key = match thumbnail {
+        Some(thumbnail) => {
+            let key = format!("{}/{}_thumb.jpg", user.id, attachment_id);
+            match storage.put(&key, thumbnail, CONTENT_TYPE_JPEG).await {
+                Ok(()) => Some(key),
+                Err(e) => {
+                    eprintln!("Erro ao gravar miniatura do anexo {}: {}", attachment_id, e);
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+
+    // O limite é conferido de novo com a transação bloqueada, para que envios simultâneos não o ultrapassem
+    let inserted: Result<Attachment, ApiError> = async {
+        let mut tx = pool.begin().await.map_err(db_error)?;
+
+        sqlx::query_scalar!("SELECT id FROM transactions WHERE id = $1 AND user_id = $2 FOR UPDATE", id, user.id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(db_error)?
+            .ok_or_else(|| (Status::NotFound, "Transação não encontrada".to_string()))?;
+
+        let count = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM attachments WHERE transaction_id = $1"#,
+            id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(db_error)?;
+
+        if count >= MAX_ATTACHMENTS_PER_TRANSACTION {
+            return Err(limit_reached());
+        }
+
+        let attachment = sqlx::query_as!(
+            Attachment,
+            r#"
+            INSERT INTO attachments (id, user_id, transaction_id, file_name, content_type, size_bytes, storage_key, thumbnail_key)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING id, transaction_id, file_name, content_type, size_bytes, storage_key, thumbnail_key, created_at
+            "#,
+            attachment_id,
+            user.id,
+            id,
+            file_name,
+            content_type,
+            size_bytes,
+            storage_key,
+            thumbnail_key
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(db_error)?;
+
+        tx.commit().await.map_err(db_error)?;
+        Ok(attachment)
+    }
+    .await;
+
+    let attachment = match inserted {
+        Ok(attachment) => attachment,
+        Err(e) => {
+            // Sem registro no banco os arquivos ficariam órfãos
+            for key in std::iter::once(&storage_key).chain(thumbnail_key.as_ref()) {
+                if let Err(e) = storage.delete(key).await {
+                    eprintln!("Erro ao remover anexo órfão {}: {}", key, e);
+                }
+            }
+            return Err(e);
+        }
+    };
+
+    Ok((Status::Created, Json(attachment.into())))
+}
+
+#[get("/transactions/<id>/attachments")]
+pub async fn get_attachments(
+    user: AuthenticatedUser,
+    id: Uuid,
+    pool: &State<PgPool>,
+) -> Result<Json<Vec<AttachmentResponse>>, ApiError> {
+    ensure_transaction(pool.inner(), user.id, id).await?;
+
+    let attachments = sqlx::query_as!(
+        Attachment,
+        r#"
+        SELECT id, transaction_id, file_name, content_type, size_bytes, storage_key, thumbnail_key, created_at
+        FROM attachments
+        WHERE transaction_id = $1 AND user_id = $2
+        ORDER BY created_at
+        "#,
+        id,
+        user.id
+    )
+    .fetch_all(pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(attachments.into_iter().map(AttachmentResponse::from).collect()))
+}
+
+#[derive(Responder)]
+pub struct AttachmentDownload {
+    content: (ContentType, Vec<u8>),
+    disposition: Header<'static>,
+    cache_control: Header<'static>,
+}
+
+impl AttachmentDownload {
+    fn new(content_type: &str, bytes: Vec<u8>, file_name: &str) -> Self {
+        // Aspas e caracteres fora do ASCII quebrariam o cabeçalho
+        let safe_name: String = file_name
+            .chars()
+            .map(|c| if (c.is_ascii_graphic() && c != '"' && c != '\\') || c == ' ' { c } else { '_' })
+            .collect();
+
+        AttachmentDownload {
+            content: (ContentType::parse_flexible(content_type).unwrap_or(ContentType::Binary), bytes),
+            disposition: Header::new("Content-Disposition", format!("inline; filename=\"{}\"", safe_name)),
+            cache_control: Header::new("Cache-Control", "private, max-age=3600"),
+        }
+    }
+}
+
+#[get("/attachments/<id>")]
+pub async fn download_attachment(
+    user: AuthenticatedUser,
+    id: Uuid,
+    pool: &State<PgPool>,
+    storage: &State<Arc<dyn Storage>>,
+) -> Result<AttachmentDownload, ApiError> {
+    let attachment = find_attachment(pool.inner(), user.id, id).await?;
+    let bytes = storage.get(&attachment.storage_key).await.map_err(storage_error)?;
+
+    Ok(AttachmentDownload::new(&attachment.content_type, bytes, &attachment.file_name))
+}
+
+#[get("/attachments/<id>/thumbnail")]
+pub async fn download_thumbnail(
+    user: AuthenticatedUser,
+    id: Uuid,
+    pool: &State<PgPool>,
+    storage: &State<Arc<dyn Storage>>,
+) -> Result<AttachmentDownload, ApiError> {
+    let attachment = find_attachment(pool.inner(), user.id, id).await?;
+    let key = attachment
+        .thumbnail_key
+        .ok_or_else(|| (Status::NotFound, "Anexo não possui miniatura".to_string()))?;
+    let bytes = storage.get(&key).await.map_err(storage_error)?;
+
+    Ok(AttachmentDownload::new(CONTENT_TYPE_JPEG, bytes, "miniatura.jpg"))
+}
+
+#[delete("/attachments/<id>")]
+pub async fn delete_attachment(
+    user: AuthenticatedUser,
+    id: Uuid,
+    pool: &State<PgPool>,
+    storage: &State<Arc<dyn Storage>>,
+) -> Result<Status, ApiError> {
+    let result = sqlx::query!("DELETE FROM attachments WHERE id = $1 AND user_id = $2", id, user.id)
+        .execute(pool.inner())
+        .await
+        .map_err(db_error)?;
+
+    if result.rows_affected() == 0 {
+        return Err((Status::NotFound, "Anexo não encontrado".to_string()));
+    }
+
+    // A trigger enfileirou os arquivos; remove já, e o agendador tenta de novo se falhar
+    if let Err(e) = storage::purge_deleted_attachments(pool.inner(), storage.inner().as_ref()).await {
+        eprintln!("Erro ao remover arquivos do anexo {}: {}", id, e);
+    }
+
+    Ok(Status::NoContent)
+}
@@ -1,17 +1,17 @@
-use crate::config::AppConfig;
-use crate::models::user::{LoginUser, NewUser, User};
+use crate::jwt_keys::KeyStore;
+use crate::models::user::{LoginUser, NewUser, OidcCallback, User};
+use crate::oidc::{self, OidcClient};
 use argon2::{
-    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    password_hash::{rand_core::OsRng, PasswordHasher, SaltString},
     Argon2,
 };
-use chrono::{Duration, Utc};
-use jsonwebtoken::{encode, EncodingKey, Header};
 use rocket::http::Status;
 use rocket::serde::json::Json;
-use rocket::{post, State};
+use rocket::{get, post, State};
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 use std::sync::Arc;
+use uuid::Uuid;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AuthResponse {
@@ -19,18 +19,24 @@ pub struct AuthResponse {
     pub user: User,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Claims {
-    sub: String,
-    exp: usize,
-    iat: usize,
+/// Gera o JWT de sessão assinado com a chave ativa
+async fn issue_token(user_id: Uuid, keys: &KeyStore) -> Result<String, (Status, String)> {
+    keys.issue_token(user_id)
+        .await
+        .map_err(|e| (Status::InternalServerError, format!("Erro ao gerar token: {}", e)))
+}
+
+/// Chaves públicas (JWKS) usadas para validar os JWTs emitidos pela aplicação
+#[get("/.well-known/jwks.json")]
+pub async fn jwks(keys: &State<Arc<KeyStore>>) -> Json<serde_json::Value> {
+    Json(keys.jwks().await)
 }
 
 #[post("/auth/register", format = "json", data = "<new_user>")]
 pub async fn register(
     new_user: Json<NewUser>,
     pool: &State<PgPool>,
-    config: &State<Arc<AppConfig>>,
+    keys: &State<Arc<KeyStore>>,
 ) -> Result<Json<AuthResponse>, (Status, String)> {
     // Verifica se email já existe
     let user_exists = sqlx::query!(
@@ -56,7 +62,7 @@ pub async fn register(
     // Inserir usuário
     let user = sqlx::query_as!(
         User,
-        "INSERT INTO users (email, full_name, password_hash) VALUES ($1, $2, $3) RETURNING id, email, full_name, password_hash, created_at, updated_at",
+        "INSERT INTO users (email, full_name, password_hash) VALUES ($1, $2, $3) RETURNING id, email, full_name, password_hash, preferred_currency, locale, timezone, pending_email, created_at, updated_at",
         new_user.email,
         new_user.full_name,
         password_hash
@@ -66,23 +72,7 @@ pub async fn register(
     .map_err(|e| (Status::InternalServerError, format!("Erro ao criar usuário: {}", e)))?;
 
     // Gera JWT
-    let expiration = Utc::now()
-        .checked_add_signed(Duration::hours(24))
-        .expect("valid timestamp")
-        .timestamp();
-
-    let claims = Claims {
-        sub: user.id.to_string(),
-        exp: expiration as usize,
-        iat: Utc::now().timestamp() as usize,
-    };
-
-    let token = encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(config.jwt_secret.as_bytes()),
-    )
-    .map_err(|e| (Status::InternalServerError, format!("Erro ao gerar token: {}", e)))?;
+    let token = issue_token(user.id, keys).await?;
 
     Ok(Json(AuthResponse { token, user }))
 }
@@ -91,12 +81,12 @@ pub async fn register(
 pub async fn login(
     login_user: Json<LoginUser>,
     pool: &State<PgPool>,
-    config: &State<Arc<AppConfig>>,
+    keys: &State<Arc<KeyStore>>,
 ) -> Result<Json<AuthResponse>, (Status, String)> {
     // Busca usuário
     let user = sqlx::query_as!(
         User,
-        "SELECT id, email, full_name, password_hash, created_at, updated_at FROM users WHERE email = $1",
+        "SELECT id, email, full_name, password_hash, preferred_currency, locale, timezone, pending_email, created_at, updated_at FROM users WHERE email = $1",
         login_user.email
     )
     .fetch_optional(pool.inner())
@@ -109,32 +99,187 @@ pub async fn login(
     };
 
     // Verifica senha
-    let parsed_hash = PasswordHash::new(&user.password_hash)
+    let password_ok = user
+        .verify_password(&login_user.password)
         .map_err(|e| (Status::InternalServerError, format!("Erro ao processar hash: {}", e)))?;
-    
-    if Argon2::default().verify_password(login_user.password.as_bytes(), &parsed_hash).is_err() {
+
+    if !password_ok {
         return Err((Status::Unauthorized, "Email ou senha inválidos".to_string()));
     }
 
     // Gera JWT
-    let expiration = Utc::now()
-        .checked_add_signed(Duration::hours(24))
-        .expect("valid timestamp")
-        .timestamp();
-
-    let claims = Claims {
-        sub: user.id.to_string(),
-        exp: expiration as usize,
-        iat: Utc::now().timestamp() as usize,
-    };
+    let token = issue_token(user.id, keys).await?;
+
+    Ok(Json(AuthResponse { token, user }))
+}
+
+#[derive(Debug, Serialize)]
+pub struct OidcAuthorizeResponse {
+    pub authorization_url: String,
+    pub state: String,
+}
+
+// Cliente OIDC com os metadados do provedor carregados (ou ainda válidos no cache)
+async fn oidc_client(oidc: &Option<Arc<OidcClient>>) -> Result<Arc<OidcClient>, (Status, String)> {
+    let client = oidc
+        .clone()
+        .ok_or_else(|| (Status::NotFound, "Login OIDC não está configurado".to_string()))?;
+
+    client.metadata().await.map_err(|e| {
+        eprintln!("Erro ao consultar provedor OIDC: {}", e);
+        (Status::BadGateway, "Provedor de identidade indisponível".to_string())
+    })?;
+    Ok(client)
+}
+
+// Inicia o fluxo authorization code + PKCE; o frontend redireciona para `authorization_url`
+#[get("/auth/oidc/authorize")]
+pub async fn oidc_authorize(
+    pool: &State<PgPool>,
+    oidc: &State<Option<Arc<OidcClient>>>,
+) -> Result<Json<OidcAuthorizeResponse>, (Status, String)> {
+    let client = oidc_client(oidc).await?;
+
+    let state = oidc::random_token();
+    let nonce = oidc::random_token();
+    let code_verifier = oidc::random_token();
+
+    // Limpa estados abandonados antes de registrar o novo
+    sqlx::query!("DELETE FROM oidc_login_states WHERE created_at < CURRENT_TIMESTAMP - INTERVAL '10 minutes'")
+        .execute(pool.inner())
+        .await
+        .map_err(|e| (Status::InternalServerError, format!("Erro de banco de dados: {}", e)))?;
+
+    sqlx::query!(
+        "INSERT INTO oidc_login_states (state, code_verifier, nonce) VALUES ($1, $2, $3)",
+        state,
+        code_verifier,
+        nonce
+    )
+    .execute(pool.inner())
+    .await
+    .map_err(|e| (Status::InternalServerError, format!("Erro de banco de dados: {}", e)))?;
+
+    let authorization_url = client
+        .authorization_url(&state, &nonce, &code_verifier)
+        .await
+        .map_err(|e| (Status::InternalServerError, format!("Erro ao montar URL de autorização: {}", e)))?;
+
+    Ok(Json(OidcAuthorizeResponse { authorization_url, state }))
+}
 
-    let token = encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(config.jwt_secret.as_bytes()),
+// Recebe o code devolvido pelo provedor (repassado pelo frontend) e emite o JWT da aplicação
+#[post("/auth/oidc/callback", format = "json", data = "<callback>")]
+pub async fn oidc_callback(
+    callback: Json<OidcCallback>,
+    pool: &State<PgPool>,
+    oidc: &State<Option<Arc<OidcClient>>>,
+    keys: &State<Arc<KeyStore>>,
+) -> Result<Json<AuthResponse>, (Status, String)> {
+    let login_state = sqlx::query!(
+        r#"
+        DELETE FROM oidc_login_states
+        WHERE state = $1 AND created_at > CURRENT_TIMESTAMP - INTERVAL '10 minutes'
+        RETURNING code_verifier, nonce
+        "#,
+        callback.state
     )
-    .map_err(|e| (Status::InternalServerError, format!("Erro ao gerar token: {}", e)))?;
+    .fetch_optional(pool.inner())
+    .await
+    .map_err(|e| (Status::InternalServerError, format!("Erro de banco de dados: {}", e)))?
+    .ok_or_else(|| (Status::BadRequest, "Estado de login inválido ou expirado".to_string()))?;
+
+    let client = oidc_client(oidc).await?;
+
+    let claims = client
+        .exchange_code(&callback.code, &login_state.code_verifier, &login_state.nonce)
+        .await
+        .map_err(|e| {
+            eprintln!("Erro na validação OIDC: {}", e);
+            (Status::Unauthorized, "Não foi possível validar o login externo".to_string())
+        })?;
+
+    let issuer = client.issuer().await.map_err(|e| {
+        eprintln!("Erro ao consultar provedor OIDC: {}", e);
+        (Status::BadGateway, "Provedor de identidade indisponível".to_string())
+    })?;
+    let user = find_or_link_user(pool.inner(), &issuer, &claims).await?;
+
+    let token = issue_token(user.id, keys).await?;
 
     Ok(Json(AuthResponse { token, user }))
 }
 
+/// Resolve o usuário local de uma identidade externa: pela própria identidade,
+/// pelo email verificado de um usuário existente ou criando um novo usuário
+async fn find_or_link_user(
+    pool: &PgPool,
+    issuer: &str,
+    claims: &oidc::IdTokenClaims,
+) -> Result<User, (Status, String)> {
+    let db_error = |e: sqlx::Error| (Status::InternalServerError, format!("Erro de banco de dados: {}", e));
+
+    let linked = sqlx::query_as!(
+        User,
+        r#"
+        SELECT u.id, u.email, u.full_name, u.password_hash, u.preferred_currency, u.locale, u.timezone, u.pending_email, u.created_at, u.updated_at
+        FROM users u
+        INNER JOIN user_identities ui ON ui.user_id = u.id
+        WHERE ui.issuer = $1 AND ui.subject = $2
+        "#,
+        issuer,
+        claims.sub
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(db_error)?;
+
+    if let Some(user) = linked {
+        return Ok(user);
+    }
+
+    // Vincular por email só é seguro quando o provedor confirma o endereço
+    let email = match (&claims.email, claims.email_verified) {
+        (Some(email), true) => email.trim().to_lowercase(),
+        _ => return Err((Status::Forbidden, "O provedor não informou um email verificado".to_string())),
+    };
+
+    let existing = sqlx::query_as!(
+        User,
+        "SELECT id, email, full_name, password_hash, preferred_currency, locale, timezone, pending_email, created_at, updated_at FROM users WHERE lower(email) = $1",
+        email
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(db_error)?;
+
+    let user = match existing {
+        Some(user) => user,
+        None => {
+            let full_name = claims.name.clone().unwrap_or_else(|| email.clone());
+            sqlx::query_as!(
+                User,
+                "INSERT INTO users (email, full_name) VALUES ($1, $2) RETURNING id, email, full_name, password_hash, preferred_currency, locale, timezone, pending_email, created_at, updated_at",
+                email,
+                full_name
+            )
+            .fetch_one(pool)
+            .await
+            .map_err(db_error)?
+        }
+    };
+
+    sqlx::query!(
+        "INSERT INTO user_identities (user_id, issuer, subject, email) VALUES ($1, $2, $3, $4)",
+        user.id,
+        issuer,
+        claims.sub,
+        email
+    )
+    .execute(pool)
+    .await
+    .map_err(db_error)?;
+
+    eprintln!("Identidade {} ({}) vinculada ao usuário {}", claims.sub, issuer, user.id);
+    Ok(user)
+}
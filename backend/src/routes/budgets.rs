@@ -0,0 +1,190 @@
+use crate::budget_planning;
+use crate::models::budget::{
+    is_valid_kind, Budget, BudgetMonth, NewBudget, UpdateBudget, KIND_FIXED, KIND_PERCENT_OF_INCOME,
+    MAX_BUDGETS_PER_MONTH,
+};
+use crate::models::category::KIND_EXPENSE;
+use crate::routes::transactions::AuthenticatedUser;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, put, State};
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+type ApiError = (Status, String);
+
+fn db_error(e: sqlx::Error) -> ApiError {
+    match e {
+        sqlx::Error::Database(ref db) if db.is_unique_violation() => {
+            (Status::Conflict, "Já existe um orçamento para essa categoria no mês".to_string())
+        }
+        e => {
+            eprintln!("Erro de banco de dados nos orçamentos: {}", e);
+            (Status::InternalServerError, format!("Erro de banco de dados: {}", e))
+        }
+    }
+}
+
+fn bad_request(message: impl Into<String>) -> ApiError {
+    (Status::BadRequest, message.into())
+}
+
+fn not_found() -> ApiError {
+    (Status::NotFound, "Orçamento não encontrado".to_string())
+}
+
+fn parse_month(month: &str) -> Result<chrono::NaiveDate, ApiError> {
+    budget_planning::parse_month(month).ok_or_else(|| bad_request("Mês inválido (use AAAA-MM)"))
+}
+
+fn validate_amount(kind: &str, amount: Decimal) -> Result<(), ApiError> {
+    if amount <= Decimal::ZERO {
+        return Err(bad_request("O valor do orçamento deve ser positivo"));
+    }
+    if kind == KIND_PERCENT_OF_INCOME && amount > Decimal::from(100) {
+        return Err(bad_request("O percentual da receita deve estar entre 0 e 100"));
+    }
+    Ok(())
+}
+
+/// Planejado x realizado x saldo dos orçamentos do mês (AAAA-MM)
+#[get("/budgets/<month>")]
+pub async fn get_budget_month(
+    user: AuthenticatedUser,
+    month: &str,
+    pool: &State<PgPool>,
+) -> Result<Json<BudgetMonth>, ApiError> {
+    let month = parse_month(month)?;
+    let progress = budget_planning::month_progress(pool.inner(), user.id, month).await.map_err(db_error)?;
+    Ok(Json(progress))
+}
+
+#[post("/budgets", format = "json", data = "<budget>")]
+pub async fn create_budget(
+    user: AuthenticatedUser,
+    budget: Json<NewBudget>,
+    pool: &State<PgPool>,
+) -> Result<Json<Budget>, ApiError> {
+    let month = parse_month(&budget.month)?;
+    let kind = budget.kind.as_deref().map(|k| k.trim().to_lowercase()).unwrap_or_else(|| KIND_FIXED.to_string());
+    if !is_valid_kind(&kind) {
+        return Err(bad_request("Tipo inválido. Use 'fixed' ou 'percent_of_income'"));
+    }
+    validate_amount(&kind, budget.amount)?;
+
+    let category = sqlx::query!(
+        "SELECT parent_id, kind FROM categories WHERE id = $1 AND user_id = $2",
+        budget.category_id,
+        user.id
+    )
+    .fetch_optional(pool.inner())
+    .await
+    .map_err(db_error)?
+    .ok_or((Status::NotFound, "Categoria não encontrada".to_string()))?;
+
+    if category.parent_id.is_some() || category.kind != KIND_EXPENSE {
+        return Err(bad_request("Orçamentos são definidos para categorias principais de despesa"));
+    }
+
+    let count = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM budgets WHERE user_id = $1 AND month = $2"#,
+        user.id,
+        month
+    )
+    .fetch_one(pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    if count >= MAX_BUDGETS_PER_MONTH {
+        return Err((Status::Conflict, format!("Limite de {} orçamentos por mês atingido", MAX_BUDGETS_PER_MONTH)));
+    }
+
+    let created = sqlx::query_as!(
+        Budget,
+        r#"
+        INSERT INTO budgets (user_id, category_id, month, kind, amount, rollover)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id, category_id, month, kind, amount, rollover, created_at, updated_at
+        "#,
+        user.id,
+        budget.category_id,
+        month,
+        kind,
+        budget.amount,
+        budget.rollover
+    )
+    .fetch_one(pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(created))
+}
+
+/// Altera o orçamento de um mês; os meses seguintes não mudam
+#[put("/budgets/<id>", format = "json", data = "<update>")]
+pub async fn update_budget(
+    user: AuthenticatedUser,
+    id: Uuid,
+    update: Json<UpdateBudget>,
+    pool: &State<PgPool>,
+) -> Result<Json<Budget>, ApiError> {
+    let current = sqlx::query!(
+        "SELECT kind, amount, rollover FROM budgets WHERE id = $1 AND user_id = $2",
+        id,
+        user.id
+    )
+    .fetch_optional(pool.inner())
+    .await
+    .map_err(db_error)?
+    .ok_or_else(not_found)?;
+
+    let kind = match update.kind.as_deref().map(|k| k.trim().to_lowercase()) {
+        Some(kind) if !is_valid_kind(&kind) => {
+            return Err(bad_request("Tipo inválido. Use 'fixed' ou 'percent_of_income'"));
+        }
+        Some(kind) => kind,
+        None => current.kind,
+    };
+    let amount = update.amount.unwrap_or(current.amount);
+    validate_amount(&kind, amount)?;
+
+    let updated = sqlx::query_as!(
+        Budget,
+        r#"
+        UPDATE budgets
+        SET kind = $1, amount = $2, rollover = $3
+        WHERE id = $4 AND user_id = $5
+        RETURNING id, category_id, month, kind, amount, rollover, created_at, updated_at
+        "#,
+        kind,
+        amount,
+        update.rollover.unwrap_or(current.rollover),
+        id,
+        user.id
+    )
+    .fetch_optional(pool.inner())
+    .await
+    .map_err(db_error)?
+    .ok_or_else(not_found)?;
+
+    Ok(Json(updated))
+}
+
+#[delete("/budgets/<id>")]
+pub async fn delete_budget(
+    user: AuthenticatedUser,
+    id: Uuid,
+    pool: &State<PgPool>,
+) -> Result<Status, ApiError> {
+    let result = sqlx::query!("DELETE FROM budgets WHERE id = $1 AND user_id = $2", id, user.id)
+        .execute(pool.inner())
+        .await
+        .map_err(db_error)?;
+
+    if result.rows_affected() == 0 {
+        return Err(not_found());
+    }
+
+    Ok(Status::NoContent)
+}
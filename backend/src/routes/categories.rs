@@ -0,0 +1,497 @@
+use crate::models::budget::KIND_PERCENT_OF_INCOME;
+use crate::models::category::{
+    Category, CategoryMapping, CategoryNode, CategoryRewriteResult, MergeCategory, NewCategory, NewCategoryMapping,
+    UpdateCategory, KIND_EXPENSE, MAX_CATEGORIES_PER_USER,
+};
+use crate::routes::transactions::AuthenticatedUser;
+use crate::taxonomy;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, put, State};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+type ApiError = (Status, String);
+
+fn db_error(e: sqlx::Error) -> ApiError {
+    match e {
+        sqlx::Error::Database(ref db) if db.is_unique_violation() => {
+            (Status::Conflict, "Já existe uma categoria com esse nome".to_string())
+        }
+        e => {
+            eprintln!("Erro de banco de dados nas categorias: {}", e);
+            (Status::InternalServerError, format!("Erro de banco de dados: {}", e))
+        }
+    }
+}
+
+fn bad_request(message: impl Into<String>) -> ApiError {
+    (Status::BadRequest, message.into())
+}
+
+fn not_found() -> ApiError {
+    (Status::NotFound, "Categoria não encontrada".to_string())
+}
+
+fn clean(value: &Option<String>) -> Option<String> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty()).map(str::to_string)
+}
+
+fn validate_color(color: &Option<String>) -> Result<(), ApiError> {
+    match clean(color) {
+        Some(color) if !Category::is_valid_color(&color) => Err(bad_request("Cor inválida. Use o formato #RRGGBB")),
+        _ => Ok(()),
+    }
+}
+
+async fn fetch_category(conn: &mut PgConnection, user_id: Uuid, id: Uuid) -> Result<Category, ApiError> {
+    sqlx::query_as!(
+        Category,
+        r#"
+        SELECT id, parent_id, name, kind, icon, color, created_at, updated_at
+        FROM categories
+        WHERE id = $1 AND user_id = $2
+        "#,
+        id,
+        user_id
+    )
+    .fetch_optional(conn)
+    .await
+    .map_err(db_error)?
+    .ok_or_else(not_found)
+}
+
+/// Árvore de categorias do usuário; na primeira consulta cria a taxonomia padrão
+#[get("/categories")]
+pub async fn get_categories(
+    user: AuthenticatedUser,
+    pool: &State<PgPool>,
+) -> Result<Json<Vec<CategoryNode>>, ApiError> {
+    taxonomy::ensure_default_categories(pool.inner(), user.id).await.map_err(db_error)?;
+
+    let all = sqlx::query_as!(
+        Category,
+        r#"
+        SELECT id, parent_id, name, kind, icon, color, created_at, updated_at
+        FROM categories
+        WHERE user_id = $1
+        ORDER BY name
+        "#,
+        user.id
+    )
+    .fetch_all(pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    let (parents, children): (Vec<Category>, Vec<Category>) = all.into_iter().partition(|c| c.parent_id.is_none());
+
+    let tree = parents
+        .into_iter()
+        .map(|category| {
+            let children = children.iter().filter(|c| c.parent_id == Some(category.id)).cloned().collect();
+            CategoryNode { category, children }
+        })
+        .collect();
+
+    Ok(Json(tree))
+}
+
+#[post("/categories", format = "json", data = "<category>")]
+pub async fn create_category(
+    user: AuthenticatedUser,
+    category: Json<NewCategory>,
+    pool: &State<PgPool>,
+) -> Result<Json<Category>, ApiError> {
+    let name = clean(&Some(category.name.clone())).ok_or_else(|| bad_request("Informe o nome da categoria"))?;
+    validate_color(&category.color)?;
+
+    let count = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM categories WHERE user_id = $1"#,
+        user.id
+    )
+    .fetch_one(pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    if count >= MAX_CATEGORIES_PER_USER {
+        return Err((Status::Conflict, format!("Limite de {} categorias atingido", MAX_CATEGORIES_PER_USER)));
+    }
+
+    let mut conn = pool.acquire().await.map_err(db_error)?;
+
+    // Subcategorias herdam o tipo da categoria principal
+    let kind = match category.parent_id {
+        Some(parent_id) => {
+            let parent = fetch_category(&mut conn, user.id, parent_id).await?;
+            if parent.parent_id.is_some() {
+                return Err(bad_request("Subcategorias não podem ter subcategorias"));
+            }
+            parent.kind
+        }
+        None => {
+            let kind = clean(&category.kind).map(|k| k.to_lowercase()).unwrap_or_else(|| KIND_EXPENSE.to_string());
+            if !Category::is_valid_kind(&kind) {
+                return Err(bad_request("Tipo inválido. Use 'expense', 'income' ou 'transfer'"));
+            }
+            kind
+        }
+    };
+
+    let created = sqlx::query_as!(
+        Category,
+        r#"
+        INSERT INTO categories (user_id, parent_id, name, kind, icon, color)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id, parent_id, name, kind, icon, color, created_at, updated_at
+        "#,
+        user.id,
+        category.parent_id,
+        name,
+        kind,
+        clean(&category.icon),
+        clean(&category.color).map(|c| c.to_uppercase())
+    )
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(created))
+}
+
+/// Altera a categoria. Um novo nome é aplicado às transações, divisões e regras que usam o nome antigo;
+/// mudar o tipo de uma categoria principal muda também o das subcategorias.
+/// Ícone e cor vazios removem o valor atual.
+#[put("/categories/<id>", format = "json", data = "<update>")]
+pub async fn update_category(
+    user: AuthenticatedUser,
+    id: Uuid,
+    update: Json<UpdateCategory>,
+    pool: &State<PgPool>,
+) -> Result<Json<CategoryRewriteResult>, ApiError> {
+    validate_color(&update.color)?;
+
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    let current = fetch_category(&mut tx, user.id, id).await?;
+
+    let kind = match clean(&update.kind).map(|k| k.to_lowercase()) {
+        Some(kind) if !Category::is_valid_kind(&kind) => {
+            return Err(bad_request("Tipo inválido. Use 'expense', 'income' ou 'transfer'"));
+        }
+        Some(kind) if current.parent_id.is_some() && kind != current.kind => {
+            return Err(bad_request("O tipo de uma subcategoria é definido pela categoria principal"));
+        }
+        Some(kind) => kind,
+        None => current.kind.clone(),
+    };
+
+    let name = match &update.name {
+        Some(name) => clean(&Some(name.clone())).ok_or_else(|| bad_request("Informe o nome da categoria"))?,
+        None => current.name.clone(),
+    };
+
+    // Campo ausente mantém o valor; vazio remove
+    let icon = match &update.icon {
+        Some(_) => clean(&update.icon),
+        None => current.icon.clone(),
+    };
+    let color = match &update.color {
+        Some(_) => clean(&update.color).map(|c| c.to_uppercase()),
+        None => current.color.clone(),
+    };
+
+    let old_path = taxonomy::category_path(&mut tx, user.id, id).await.map_err(db_error)?.ok_or_else(not_found)?;
+
+    sqlx::query!(
+        "UPDATE categories SET name = $1, kind = $2, icon = $3, color = $4 WHERE id = $5",
+        name,
+        kind,
+        icon,
+        color,
+        id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(db_error)?;
+
+    if current.parent_id.is_none() && kind != current.kind {
+        sqlx::query!("UPDATE categories SET kind = $1 WHERE parent_id = $2", kind, id)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error)?;
+    }
+
+    let mut transactions_updated = 0;
+    if name != current.name {
+        let new_path = taxonomy::category_path(&mut tx, user.id, id).await.map_err(db_error)?.ok_or_else(not_found)?;
+        transactions_updated = taxonomy::rewrite_path(&mut tx, user.id, &old_path, &new_path)
+            .await
+            .map_err(db_error)?;
+    }
+
+    tx.commit().await.map_err(db_error)?;
+
+    Ok(Json(CategoryRewriteResult { transactions_updated }))
+}
+
+/// Remove a categoria e suas subcategorias. As transações mantêm o nome atual e os ids da
+/// Pluggy associados a ela voltam a ser aprendidos na próxima sincronização.
+#[delete("/categories/<id>")]
+pub async fn delete_category(
+    user: AuthenticatedUser,
+    id: Uuid,
+    pool: &State<PgPool>,
+) -> Result<Status, ApiError> {
+    let result = sqlx::query!("DELETE FROM categories WHERE id = $1 AND user_id = $2", id, user.id)
+        .execute(pool.inner())
+        .await
+        .map_err(db_error)?;
+
+    if result.rows_affected() == 0 {
+        return Err(not_found());
+    }
+
+    Ok(Status::NoContent)
+}
+
+/// Passa os orçamentos da categoria mesclada para o destino. No mês em que os dois têm orçamento,
+/// os valores são somados; tipos diferentes, percentual acima de 100% ou destino que não aceita orçamento
+/// (subcategoria ou categoria de receita) são conflito, para que nenhum orçamento seja perdido.
+async fn merge_budgets(conn: &mut PgConnection, source: &Category, target: &Category) -> Result<(), ApiError> {
+    let budgets = sqlx::query_scalar!(r#"SELECT COUNT(*) AS "count!" FROM budgets WHERE category_id = $1"#, source.id)
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(db_error)?;
+    if budgets == 0 {
+        return Ok(());
+    }
+
+    if target.parent_id.is_some() || target.kind != KIND_EXPENSE {
+        return Err((
+            Status::Conflict,
+            "A categoria tem orçamentos e o destino não aceita orçamento; remova-os antes de mesclar".to_string(),
+        ));
+    }
+
+    let conflicting = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) AS "count!"
+        FROM budgets s
+        INNER JOIN budgets t ON t.category_id = $1 AND t.month = s.month
+        WHERE s.category_id = $2
+          AND (s.kind <> t.kind OR (s.kind = $3 AND s.amount + t.amount > 100))
+        "#,
+        target.id,
+        source.id,
+        KIND_PERCENT_OF_INCOME
+    )
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(db_error)?;
+
+    if conflicting > 0 {
+        return Err((
+            Status::Conflict,
+            "As duas categorias têm orçamentos incompatíveis no mesmo mês (tipos diferentes ou mais de 100% da receita); \
+             ajuste-os antes de mesclar"
+                .to_string(),
+        ));
+    }
+
+    sqlx::query!(
+        r#"
+        UPDATE budgets t
+        SET amount = t.amount + s.amount, rollover = t.rollover OR s.rollover
+        FROM budgets s
+        WHERE t.category_id = $1 AND s.category_id = $2 AND s.month = t.month
+        "#,
+        target.id,
+        source.id
+    )
+    .execute(&mut *conn)
+    .await
+    .map_err(db_error)?;
+
+    sqlx::query!(
+        r#"
+        UPDATE budgets SET category_id = $1
+        WHERE category_id = $2
+          AND month NOT IN (SELECT month FROM budgets WHERE category_id = $1)
+        "#,
+        target.id,
+        source.id
+    )
+    .execute(&mut *conn)
+    .await
+    .map_err(db_error)?;
+
+    Ok(())
+}
+
+/// Mescla a categoria em outra: as transações, divisões, regras e associações com a Pluggy passam
+/// para o destino (e os orçamentos, somados no mês em que os dois têm) e a categoria é removida. Subcategorias de uma categoria principal mesclada em outra
+/// principal são movidas para o destino, ou mescladas com a subcategoria de mesmo nome.
+#[post("/categories/<id>/merge", format = "json", data = "<merge>")]
+pub async fn merge_category(
+    user: AuthenticatedUser,
+    id: Uuid,
+    merge: Json<MergeCategory>,
+    pool: &State<PgPool>,
+) -> Result<Json<CategoryRewriteResult>, ApiError> {
+    if merge.into == id {
+        return Err(bad_request("Não é possível mesclar uma categoria nela mesma"));
+    }
+
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    let source = fetch_category(&mut tx, user.id, id).await?;
+    let target = fetch_category(&mut tx, user.id, merge.into).await?;
+
+    let children = sqlx::query!(
+        "SELECT id, name FROM categories WHERE parent_id = $1",
+        source.id
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(db_error)?;
+
+    if !children.is_empty() && target.parent_id.is_some() {
+        return Err(bad_request("Uma categoria com subcategorias só pode ser mesclada em outra categoria principal"));
+    }
+
+    let source_path = taxonomy::category_path(&mut tx, user.id, source.id).await.map_err(db_error)?.ok_or_else(not_found)?;
+    let target_path = taxonomy::category_path(&mut tx, user.id, target.id).await.map_err(db_error)?.ok_or_else(not_found)?;
+
+    let transactions_updated = taxonomy::rewrite_path(&mut tx, user.id, &source_path, &target_path)
+        .await
+        .map_err(db_error)?;
+
+    for child in children {
+        let existing = sqlx::query_scalar!(
+            "SELECT id FROM categories WHERE parent_id = $1 AND LOWER(name) = LOWER($2)",
+            target.id,
+            child.name
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(db_error)?;
+
+        match existing {
+            Some(existing_id) => {
+                sqlx::query!("UPDATE category_mappings SET category_id = $1 WHERE category_id = $2", existing_id, child.id)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(db_error)?;
+                sqlx::query!("DELETE FROM categories WHERE id = $1", child.id)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(db_error)?;
+            }
+            None => {
+                sqlx::query!(
+                    "UPDATE categories SET parent_id = $1, kind = $2 WHERE id = $3",
+                    target.id,
+                    target.kind,
+                    child.id
+                )
+                .execute(&mut *tx)
+                .await
+                .map_err(db_error)?;
+            }
+        }
+    }
+
+    sqlx::query!("UPDATE category_mappings SET category_id = $1 WHERE category_id = $2", target.id, source.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+
+    merge_budgets(&mut tx, &source, &target).await?;
+
+    sqlx::query!("DELETE FROM categories WHERE id = $1", source.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+
+    tx.commit().await.map_err(db_error)?;
+
+    Ok(Json(CategoryRewriteResult { transactions_updated }))
+}
+
+#[get("/categories/mappings")]
+pub async fn get_category_mappings(
+    user: AuthenticatedUser,
+    pool: &State<PgPool>,
+) -> Result<Json<Vec<CategoryMapping>>, ApiError> {
+    let mappings = sqlx::query_as!(
+        CategoryMapping,
+        r#"
+        SELECT pluggy_category_id, category_id, created_at
+        FROM category_mappings
+        WHERE user_id = $1
+        ORDER BY pluggy_category_id
+        "#,
+        user.id
+    )
+    .fetch_all(pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(mappings))
+}
+
+/// Associa um id de categoria da Pluggy a uma categoria do usuário.
+/// Vale para as próximas sincronizações; transações existentes não são alteradas.
+#[put("/categories/mappings/<pluggy_category_id>", format = "json", data = "<mapping>")]
+pub async fn set_category_mapping(
+    user: AuthenticatedUser,
+    pluggy_category_id: &str,
+    mapping: Json<NewCategoryMapping>,
+    pool: &State<PgPool>,
+) -> Result<Json<CategoryMapping>, ApiError> {
+    let pluggy_category_id = pluggy_category_id.trim();
+    if pluggy_category_id.is_empty() || pluggy_category_id.len() > 20 {
+        return Err(bad_request("Id de categoria da Pluggy inválido"));
+    }
+
+    let mut conn = pool.acquire().await.map_err(db_error)?;
+    fetch_category(&mut conn, user.id, mapping.category_id).await?;
+
+    let saved = sqlx::query_as!(
+        CategoryMapping,
+        r#"
+        INSERT INTO category_mappings (user_id, pluggy_category_id, category_id)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (user_id, pluggy_category_id) DO UPDATE SET category_id = EXCLUDED.category_id
+        RETURNING pluggy_category_id, category_id, created_at
+        "#,
+        user.id,
+        pluggy_category_id,
+        mapping.category_id
+    )
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(saved))
+}
+
+#[delete("/categories/mappings/<pluggy_category_id>")]
+pub async fn delete_category_mapping(
+    user: AuthenticatedUser,
+    pluggy_category_id: &str,
+    pool: &State<PgPool>,
+) -> Result<Status, ApiError> {
+    let result = sqlx::query!(
+        "DELETE FROM category_mappings WHERE user_id = $1 AND pluggy_category_id = $2",
+        user.id,
+        pluggy_category_id
+    )
+    .execute(pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    if result.rows_affected() == 0 {
+        return Err((Status::NotFound, "Associação não encontrada".to_string()));
+    }
+
+    Ok(Status::NoContent)
+}
@@ -0,0 +1,67 @@
+use crate::classifier::{self, CategoryPrediction, ClassifierStatus};
+use crate::routes::transactions::AuthenticatedUser;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{get, post, State};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+type ApiError = (Status, String);
+
+const DEFAULT_SUGGESTIONS: usize = 3;
+
+fn classifier_error(e: anyhow::Error) -> ApiError {
+    eprintln!("Erro no classificador de categorias: {}", e);
+    (Status::InternalServerError, format!("Erro no classificador: {}", e))
+}
+
+/// Quantos exemplos o classificador tem, se já está sendo usado na sincronização e se está desatualizado
+#[get("/classifier")]
+pub async fn get_classifier_status(
+    user: AuthenticatedUser,
+    pool: &State<PgPool>,
+) -> Result<Json<ClassifierStatus>, ApiError> {
+    let (_, status) = classifier::load(pool.inner(), user.id).await.map_err(classifier_error)?;
+    Ok(Json(status))
+}
+
+/// Treina o classificador com as categorizações atuais, sem esperar a próxima sincronização
+#[post("/classifier/train")]
+pub async fn train_classifier(
+    user: AuthenticatedUser,
+    pool: &State<PgPool>,
+) -> Result<Json<ClassifierStatus>, ApiError> {
+    let (_, status) = classifier::train(pool.inner(), user.id).await.map_err(classifier_error)?;
+    Ok(Json(status))
+}
+
+/// Categorias mais prováveis para a transação, com a confiança de cada uma, pelo último modelo treinado
+#[get("/transactions/<id>/category-suggestions?<limit>")]
+pub async fn get_category_suggestions(
+    user: AuthenticatedUser,
+    id: Uuid,
+    limit: Option<usize>,
+    pool: &State<PgPool>,
+) -> Result<Json<Vec<CategoryPrediction>>, ApiError> {
+    let transaction = sqlx::query!(
+        r#"SELECT description, merchant->>'name' AS merchant_name FROM transactions WHERE id = $1 AND user_id = $2"#,
+        id,
+        user.id
+    )
+    .fetch_optional(pool.inner())
+    .await
+    .map_err(|e| (Status::InternalServerError, format!("Erro de banco de dados: {}", e)))?
+    .ok_or_else(|| (Status::NotFound, "Transação não encontrada".to_string()))?;
+
+    let (model, _) = classifier::load(pool.inner(), user.id).await.map_err(classifier_error)?;
+
+    let Some(model) = model else {
+        return Ok(Json(Vec::new()));
+    };
+
+    let tokens = classifier::tokenize(transaction.description.as_deref(), transaction.merchant_name.as_deref());
+    let mut predictions = model.predict(&tokens);
+    predictions.truncate(limit.unwrap_or(DEFAULT_SUGGESTIONS).clamp(1, 20));
+
+    Ok(Json(predictions))
+}
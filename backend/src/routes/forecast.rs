@@ -0,0 +1,47 @@
+use crate::cash_flow;
+use crate::models::forecast::{CashFlowForecast, DEFAULT_FORECAST_DAYS, MAX_FORECAST_DAYS};
+use crate::routes::transactions::AuthenticatedUser;
+use chrono::{Days, Utc};
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{get, State};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+type ApiError = (Status, String);
+
+fn db_error(e: sqlx::Error) -> ApiError {
+    eprintln!("Erro de banco de dados na previsão de saldo: {}", e);
+    (Status::InternalServerError, format!("Erro de banco de dados: {}", e))
+}
+
+/// Saldo previsto dia a dia das contas correntes e poupanças nos próximos `days` dias (padrão 30, máximo 90),
+/// com as recorrências, parcelas e faturas de cartão previstas e os dias em que o saldo fica negativo
+#[get("/forecast?<days>&<account_id>")]
+pub async fn get_forecast(
+    user: AuthenticatedUser,
+    days: Option<i64>,
+    account_id: Option<Uuid>,
+    pool: &State<PgPool>,
+) -> Result<Json<CashFlowForecast>, ApiError> {
+    let days = days.unwrap_or(DEFAULT_FORECAST_DAYS);
+    if !(1..=MAX_FORECAST_DAYS).contains(&days) {
+        return Err((
+            Status::BadRequest,
+            format!("O período da previsão deve ter entre 1 e {} dias", MAX_FORECAST_DAYS),
+        ));
+    }
+
+    let from = Utc::now().date_naive();
+    let to = from + Days::new(days as u64);
+    let mut accounts = cash_flow::forecast(pool.inner(), user.id, from, to).await.map_err(db_error)?;
+
+    if let Some(account_id) = account_id {
+        accounts.retain(|a| a.account_id == account_id);
+        if accounts.is_empty() {
+            return Err((Status::NotFound, "Conta corrente ou poupança não encontrada".to_string()));
+        }
+    }
+
+    Ok(Json(CashFlowForecast { from, to, accounts }))
+}
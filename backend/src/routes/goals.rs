@@ -0,0 +1,306 @@
+use crate::goal_tracking;
+use crate::models::goal::{
+    GoalContribution, GoalProgress, NewGoal, NewGoalContribution, UpdateGoal, LINKABLE_ACCOUNT_TYPES,
+    MAX_GOALS_PER_USER,
+};
+use crate::routes::transactions::AuthenticatedUser;
+use chrono::Utc;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, put, State};
+use rust_decimal::Decimal;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+type ApiError = (Status, String);
+
+fn db_error(e: sqlx::Error) -> ApiError {
+    match e {
+        sqlx::Error::Database(ref db) if db.is_unique_violation() => {
+            (Status::Conflict, "Uma das contas já está vinculada a outra meta".to_string())
+        }
+        e => {
+            eprintln!("Erro de banco de dados nas metas: {}", e);
+            (Status::InternalServerError, format!("Erro de banco de dados: {}", e))
+        }
+    }
+}
+
+fn bad_request(message: impl Into<String>) -> ApiError {
+    (Status::BadRequest, message.into())
+}
+
+fn not_found() -> ApiError {
+    (Status::NotFound, "Meta não encontrada".to_string())
+}
+
+fn validate_name(name: &str) -> Result<&str, ApiError> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > 255 {
+        return Err(bad_request("O nome da meta deve ter entre 1 e 255 caracteres"));
+    }
+    Ok(name)
+}
+
+fn validate_target_amount(amount: Decimal) -> Result<(), ApiError> {
+    if amount <= Decimal::ZERO {
+        return Err(bad_request("O valor alvo deve ser positivo"));
+    }
+    Ok(())
+}
+
+async fn ensure_goal(conn: &mut PgConnection, user_id: Uuid, id: Uuid) -> Result<(), ApiError> {
+    sqlx::query_scalar!("SELECT id FROM goals WHERE id = $1 AND user_id = $2", id, user_id)
+        .fetch_optional(conn)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(not_found)?;
+    Ok(())
+}
+
+async fn fetch_progress(pool: &PgPool, user_id: Uuid, id: Uuid) -> Result<GoalProgress, ApiError> {
+    goal_tracking::progress(pool, user_id, Some(id))
+        .await
+        .map_err(db_error)?
+        .pop()
+        .ok_or_else(not_found)
+}
+
+/// Substitui as contas vinculadas à meta, que devem ser contas correntes ou poupanças do usuário
+async fn set_accounts(conn: &mut PgConnection, user_id: Uuid, goal_id: Uuid, account_ids: &[Uuid]) -> Result<(), ApiError> {
+    let mut account_ids = account_ids.to_vec();
+    account_ids.sort();
+    account_ids.dedup();
+
+    let types: Vec<String> = LINKABLE_ACCOUNT_TYPES.iter().map(|t| t.to_string()).collect();
+    let valid = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) AS "count!"
+        FROM accounts a
+        INNER JOIN items i ON a.item_id = i.id
+        WHERE a.id = ANY($1) AND i.user_id = $2 AND a.type = ANY($3)
+        "#,
+        &account_ids,
+        user_id,
+        &types
+    )
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(db_error)?;
+
+    if valid != account_ids.len() as i64 {
+        return Err(bad_request("Conta não encontrada ou não vinculável (use contas correntes ou poupanças)"));
+    }
+
+    sqlx::query!("DELETE FROM goal_accounts WHERE goal_id = $1", goal_id)
+        .execute(&mut *conn)
+        .await
+        .map_err(db_error)?;
+
+    sqlx::query!(
+        "INSERT INTO goal_accounts (goal_id, account_id) SELECT $1, UNNEST($2::UUID[])",
+        goal_id,
+        &account_ids
+    )
+    .execute(&mut *conn)
+    .await
+    .map_err(db_error)?;
+
+    Ok(())
+}
+
+/// Metas com progresso, aporte mensal necessário e projeção de conclusão
+#[get("/goals")]
+pub async fn get_goals(user: AuthenticatedUser, pool: &State<PgPool>) -> Result<Json<Vec<GoalProgress>>, ApiError> {
+    let goals = goal_tracking::progress(pool.inner(), user.id, None).await.map_err(db_error)?;
+    Ok(Json(goals))
+}
+
+#[get("/goals/<id>")]
+pub async fn get_goal(user: AuthenticatedUser, id: Uuid, pool: &State<PgPool>) -> Result<Json<GoalProgress>, ApiError> {
+    Ok(Json(fetch_progress(pool.inner(), user.id, id).await?))
+}
+
+#[post("/goals", format = "json", data = "<goal>")]
+pub async fn create_goal(
+    user: AuthenticatedUser,
+    goal: Json<NewGoal>,
+    pool: &State<PgPool>,
+) -> Result<Json<GoalProgress>, ApiError> {
+    let name = validate_name(&goal.name)?;
+    validate_target_amount(goal.target_amount)?;
+    if goal.target_date < Utc::now().date_naive() {
+        return Err(bad_request("A data alvo não pode estar no passado"));
+    }
+
+    let mut tx = pool.begin().await.map_err(db_error)?;
+
+    let count = sqlx::query_scalar!(r#"SELECT COUNT(*) AS "count!" FROM goals WHERE user_id = $1"#, user.id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(db_error)?;
+
+    if count >= MAX_GOALS_PER_USER {
+        return Err((Status::Conflict, format!("Limite de {} metas atingido", MAX_GOALS_PER_USER)));
+    }
+
+    let id = sqlx::query_scalar!(
+        "INSERT INTO goals (user_id, name, target_amount, target_date) VALUES ($1, $2, $3, $4) RETURNING id",
+        user.id,
+        name,
+        goal.target_amount,
+        goal.target_date
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(db_error)?;
+
+    set_accounts(&mut tx, user.id, id, &goal.account_ids).await?;
+
+    tx.commit().await.map_err(db_error)?;
+    Ok(Json(fetch_progress(pool.inner(), user.id, id).await?))
+}
+
+#[put("/goals/<id>", format = "json", data = "<update>")]
+pub async fn update_goal(
+    user: AuthenticatedUser,
+    id: Uuid,
+    update: Json<UpdateGoal>,
+    pool: &State<PgPool>,
+) -> Result<Json<GoalProgress>, ApiError> {
+    let name = update.name.as_deref().map(validate_name).transpose()?;
+    if let Some(amount) = update.target_amount {
+        validate_target_amount(amount)?;
+    }
+
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    ensure_goal(&mut tx, user.id, id).await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE goals
+        SET name = COALESCE($1, name), target_amount = COALESCE($2, target_amount), target_date = COALESCE($3, target_date)
+        WHERE id = $4
+        "#,
+        name,
+        update.target_amount,
+        update.target_date,
+        id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(db_error)?;
+
+    if let Some(account_ids) = &update.account_ids {
+        set_accounts(&mut tx, user.id, id, account_ids).await?;
+    }
+
+    tx.commit().await.map_err(db_error)?;
+    Ok(Json(fetch_progress(pool.inner(), user.id, id).await?))
+}
+
+/// Remove a meta e seus aportes; as contas vinculadas não são afetadas
+#[delete("/goals/<id>")]
+pub async fn delete_goal(user: AuthenticatedUser, id: Uuid, pool: &State<PgPool>) -> Result<Status, ApiError> {
+    let result = sqlx::query!("DELETE FROM goals WHERE id = $1 AND user_id = $2", id, user.id)
+        .execute(pool.inner())
+        .await
+        .map_err(db_error)?;
+
+    if result.rows_affected() == 0 {
+        return Err(not_found());
+    }
+
+    Ok(Status::NoContent)
+}
+
+#[get("/goals/<id>/contributions")]
+pub async fn get_goal_contributions(
+    user: AuthenticatedUser,
+    id: Uuid,
+    pool: &State<PgPool>,
+) -> Result<Json<Vec<GoalContribution>>, ApiError> {
+    let mut conn = pool.acquire().await.map_err(db_error)?;
+    ensure_goal(&mut conn, user.id, id).await?;
+
+    let contributions = sqlx::query_as!(
+        GoalContribution,
+        r#"
+        SELECT id, goal_id, amount, date, note, created_at
+        FROM goal_contributions
+        WHERE goal_id = $1
+        ORDER BY date DESC, created_at DESC
+        "#,
+        id
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(contributions))
+}
+
+/// Registra um aporte (ou retirada, com valor negativo) manual na meta
+#[post("/goals/<id>/contributions", format = "json", data = "<contribution>")]
+pub async fn create_goal_contribution(
+    user: AuthenticatedUser,
+    id: Uuid,
+    contribution: Json<NewGoalContribution>,
+    pool: &State<PgPool>,
+) -> Result<Json<GoalContribution>, ApiError> {
+    if contribution.amount.is_zero() {
+        return Err(bad_request("O valor do aporte não pode ser zero"));
+    }
+    let today = Utc::now().date_naive();
+    let date = contribution.date.unwrap_or(today);
+    if date > today {
+        return Err(bad_request("A data do aporte não pode estar no futuro"));
+    }
+    let note = contribution.note.as_deref().map(str::trim).filter(|n| !n.is_empty());
+
+    let mut conn = pool.acquire().await.map_err(db_error)?;
+    ensure_goal(&mut conn, user.id, id).await?;
+
+    let created = sqlx::query_as!(
+        GoalContribution,
+        r#"
+        INSERT INTO goal_contributions (user_id, goal_id, amount, date, note)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id, goal_id, amount, date, note, created_at
+        "#,
+        user.id,
+        id,
+        contribution.amount,
+        date,
+        note
+    )
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(created))
+}
+
+#[delete("/goals/<id>/contributions/<contribution_id>")]
+pub async fn delete_goal_contribution(
+    user: AuthenticatedUser,
+    id: Uuid,
+    contribution_id: Uuid,
+    pool: &State<PgPool>,
+) -> Result<Status, ApiError> {
+    let result = sqlx::query!(
+        "DELETE FROM goal_contributions WHERE id = $1 AND goal_id = $2 AND user_id = $3",
+        contribution_id,
+        id,
+        user.id
+    )
+    .execute(pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    if result.rows_affected() == 0 {
+        return Err((Status::NotFound, "Aporte não encontrado".to_string()));
+    }
+
+    Ok(Status::NoContent)
+}
@@ -0,0 +1,310 @@
+use crate::import::{self, ParsedTransaction};
+use crate::installment_tracking::{self, InstallmentTransaction};
+use crate::merchant_matching;
+use crate::models::import_batch::{
+    ImportBatch, ImportPreviewRow, ImportRequest, ImportResult, FORMAT_CSV, FORMAT_OFX,
+};
+use crate::routes::transactions::AuthenticatedUser;
+use crate::transfer_detection;
+use chrono::NaiveDate;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, State};
+use sqlx::PgPool;
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+type ApiError = (Status, String);
+
+const MAX_IMPORT_ROWS: usize = 5000;
+
+fn db_error(e: sqlx::Error) -> ApiError {
+    eprintln!("Erro de banco de dados na importação: {}", e);
+    (Status::InternalServerError, format!("Erro de banco de dados: {}", e))
+}
+
+// Mesma data, valor e descrição (sem diferenciar maiúsculas) identificam uma transação repetida
+fn duplicate_key(date: NaiveDate, amount: rust_decimal::Decimal, description: &str) -> (NaiveDate, String, String) {
+    (date, amount.normalize().to_string(), description.trim().to_lowercase())
+}
+
+/// Marca as transações do arquivo que já existem para o usuário.
+///
+/// A comparação é por contagem: se já existe uma compra igual no dia e o arquivo traz duas,
+/// apenas a primeira é considerada duplicata.
+async fn find_duplicates(
+    pool: &PgPool,
+    user_id: Uuid,
+    transactions: &[ParsedTransaction],
+) -> Result<Vec<bool>, ApiError> {
+    let (Some(min_date), Some(max_date)) = (
+        transactions.iter().map(|t| t.date).min(),
+        transactions.iter().map(|t| t.date).max(),
+    ) else {
+        return Ok(Vec::new());
+    };
+
+    let existing = sqlx::query!(
+        r#"
+        SELECT date, amount, COALESCE(description, '') AS "description!", import_external_id
+        FROM transactions
+        WHERE user_id = $1 AND date BETWEEN $2 AND $3
+        "#,
+        user_id,
+        min_date,
+        max_date
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(db_error)?;
+
+    let mut counts: HashMap<(NaiveDate, String, String), usize> = HashMap::new();
+    let mut external_ids: HashSet<String> = HashSet::new();
+    for row in existing {
+        *counts.entry(duplicate_key(row.date, row.amount, &row.description)).or_default() += 1;
+        if let Some(id) = row.import_external_id {
+            external_ids.insert(id);
+        }
+    }
+
+    Ok(transactions
+        .iter()
+        .map(|t| {
+            if let Some(id) = &t.external_id {
+                if !external_ids.insert(id.clone()) {
+                    return true;
+                }
+            }
+            match counts.get_mut(&duplicate_key(t.date, t.amount, &t.description)) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    true
+                }
+                _ => false,
+            }
+        })
+        .collect())
+}
+
+#[post("/transactions/import", format = "json", data = "<request>")]
+pub async fn import_transactions(
+    user: AuthenticatedUser,
+    request: Json<ImportRequest>,
+    pool: &State<PgPool>,
+) -> Result<Json<ImportResult>, ApiError> {
+    let format = request.format.to_lowercase();
+    let parsed = match format.as_str() {
+        FORMAT_CSV => import::parse_csv(&request.content, &request.csv),
+        FORMAT_OFX => import::parse_ofx(&request.content),
+        _ => return Err((Status::BadRequest, "Formato inválido. Use 'csv' ou 'ofx'".to_string())),
+    }
+    .map_err(|e| (Status::BadRequest, format!("Arquivo inválido: {}", e)))?;
+
+    if parsed.transactions.len() > MAX_IMPORT_ROWS {
+        return Err((
+            Status::BadRequest,
+            format!("Arquivo excede o limite de {} transações", MAX_IMPORT_ROWS),
+        ));
+    }
+
+    let mut account_type = None;
+    if let Some(account_id) = request.account_id {
+        let account = sqlx::query!(
+            r#"
+            SELECT a.id, a.type
+            FROM accounts a
+            INNER JOIN items i ON a.item_id = i.id
+            WHERE a.id = $1 AND i.user_id = $2
+            "#,
+            account_id,
+            user.id
+        )
+        .fetch_optional(pool.inner())
+        .await
+        .map_err(db_error)?;
+
+        let Some(account) = account else {
+            return Err((Status::NotFound, "Conta não encontrada".to_string()));
+        };
+        account_type = account.r#type;
+    }
+
+    let currency = request
+        .currency
+        .clone()
+        .or(parsed.currency)
+        .unwrap_or_else(|| "BRL".to_string())
+        .to_uppercase();
+
+    let duplicates = find_duplicates(pool.inner(), user.id, &parsed.transactions).await?;
+    let duplicate_count = duplicates.iter().filter(|d| **d).count();
+
+    let rows: Vec<ImportPreviewRow> = parsed
+        .transactions
+        .iter()
+        .zip(&duplicates)
+        .map(|(t, duplicate)| ImportPreviewRow {
+            line: t.line,
+            date: t.date,
+            amount: t.amount,
+            description: t.description.clone(),
+            category: t.category.clone(),
+            duplicate: *duplicate,
+        })
+        .collect();
+
+    let to_import: Vec<&ParsedTransaction> = parsed
+        .transactions
+        .iter()
+        .zip(&duplicates)
+        .filter(|(_, duplicate)| !**duplicate)
+        .map(|(t, _)| t)
+        .collect();
+
+    if request.dry_run || to_import.is_empty() {
+        return Ok(Json(ImportResult {
+            dry_run: request.dry_run,
+            batch: None,
+            imported: 0,
+            duplicates: duplicate_count,
+            rows,
+            errors: parsed.errors,
+        }));
+    }
+
+    // Lote e transações na mesma transação de banco: ou tudo é importado, ou nada
+    let mut tx = pool.begin().await.map_err(db_error)?;
+
+    let batch = sqlx::query_as!(
+        ImportBatch,
+        r#"
+        INSERT INTO import_batches (user_id, format, file_name, account_id, imported_count, duplicate_count, error_count)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id, user_id, format, file_name, account_id, imported_count, duplicate_count, error_count, created_at
+        "#,
+        user.id,
+        format,
+        request.file_name,
+        request.account_id,
+        to_import.len() as i32,
+        duplicate_count as i32,
+        parsed.errors.len() as i32
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(db_error)?;
+
+    for t in &to_import {
+        let merchant_id = merchant_matching::resolve(&mut tx, user.id, Some(&t.description), None)
+            .await
+            .map_err(db_error)?;
+
+        let id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO transactions
+                (user_id, account_id, amount, date, description, category, currency, import_batch_id, import_external_id,
+                 merchant_id, merchant_key)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            RETURNING id
+            "#,
+            user.id,
+            request.account_id,
+            t.amount,
+            t.date,
+            t.description,
+            t.category,
+            currency,
+            batch.id,
+            t.external_id,
+            merchant_id,
+            merchant_matching::alias_key(Some(&t.description))
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(db_error)?;
+
+        // Faturas de cartão trazem as parcelas na descrição ("LOJA PARC 03/10")
+        let card_charge = installment_tracking::is_card_charge(account_type.as_deref(), t.amount);
+        if let Some(info) = installment_tracking::parse_description(&t.description, card_charge) {
+            let installment = InstallmentTransaction {
+                id,
+                user_id: user.id,
+                account_id: request.account_id,
+                amount: t.amount,
+                currency: &currency,
+                date: t.date,
+                info: &info,
+            };
+            installment_tracking::assign(&mut tx, installment).await.map_err(db_error)?;
+        }
+    }
+
+    tx.commit().await.map_err(db_error)?;
+
+    if let Err(e) = transfer_detection::detect_transfers(pool.inner(), user.id).await {
+        eprintln!("Erro ao detectar transferências: {}", e);
+    }
+
+    eprintln!(
+        "✓ Importação {} ({}): {} transações, {} duplicatas, {} erros",
+        batch.id,
+        format,
+        to_import.len(),
+        duplicate_count,
+        parsed.errors.len()
+    );
+
+    Ok(Json(ImportResult {
+        dry_run: false,
+        imported: to_import.len(),
+        batch: Some(batch),
+        duplicates: duplicate_count,
+        rows,
+        errors: parsed.errors,
+    }))
+}
+
+#[get("/transactions/imports")]
+pub async fn get_import_batches(
+    user: AuthenticatedUser,
+    pool: &State<PgPool>,
+) -> Result<Json<Vec<ImportBatch>>, ApiError> {
+    let batches = sqlx::query_as!(
+        ImportBatch,
+        r#"
+        SELECT id, user_id, format, file_name, account_id, imported_count, duplicate_count, error_count, created_at
+        FROM import_batches
+        WHERE user_id = $1
+        ORDER BY created_at DESC
+        "#,
+        user.id
+    )
+    .fetch_all(pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(batches))
+}
+
+/// Desfaz uma importação: as transações do lote são removidas em cascata
+#[delete("/transactions/imports/<id>")]
+pub async fn undo_import(
+    user: AuthenticatedUser,
+    id: Uuid,
+    pool: &State<PgPool>,
+) -> Result<Status, ApiError> {
+    let result = sqlx::query!(
+        "DELETE FROM import_batches WHERE id = $1 AND user_id = $2",
+        id,
+        user.id
+    )
+    .execute(pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    if result.rows_affected() == 0 {
+        return Err((Status::NotFound, "Importação não encontrada".to_string()));
+    }
+
+    Ok(Status::NoContent)
+}
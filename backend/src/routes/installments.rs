@@ -0,0 +1,130 @@
+use crate::installment_tracking::{installment_date, PurchaseRow};
+use crate::models::installment::{
+    InstallmentEntry, InstallmentProjectionMonth, InstallmentPurchase, InstallmentPurchaseDetail,
+};
+use crate::routes::transactions::AuthenticatedUser;
+use chrono::{Datelike, Utc};
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{get, State};
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+type ApiError = (Status, String);
+
+fn db_error(e: sqlx::Error) -> ApiError {
+    eprintln!("Erro de banco de dados nos parcelamentos: {}", e);
+    (Status::InternalServerError, format!("Erro de banco de dados: {}", e))
+}
+
+async fn load_purchases(pool: &PgPool, user_id: Uuid, id: Option<Uuid>) -> Result<Vec<InstallmentPurchase>, ApiError> {
+    let rows = sqlx::query_as!(
+        PurchaseRow,
+        r#"
+        SELECT p.id, p.description, p.account_id, p.currency, p.installment_count, p.installment_amount,
+               p.total_amount, p.purchase_date, p.first_installment_date,
+               COALESCE(MAX(t.installment_number), 0) AS "paid_installments!", p.created_at
+        FROM installment_purchases p
+        LEFT JOIN transactions t ON t.installment_purchase_id = p.id
+        WHERE p.user_id = $1 AND ($2::UUID IS NULL OR p.id = $2)
+        GROUP BY p.id
+        ORDER BY p.first_installment_date DESC, p.created_at DESC
+        "#,
+        user_id,
+        id
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(db_error)?;
+
+    Ok(rows.into_iter().map(InstallmentPurchase::from).collect())
+}
+
+/// Compras parceladas do usuário; `active=true` traz apenas as que ainda têm parcelas a vencer
+#[get("/installments?<active>")]
+pub async fn get_installments(
+    user: AuthenticatedUser,
+    active: Option<bool>,
+    pool: &State<PgPool>,
+) -> Result<Json<Vec<InstallmentPurchase>>, ApiError> {
+    let mut purchases = load_purchases(pool.inner(), user.id, None).await?;
+    if active.unwrap_or(false) {
+        purchases.retain(|p| p.remaining_installments > 0);
+    }
+
+    Ok(Json(purchases))
+}
+
+#[get("/installments/<id>")]
+pub async fn get_installment(
+    user: AuthenticatedUser,
+    id: Uuid,
+    pool: &State<PgPool>,
+) -> Result<Json<InstallmentPurchaseDetail>, ApiError> {
+    let purchase = load_purchases(pool.inner(), user.id, Some(id))
+        .await?
+        .into_iter()
+        .next()
+        .ok_or((Status::NotFound, "Compra parcelada não encontrada".to_string()))?;
+
+    let installments = sqlx::query_as!(
+        InstallmentEntry,
+        r#"
+        SELECT id AS transaction_id, installment_number AS "number!", date, amount
+        FROM transactions
+        WHERE installment_purchase_id = $1 AND user_id = $2
+        ORDER BY installment_number, date
+        "#,
+        id,
+        user.id
+    )
+    .fetch_all(pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(InstallmentPurchaseDetail { purchase, installments }))
+}
+
+/// Parcelas futuras já comprometidas, por mês, a partir do mês atual.
+/// Parcelas previstas para meses já passados (ainda não recebidas) entram no mês atual.
+#[get("/installments/projection?<months>")]
+pub async fn get_installment_projection(
+    user: AuthenticatedUser,
+    months: Option<u32>,
+    pool: &State<PgPool>,
+) -> Result<Json<Vec<InstallmentProjectionMonth>>, ApiError> {
+    let months = months.unwrap_or(12).clamp(1, 60);
+    let today = Utc::now().date_naive();
+    let current_month = (today.year(), today.month());
+
+    let mut projection: BTreeMap<(i32, u32), (Decimal, usize)> = BTreeMap::new();
+    for offset in 0..months {
+        let month = today.with_day(1).and_then(|d| d.checked_add_months(chrono::Months::new(offset)));
+        if let Some(month) = month {
+            projection.insert((month.year(), month.month()), (Decimal::ZERO, 0));
+        }
+    }
+
+    let purchases = load_purchases(pool.inner(), user.id, None).await?;
+    for purchase in purchases.iter().filter(|p| p.remaining_installments > 0) {
+        for number in (purchase.paid_installments + 1)..=purchase.installment_count {
+            let Some(date) = installment_date(purchase.first_installment_date, number) else {
+                continue;
+            };
+            let key = (date.year(), date.month()).max(current_month);
+            if let Some((total, count)) = projection.get_mut(&key) {
+                *total += purchase.installment_amount;
+                *count += 1;
+            }
+        }
+    }
+
+    Ok(Json(
+        projection
+            .into_iter()
+            .map(|((year, month), (total, installments))| InstallmentProjectionMonth { year, month, total, installments })
+            .collect(),
+    ))
+}
@@ -1,6 +1,11 @@
+use crate::alerts;
+use crate::balance_history;
 use crate::config::AppConfig;
+use crate::ingest::{self, IncomingTransaction, IngestContext};
+use crate::notifier::Notifier;
 use crate::pluggy::client::PluggyClient;
 use crate::routes::transactions::AuthenticatedUser;
+use crate::transfer_detection;
 use rocket::http::Status;
 use rocket::serde::json::Json;
 use rocket::{post, State};
@@ -27,6 +32,7 @@ pub async fn create_item(
     item_request: Json<CreateItemRequest>,
     pool: &State<PgPool>,
     config: &State<Arc<AppConfig>>,
+    notifier: &State<Arc<Notifier>>,
 ) -> Result<Json<ItemResponse>, Status> {
     // 1. Salvar o Item no banco de dados
     let item_id = Uuid::new_v4();
@@ -54,13 +60,14 @@ pub async fn create_item(
     
     let config_clone = config.inner().clone(); // Arc clone
     let pool_clone = pool.inner().clone();
+    let notifier_clone = notifier.inner().clone();
     let pluggy_item_id = item.pluggy_item_id.clone();
     let db_item_id = item.id;
     let user_id = user.id;
 
     tokio::spawn(async move {
         eprintln!("Iniciando sincronização para item: {}", pluggy_item_id);
-        if let Err(e) = sync_item_data(config_clone, pool_clone, &pluggy_item_id, db_item_id, user_id).await {
+        if let Err(e) = sync_item_data(config_clone, pool_clone, notifier_clone, &pluggy_item_id, db_item_id, user_id).await {
             eprintln!("Erro na sincronização: {}", e);
         } else {
             eprintln!("Sincronização concluída com sucesso para item: {}", pluggy_item_id);
@@ -77,6 +84,7 @@ pub async fn create_item(
 pub async fn sync_item_data(
     config: Arc<AppConfig>,
     pool: PgPool,
+    notifier: Arc<Notifier>,
     pluggy_item_id: &str,
     db_item_id: Uuid,
     user_id: Uuid,
@@ -84,37 +92,30 @@ pub async fn sync_item_data(
     // Configurar client da Pluggy (precisamos criar AppConfig compatível com PluggyConfig ou adaptar)
     // O PluggyClient espera AppConfig agora (conforme nossa refatoração anterior).
     
-    // Hack: Reconstruir AppConfig ou clonar. O client espera struct AppConfig.
-    // Como passamos Arc<AppConfig>, precisamos desreferenciar.
-    let app_config = AppConfig {
-        client_id: config.client_id.clone(),
-        client_secret: config.client_secret.clone(),
-        environment: config.environment.clone(),
-        base_url: config.base_url.clone(),
-        database_url: config.database_url.clone(),
-        jwt_secret: config.jwt_secret.clone(),
-        admin_email: config.admin_email.clone(),
-        admin_password: config.admin_password.clone(),
-        admin_name: config.admin_name.clone(),
-    };
+    let app_config = config.as_ref().clone();
 
     let mut client = PluggyClient::new(app_config);
 
-    // 0. Buscar detalhes do Item (para pegar o connector e salvar no banco)
+    // 0. Buscar detalhes do Item (para pegar o connector e o status da conexão e salvar no banco)
     match client.get_item_by_id(pluggy_item_id).await {
         Ok(item_details) => {
-            if let Some(connector) = item_details.connector {
-                let update_result = sqlx::query!(
-                    "UPDATE items SET connector = $1 WHERE id = $2",
-                    connector,
-                    db_item_id
-                )
-                .execute(&pool)
-                .await;
-                
-                if let Err(e) = update_result {
-                    eprintln!("Erro ao atualizar connector do item: {}", e);
-                }
+            let update_result = sqlx::query!(
+                r#"
+                UPDATE items
+                SET connector = COALESCE($1, connector), status = $2, execution_status = $3, error = $4
+                WHERE id = $5
+                "#,
+                item_details.connector,
+                item_details.status,
+                item_details.execution_status,
+                item_details.error,
+                db_item_id
+            )
+            .execute(&pool)
+            .await;
+
+            if let Err(e) = update_result {
+                eprintln!("Erro ao atualizar dados do item: {}", e);
             }
         },
         Err(e) => {
@@ -123,6 +124,31 @@ pub async fn sync_item_data(
         }
     }
 
+    let result = sync_accounts(&mut client, &pool, pluggy_item_id, db_item_id, user_id).await;
+
+    // Investimentos entram no patrimônio; uma falha aqui não impede a sincronização das contas
+    if let Err(e) = sync_investments(&mut client, &pool, pluggy_item_id, db_item_id).await {
+        eprintln!("Erro ao sincronizar investimentos do item {}: {}", pluggy_item_id, e);
+    }
+
+    // Alertas também são avaliados quando a sincronização falha, já que a conexão pode ter quebrado
+    if let Err(e) = alerts::evaluate(&pool, &notifier, user_id).await {
+        eprintln!("Erro ao avaliar alertas: {}", e);
+    }
+
+    result
+}
+
+async fn sync_accounts(
+    client: &mut PluggyClient,
+    pool: &PgPool,
+    pluggy_item_id: &str,
+    db_item_id: Uuid,
+    user_id: Uuid,
+) -> anyhow::Result<()> {
+    // Regras e classificador de categorias do usuário, avaliados em cada transação recebida
+    let mut context = IngestContext::load(pool, user_id).await?;
+
     // 1. Buscar Contas
     let accounts = client.get_accounts(Some(pluggy_item_id)).await?;
     
@@ -138,11 +164,13 @@ pub async fn sync_item_data(
 
         let db_account = sqlx::query!(
             r#"
-            INSERT INTO accounts (id, pluggy_account_id, item_id, name, number, balance, currency, type, subtype)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            INSERT INTO accounts (id, pluggy_account_id, item_id, name, number, balance, currency, type, subtype, credit_data)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
             ON CONFLICT (pluggy_account_id) DO UPDATE SET 
                 balance = EXCLUDED.balance,
                 name = EXCLUDED.name,
+                -- Vencimento e fechamento da fatura, usados na previsão de saldo
+                credit_data = EXCLUDED.credit_data,
                 updated_at = CURRENT_TIMESTAMP
             RETURNING id
             "#,
@@ -154,9 +182,10 @@ pub async fn sync_item_data(
             balance_decimal,
             acc.currency_code,
             acc.type_field,
-            acc.subtype
+            acc.subtype,
+            acc.credit_data
         )
-        .fetch_one(&pool)
+        .fetch_one(pool)
         .await?;
 
         // 2. Buscar Transações desta conta
@@ -164,36 +193,62 @@ pub async fn sync_item_data(
         let transactions = client.get_transactions(Some(pluggy_item_id), Some(&acc.id)).await?;
 
         for tx in transactions {
-            let tx_amount = Decimal::from_f64(tx.amount).unwrap_or(Decimal::ZERO);
-            // Parse date (string ISO)
-            let tx_date = chrono::NaiveDate::parse_from_str(&tx.date[0..10], "%Y-%m-%d")
-                .unwrap_or_else(|_| chrono::Utc::now().date_naive());
+            let incoming = IncomingTransaction::from_pluggy(tx, db_account.id, acc.type_field.as_deref(), db_item_id, user_id);
+            ingest::upsert_transaction(pool, &mut context, incoming).await?;
+        }
 
-            sqlx::query!(
-                r#"
-                INSERT INTO transactions (
-                    pluggy_transaction_id, account_id, item_id, user_id, 
-                    amount, date, description, category, currency, status
-                )
-                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
-                ON CONFLICT (pluggy_transaction_id) DO NOTHING
-                "#,
-                tx.id,
-                db_account.id,
-                db_item_id,
-                user_id,
-                tx_amount,
-                tx_date,
-                tx.description,
-                tx.category,
-                tx.currency_code,
-                tx.status.unwrap_or_else(|| "PENDING".to_string()) // Default status if missing
-            )
-            .execute(&pool)
-            .await?;
+        // Histórico de saldo anterior ao primeiro registro, reconstruído pelas transações
+        if let Err(e) = balance_history::backfill_account(pool, db_account.id).await {
+            eprintln!("Erro ao reconstruir o histórico de saldo da conta {}: {}", acc.id, e);
         }
     }
 
+    // Movimentações entre as contas do usuário não contam como despesa nem receita
+    if let Err(e) = transfer_detection::detect_transfers(pool, user_id).await {
+        eprintln!("Erro ao detectar transferências: {}", e);
+    }
+
     Ok(())
 }
 
+async fn sync_investments(
+    client: &mut PluggyClient,
+    pool: &PgPool,
+    pluggy_item_id: &str,
+    db_item_id: Uuid,
+) -> anyhow::Result<()> {
+    use rust_decimal::prelude::FromPrimitive;
+    use rust_decimal::Decimal;
+
+    let investments = client.get_investments(pluggy_item_id).await?;
+
+    for investment in investments {
+        let balance = investment.balance.and_then(Decimal::from_f64);
+
+        sqlx::query!(
+            r#"
+            INSERT INTO investments (pluggy_investment_id, item_id, name, number, type, subtype, balance, currency, status)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT (pluggy_investment_id) DO UPDATE SET
+                name = EXCLUDED.name,
+                balance = EXCLUDED.balance,
+                currency = EXCLUDED.currency,
+                status = EXCLUDED.status,
+                updated_at = CURRENT_TIMESTAMP
+            "#,
+            investment.id,
+            db_item_id,
+            investment.name,
+            investment.number,
+            investment.type_field,
+            investment.subtype,
+            balance,
+            investment.currency_code,
+            investment.status
+        )
+        .execute(pool)
+        .await?;
+    }
+
+    Ok(())
+}
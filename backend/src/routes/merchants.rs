@@ -0,0 +1,325 @@
+use crate::merchant_matching;
+use crate::models::merchant::{
+    Merchant, MerchantAlias, MerchantDetail, MerchantLinkResult, MerchantSummary, MergeMerchant, NewMerchantAlias,
+    UpdateMerchant,
+};
+use crate::routes::transactions::AuthenticatedUser;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use chrono::{DateTime, NaiveDate, Utc};
+use rocket::{delete, get, post, put, State};
+use rust_decimal::Decimal;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+type ApiError = (Status, String);
+
+fn db_error(e: sqlx::Error) -> ApiError {
+    match e {
+        sqlx::Error::Database(ref db) if db.is_unique_violation() => {
+            (Status::Conflict, "Já existe um estabelecimento com esse nome".to_string())
+        }
+        e => {
+            eprintln!("Erro de banco de dados nos estabelecimentos: {}", e);
+            (Status::InternalServerError, format!("Erro de banco de dados: {}", e))
+        }
+    }
+}
+
+fn bad_request(message: impl Into<String>) -> ApiError {
+    (Status::BadRequest, message.into())
+}
+
+fn not_found() -> ApiError {
+    (Status::NotFound, "Estabelecimento não encontrado".to_string())
+}
+
+async fn fetch_merchant(conn: &mut PgConnection, user_id: Uuid, id: Uuid) -> Result<Merchant, ApiError> {
+    sqlx::query_as!(
+        Merchant,
+        r#"
+        SELECT id, name, legal_name, cnpj, category, created_at, updated_at
+        FROM merchants
+        WHERE id = $1 AND user_id = $2
+        "#,
+        id,
+        user_id
+    )
+    .fetch_optional(conn)
+    .await
+    .map_err(db_error)?
+    .ok_or_else(not_found)
+}
+
+// Linha de `get_merchants`; `query_as!` não monta o `Merchant` aninhado em `MerchantSummary`
+struct MerchantSummaryRow {
+    id: Uuid,
+    name: String,
+    legal_name: Option<String>,
+    cnpj: Option<String>,
+    category: Option<String>,
+    created_at: Option<DateTime<Utc>>,
+    updated_at: Option<DateTime<Utc>>,
+    transaction_count: i64,
+    total_amount: Decimal,
+    last_transaction_date: Option<NaiveDate>,
+}
+
+impl From<MerchantSummaryRow> for MerchantSummary {
+    fn from(row: MerchantSummaryRow) -> Self {
+        MerchantSummary {
+            merchant: Merchant {
+                id: row.id,
+                name: row.name,
+                legal_name: row.legal_name,
+                cnpj: row.cnpj,
+                category: row.category,
+                created_at: row.created_at,
+                updated_at: row.updated_at,
+            },
+            transaction_count: row.transaction_count,
+            total_amount: row.total_amount,
+            last_transaction_date: row.last_transaction_date,
+        }
+    }
+}
+
+/// Estabelecimentos do usuário com a quantidade e a soma das transações, dos mais frequentes aos menos
+#[get("/merchants?<q>")]
+pub async fn get_merchants(
+    user: AuthenticatedUser,
+    q: Option<String>,
+    pool: &State<PgPool>,
+) -> Result<Json<Vec<MerchantSummary>>, ApiError> {
+    let pattern = q
+        .as_deref()
+        .map(str::trim)
+        .filter(|q| !q.is_empty())
+        .map(|q| format!("%{}%", q.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")));
+
+    let merchants = sqlx::query_as!(
+        MerchantSummaryRow,
+        r#"
+        SELECT m.id, m.name, m.legal_name, m.cnpj, m.category, m.created_at, m.updated_at,
+               COUNT(t.id) AS "transaction_count!",
+               COALESCE(SUM(t.amount), 0) AS "total_amount!",
+               MAX(t.date) AS last_transaction_date
+        FROM merchants m
+        LEFT JOIN transactions t ON t.merchant_id = m.id
+        WHERE m.user_id = $1 AND ($2::TEXT IS NULL OR m.name ILIKE $2 OR m.legal_name ILIKE $2)
+        GROUP BY m.id
+        ORDER BY COUNT(t.id) DESC, m.name
+        "#,
+        user.id,
+        pattern
+    )
+    .fetch_all(pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(merchants.into_iter().map(MerchantSummary::from).collect()))
+}
+
+#[get("/merchants/<id>")]
+pub async fn get_merchant(
+    user: AuthenticatedUser,
+    id: Uuid,
+    pool: &State<PgPool>,
+) -> Result<Json<MerchantDetail>, ApiError> {
+    let mut conn = pool.acquire().await.map_err(db_error)?;
+    let merchant = fetch_merchant(&mut conn, user.id, id).await?;
+
+    let aliases = sqlx::query_as!(
+        MerchantAlias,
+        "SELECT id, merchant_id, alias, created_at FROM merchant_aliases WHERE merchant_id = $1 ORDER BY alias",
+        id
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(MerchantDetail { merchant, aliases }))
+}
+
+/// Renomeia o estabelecimento ou altera sua categoria. O nome antigo continua como alias,
+/// para que a Pluggy não recrie o estabelecimento com ele.
+#[put("/merchants/<id>", format = "json", data = "<update>")]
+pub async fn update_merchant(
+    user: AuthenticatedUser,
+    id: Uuid,
+    update: Json<UpdateMerchant>,
+    pool: &State<PgPool>,
+) -> Result<Json<Merchant>, ApiError> {
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    let current = fetch_merchant(&mut tx, user.id, id).await?;
+
+    let name = match update.name.as_deref().map(str::trim) {
+        Some(name) => {
+            let normalized = merchant_matching::normalize(name).ok_or_else(|| bad_request("Nome inválido"))?;
+            Some((name.to_string(), normalized.key))
+        }
+        None => None,
+    };
+
+    if let Some((_, key)) = &name {
+        let old_key = sqlx::query_scalar!("SELECT name_key FROM merchants WHERE id = $1", id)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(db_error)?;
+        if *key != old_key {
+            merchant_matching::add_alias(&mut tx, user.id, id, &old_key, false).await.map_err(db_error)?;
+        }
+    }
+
+    // Campo ausente mantém o valor; vazio remove
+    let category = match update.category.as_deref().map(str::trim) {
+        Some("") => None,
+        Some(category) => Some(category.to_string()),
+        None => current.category,
+    };
+
+    let merchant = sqlx::query_as!(
+        Merchant,
+        r#"
+        UPDATE merchants
+        SET name = COALESCE($1, name), name_key = COALESCE($2, name_key), category = $3
+        WHERE id = $4
+        RETURNING id, name, legal_name, cnpj, category, created_at, updated_at
+        "#,
+        name.as_ref().map(|(name, _)| name.clone()),
+        name.as_ref().map(|(_, key)| key.clone()),
+        category,
+        id
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(db_error)?;
+
+    tx.commit().await.map_err(db_error)?;
+    Ok(Json(merchant))
+}
+
+/// Associa uma descrição ao estabelecimento (movendo-a de outro, se for o caso)
+/// e reassocia as transações existentes com essa descrição
+#[post("/merchants/<id>/aliases", format = "json", data = "<alias>")]
+pub async fn add_merchant_alias(
+    user: AuthenticatedUser,
+    id: Uuid,
+    alias: Json<NewMerchantAlias>,
+    pool: &State<PgPool>,
+) -> Result<Json<MerchantLinkResult>, ApiError> {
+    let normalized = merchant_matching::normalize(&alias.description)
+        .ok_or_else(|| bad_request("A descrição não contém um nome de estabelecimento"))?;
+
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    fetch_merchant(&mut tx, user.id, id).await?;
+
+    merchant_matching::add_alias(&mut tx, user.id, id, &normalized.key, true).await.map_err(db_error)?;
+    let transactions_updated = merchant_matching::relink_alias(&mut tx, user.id, id, &normalized.key)
+        .await
+        .map_err(db_error)?;
+
+    tx.commit().await.map_err(db_error)?;
+    Ok(Json(MerchantLinkResult { transactions_updated }))
+}
+
+/// Remove um alias; as transações já associadas continuam no estabelecimento
+#[delete("/merchants/<id>/aliases/<alias_id>")]
+pub async fn delete_merchant_alias(
+    user: AuthenticatedUser,
+    id: Uuid,
+    alias_id: Uuid,
+    pool: &State<PgPool>,
+) -> Result<Status, ApiError> {
+    let result = sqlx::query!(
+        "DELETE FROM merchant_aliases WHERE id = $1 AND merchant_id = $2 AND user_id = $3",
+        alias_id,
+        id,
+        user.id
+    )
+    .execute(pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    if result.rows_affected() == 0 {
+        return Err((Status::NotFound, "Alias não encontrado".to_string()));
+    }
+
+    Ok(Status::NoContent)
+}
+
+/// Mescla o estabelecimento em outro: transações e aliases passam para o destino e este é removido
+#[post("/merchants/<id>/merge", format = "json", data = "<merge>")]
+pub async fn merge_merchant(
+    user: AuthenticatedUser,
+    id: Uuid,
+    merge: Json<MergeMerchant>,
+    pool: &State<PgPool>,
+) -> Result<Json<MerchantLinkResult>, ApiError> {
+    if merge.into == id {
+        return Err(bad_request("Não é possível mesclar um estabelecimento nele mesmo"));
+    }
+
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    let source = fetch_merchant(&mut tx, user.id, id).await?;
+    fetch_merchant(&mut tx, user.id, merge.into).await?;
+
+    let transactions_updated = sqlx::query!(
+        r#"
+        UPDATE transactions
+        SET merchant_id = $1,
+            user_modified_fields = ARRAY(SELECT DISTINCT field FROM unnest(user_modified_fields || ARRAY['merchant_id']) AS field)
+        WHERE merchant_id = $2
+        "#,
+        merge.into,
+        source.id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(db_error)?
+    .rows_affected();
+
+    sqlx::query!("UPDATE merchant_aliases SET merchant_id = $1 WHERE merchant_id = $2", merge.into, source.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+
+    let source_key = sqlx::query_scalar!("SELECT name_key FROM merchants WHERE id = $1", source.id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    merchant_matching::add_alias(&mut tx, user.id, merge.into, &source_key, true).await.map_err(db_error)?;
+
+    sqlx::query!(
+        r#"
+        UPDATE merchants
+        SET legal_name = COALESCE(legal_name, $1), cnpj = COALESCE(cnpj, $2), category = COALESCE(category, $3)
+        WHERE id = $4
+        "#,
+        source.legal_name,
+        source.cnpj,
+        source.category,
+        merge.into
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(db_error)?;
+
+    sqlx::query!("DELETE FROM merchants WHERE id = $1", source.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+
+    tx.commit().await.map_err(db_error)?;
+    Ok(Json(MerchantLinkResult { transactions_updated }))
+}
+
+/// Associa a estabelecimentos as transações que ainda não têm um (ex: anteriores a esta funcionalidade)
+#[post("/merchants/link")]
+pub async fn link_merchants(
+    user: AuthenticatedUser,
+    pool: &State<PgPool>,
+) -> Result<Json<MerchantLinkResult>, ApiError> {
+    let transactions_updated = merchant_matching::link_unlinked(pool.inner(), user.id).await.map_err(db_error)?;
+    Ok(Json(MerchantLinkResult { transactions_updated }))
+}
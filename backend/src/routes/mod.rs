@@ -3,3 +3,22 @@ pub mod transactions;
 pub mod items;
 pub mod accounts;
 pub mod webhooks;
+pub mod tokens;
+pub mod profile;
+pub mod imports;
+pub mod splits;
+pub mod tags;
+pub mod attachments;
+pub mod rules;
+pub mod category_suggestions;
+pub mod categories;
+pub mod transfers;
+pub mod recurring;
+pub mod installments;
+pub mod merchants;
+pub mod budgets;
+pub mod goals;
+pub mod net_worth;
+pub mod forecast;
+pub mod reports;
+pub mod notifications;
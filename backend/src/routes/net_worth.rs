@@ -0,0 +1,70 @@
+use crate::balance_history;
+use crate::models::net_worth::{
+    is_valid_interval, NetWorthHistory, INTERVAL_DAY, INTERVAL_MONTH, INTERVAL_WEEK, MAX_NET_WORTH_POINTS,
+};
+use crate::routes::transactions::AuthenticatedUser;
+use chrono::{Datelike, Months, NaiveDate, Utc};
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{get, State};
+use sqlx::PgPool;
+
+type ApiError = (Status, String);
+
+fn db_error(e: sqlx::Error) -> ApiError {
+    eprintln!("Erro de banco de dados no patrimônio: {}", e);
+    (Status::InternalServerError, format!("Erro de banco de dados: {}", e))
+}
+
+fn bad_request(message: impl Into<String>) -> ApiError {
+    (Status::BadRequest, message.into())
+}
+
+fn parse_date(value: &str, field: &str) -> Result<NaiveDate, ApiError> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| bad_request(format!("Data inválida em '{}' (use AAAA-MM-DD)", field)))
+}
+
+/// Evolução de ativos, passivos e patrimônio líquido, a partir do histórico diário de saldos das contas e investimentos.
+/// Padrão: últimos 12 meses, um ponto por mês.
+#[get("/net-worth?<from>&<to>&<interval>")]
+pub async fn get_net_worth(
+    user: AuthenticatedUser,
+    from: Option<&str>,
+    to: Option<&str>,
+    interval: Option<&str>,
+    pool: &State<PgPool>,
+) -> Result<Json<NetWorthHistory>, ApiError> {
+    let today = Utc::now().date_naive();
+    let to = to.map(|v| parse_date(v, "to")).transpose()?.unwrap_or(today);
+    let from = match from {
+        Some(value) => parse_date(value, "from")?,
+        None => to.checked_sub_months(Months::new(12)).unwrap_or(to),
+    };
+    if from > to {
+        return Err(bad_request("'from' deve ser anterior a 'to'"));
+    }
+
+    let interval = interval.map(|i| i.trim().to_lowercase()).unwrap_or_else(|| INTERVAL_MONTH.to_string());
+    if !is_valid_interval(&interval) {
+        return Err(bad_request("Intervalo inválido. Use 'day', 'week' ou 'month'"));
+    }
+
+    let points = match interval.as_str() {
+        INTERVAL_DAY => (to - from).num_days() + 1,
+        INTERVAL_WEEK => (to - from).num_days() / 7 + 2,
+        _ => i64::from((to.year() - from.year()) * 12 + to.month() as i32 - from.month() as i32) + 1,
+    };
+    if points > MAX_NET_WORTH_POINTS {
+        return Err(bad_request(format!(
+            "Período longo demais para o intervalo (máximo de {} pontos)",
+            MAX_NET_WORTH_POINTS
+        )));
+    }
+
+    let points = balance_history::net_worth(pool.inner(), user.id, from, to, &interval)
+        .await
+        .map_err(db_error)?;
+
+    Ok(Json(NetWorthHistory { from, to, interval, points }))
+}
@@ -0,0 +1,413 @@
+use crate::models::notification::{
+    NewPushSubscription, Notification, NotificationFeed, NotificationSettings, PushPublicKey, PushSubscription,
+    UpdateNotificationSettings, DEFAULT_FEED_LIMIT, KIND_TEST, MAX_FEED_LIMIT, MAX_PUSH_SUBSCRIPTIONS,
+};
+use crate::notifier::{self, Notifier};
+use crate::routes::transactions::AuthenticatedUser;
+use crate::storage::hex;
+use rand::RngCore;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, put, State};
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+type ApiError = (Status, String);
+
+fn db_error(e: sqlx::Error) -> ApiError {
+    match e {
+        sqlx::Error::Database(ref db) if db.is_unique_violation() => {
+            (Status::Conflict, "Registro de notificação duplicado".to_string())
+        }
+        e => {
+            eprintln!("Erro de banco de dados nas notificações: {}", e);
+            (Status::InternalServerError, format!("Erro de banco de dados: {}", e))
+        }
+    }
+}
+
+fn bad_request(message: impl Into<String>) -> ApiError {
+    (Status::BadRequest, message.into())
+}
+
+fn not_found() -> ApiError {
+    (Status::NotFound, "Notificação não encontrada".to_string())
+}
+
+fn push_unavailable() -> ApiError {
+    (Status::NotFound, "Web push não está configurado no servidor".to_string())
+}
+
+fn generate_secret() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex(&bytes)
+}
+
+/// Feed de notificações do aplicativo, das mais recentes às mais antigas
+#[get("/notifications?<unread>&<limit>")]
+pub async fn get_notifications(
+    user: AuthenticatedUser,
+    unread: Option<bool>,
+    limit: Option<i64>,
+    pool: &State<PgPool>,
+) -> Result<Json<NotificationFeed>, ApiError> {
+    let limit = limit.unwrap_or(DEFAULT_FEED_LIMIT).clamp(1, MAX_FEED_LIMIT);
+
+    let notifications = sqlx::query_as!(
+        Notification,
+        r#"
+        SELECT id, kind, title, body, data, read_at, created_at
+        FROM notifications
+        WHERE user_id = $1 AND (NOT $2 OR read_at IS NULL)
+        ORDER BY created_at DESC
+        LIMIT $3
+        "#,
+        user.id,
+        unread.unwrap_or(false),
+        limit
+    )
+    .fetch_all(pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    let unread_count = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM notifications WHERE user_id = $1 AND read_at IS NULL"#,
+        user.id
+    )
+    .fetch_one(pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(NotificationFeed { unread_count, notifications }))
+}
+
+#[post("/notifications/<id>/read")]
+pub async fn mark_notification_read(
+    user: AuthenticatedUser,
+    id: Uuid,
+    pool: &State<PgPool>,
+) -> Result<Json<Notification>, ApiError> {
+    let notification = sqlx::query_as!(
+        Notification,
+        r#"
+        UPDATE notifications
+        SET read_at = COALESCE(read_at, CURRENT_TIMESTAMP)
+        WHERE id = $1 AND user_id = $2
+        RETURNING id, kind, title, body, data, read_at, created_at
+        "#,
+        id,
+        user.id
+    )
+    .fetch_optional(pool.inner())
+    .await
+    .map_err(db_error)?
+    .ok_or_else(not_found)?;
+
+    Ok(Json(notification))
+}
+
+#[post("/notifications/read-all")]
+pub async fn mark_all_notifications_read(user: AuthenticatedUser, pool: &State<PgPool>) -> Result<Status, ApiError> {
+    sqlx::query!(
+        "UPDATE notifications SET read_at = CURRENT_TIMESTAMP WHERE user_id = $1 AND read_at IS NULL",
+        user.id
+    )
+    .execute(pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Status::NoContent)
+}
+
+/// Remove a notificação do feed. Alertas cuja situação continua ativa só podem ser marcados como lidos,
+/// já que removê-los faria o alerta ser recriado na próxima sincronização.
+#[delete("/notifications/<id>")]
+pub async fn delete_notification(
+    user: AuthenticatedUser,
+    id: Uuid,
+    pool: &State<PgPool>,
+) -> Result<Status, ApiError> {
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM notifications
+        WHERE id = $1 AND user_id = $2 AND dedup_key IS NULL
+        "#,
+        id,
+        user.id
+    )
+    .execute(pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    if result.rows_affected() == 0 {
+        let exists = sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM notifications WHERE id = $1 AND user_id = $2) AS "exists!""#,
+            id,
+            user.id
+        )
+        .fetch_one(pool.inner())
+        .await
+        .map_err(db_error)?;
+        if !exists {
+            return Err(not_found());
+        }
+        return Err((Status::Conflict, "O alerta continua ativo; marque-o como lido".to_string()));
+    }
+
+    Ok(Status::NoContent)
+}
+
+// O segredo fica cifrado no banco; sem a chave com que foi cifrado, não há o que exibir
+fn reveal_webhook_secret(notifier: &Notifier, secret: &str) -> Option<String> {
+    match notifier.secrets()?.decrypt(secret) {
+        Ok(secret) => Some(secret),
+        Err(e) => {
+            eprintln!("Segredo de webhook ilegível: {}", e);
+            None
+        }
+    }
+}
+
+fn settings_response(recipient: notifier::Recipient, notifier: &Notifier, reveal_secret: bool) -> NotificationSettings {
+    let webhook_secret = recipient
+        .webhook_secret
+        .as_deref()
+        .filter(|_| reveal_secret)
+        .and_then(|s| reveal_webhook_secret(notifier, s));
+
+    NotificationSettings {
+        budget_alerts: recipient.budget_alerts,
+        connection_alerts: recipient.connection_alerts,
+        large_transaction_threshold: recipient.large_transaction_threshold,
+        low_balance_threshold: recipient.low_balance_threshold,
+        email_enabled: recipient.email_enabled,
+        webhook_url: recipient.webhook_url,
+        webhook_secret,
+        webhook_available: notifier.secrets().is_some(),
+        email_available: notifier.email_available(),
+        push_available: notifier.push_public_key().is_some(),
+    }
+}
+
+#[get("/notifications/settings")]
+pub async fn get_notification_settings(
+    user: AuthenticatedUser,
+    pool: &State<PgPool>,
+    notifier: &State<Arc<Notifier>>,
+) -> Result<Json<NotificationSettings>, ApiError> {
+    let recipient = notifier::recipient(pool.inner(), user.id)
+        .await
+        .map_err(db_error)?
+        .ok_or((Status::NotFound, "Usuário não encontrado".to_string()))?;
+
+    Ok(Json(settings_response(recipient, notifier.inner(), false)))
+}
+
+/// Substitui as preferências de alertas. Um segredo de assinatura é gerado (e guardado cifrado)
+/// quando o webhook é configurado; a resposta traz o segredo apenas quando ele é novo.
+#[put("/notifications/settings", format = "json", data = "<settings>")]
+pub async fn update_notification_settings(
+    user: AuthenticatedUser,
+    settings: Json<UpdateNotificationSettings>,
+    pool: &State<PgPool>,
+    notifier: &State<Arc<Notifier>>,
+) -> Result<Json<NotificationSettings>, ApiError> {
+    // O saldo mínimo pode ser negativo (ex: alertar só ao passar de parte do cheque especial)
+    if settings.large_transaction_threshold.is_some_and(|t| t <= Decimal::ZERO) {
+        return Err(bad_request("O valor para alertar despesas grandes deve ser positivo"));
+    }
+
+    let webhook_url = settings.webhook_url.as_deref().map(str::trim).filter(|url| !url.is_empty());
+    if let Some(url) = webhook_url {
+        if notifier.secrets().is_none() {
+            return Err(bad_request("Webhooks não estão configurados no servidor"));
+        }
+        notifier::resolve_public_endpoint(url)
+            .await
+            .map_err(|e| bad_request(format!("URL de webhook inválida: {}", e)))?;
+    }
+
+    if settings.email_enabled && !notifier.email_available() {
+        return Err(bad_request("O envio de e-mails não está configurado no servidor"));
+    }
+
+    let current_secret = sqlx::query_scalar!(
+        "SELECT webhook_secret FROM notification_settings WHERE user_id = $1",
+        user.id
+    )
+    .fetch_optional(pool.inner())
+    .await
+    .map_err(db_error)?
+    .flatten();
+
+    // Mantém o segredo atual, a menos que o usuário peça outro ou ele não possa mais ser decifrado
+    let (webhook_secret, generated) = match (webhook_url, notifier.secrets()) {
+        (Some(_), Some(secrets)) => match current_secret {
+            Some(secret) if !settings.rotate_webhook_secret && secrets.decrypt(&secret).is_ok() => (Some(secret), false),
+            _ => {
+                let secret = secrets.encrypt(&generate_secret()).map_err(|e| {
+                    eprintln!("Erro ao cifrar o segredo do webhook: {}", e);
+                    (Status::InternalServerError, "Erro ao gerar o segredo do webhook".to_string())
+                })?;
+                (Some(secret), true)
+            }
+        },
+        _ => (None, false),
+    };
+
+    sqlx::query!(
+        r#"
+        INSERT INTO notification_settings (
+            user_id, budget_alerts, connection_alerts, large_transaction_threshold, low_balance_threshold,
+            email_enabled, webhook_url, webhook_secret
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        ON CONFLICT (user_id) DO UPDATE SET
+            budget_alerts = EXCLUDED.budget_alerts,
+            connection_alerts = EXCLUDED.connection_alerts,
+            large_transaction_threshold = EXCLUDED.large_transaction_threshold,
+            low_balance_threshold = EXCLUDED.low_balance_threshold,
+            email_enabled = EXCLUDED.email_enabled,
+            webhook_url = EXCLUDED.webhook_url,
+            webhook_secret = EXCLUDED.webhook_secret
+        "#,
+        user.id,
+        settings.budget_alerts,
+        settings.connection_alerts,
+        settings.large_transaction_threshold,
+        settings.low_balance_threshold,
+        settings.email_enabled,
+        webhook_url,
+        webhook_secret
+    )
+    .execute(pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    let recipient = notifier::recipient(pool.inner(), user.id)
+        .await
+        .map_err(db_error)?
+        .ok_or((Status::NotFound, "Usuário não encontrado".to_string()))?;
+
+    Ok(Json(settings_response(recipient, notifier.inner(), generated)))
+}
+
+/// Cria uma notificação de teste e a envia por todos os canais configurados
+#[post("/notifications/test")]
+pub async fn send_test_notification(
+    user: AuthenticatedUser,
+    pool: &State<PgPool>,
+    notifier: &State<Arc<Notifier>>,
+) -> Result<Json<Notification>, ApiError> {
+    let recipient = notifier::recipient(pool.inner(), user.id)
+        .await
+        .map_err(db_error)?
+        .ok_or((Status::NotFound, "Usuário não encontrado".to_string()))?;
+
+    let notification = sqlx::query_as!(
+        Notification,
+        r#"
+        INSERT INTO notifications (user_id, kind, title, body)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id, kind, title, body, data, read_at, created_at
+        "#,
+        user.id,
+        KIND_TEST,
+        "Notificação de teste",
+        "Se você recebeu esta mensagem, os alertas do Firebudget estão funcionando."
+    )
+    .fetch_one(pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    notifier.dispatch(pool.inner(), recipient, vec![notification.clone()]);
+    Ok(Json(notification))
+}
+
+/// Chave pública VAPID para `pushManager.subscribe({ applicationServerKey })`
+#[get("/notifications/push/public-key")]
+pub async fn get_push_public_key(
+    _user: AuthenticatedUser,
+    notifier: &State<Arc<Notifier>>,
+) -> Result<Json<PushPublicKey>, ApiError> {
+    let public_key = notifier.push_public_key().ok_or_else(push_unavailable)?;
+    Ok(Json(PushPublicKey { public_key: public_key.to_string() }))
+}
+
+/// Registra a inscrição de push do navegador; registrar de novo o mesmo endpoint atualiza as chaves.
+/// Endpoints registrados por outro usuário são recusados.
+#[post("/notifications/push/subscriptions", format = "json", data = "<subscription>")]
+pub async fn create_push_subscription(
+    user: AuthenticatedUser,
+    subscription: Json<NewPushSubscription>,
+    pool: &State<PgPool>,
+    notifier: &State<Arc<Notifier>>,
+) -> Result<Json<PushSubscription>, ApiError> {
+    if notifier.push_public_key().is_none() {
+        return Err(push_unavailable());
+    }
+    notifier::resolve_public_endpoint(&subscription.endpoint)
+        .await
+        .map_err(|e| bad_request(format!("Endpoint de push inválido: {}", e)))?;
+    if subscription.keys.p256dh.trim().is_empty() || subscription.keys.auth.trim().is_empty() {
+        return Err(bad_request("Chaves da inscrição de push ausentes"));
+    }
+
+    let count = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM push_subscriptions WHERE user_id = $1 AND endpoint <> $2"#,
+        user.id,
+        subscription.endpoint
+    )
+    .fetch_one(pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    if count >= MAX_PUSH_SUBSCRIPTIONS {
+        return Err((
+            Status::Conflict,
+            format!("Limite de {} navegadores com push atingido", MAX_PUSH_SUBSCRIPTIONS),
+        ));
+    }
+
+    let created = sqlx::query_as!(
+        PushSubscription,
+        r#"
+        INSERT INTO push_subscriptions (user_id, endpoint, p256dh, auth)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (endpoint) DO UPDATE SET p256dh = EXCLUDED.p256dh, auth = EXCLUDED.auth
+        WHERE push_subscriptions.user_id = EXCLUDED.user_id
+        RETURNING id, endpoint, created_at
+        "#,
+        user.id,
+        subscription.endpoint,
+        subscription.keys.p256dh.trim(),
+        subscription.keys.auth.trim()
+    )
+    .fetch_optional(pool.inner())
+    .await
+    .map_err(db_error)?
+    .ok_or((Status::Conflict, "Endpoint de push já registrado por outro usuário".to_string()))?;
+
+    Ok(Json(created))
+}
+
+#[delete("/notifications/push/subscriptions/<id>")]
+pub async fn delete_push_subscription(
+    user: AuthenticatedUser,
+    id: Uuid,
+    pool: &State<PgPool>,
+) -> Result<Status, ApiError> {
+    let result = sqlx::query!("DELETE FROM push_subscriptions WHERE id = $1 AND user_id = $2", id, user.id)
+        .execute(pool.inner())
+        .await
+        .map_err(db_error)?;
+
+    if result.rows_affected() == 0 {
+        return Err((Status::NotFound, "Inscrição de push não encontrada".to_string()));
+    }
+
+    Ok(Status::NoContent)
+}
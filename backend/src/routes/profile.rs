@@ -0,0 +1,407 @@
+use crate::config::AppConfig;
+use crate::data_export;
+use crate::mailer::Mailer;
+use crate::models::data_export::{DataExport, DataExportResponse, STATUS_PENDING, STATUS_READY};
+use crate::models::user::{ChangePassword, DeleteUser, UpdateUser, User, VerifyEmail};
+use crate::pluggy::client::PluggyClient;
+use crate::routes::transactions::AuthenticatedUser;
+use chrono::{Duration, Utc};
+use rocket::fs::NamedFile;
+use rocket::http::{Header, Status};
+use rocket::serde::json::Json;
+use rocket::{delete, get, patch, post, Responder, State};
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+type ApiError = (Status, String);
+
+fn db_error(e: sqlx::Error) -> ApiError {
+    (Status::InternalServerError, format!("Erro de banco de dados: {}", e))
+}
+
+// Alterações de conta exigem uma sessão de login, não um token de acesso
+fn require_session(user: &AuthenticatedUser) -> Result<(), ApiError> {
+    if user.api_token_id.is_some() {
+        return Err((Status::Forbidden, "Operação não permitida com token de acesso".to_string()));
+    }
+    Ok(())
+}
+
+async fn find_user(pool: &PgPool, id: Uuid) -> Result<User, ApiError> {
+    sqlx::query_as!(
+        User,
+        "SELECT id, email, full_name, password_hash, preferred_currency, locale, timezone, pending_email, created_at, updated_at FROM users WHERE id = $1",
+        id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(db_error)?
+    .ok_or_else(|| (Status::NotFound, "Usuário não encontrado".to_string()))
+}
+
+fn validate_update(update: &UpdateUser) -> Result<(), ApiError> {
+    if matches!(&update.full_name, Some(name) if name.trim().is_empty()) {
+        return Err((Status::BadRequest, "Nome não pode ser vazio".to_string()));
+    }
+    if matches!(&update.email, Some(email) if !email.contains('@')) {
+        return Err((Status::BadRequest, "Email inválido".to_string()));
+    }
+    if let Some(currency) = &update.preferred_currency {
+        if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_uppercase()) {
+            return Err((Status::BadRequest, "Moeda deve ser um código ISO 4217 (ex: BRL)".to_string()));
+        }
+    }
+    if matches!(&update.locale, Some(locale) if locale.is_empty() || locale.len() > 20) {
+        return Err((Status::BadRequest, "Locale inválido".to_string()));
+    }
+    if let Some(timezone) = &update.timezone {
+        if timezone.parse::<chrono_tz::Tz>().is_err() {
+            return Err((Status::BadRequest, "Fuso horário inválido".to_string()));
+        }
+    }
+    Ok(())
+}
+
+#[get("/me")]
+pub async fn get_me(user: AuthenticatedUser, pool: &State<PgPool>) -> Result<Json<User>, ApiError> {
+    Ok(Json(find_user(pool.inner(), user.id).await?))
+}
+
+#[patch("/me", format = "json", data = "<update>")]
+pub async fn update_me(
+    user: AuthenticatedUser,
+    update: Json<UpdateUser>,
+    pool: &State<PgPool>,
+    mailer: &State<Option<Arc<Mailer>>>,
+) -> Result<Json<User>, ApiError> {
+    require_session(&user)?;
+    validate_update(&update)?;
+
+    let current = find_user(pool.inner(), user.id).await?;
+
+    // Troca de email só é aplicada após confirmação do novo endereço
+    if let Some(email) = update.email.as_ref().map(|e| e.trim().to_lowercase()) {
+        if email == current.email.to_lowercase() {
+            sqlx::query!(
+                "UPDATE users SET pending_email = NULL, email_verification_token_hash = NULL, email_verification_expires_at = NULL WHERE id = $1",
+                user.id
+            )
+            .execute(pool.inner())
+            .await
+            .map_err(db_error)?;
+        } else {
+            let email_taken = sqlx::query!("SELECT id FROM users WHERE lower(email) = $1", email)
+                .fetch_optional(pool.inner())
+                .await
+                .map_err(db_error)?;
+
+            if email_taken.is_some() {
+                return Err((Status::Conflict, "Email já cadastrado".to_string()));
+            }
+
+            // O token de confirmação só chega ao usuário por email
+            let mailer = mailer
+                .as_ref()
+                .ok_or_else(|| (Status::BadRequest, "O envio de e-mails não está configurado no servidor".to_string()))?;
+
+            let (token, token_hash) = User::generate_email_token();
+            let expires_at = Utc::now() + Duration::hours(24);
+
+            sqlx::query!(
+                "UPDATE users SET pending_email = $1, email_verification_token_hash = $2, email_verification_expires_at = $3 WHERE id = $4",
+                email,
+                token_hash,
+                expires_at,
+                user.id
+            )
+            .execute(pool.inner())
+            .await
+            .map_err(db_error)?;
+
+            let body = format!(
+                "Olá, {}.\n\nUse o código abaixo para confirmar o novo email da sua conta Firebudget:\n\n{}\n\n\
+                 O código expira em 24 horas. Se você não pediu a troca, ignore esta mensagem.",
+                current.full_name, token
+            );
+            mailer
+                .send(&current.full_name, &email, "Confirme seu novo email", body)
+                .await
+                .map_err(|e| {
+                    eprintln!("Erro ao enviar confirmação de email para o usuário {}: {}", user.id, e);
+                    (Status::BadGateway, "Não foi possível enviar o email de confirmação".to_string())
+                })?;
+        }
+    }
+
+    let user = sqlx::query_as!(
+        User,
+        r#"
+        UPDATE users SET
+            full_name = COALESCE($1, full_name),
+            preferred_currency = COALESCE($2, preferred_currency),
+            locale = COALESCE($3, locale),
+            timezone = COALESCE($4, timezone)
+        WHERE id = $5
+        RETURNING id, email, full_name, password_hash, preferred_currency, locale, timezone, pending_email, created_at, updated_at
+        "#,
+        update.full_name.as_deref().map(str::trim),
+        update.preferred_currency,
+        update.locale,
+        update.timezone,
+        user.id
+    )
+    .fetch_one(pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(user))
+}
+
+#[post("/me/email/verify", format = "json", data = "<verification>")]
+pub async fn verify_email(
+    user: AuthenticatedUser,
+    verification: Json<VerifyEmail>,
+    pool: &State<PgPool>,
+) -> Result<Json<User>, ApiError> {
+    require_session(&user)?;
+
+    let user = sqlx::query_as!(
+        User,
+        r#"
+        UPDATE users SET
+            email = pending_email,
+            pending_email = NULL,
+            email_verification_token_hash = NULL,
+            email_verification_expires_at = NULL
+        WHERE id = $1
+          AND pending_email IS NOT NULL
+          AND email_verification_token_hash = $2
+          AND email_verification_expires_at > CURRENT_TIMESTAMP
+        RETURNING id, email, full_name, password_hash, preferred_currency, locale, timezone, pending_email, created_at, updated_at
+        "#,
+        user.id,
+        User::hash_email_token(&verification.token)
+    )
+    .fetch_optional(pool.inner())
+    .await
+    .map_err(|e| match e {
+        sqlx::Error::Database(ref db) if db.is_unique_violation() => {
+            (Status::Conflict, "Email já cadastrado".to_string())
+        }
+        e => db_error(e),
+    })?;
+
+    user.map(Json)
+        .ok_or_else(|| (Status::BadRequest, "Token de confirmação inválido ou expirado".to_string()))
+}
+
+#[post("/me/password", format = "json", data = "<change>")]
+pub async fn change_password(
+    user: AuthenticatedUser,
+    change: Json<ChangePassword>,
+    pool: &State<PgPool>,
+) -> Result<Status, ApiError> {
+    require_session(&user)?;
+
+    let current = find_user(pool.inner(), user.id).await?;
+
+    // Usuários que só entram via OIDC podem definir uma senha sem informar a atual
+    if current.password_hash.is_some() {
+        let password_ok = current
+            .verify_password(&change.current_password)
+            .map_err(|e| (Status::InternalServerError, format!("Erro ao processar hash: {}", e)))?;
+
+        if !password_ok {
+            return Err((Status::Unauthorized, "Senha atual incorreta".to_string()));
+        }
+    }
+
+    if change.new_password.is_empty() {
+        return Err((Status::BadRequest, "Nova senha não pode ser vazia".to_string()));
+    }
+
+    let password_hash = User::hash_password(&change.new_password)
+        .map_err(|e| (Status::InternalServerError, format!("Erro ao criar hash da senha: {}", e)))?;
+
+    sqlx::query!(
+        "UPDATE users SET password_hash = $1 WHERE id = $2",
+        password_hash,
+        user.id
+    )
+    .execute(pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Status::NoContent)
+}
+
+#[delete("/me", format = "json", data = "<confirmation>")]
+pub async fn delete_me(
+    user: AuthenticatedUser,
+    confirmation: Json<DeleteUser>,
+    pool: &State<PgPool>,
+    config: &State<Arc<AppConfig>>,
+) -> Result<Status, ApiError> {
+    require_session(&user)?;
+
+    let current = find_user(pool.inner(), user.id).await?;
+
+    if current.password_hash.is_some() {
+        let password_ok = current
+            .verify_password(&confirmation.password)
+            .map_err(|e| (Status::InternalServerError, format!("Erro ao processar hash: {}", e)))?;
+
+        if !password_ok {
+            return Err((Status::Unauthorized, "Senha incorreta".to_string()));
+        }
+    }
+
+    // 1. Remover as conexões bancárias na Pluggy antes de apagar os dados locais
+    let items = sqlx::query!("SELECT pluggy_item_id FROM items WHERE user_id = $1", user.id)
+        .fetch_all(pool.inner())
+        .await
+        .map_err(db_error)?;
+
+    let mut client = PluggyClient::new(config.inner().as_ref().clone());
+
+    for item in items {
+        if let Err(e) = client.delete_item(&item.pluggy_item_id).await {
+            eprintln!("Erro ao deletar item {} na Pluggy: {}", item.pluggy_item_id, e);
+            return Err((
+                Status::BadGateway,
+                "Não foi possível remover as conexões bancárias. Tente novamente.".to_string(),
+            ));
+        }
+    }
+
+    // 2. Arquivos de exportação ficam fora do banco e não somem com a cascata
+    data_export::delete_user_exports(pool.inner(), user.id).await.map_err(db_error)?;
+
+    // 3. Apagar o usuário (items, contas, transações e demais dados em cascata)
+    sqlx::query!("DELETE FROM users WHERE id = $1", user.id)
+        .execute(pool.inner())
+        .await
+        .map_err(db_error)?;
+
+    eprintln!("Usuário {} removido", user.id);
+    Ok(Status::NoContent)
+}
+
+// Situação da exportação mais recente; a geração é pedida via POST
+#[get("/me/export")]
+pub async fn get_my_export(
+    user: AuthenticatedUser,
+    pool: &State<PgPool>,
+) -> Result<(Status, Json<DataExportResponse>), ApiError> {
+    // Uma geração interrompida não pode ficar pendente para sempre
+    data_export::fail_stale_exports(pool.inner(), user.id).await.map_err(db_error)?;
+
+    let export = sqlx::query_as!(
+        DataExport,
+        r#"
+        SELECT id, user_id, status, download_token, error, expires_at, completed_at, created_at, updated_at
+        FROM data_exports
+        WHERE user_id = $1
+        ORDER BY created_at DESC
+        LIMIT 1
+        "#,
+        user.id
+    )
+    .fetch_optional(pool.inner())
+    .await
+    .map_err(db_error)?
+    .ok_or_else(|| (Status::NotFound, "Nenhuma exportação solicitada".to_string()))?;
+
+    let status = if export.status == STATUS_PENDING { Status::Accepted } else { Status::Ok };
+    Ok((status, Json(export.into())))
+}
+
+#[post("/me/export")]
+pub async fn create_my_export(
+    user: AuthenticatedUser,
+    pool: &State<PgPool>,
+    config: &State<Arc<AppConfig>>,
+) -> Result<(Status, Json<DataExportResponse>), ApiError> {
+    data_export::fail_stale_exports(pool.inner(), user.id).await.map_err(db_error)?;
+
+    // Uma exportação em andamento é reaproveitada em vez de gerar outra em paralelo
+    let pending = sqlx::query_as!(
+        DataExport,
+        r#"
+        SELECT id, user_id, status, download_token, error, expires_at, completed_at, created_at, updated_at
+        FROM data_exports
+        WHERE user_id = $1 AND status = $2
+        ORDER BY created_at DESC
+        LIMIT 1
+        "#,
+        user.id,
+        STATUS_PENDING
+    )
+    .fetch_optional(pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    if let Some(export) = pending {
+        return Ok((Status::Accepted, Json(export.into())));
+    }
+
+    let export = sqlx::query_as!(
+        DataExport,
+        r#"
+        INSERT INTO data_exports (user_id, status)
+        VALUES ($1, $2)
+        RETURNING id, user_id, status, download_token, error, expires_at, completed_at, created_at, updated_at
+        "#,
+        user.id,
+        STATUS_PENDING
+    )
+    .fetch_one(pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    // Geração em background para não bloquear a requisição
+    tokio::spawn(data_export::run_export(
+        pool.inner().clone(),
+        config.inner().clone(),
+        export.id,
+        user.id,
+    ));
+
+    Ok((Status::Accepted, Json(export.into())))
+}
+
+#[derive(Responder)]
+pub struct ExportDownload {
+    file: NamedFile,
+    disposition: Header<'static>,
+}
+
+// Download público: o token do link é a credencial e expira junto com a exportação
+#[get("/exports/<token>")]
+pub async fn download_export(token: &str, pool: &State<PgPool>) -> Result<ExportDownload, Status> {
+    let export = sqlx::query!(
+        r#"
+        SELECT id, file_path
+        FROM data_exports
+        WHERE download_token = $1 AND status = $2 AND expires_at > CURRENT_TIMESTAMP
+        "#,
+        token,
+        STATUS_READY
+    )
+    .fetch_optional(pool.inner())
+    .await
+    .map_err(|_| Status::InternalServerError)?
+    .ok_or(Status::NotFound)?;
+
+    let path = export.file_path.ok_or(Status::NotFound)?;
+    let file = NamedFile::open(path).await.map_err(|_| Status::NotFound)?;
+
+    Ok(ExportDownload {
+        file,
+        disposition: Header::new(
+            "Content-Disposition",
+            format!("attachment; filename=\"firebudget-export-{}.zip\"", export.id),
+        ),
+    })
+}
@@ -0,0 +1,261 @@
+use crate::models::recurring::{
+    is_valid_frequency, NewRecurringTransaction, RecurringSeries, RecurringTransaction, UpdateRecurringTransaction,
+    MAX_RECURRING_PER_USER,
+};
+use crate::recurrence;
+use crate::routes::transactions::AuthenticatedUser;
+use chrono::Utc;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, put, State};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+type ApiError = (Status, String);
+
+fn db_error(e: sqlx::Error) -> ApiError {
+    eprintln!("Erro de banco de dados nos lançamentos recorrentes: {}", e);
+    (Status::InternalServerError, format!("Erro de banco de dados: {}", e))
+}
+
+fn bad_request(message: impl Into<String>) -> ApiError {
+    (Status::BadRequest, message.into())
+}
+
+fn not_found() -> ApiError {
+    (Status::NotFound, "Lançamento recorrente não encontrado".to_string())
+}
+
+async fn fetch_recurring(pool: &PgPool, user_id: Uuid, id: Uuid) -> Result<RecurringTransaction, ApiError> {
+    sqlx::query_as!(
+        RecurringTransaction,
+        r#"
+        SELECT id, description, amount, currency, category, account_id, frequency, start_date, end_date,
+               next_date, occurrence_count, enabled, created_at, updated_at
+        FROM recurring_transactions
+        WHERE id = $1 AND user_id = $2
+        "#,
+        id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(db_error)?
+    .ok_or_else(not_found)
+}
+
+/// Séries recorrentes do usuário: as detectadas no histórico (assinaturas, aluguel, salário)
+/// e os lançamentos recorrentes cadastrados, ordenadas pela próxima data esperada
+#[get("/recurring")]
+pub async fn get_recurring(
+    user: AuthenticatedUser,
+    pool: &State<PgPool>,
+) -> Result<Json<Vec<RecurringSeries>>, ApiError> {
+    let today = Utc::now().date_naive();
+    let series = recurrence::load_series(pool.inner(), user.id, today).await.map_err(db_error)?;
+
+    Ok(Json(series))
+}
+
+#[get("/recurring/manual")]
+pub async fn get_recurring_transactions(
+    user: AuthenticatedUser,
+    pool: &State<PgPool>,
+) -> Result<Json<Vec<RecurringTransaction>>, ApiError> {
+    let recurring = sqlx::query_as!(
+        RecurringTransaction,
+        r#"
+        SELECT id, description, amount, currency, category, account_id, frequency, start_date, end_date,
+               next_date, occurrence_count, enabled, created_at, updated_at
+        FROM recurring_transactions
+        WHERE user_id = $1
+        ORDER BY next_date NULLS LAST, created_at
+        "#,
+        user.id
+    )
+    .fetch_all(pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(recurring))
+}
+
+/// Cadastra um lançamento recorrente. Ocorrências com data até hoje são criadas imediatamente.
+#[post("/recurring/manual", format = "json", data = "<recurring>")]
+pub async fn create_recurring_transaction(
+    user: AuthenticatedUser,
+    recurring: Json<NewRecurringTransaction>,
+    pool: &State<PgPool>,
+) -> Result<Json<RecurringTransaction>, ApiError> {
+    let description = recurring.description.trim();
+    if description.is_empty() {
+        return Err(bad_request("Informe a descrição"));
+    }
+    if recurring.amount.is_zero() {
+        return Err(bad_request("O valor não pode ser zero"));
+    }
+    let frequency = recurring.frequency.trim().to_lowercase();
+    if !is_valid_frequency(&frequency) {
+        return Err(bad_request("Frequência inválida. Use 'weekly', 'biweekly', 'monthly', 'quarterly' ou 'yearly'"));
+    }
+    if recurring.end_date.is_some_and(|end| end < recurring.start_date) {
+        return Err(bad_request("A data final é anterior à inicial"));
+    }
+    let currency = recurring.currency.as_deref().map(str::trim).filter(|c| !c.is_empty()).unwrap_or("BRL");
+    if currency.len() != 3 {
+        return Err(bad_request("Moeda inválida"));
+    }
+
+    let count = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM recurring_transactions WHERE user_id = $1"#,
+        user.id
+    )
+    .fetch_one(pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    if count >= MAX_RECURRING_PER_USER {
+        return Err((Status::Conflict, format!("Limite de {} lançamentos recorrentes atingido", MAX_RECURRING_PER_USER)));
+    }
+
+    if let Some(account_id) = recurring.account_id {
+        let owned = sqlx::query_scalar!(
+            r#"
+            SELECT a.id
+            FROM accounts a
+            INNER JOIN items i ON a.item_id = i.id
+            WHERE a.id = $1 AND i.user_id = $2
+            "#,
+            account_id,
+            user.id
+        )
+        .fetch_optional(pool.inner())
+        .await
+        .map_err(db_error)?;
+
+        if owned.is_none() {
+            return Err((Status::NotFound, "Conta não encontrada".to_string()));
+        }
+    }
+
+    let category = recurring.category.as_deref().map(str::trim).filter(|c| !c.is_empty());
+
+    let id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO recurring_transactions
+            (user_id, account_id, description, amount, currency, category, frequency, start_date, end_date, next_date)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $8)
+        RETURNING id
+        "#,
+        user.id,
+        recurring.account_id,
+        description,
+        recurring.amount,
+        currency.to_uppercase(),
+        category,
+        frequency,
+        recurring.start_date,
+        recurring.end_date
+    )
+    .fetch_one(pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    recurrence::materialize(pool.inner(), id, Utc::now().date_naive())
+        .await
+        .map_err(|e| {
+            eprintln!("Erro ao criar ocorrências do lançamento recorrente {}: {}", id, e);
+            (Status::InternalServerError, "Erro ao criar ocorrências do lançamento recorrente".to_string())
+        })?;
+
+    Ok(Json(fetch_recurring(pool.inner(), user.id, id).await?))
+}
+
+/// Altera as próximas ocorrências; as transações já criadas não mudam.
+/// Ao reativar, as ocorrências do período em que ficou desativado são puladas.
+#[put("/recurring/manual/<id>", format = "json", data = "<update>")]
+pub async fn update_recurring_transaction(
+    user: AuthenticatedUser,
+    id: Uuid,
+    update: Json<UpdateRecurringTransaction>,
+    pool: &State<PgPool>,
+) -> Result<Json<RecurringTransaction>, ApiError> {
+    let current = fetch_recurring(pool.inner(), user.id, id).await?;
+
+    let description = match update.description.as_deref().map(str::trim) {
+        Some("") => return Err(bad_request("Informe a descrição")),
+        Some(description) => description.to_string(),
+        None => current.description,
+    };
+    let amount = update.amount.unwrap_or(current.amount);
+    if amount.is_zero() {
+        return Err(bad_request("O valor não pode ser zero"));
+    }
+    let category = match &update.category {
+        Some(category) => Some(category.trim().to_string()).filter(|c| !c.is_empty()),
+        None => current.category,
+    };
+    let end_date = update.end_date.or(current.end_date);
+    if end_date.is_some_and(|end| end < current.start_date) {
+        return Err(bad_request("A data final é anterior à inicial"));
+    }
+    let enabled = update.enabled.unwrap_or(current.enabled);
+
+    // Reativado: continua a partir da primeira ocorrência de hoje em diante
+    let mut occurrence_count = current.occurrence_count;
+    if enabled && !current.enabled {
+        let today = Utc::now().date_naive();
+        while recurrence::occurrence_date(current.start_date, &current.frequency, occurrence_count.max(0) as u32)
+            .is_some_and(|d| d < today)
+        {
+            occurrence_count += 1;
+        }
+    }
+    let next_date = recurrence::next_occurrence(current.start_date, &current.frequency, occurrence_count, end_date);
+
+    sqlx::query!(
+        r#"
+        UPDATE recurring_transactions
+        SET description = $1, amount = $2, category = $3, end_date = $4, enabled = $5,
+            occurrence_count = $6, next_date = $7
+        WHERE id = $8 AND user_id = $9
+        "#,
+        description,
+        amount,
+        category,
+        end_date,
+        enabled,
+        occurrence_count,
+        next_date,
+        id,
+        user.id
+    )
+    .execute(pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(fetch_recurring(pool.inner(), user.id, id).await?))
+}
+
+/// Remove o lançamento recorrente; as transações já criadas são mantidas
+#[delete("/recurring/manual/<id>")]
+pub async fn delete_recurring_transaction(
+    user: AuthenticatedUser,
+    id: Uuid,
+    pool: &State<PgPool>,
+) -> Result<Status, ApiError> {
+    let result = sqlx::query!(
+        "DELETE FROM recurring_transactions WHERE id = $1 AND user_id = $2",
+        id,
+        user.id
+    )
+    .execute(pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    if result.rows_affected() == 0 {
+        return Err(not_found());
+    }
+
+    Ok(Status::NoContent)
+}
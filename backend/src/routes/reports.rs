@@ -0,0 +1,220 @@
+use crate::models::report::{
+    Report, ReportGroup, ReportQuery, ReportRow, MAX_REPORT_DIMENSIONS, METRICS, METRIC_EXPENSE, METRIC_INCOME,
+};
+use crate::models::tag::Tag;
+use crate::reporting::{self, Aggregate, ReportDimension, ReportFilter, ReportPeriod};
+use crate::routes::transactions::AuthenticatedUser;
+use chrono::{Datelike, NaiveDate, Utc};
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{get, State};
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use std::collections::{BTreeMap, HashMap};
+
+type ApiError = (Status, String);
+
+fn db_error(e: sqlx::Error) -> ApiError {
+    eprintln!("Erro de banco de dados nos relatórios: {}", e);
+    (Status::InternalServerError, format!("Erro de banco de dados: {}", e))
+}
+
+fn bad_request(message: impl Into<String>) -> ApiError {
+    (Status::BadRequest, message.into())
+}
+
+fn parse_date(value: &str, field: &str) -> Result<NaiveDate, ApiError> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| bad_request(format!("Data inválida em '{}' (use AAAA-MM-DD)", field)))
+}
+
+fn parse_metrics(values: &[String]) -> Result<Vec<String>, ApiError> {
+    if values.is_empty() {
+        return Ok(METRICS.iter().map(|m| m.to_string()).collect());
+    }
+
+    let mut metrics: Vec<String> = Vec::new();
+    for value in values {
+        let metric = value.trim().to_lowercase();
+        if !METRICS.contains(&metric.as_str()) {
+            return Err(bad_request(format!("Métrica inválida: {} (use income, expense ou net)", value)));
+        }
+        if !metrics.contains(&metric) {
+            metrics.push(metric);
+        }
+    }
+    Ok(metrics)
+}
+
+fn parse_dimensions(values: &[String]) -> Result<Vec<ReportDimension>, ApiError> {
+    let mut dimensions: Vec<ReportDimension> = Vec::new();
+    for value in values {
+        let dimension = ReportDimension::parse(&value.trim().to_lowercase())
+            .ok_or_else(|| bad_request(format!("Agrupamento inválido: {}", value)))?;
+        if dimensions.contains(&dimension) {
+            return Err(bad_request(format!("Agrupamento repetido: {}", dimension.name())));
+        }
+        dimensions.push(dimension);
+    }
+
+    if dimensions.len() > MAX_REPORT_DIMENSIONS {
+        return Err(bad_request(format!("Use no máximo {} agrupamentos", MAX_REPORT_DIMENSIONS)));
+    }
+    if dimensions.iter().filter(|d| d.is_period()).count() > 1 {
+        return Err(bad_request("Use apenas um agrupamento por período (day, week, month ou year)"));
+    }
+    Ok(dimensions)
+}
+
+fn metric_values(metrics: &[String], income: Decimal, expense: Decimal) -> BTreeMap<String, Decimal> {
+    metrics
+        .iter()
+        .map(|metric| {
+            let value = match metric.as_str() {
+                METRIC_INCOME => income,
+                METRIC_EXPENSE => expense,
+                _ => income - expense,
+            };
+            (metric.clone(), value)
+        })
+        .collect()
+}
+
+fn report_row(
+    dimensions: &[ReportDimension],
+    metrics: &[String],
+    current: &Aggregate,
+    previous: Option<&Aggregate>,
+    comparing: bool,
+) -> ReportRow {
+    let groups = dimensions
+        .iter()
+        .zip(current.keys.iter().zip(&current.labels))
+        .map(|(dimension, (key, label))| ReportGroup {
+            dimension: dimension.name().to_string(),
+            key: key.clone(),
+            label: label.clone(),
+        })
+        .collect();
+
+    let values = metric_values(metrics, current.income, current.expense);
+    if !comparing {
+        return ReportRow { groups, values, previous: None, change: None, change_percent: None };
+    }
+
+    let previous = previous
+        .map(|p| metric_values(metrics, p.income, p.expense))
+        .unwrap_or_else(|| metric_values(metrics, Decimal::ZERO, Decimal::ZERO));
+    let change: BTreeMap<String, Decimal> = values.iter().map(|(m, v)| (m.clone(), *v - previous[m])).collect();
+    let change_percent = change
+        .iter()
+        .filter(|(m, _)| !previous[*m].is_zero())
+        .map(|(m, delta)| (m.clone(), (*delta / previous[m].abs() * Decimal::from(100)).round_dp(2)))
+        .collect();
+
+    ReportRow { groups, values, previous: Some(previous), change: Some(change), change_percent: Some(change_percent) }
+}
+
+/// Relatório de receitas, despesas e saldo (net) em um período qualquer, com até dois agrupamentos
+/// (ex.: `group_by=month&group_by=category`) e comparação opcional com o período anterior ou o mesmo
+/// período do ano anterior. Transferências entre contas do usuário ficam de fora.
+#[get("/reports?<query..>")]
+pub async fn get_report(
+    user: AuthenticatedUser,
+    query: ReportQuery,
+    pool: &State<PgPool>,
+) -> Result<Json<Report>, ApiError> {
+    let today = Utc::now().date_naive();
+    let to = query.to.as_deref().map(|v| parse_date(v, "to")).transpose()?.unwrap_or(today);
+    let from = match query.from.as_deref() {
+        Some(value) => parse_date(value, "from")?,
+        None => to.with_day(1).unwrap_or(to),
+    };
+    if from > to {
+        return Err(bad_request("'from' deve ser anterior a 'to'"));
+    }
+
+    let metrics = parse_metrics(&query.metric)?;
+    let dimensions = parse_dimensions(&query.group_by)?;
+
+    let compare = query.compare.as_deref().map(|c| c.trim().to_lowercase());
+    let comparison = compare
+        .as_deref()
+        .map(|c| {
+            ReportPeriod::comparison(c, from, to)
+                .ok_or_else(|| bad_request("Comparação inválida. Use 'previous_period' ou 'previous_year'"))
+        })
+        .transpose()?;
+
+    let tags = query
+        .tag
+        .iter()
+        .map(|t| Tag::normalize_name(t).ok_or_else(|| bad_request(format!("Tag inválida: {}", t))))
+        .collect::<Result<Vec<_>, _>>()?;
+    let filter = ReportFilter {
+        account_id: query.account_id,
+        category: query.category.as_deref().map(str::trim).filter(|c| !c.is_empty()).map(str::to_string),
+        tags,
+    };
+
+    let period = ReportPeriod::new(from, to);
+    let current_totals = reporting::aggregate(pool.inner(), user.id, &period, &[], &filter).await.map_err(db_error)?;
+    let current_rows = reporting::aggregate(pool.inner(), user.id, &period, &dimensions, &filter)
+        .await
+        .map_err(db_error)?;
+
+    let (previous_totals, previous_rows) = match &comparison {
+        Some(previous) => (
+            reporting::aggregate(pool.inner(), user.id, previous, &[], &filter).await.map_err(db_error)?,
+            reporting::aggregate(pool.inner(), user.id, previous, &dimensions, &filter)
+                .await
+                .map_err(db_error)?,
+        ),
+        None => (Vec::new(), Vec::new()),
+    };
+
+    let comparing = comparison.is_some();
+    let totals = match current_totals.first() {
+        Some(current) => report_row(&[], &metrics, current, previous_totals.first(), comparing),
+        None => return Err((Status::InternalServerError, "Totais do relatório indisponíveis".to_string())),
+    };
+
+    // Grupos que só existem no período anterior entram com valores zerados no atual
+    let mut previous_by_key: HashMap<Vec<Option<String>>, Aggregate> =
+        previous_rows.into_iter().map(|row| (row.keys.clone(), row)).collect();
+    let mut groups: Vec<(Aggregate, Option<Aggregate>)> = current_rows
+        .into_iter()
+        .map(|row| {
+            let previous = previous_by_key.remove(&row.keys);
+            (row, previous)
+        })
+        .collect();
+    groups.extend(previous_by_key.into_values().map(|previous| {
+        let current = Aggregate {
+            keys: previous.keys.clone(),
+            labels: previous.labels.clone(),
+            income: Decimal::ZERO,
+            expense: Decimal::ZERO,
+        };
+        (current, Some(previous))
+    }));
+    // Ordena pelos grupos, com os lançamentos sem o campo por último
+    groups.sort_by_key(|(row, _)| row.keys.iter().map(|k| (k.is_none(), k.clone())).collect::<Vec<_>>());
+
+    let rows = groups
+        .iter()
+        .map(|(current, previous)| report_row(&dimensions, &metrics, current, previous.as_ref(), comparing))
+        .collect();
+
+    Ok(Json(Report {
+        from,
+        to,
+        metrics,
+        group_by: dimensions.iter().map(|d| d.name().to_string()).collect(),
+        compare: comparison.as_ref().and(compare),
+        previous_from: comparison.as_ref().map(|p| p.from),
+        previous_to: comparison.as_ref().map(|p| p.to),
+        totals,
+        rows,
+    }))
+}
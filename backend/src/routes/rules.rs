@@ -0,0 +1,350 @@
+use crate::ingest::{self, first_match};
+use crate::models::categorization_rule::{
+    ApplyRulesResult, CategorizationRule, NewCategorizationRule, RuleInput, DIRECTION_EXPENSE, DIRECTION_INCOME,
+    MAX_RULES_PER_USER,
+};
+use crate::models::tag::Tag;
+use crate::routes::transactions::AuthenticatedUser;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, put, State};
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+type ApiError = (Status, String);
+
+fn db_error(e: sqlx::Error) -> ApiError {
+    eprintln!("Erro de banco de dados nas regras: {}", e);
+    (Status::InternalServerError, format!("Erro de banco de dados: {}", e))
+}
+
+fn bad_request(message: impl Into<String>) -> ApiError {
+    (Status::BadRequest, message.into())
+}
+
+fn clean(value: &Option<String>) -> Option<String> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty()).map(str::to_string)
+}
+
+/// Regra validada e normalizada, pronta para gravar
+struct ValidRule {
+    name: String,
+    priority: i32,
+    enabled: bool,
+    description_contains: Option<String>,
+    original_category: Option<String>,
+    direction: Option<String>,
+    min_amount: Option<Decimal>,
+    max_amount: Option<Decimal>,
+    account_id: Option<Uuid>,
+    set_category: Option<String>,
+    set_subcategory: Option<String>,
+    rename_to: Option<String>,
+    add_tag: Option<String>,
+}
+
+async fn validate(pool: &PgPool, user_id: Uuid, rule: &NewCategorizationRule) -> Result<ValidRule, ApiError> {
+    let name = clean(&Some(rule.name.clone())).ok_or_else(|| bad_request("Informe o nome da regra"))?;
+
+    let direction = clean(&rule.direction).map(|d| d.to_lowercase());
+    if direction.as_deref().is_some_and(|d| d != DIRECTION_EXPENSE && d != DIRECTION_INCOME) {
+        return Err(bad_request("Direção inválida. Use 'expense' ou 'income'"));
+    }
+
+    if rule.min_amount.is_some_and(|v| v < Decimal::ZERO) || rule.max_amount.is_some_and(|v| v < Decimal::ZERO) {
+        return Err(bad_request("Os limites de valor se aplicam ao valor absoluto e não podem ser negativos"));
+    }
+    if let (Some(min), Some(max)) = (rule.min_amount, rule.max_amount) {
+        if min > max {
+            return Err(bad_request("Valor mínimo maior que o máximo"));
+        }
+    }
+
+    if let Some(account_id) = rule.account_id {
+        let owned = sqlx::query_scalar!(
+            r#"
+            SELECT a.id
+            FROM accounts a
+            INNER JOIN items i ON a.item_id = i.id
+            WHERE a.id = $1 AND i.user_id = $2
+            "#,
+            account_id,
+            user_id
+        )
+        .fetch_optional(pool)
+        .await
+        .map_err(db_error)?;
+
+        if owned.is_none() {
+            return Err((Status::NotFound, "Conta não encontrada".to_string()));
+        }
+    }
+
+    let add_tag = match clean(&rule.add_tag) {
+        Some(tag) => Some(Tag::normalize_name(&tag).ok_or_else(|| bad_request(format!("Tag inválida: {}", tag)))?),
+        None => None,
+    };
+
+    let valid = ValidRule {
+        name,
+        priority: rule.priority.unwrap_or(0),
+        enabled: rule.enabled.unwrap_or(true),
+        description_contains: clean(&rule.description_contains),
+        original_category: clean(&rule.original_category),
+        direction,
+        min_amount: rule.min_amount,
+        max_amount: rule.max_amount,
+        account_id: rule.account_id,
+        set_category: clean(&rule.set_category),
+        set_subcategory: clean(&rule.set_subcategory),
+        rename_to: clean(&rule.rename_to),
+        add_tag,
+    };
+
+    // Uma regra sem condições casaria com todas as transações
+    if valid.description_contains.is_none()
+        && valid.original_category.is_none()
+        && valid.direction.is_none()
+        && valid.min_amount.is_none()
+        && valid.max_amount.is_none()
+        && valid.account_id.is_none()
+    {
+        return Err(bad_request("Informe ao menos uma condição"));
+    }
+    if valid.set_category.is_none() && valid.set_subcategory.is_none() && valid.rename_to.is_none() && valid.add_tag.is_none() {
+        return Err(bad_request("Informe ao menos uma ação"));
+    }
+    // A subcategoria pertence a uma categoria e não pode ser definida sozinha
+    if valid.set_subcategory.is_some() && valid.set_category.is_none() {
+        return Err(bad_request("Informe a categoria junto com a subcategoria"));
+    }
+
+    Ok(valid)
+}
+
+#[get("/rules")]
+pub async fn get_rules(
+    user: AuthenticatedUser,
+    pool: &State<PgPool>,
+) -> Result<Json<Vec<CategorizationRule>>, ApiError> {
+    let rules = sqlx::query_as!(
+        CategorizationRule,
+        r#"
+        SELECT id, name, priority, enabled, description_contains, original_category, direction,
+               min_amount, max_amount, account_id, set_category, set_subcategory, rename_to, add_tag,
+               created_at, updated_at
+        FROM categorization_rules
+        WHERE user_id = $1
+        ORDER BY priority, created_at
+        "#,
+        user.id
+    )
+    .fetch_all(pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(rules))
+}
+
+#[post("/rules", format = "json", data = "<rule>")]
+pub async fn create_rule(
+    user: AuthenticatedUser,
+    rule: Json<NewCategorizationRule>,
+    pool: &State<PgPool>,
+) -> Result<Json<CategorizationRule>, ApiError> {
+    let count = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM categorization_rules WHERE user_id = $1"#,
+        user.id
+    )
+    .fetch_one(pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    if count >= MAX_RULES_PER_USER {
+        return Err((Status::Conflict, format!("Limite de {} regras atingido", MAX_RULES_PER_USER)));
+    }
+
+    let rule = validate(pool.inner(), user.id, &rule).await?;
+
+    let created = sqlx::query_as!(
+        CategorizationRule,
+        r#"
+        INSERT INTO categorization_rules (
+            user_id, name, priority, enabled, description_contains, original_category, direction,
+            min_amount, max_amount, account_id, set_category, set_subcategory, rename_to, add_tag
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+        RETURNING id, name, priority, enabled, description_contains, original_category, direction,
+                  min_amount, max_amount, account_id, set_category, set_subcategory, rename_to, add_tag,
+                  created_at, updated_at
+        "#,
+        user.id,
+        rule.name,
+        rule.priority,
+        rule.enabled,
+        rule.description_contains,
+        rule.original_category,
+        rule.direction,
+        rule.min_amount,
+        rule.max_amount,
+        rule.account_id,
+        rule.set_category,
+        rule.set_subcategory,
+        rule.rename_to,
+        rule.add_tag
+    )
+    .fetch_one(pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(created))
+}
+
+#[put("/rules/<id>", format = "json", data = "<rule>")]
+pub async fn update_rule(
+    user: AuthenticatedUser,
+    id: Uuid,
+    rule: Json<NewCategorizationRule>,
+    pool: &State<PgPool>,
+) -> Result<Json<CategorizationRule>, ApiError> {
+    let rule = validate(pool.inner(), user.id, &rule).await?;
+
+    let updated = sqlx::query_as!(
+        CategorizationRule,
+        r#"
+        UPDATE categorization_rules
+        SET name = $1, priority = $2, enabled = $3, description_contains = $4, original_category = $5,
+            direction = $6, min_amount = $7, max_amount = $8, account_id = $9, set_category = $10,
+            set_subcategory = $11, rename_to = $12, add_tag = $13
+        WHERE id = $14 AND user_id = $15
+        RETURNING id, name, priority, enabled, description_contains, original_category, direction,
+                  min_amount, max_amount, account_id, set_category, set_subcategory, rename_to, add_tag,
+                  created_at, updated_at
+        "#,
+        rule.name,
+        rule.priority,
+        rule.enabled,
+        rule.description_contains,
+        rule.original_category,
+        rule.direction,
+        rule.min_amount,
+        rule.max_amount,
+        rule.account_id,
+        rule.set_category,
+        rule.set_subcategory,
+        rule.rename_to,
+        rule.add_tag,
+        id,
+        user.id
+    )
+    .fetch_optional(pool.inner())
+    .await
+    .map_err(db_error)?
+    .ok_or_else(|| (Status::NotFound, "Regra não encontrada".to_string()))?;
+
+    Ok(Json(updated))
+}
+
+/// Remove a regra; as transações já alteradas por ela mantêm a categoria atual
+#[delete("/rules/<id>")]
+pub async fn delete_rule(
+    user: AuthenticatedUser,
+    id: Uuid,
+    pool: &State<PgPool>,
+) -> Result<Status, ApiError> {
+    let result = sqlx::query!(
+        "DELETE FROM categorization_rules WHERE id = $1 AND user_id = $2",
+        id,
+        user.id
+    )
+    .execute(pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    if result.rows_affected() == 0 {
+        return Err((Status::NotFound, "Regra não encontrada".to_string()));
+    }
+
+    Ok(Status::NoContent)
+}
+
+/// Avalia as regras sobre as transações já existentes do usuário.
+/// Com uma única regra, ela é aplicada a tudo que casar, independente da prioridade.
+async fn apply_to_existing(
+    pool: &PgPool,
+    user_id: Uuid,
+    rules: &[CategorizationRule],
+) -> Result<ApplyRulesResult, ApiError> {
+    let transactions = sqlx::query!(
+        "SELECT id, description, category, amount, account_id FROM transactions WHERE user_id = $1",
+        user_id
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(db_error)?;
+
+    let mut result = ApplyRulesResult { matched: 0, updated: 0 };
+    let mut tx = pool.begin().await.map_err(db_error)?;
+
+    for transaction in &transactions {
+        let input = RuleInput {
+            description: transaction.description.as_deref(),
+            category: transaction.category.as_deref(),
+            amount: transaction.amount,
+            account_id: transaction.account_id,
+        };
+        let Some(rule) = first_match(rules, &input) else {
+            continue;
+        };
+
+        result.matched += 1;
+        if ingest::apply_rule(&mut tx, user_id, transaction.id, rule).await.map_err(db_error)? {
+            result.updated += 1;
+        }
+    }
+
+    tx.commit().await.map_err(db_error)?;
+
+    Ok(result)
+}
+
+#[post("/rules/<id>/apply")]
+pub async fn apply_rule(
+    user: AuthenticatedUser,
+    id: Uuid,
+    pool: &State<PgPool>,
+) -> Result<Json<ApplyRulesResult>, ApiError> {
+    let rule = sqlx::query_as!(
+        CategorizationRule,
+        r#"
+        SELECT id, name, priority, enabled, description_contains, original_category, direction,
+               min_amount, max_amount, account_id, set_category, set_subcategory, rename_to, add_tag,
+               created_at, updated_at
+        FROM categorization_rules
+        WHERE id = $1 AND user_id = $2
+        "#,
+        id,
+        user.id
+    )
+    .fetch_optional(pool.inner())
+    .await
+    .map_err(db_error)?
+    .ok_or_else(|| (Status::NotFound, "Regra não encontrada".to_string()))?;
+
+    if !rule.enabled {
+        return Err((Status::Conflict, "A regra está desativada".to_string()));
+    }
+
+    Ok(Json(apply_to_existing(pool.inner(), user.id, &[rule]).await?))
+}
+
+/// Reaplica todas as regras ativas, em ordem de prioridade, a todas as transações
+#[post("/rules/apply")]
+pub async fn apply_all_rules(
+    user: AuthenticatedUser,
+    pool: &State<PgPool>,
+) -> Result<Json<ApplyRulesResult>, ApiError> {
+    let rules = ingest::load_rules(pool.inner(), user.id).await.map_err(db_error)?;
+    Ok(Json(apply_to_existing(pool.inner(), user.id, &rules).await?))
+}
@@ -0,0 +1,131 @@
+use crate::models::transaction_split::{ReplaceSplits, TransactionSplit};
+use crate::routes::transactions::AuthenticatedUser;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{delete, get, put, State};
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+type ApiError = (Status, String);
+
+const MAX_SPLITS: usize = 50;
+
+fn db_error(e: sqlx::Error) -> ApiError {
+    eprintln!("Erro de banco de dados nas divisões: {}", e);
+    (Status::InternalServerError, format!("Erro de banco de dados: {}", e))
+}
+
+// Valor da transação, confirmando que ela pertence ao usuário
+async fn transaction_amount(pool: &PgPool, user_id: Uuid, transaction_id: Uuid) -> Result<Decimal, ApiError> {
+    sqlx::query_scalar!(
+        "SELECT amount FROM transactions WHERE id = $1 AND user_id = $2",
+        transaction_id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(db_error)?
+    .ok_or_else(|| (Status::NotFound, "Transação não encontrada".to_string()))
+}
+
+async fn find_splits(pool: &PgPool, transaction_id: Uuid) -> Result<Vec<TransactionSplit>, ApiError> {
+    sqlx::query_as!(
+        TransactionSplit,
+        r#"
+        SELECT id, transaction_id, amount, category, notes, position, created_at, updated_at
+        FROM transaction_splits
+        WHERE transaction_id = $1
+        ORDER BY position
+        "#,
+        transaction_id
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(db_error)
+}
+
+#[get("/transactions/<id>/splits")]
+pub async fn get_splits(
+    user: AuthenticatedUser,
+    id: Uuid,
+    pool: &State<PgPool>,
+) -> Result<Json<Vec<TransactionSplit>>, ApiError> {
+    transaction_amount(pool.inner(), user.id, id).await?;
+    Ok(Json(find_splits(pool.inner(), id).await?))
+}
+
+#[put("/transactions/<id>/splits", format = "json", data = "<request>")]
+pub async fn replace_splits(
+    user: AuthenticatedUser,
+    id: Uuid,
+    request: Json<ReplaceSplits>,
+    pool: &State<PgPool>,
+) -> Result<Json<Vec<TransactionSplit>>, ApiError> {
+    let amount = transaction_amount(pool.inner(), user.id, id).await?;
+
+    if request.splits.len() < 2 || request.splits.len() > MAX_SPLITS {
+        return Err((
+            Status::BadRequest,
+            format!("Informe entre 2 e {} partes", MAX_SPLITS),
+        ));
+    }
+    if request.splits.iter().any(|s| s.amount.is_zero()) {
+        return Err((Status::BadRequest, "Partes não podem ter valor zero".to_string()));
+    }
+
+    let total: Decimal = request.splits.iter().map(|s| s.amount).sum();
+    if total != amount {
+        return Err((
+            Status::BadRequest,
+            format!("A soma das partes ({}) deve ser igual ao valor da transação ({})", total, amount.normalize()),
+        ));
+    }
+
+    let mut tx = pool.begin().await.map_err(db_error)?;
+
+    sqlx::query!("DELETE FROM transaction_splits WHERE transaction_id = $1", id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+
+    for (position, split) in request.splits.iter().enumerate() {
+        sqlx::query!(
+            r#"
+            INSERT INTO transaction_splits (transaction_id, user_id, amount, category, notes, position)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            id,
+            user.id,
+            split.amount,
+            split.category.as_deref().map(str::trim).filter(|c| !c.is_empty()),
+            split.notes,
+            position as i32
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    }
+
+    tx.commit().await.map_err(db_error)?;
+
+    Ok(Json(find_splits(pool.inner(), id).await?))
+}
+
+/// Desfaz a divisão: a transação volta a contar inteira na categoria original
+// rank 2: DELETE /transactions/imports/<id> tem a mesma forma e deve ser tentada antes
+#[delete("/transactions/<id>/splits", rank = 2)]
+pub async fn delete_splits(
+    user: AuthenticatedUser,
+    id: Uuid,
+    pool: &State<PgPool>,
+) -> Result<Status, ApiError> {
+    transaction_amount(pool.inner(), user.id, id).await?;
+
+    sqlx::query!("DELETE FROM transaction_splits WHERE transaction_id = $1", id)
+        .execute(pool.inner())
+        .await
+        .map_err(db_error)?;
+
+    Ok(Status::NoContent)
+}
@@ -0,0 +1,53 @@
+use crate::models::tag::Tag;
+use crate::routes::transactions::AuthenticatedUser;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{delete, get, State};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[get("/tags")]
+pub async fn get_tags(
+    user: AuthenticatedUser,
+    pool: &State<PgPool>,
+) -> Result<Json<Vec<Tag>>, Status> {
+    let tags = sqlx::query_as!(
+        Tag,
+        r#"
+        SELECT g.id, g.name, COUNT(tt.transaction_id) AS "transaction_count!", g.created_at
+        FROM tags g
+        LEFT JOIN transaction_tags tt ON tt.tag_id = g.id
+        WHERE g.user_id = $1
+        GROUP BY g.id
+        ORDER BY g.name
+        "#,
+        user.id
+    )
+    .fetch_all(pool.inner())
+    .await
+    .map_err(|e| {
+        eprintln!("Erro ao buscar tags: {}", e);
+        Status::InternalServerError
+    })?;
+
+    Ok(Json(tags))
+}
+
+/// Remove a tag de todas as transações
+#[delete("/tags/<id>")]
+pub async fn delete_tag(
+    user: AuthenticatedUser,
+    id: Uuid,
+    pool: &State<PgPool>,
+) -> Result<Status, Status> {
+    let result = sqlx::query!("DELETE FROM tags WHERE id = $1 AND user_id = $2", id, user.id)
+        .execute(pool.inner())
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+
+    if result.rows_affected() == 0 {
+        return Err(Status::NotFound);
+    }
+
+    Ok(Status::NoContent)
+}
@@ -0,0 +1,117 @@
+use crate::models::api_token::{ApiToken, NewApiToken};
+use crate::routes::transactions::AuthenticatedUser;
+use chrono::Utc;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, State};
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize)]
+pub struct CreatedApiTokenResponse {
+    // Exibido apenas uma vez; depois disso só o hash fica armazenado
+    pub token: String,
+    pub api_token: ApiToken,
+}
+
+#[post("/tokens", format = "json", data = "<new_token>")]
+pub async fn create_api_token(
+    user: AuthenticatedUser,
+    new_token: Json<NewApiToken>,
+    pool: &State<PgPool>,
+) -> Result<Json<CreatedApiTokenResponse>, (Status, String)> {
+    // Tokens só podem ser gerenciados a partir de uma sessão de login
+    if user.api_token_id.is_some() {
+        return Err((Status::Forbidden, "Tokens de acesso não podem gerenciar tokens".to_string()));
+    }
+
+    if new_token.name.trim().is_empty() {
+        return Err((Status::BadRequest, "Nome do token é obrigatório".to_string()));
+    }
+
+    if !ApiToken::is_valid_scope(&new_token.scope) {
+        return Err((Status::BadRequest, "Escopo inválido. Use 'read' ou 'read_write'".to_string()));
+    }
+
+    if matches!(new_token.expires_at, Some(expires_at) if expires_at <= Utc::now()) {
+        return Err((Status::BadRequest, "Data de expiração deve estar no futuro".to_string()));
+    }
+
+    let (token, token_prefix, token_hash) = ApiToken::generate();
+
+    let api_token = sqlx::query_as!(
+        ApiToken,
+        r#"
+        INSERT INTO api_tokens (user_id, name, token_prefix, token_hash, scope, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING
+            id, user_id, name, token_prefix, scope,
+            expires_at, last_used_at, revoked_at, created_at, updated_at
+        "#,
+        user.id,
+        new_token.name.trim(),
+        token_prefix,
+        token_hash,
+        new_token.scope,
+        new_token.expires_at
+    )
+    .fetch_one(pool.inner())
+    .await
+    .map_err(|e| (Status::InternalServerError, format!("Erro ao criar token: {}", e)))?;
+
+    Ok(Json(CreatedApiTokenResponse { token, api_token }))
+}
+
+#[get("/tokens")]
+pub async fn get_api_tokens(
+    user: AuthenticatedUser,
+    pool: &State<PgPool>,
+) -> Result<Json<Vec<ApiToken>>, Status> {
+    let tokens = sqlx::query_as!(
+        ApiToken,
+        r#"
+        SELECT
+            id, user_id, name, token_prefix, scope,
+            expires_at, last_used_at, revoked_at, created_at, updated_at
+        FROM api_tokens
+        WHERE user_id = $1
+        ORDER BY created_at DESC
+        "#,
+        user.id
+    )
+    .fetch_all(pool.inner())
+    .await
+    .map_err(|e| {
+        eprintln!("Erro ao buscar tokens: {}", e);
+        Status::InternalServerError
+    })?;
+
+    Ok(Json(tokens))
+}
+
+#[delete("/tokens/<id>")]
+pub async fn revoke_api_token(
+    user: AuthenticatedUser,
+    id: Uuid,
+    pool: &State<PgPool>,
+) -> Result<Status, Status> {
+    if user.api_token_id.is_some() {
+        return Err(Status::Forbidden);
+    }
+
+    let result = sqlx::query!(
+        "UPDATE api_tokens SET revoked_at = CURRENT_TIMESTAMP WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL",
+        id,
+        user.id
+    )
+    .execute(pool.inner())
+    .await
+    .map_err(|_| Status::InternalServerError)?;
+
+    if result.rows_affected() == 0 {
+        return Err(Status::NotFound);
+    }
+
+    Ok(Status::NoContent)
+}
@@ -1,26 +1,35 @@
-use crate::models::transaction::{NewTransaction, Transaction};
-use crate::config::AppConfig; // Used for JWT secret verification if we manually decode token
-use rocket::http::Status;
+use crate::models::api_token::{ApiToken, SCOPE_READ_WRITE, TOKEN_PREFIX};
+use crate::models::tag::{Tag, MAX_TAGS_PER_TRANSACTION};
+use crate::models::transaction::{
+    NewTransaction, Transaction, TransactionAnnotations, TransactionFilter, TransactionPage,
+    TransactionSearchResult,
+};
+use crate::jwt_keys::KeyStore;
+use crate::merchant_matching;
+use crate::transaction_export::{
+    ofx_footer, ofx_header, ofx_transaction, xlsx_workbook, CsvEncoder, ExportLocale, ExportRow, EXPORT_COLUMNS,
+    FORMAT_CSV, FORMAT_OFX, FORMAT_XLSX,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use chrono::{NaiveDate, Utc};
+use rocket::futures::StreamExt;
+use rocket::http::{ContentType, Header, Method, Status};
+use rocket::response::{self, Responder};
 use rocket::serde::json::Json;
-use rocket::{delete, get, post, put, State};
-use sqlx::PgPool;
+use rocket::{delete, get, patch, post, put, State};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Postgres, QueryBuilder};
 use uuid::Uuid;
 use rocket::request::{Outcome, Request, FromRequest};
-use jsonwebtoken::{decode, Validation, DecodingKey};
-use serde::{Deserialize, Serialize};
-
-// --- Auth Middleware Placeholder ---
-// In a real app, this should be in a shared module. 
-// Re-implementing `Claims` here to allow decoding.
-#[derive(Debug, Serialize, Deserialize)]
-struct Claims {
-    sub: String,
-    exp: usize,
-    iat: usize,
-}
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::io::{AsyncWriteExt, BufWriter};
 
 pub struct AuthenticatedUser {
     pub id: Uuid,
+    // Preenchido quando a requisição foi autenticada com um token de acesso pessoal
+    pub api_token_id: Option<Uuid>,
 }
 
 #[rocket::async_trait]
@@ -34,20 +43,19 @@ impl<'r> FromRequest<'r> for AuthenticatedUser {
         }
 
         let token_str = keys[0].replace("Bearer ", "");
-        let config = request.guard::<&State<std::sync::Arc<AppConfig>>>().await;
-        
-        if let Outcome::Success(config) = config {
-            let validation = Validation::default();
-            let token_data = decode::<Claims>(
-                &token_str,
-                &DecodingKey::from_secret(config.jwt_secret.as_bytes()),
-                &validation,
-            );
-
-            match token_data {
-                Ok(c) => {
-                    if let Ok(uuid) = Uuid::parse_str(&c.claims.sub) {
-                        Outcome::Success(AuthenticatedUser { id: uuid })
+
+        if token_str.starts_with(TOKEN_PREFIX) {
+            return authenticate_api_token(request, &token_str).await;
+        }
+
+        let keys = request.guard::<&State<Arc<KeyStore>>>().await;
+        let pool = request.guard::<&State<PgPool>>().await;
+
+        if let (Outcome::Success(keys), Outcome::Success(pool)) = (keys, pool) {
+            match keys.verify(pool.inner(), &token_str).await {
+                Ok(claims) => {
+                    if let Ok(uuid) = Uuid::parse_str(&claims.sub) {
+                        Outcome::Success(AuthenticatedUser { id: uuid, api_token_id: None })
                     } else {
                         Outcome::Forward(Status::Unauthorized)
                     }
@@ -60,30 +68,525 @@ impl<'r> FromRequest<'r> for AuthenticatedUser {
     }
 }
 
+async fn authenticate_api_token(
+    request: &Request<'_>,
+    token_str: &str,
+) -> Outcome<AuthenticatedUser, ()> {
+    let pool = match request.guard::<&State<PgPool>>().await {
+        Outcome::Success(pool) => pool,
+        _ => return Outcome::Forward(Status::InternalServerError),
+    };
+
+    // Busca o token pelo hash, ignorando revogados e expirados, e registra o uso
+    let token = sqlx::query!(
+        r#"
+        UPDATE api_tokens
+        SET last_used_at = CURRENT_TIMESTAMP
+        WHERE token_hash = $1
+          AND revoked_at IS NULL
+          AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+        RETURNING id, user_id, scope
+        "#,
+        ApiToken::hash(token_str)
+    )
+    .fetch_optional(pool.inner())
+    .await;
+
+    match token {
+        Ok(Some(token)) => {
+            // Tokens somente leitura não podem alterar dados
+            let is_read = matches!(request.method(), Method::Get | Method::Head);
+            if token.scope != SCOPE_READ_WRITE && !is_read {
+                return Outcome::Error((Status::Forbidden, ()));
+            }
+            Outcome::Success(AuthenticatedUser { id: token.user_id, api_token_id: Some(token.id) })
+        }
+        Ok(None) => Outcome::Forward(Status::Unauthorized),
+        Err(e) => {
+            eprintln!("Erro ao validar token de acesso: {}", e);
+            Outcome::Forward(Status::InternalServerError)
+        }
+    }
+}
+
+
+type ApiError = (Status, String);
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 200;
+
+/// Colunas de `Transaction` com o alias `t`, para consultas montadas dinamicamente
+pub const TRANSACTION_COLUMNS: &str = r#"
+    t.id, t.pluggy_transaction_id, t.account_id, t.item_id, t.amount, t.date,
+    t.description, t.category, t.subcategory, t.currency, t.status, t.merchant, t.balance, t.notes, t.rule_id,
+    t.recurring_transaction_id, t.installment_purchase_id, t.installment_number, t.installment_total, t.merchant_id,
+    t.predicted_category, t.prediction_confidence,
+    ARRAY(
+        SELECT g.name FROM transaction_tags tt INNER JOIN tags g ON tt.tag_id = g.id
+        WHERE tt.transaction_id = t.id ORDER BY g.name
+    ) AS tags,
+    (
+        SELECT tr.id FROM transfers tr
+        WHERE t.id IN (tr.outgoing_transaction_id, tr.incoming_transaction_id)
+    ) AS transfer_id,
+    t.created_at, t.updated_at
+"#;
+
+fn bad_request(message: impl Into<String>) -> ApiError {
+    (Status::BadRequest, message.into())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransactionSort {
+    DateDesc,
+    DateAsc,
+    AmountDesc,
+    AmountAsc,
+}
+
+impl TransactionSort {
+    pub fn parse(value: Option<&str>) -> Result<Self, ApiError> {
+        match value.unwrap_or("date_desc") {
+            "date_desc" => Ok(TransactionSort::DateDesc),
+            "date_asc" => Ok(TransactionSort::DateAsc),
+            "amount_desc" => Ok(TransactionSort::AmountDesc),
+            "amount_asc" => Ok(TransactionSort::AmountAsc),
+            other => Err(bad_request(format!("Ordenação inválida: {}", other))),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            TransactionSort::DateDesc => "date_desc",
+            TransactionSort::DateAsc => "date_asc",
+            TransactionSort::AmountDesc => "amount_desc",
+            TransactionSort::AmountAsc => "amount_asc",
+        }
+    }
+
+    fn is_descending(self) -> bool {
+        matches!(self, TransactionSort::DateDesc | TransactionSort::AmountDesc)
+    }
+
+    /// Cláusula ORDER BY; o id desempata para que a ordem seja estável entre páginas
+    pub fn order_by(self) -> &'static str {
+        match self {
+            TransactionSort::DateDesc => "t.date DESC, t.id DESC",
+            TransactionSort::DateAsc => "t.date ASC, t.id ASC",
+            TransactionSort::AmountDesc => "t.amount DESC, t.id DESC",
+            TransactionSort::AmountAsc => "t.amount ASC, t.id ASC",
+        }
+    }
+}
+
+// Posição da última transação retornada, serializada em base64url no parâmetro `cursor`
+#[derive(Serialize, Deserialize)]
+struct Cursor {
+    sort: String,
+    key: String,
+    id: Uuid,
+}
+
+fn encode_cursor(sort: TransactionSort, last: &Transaction) -> String {
+    let key = match sort {
+        TransactionSort::DateDesc | TransactionSort::DateAsc => last.date.to_string(),
+        TransactionSort::AmountDesc | TransactionSort::AmountAsc => last.amount.to_string(),
+    };
+    let cursor = Cursor { sort: sort.name().to_string(), key, id: last.id };
+    URL_SAFE_NO_PAD.encode(serde_json::to_vec(&cursor).unwrap_or_default())
+}
+
+fn push_cursor(qb: &mut QueryBuilder<'_, Postgres>, sort: TransactionSort, value: &str) -> Result<(), ApiError> {
+    let cursor: Cursor = URL_SAFE_NO_PAD
+        .decode(value)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .ok_or_else(|| bad_request("Cursor inválido"))?;
+
+    if cursor.sort != sort.name() {
+        return Err(bad_request("Cursor não corresponde à ordenação informada"));
+    }
+
+    let op = if sort.is_descending() { "<" } else { ">" };
+    match sort {
+        TransactionSort::DateDesc | TransactionSort::DateAsc => {
+            qb.push(format!(" AND (t.date, t.id) {} (", op));
+            qb.push_bind(parse_date(&cursor.key, "cursor")?);
+        }
+        TransactionSort::AmountDesc | TransactionSort::AmountAsc => {
+            qb.push(format!(" AND (t.amount, t.id) {} (", op));
+            qb.push_bind(parse_amount(&cursor.key, "cursor")?);
+        }
+    }
+    qb.push(", ").push_bind(cursor.id).push(")");
+    Ok(())
+}
+
+fn parse_date(value: &str, field: &str) -> Result<NaiveDate, ApiError> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| bad_request(format!("Data inválida em '{}' (use AAAA-MM-DD)", field)))
+}
+
+fn parse_amount(value: &str, field: &str) -> Result<Decimal, ApiError> {
+    Decimal::from_str(value.trim()).map_err(|_| bad_request(format!("Valor inválido em '{}'", field)))
+}
+
+// Escapa curingas do LIKE para que a busca seja literal
+fn like_pattern(term: &str) -> String {
+    let escaped = term.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+/// Adiciona à consulta (já com `WHERE t.user_id = ...`) as condições dos filtros da listagem
+pub fn push_transaction_filters(
+    qb: &mut QueryBuilder<'_, Postgres>,
+    filter: &TransactionFilter,
+) -> Result<(), ApiError> {
+    let from = filter.from.as_deref().map(|v| parse_date(v, "from")).transpose()?;
+    let to = filter.to.as_deref().map(|v| parse_date(v, "to")).transpose()?;
+    if let (Some(from), Some(to)) = (from, to) {
+        if from > to {
+            return Err(bad_request("'from' deve ser anterior a 'to'"));
+        }
+    }
+    if let Some(from) = from {
+        qb.push(" AND t.date >= ").push_bind(from);
+    }
+    if let Some(to) = to {
+        qb.push(" AND t.date <= ").push_bind(to);
+    }
+
+    if let Some(account_id) = filter.account_id {
+        qb.push(" AND t.account_id = ").push_bind(account_id);
+    }
+    if let Some(item_id) = filter.item_id {
+        qb.push(" AND t.item_id = ").push_bind(item_id);
+    }
+    if let Some(merchant_id) = filter.merchant_id {
+        qb.push(" AND t.merchant_id = ").push_bind(merchant_id);
+    }
+    // Transações divididas também aparecem pela categoria de qualquer uma das partes
+    if let Some(category) = filter.category.as_ref().filter(|c| !c.is_empty()) {
+        qb.push(" AND (t.category = ")
+            .push_bind(category.clone())
+            .push(" OR EXISTS (SELECT 1 FROM transaction_splits s WHERE s.transaction_id = t.id AND s.category = ")
+            .push_bind(category.clone())
+            .push("))");
+    }
+    for tag in &filter.tag {
+        let Some(name) = Tag::normalize_name(tag) else {
+            return Err(bad_request(format!("Tag inválida: {}", tag)));
+        };
+        qb.push(
+            " AND EXISTS (SELECT 1 FROM transaction_tags tt INNER JOIN tags g ON tt.tag_id = g.id \
+             WHERE tt.transaction_id = t.id AND g.name = ",
+        )
+        .push_bind(name)
+        .push(")");
+    }
+    if let Some(status) = filter.status.as_ref().filter(|s| !s.is_empty()) {
+        qb.push(" AND t.status = ").push_bind(status.to_uppercase());
+    }
+
+    if let Some(min) = filter.min_amount.as_deref() {
+        qb.push(" AND t.amount >= ").push_bind(parse_amount(min, "min_amount")?);
+    }
+    if let Some(max) = filter.max_amount.as_deref() {
+        qb.push(" AND t.amount <= ").push_bind(parse_amount(max, "max_amount")?);
+    }
+
+    match filter.source.as_deref() {
+        None => {}
+        Some("manual") => {
+            qb.push(" AND t.pluggy_transaction_id IS NULL");
+        }
+        Some("synced") => {
+            qb.push(" AND t.pluggy_transaction_id IS NOT NULL");
+        }
+        Some(other) => return Err(bad_request(format!("Origem inválida: {} (use manual ou synced)", other))),
+    }
+
+    if let Some(term) = filter.q.as_deref().map(str::trim).filter(|q| !q.is_empty()) {
+        let pattern = like_pattern(term);
+        qb.push(" AND (t.description ILIKE ")
+            .push_bind(pattern.clone())
+            .push(" OR t.merchant->>'name' ILIKE ")
+            .push_bind(pattern)
+            .push(")");
+    }
+
+    Ok(())
+}
 
-#[get("/transactions")]
+#[get("/transactions?<filter..>")]
 pub async fn get_transactions(
     user: AuthenticatedUser,
+    filter: TransactionFilter,
     pool: &State<PgPool>,
-) -> Result<Json<Vec<Transaction>>, Status> {
-    let transactions = sqlx::query_as!(
-        Transaction,
+) -> Result<Json<TransactionPage>, ApiError> {
+    let sort = TransactionSort::parse(filter.sort.as_deref())?;
+    let limit = filter.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+
+    let mut qb = QueryBuilder::new(format!("SELECT {} FROM transactions t WHERE t.user_id = ", TRANSACTION_COLUMNS));
+    qb.push_bind(user.id);
+    push_transaction_filters(&mut qb, &filter)?;
+    if let Some(cursor) = filter.cursor.as_deref() {
+        push_cursor(&mut qb, sort, cursor)?;
+    }
+    // Busca um registro a mais para saber se existe próxima página
+    qb.push(format!(" ORDER BY {} LIMIT ", sort.order_by()));
+    qb.push_bind(limit + 1);
+
+    let mut items = qb
+        .build_query_as::<Transaction>()
+        .fetch_all(pool.inner())
+        .await
+        .map_err(|e| {
+            eprintln!("Erro ao buscar transações: {}", e);
+            (Status::InternalServerError, "Erro ao buscar transações".to_string())
+        })?;
+
+    let next_cursor = if items.len() as i64 > limit {
+        items.truncate(limit as usize);
+        items.last().map(|last| encode_cursor(sort, last))
+    } else {
+        None
+    };
+
+    Ok(Json(TransactionPage { items, next_cursor }))
+}
+
+// Delimitadores do destaque no ts_headline (chr(2) e chr(3)), trocados por <mark> depois do escape do HTML
+const HIGHLIGHT_START: char = '\u{2}';
+const HIGHLIGHT_STOP: char = '\u{3}';
+
+/// Trecho em HTML seguro: o texto da transação é escapado e só os termos encontrados ganham <mark>
+fn highlight_snippet(snippet: &str) -> String {
+    let mut html = String::with_capacity(snippet.len());
+    for c in snippet.chars() {
+        match c {
+            HIGHLIGHT_START => html.push_str("<mark>"),
+            HIGHLIGHT_STOP => html.push_str("</mark>"),
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#39;"),
+            c => html.push(c),
+        }
+    }
+    html
+}
+
+/// Busca textual (português, sem acentos) na descrição, no estabelecimento e nas anotações
+#[get("/transactions/search?<q>&<limit>&<offset>")]
+pub async fn search_transactions(
+    user: AuthenticatedUser,
+    q: &str,
+    limit: Option<i64>,
+    offset: Option<i64>,
+    pool: &State<PgPool>,
+) -> Result<Json<Vec<TransactionSearchResult>>, ApiError> {
+    let q = q.trim();
+    if q.is_empty() {
+        return Err(bad_request("Informe o termo de busca"));
+    }
+
+    let sql = format!(
         r#"
-        SELECT 
-            id, pluggy_transaction_id, account_id, item_id, amount, date, 
-            description, category, subcategory, currency, status, merchant, balance, 
-            created_at, updated_at 
-        FROM transactions 
-        WHERE user_id = $1
-        ORDER BY date DESC
+        SELECT {},
+            ts_rank_cd(t.search_vector, query) AS rank,
+            ts_headline(
+                'portuguese_unaccent',
+                concat_ws(' · ', t.description, t.merchant->>'name', t.notes),
+                query,
+                'StartSel=' || chr(2) || ', StopSel=' || chr(3) || ', MaxFragments=2, MaxWords=15, MinWords=5'
+            ) AS snippet
+        FROM transactions t, websearch_to_tsquery('portuguese_unaccent', $2) query
+        WHERE t.user_id = $1 AND t.search_vector @@ query
+        ORDER BY rank DESC, t.date DESC, t.id DESC
+        LIMIT $3 OFFSET $4
         "#,
-        user.id
-    )
-    .fetch_all(pool.inner())
-    .await
-    .map_err(|_| Status::InternalServerError)?;
+        TRANSACTION_COLUMNS
+    );
+
+    let mut results = sqlx::query_as::<_, TransactionSearchResult>(&sql)
+        .bind(user.id)
+        .bind(q)
+        .bind(limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE))
+        .bind(offset.unwrap_or(0).max(0))
+        .fetch_all(pool.inner())
+        .await
+        .map_err(|e| {
+            eprintln!("Erro na busca de transações: {}", e);
+            (Status::InternalServerError, "Erro na busca de transações".to_string())
+        })?;
+
+    for result in &mut results {
+        result.snippet = result.snippet.as_deref().map(highlight_snippet);
+    }
+
+    Ok(Json(results))
+}
+
+// Buffer de escrita do arquivo temporário da exportação
+const EXPORT_BUFFER_SIZE: usize = 64 * 1024;
+// O XLSX é montado em memória; acima disso, a exportação deve ser feita em CSV ou com filtros
+const MAX_XLSX_EXPORT_ROWS: i64 = 50_000;
+
+enum ExportBody {
+    File(tokio::fs::File),
+    Buffer(Vec<u8>),
+}
+
+pub struct TransactionExport {
+    body: ExportBody,
+    content_type: ContentType,
+    disposition: Header<'static>,
+}
+
+impl<'r> Responder<'r, 'r> for TransactionExport {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'r> {
+        let mut response = match self.body {
+            ExportBody::File(file) => file.respond_to(request)?,
+            ExportBody::Buffer(bytes) => bytes.respond_to(request)?,
+        };
+        response.set_header(self.content_type);
+        response.set_header(self.disposition);
+        Ok(response)
+    }
+}
+
+fn export_query(user_id: Uuid, filter: &TransactionFilter, sort: TransactionSort) -> Result<QueryBuilder<'static, Postgres>, ApiError> {
+    let mut qb = QueryBuilder::new(format!(
+        r#"
+        SELECT {}
+        FROM transactions t
+        LEFT JOIN accounts a ON t.account_id = a.id
+        LEFT JOIN items i ON t.item_id = i.id
+        WHERE t.user_id = "#,
+        EXPORT_COLUMNS
+    ));
+    qb.push_bind(user_id);
+    push_transaction_filters(&mut qb, filter)?;
+    qb.push(format!(" ORDER BY {}", sort.order_by()));
+    Ok(qb)
+}
 
-    Ok(Json(transactions))
+fn export_error(e: impl std::fmt::Display) -> ApiError {
+    eprintln!("Erro durante exportação de transações: {}", e);
+    (Status::InternalServerError, "Erro ao gerar a exportação".to_string())
+}
+
+/// Grava a exportação em um arquivo temporário, linha a linha, e o devolve aberto para leitura.
+/// Assim a memória fica limitada e uma falha no meio da consulta vira um erro na resposta,
+/// em vez de um download truncado com status 200.
+async fn spool_export(
+    pool: &PgPool,
+    mut qb: QueryBuilder<'static, Postgres>,
+    header: Vec<u8>,
+    footer: &[u8],
+    encode: impl Fn(&ExportRow) -> anyhow::Result<Vec<u8>>,
+) -> Result<tokio::fs::File, ApiError> {
+    let path = std::env::temp_dir().join(format!("firebudget-export-{}", Uuid::new_v4()));
+
+    let written: anyhow::Result<()> = async {
+        let mut writer = BufWriter::with_capacity(EXPORT_BUFFER_SIZE, tokio::fs::File::create(&path).await?);
+        writer.write_all(&header).await?;
+
+        let mut rows = qb.build_query_as::<ExportRow>().fetch(pool);
+        while let Some(row) = rows.next().await {
+            writer.write_all(&encode(&row?)?).await?;
+        }
+
+        writer.write_all(footer).await?;
+        writer.flush().await?;
+        Ok(())
+    }
+    .await;
+
+    // O arquivo aberto continua legível depois de removido do diretório
+    let file = match written {
+        Ok(()) => tokio::fs::File::open(&path).await.map_err(export_error),
+        Err(e) => Err(export_error(e)),
+    };
+    if let Err(e) = tokio::fs::remove_file(&path).await {
+        eprintln!("Erro ao remover arquivo temporário de exportação {}: {}", path.display(), e);
+    }
+    file
+}
+
+/// Exporta as transações que atendem aos mesmos filtros da listagem (sem paginação)
+#[get("/transactions/export?<format>&<locale>&<filter..>")]
+pub async fn export_transactions(
+    user: AuthenticatedUser,
+    format: &str,
+    locale: Option<&str>,
+    filter: TransactionFilter,
+    pool: &State<PgPool>,
+) -> Result<TransactionExport, ApiError> {
+    let sort = TransactionSort::parse(filter.sort.as_deref())?;
+    let mut qb = export_query(user.id, &filter, sort)?;
+
+    let profile = sqlx::query!("SELECT locale, preferred_currency FROM users WHERE id = $1", user.id)
+        .fetch_one(pool.inner())
+        .await
+        .map_err(|e| (Status::InternalServerError, format!("Erro de banco de dados: {}", e)))?;
+    let locale = ExportLocale::from_tag(locale.unwrap_or(&profile.locale));
+
+    let file_name = format!("transacoes-{}.{}", Utc::now().format("%Y%m%d"), format);
+    let disposition = Header::new("Content-Disposition", format!("attachment; filename=\"{}\"", file_name));
+
+    let (body, content_type) = match format {
+        FORMAT_CSV => {
+            let encoder = CsvEncoder::new(locale);
+            let header = encoder.header().map_err(export_error)?;
+            let file = spool_export(pool.inner(), qb, header, &[], |row| Ok(encoder.row(row)?)).await?;
+            (ExportBody::File(file), ContentType::CSV)
+        }
+        FORMAT_OFX => {
+            // O cabeçalho do OFX declara o período antes das transações
+            let mut range_qb = QueryBuilder::new("SELECT MIN(t.date), MAX(t.date) FROM transactions t WHERE t.user_id = ");
+            range_qb.push_bind(user.id);
+            push_transaction_filters(&mut range_qb, &filter)?;
+            let (start, end) = range_qb
+                .build_query_as::<(Option<NaiveDate>, Option<NaiveDate>)>()
+                .fetch_one(pool.inner())
+                .await
+                .map_err(|e| (Status::InternalServerError, format!("Erro de banco de dados: {}", e)))?;
+
+            let header = ofx_header(&profile.preferred_currency, start, end).into_bytes();
+            let file = spool_export(pool.inner(), qb, header, ofx_footer().as_bytes(), |row| {
+                Ok(ofx_transaction(row).into_bytes())
+            })
+            .await?;
+            (ExportBody::File(file), ContentType::new("application", "x-ofx"))
+        }
+        // O XLSX é um zip e precisa ser montado inteiro em memória antes do envio
+        FORMAT_XLSX => {
+            qb.push(" LIMIT ").push_bind(MAX_XLSX_EXPORT_ROWS + 1);
+            let rows = qb
+                .build_query_as::<ExportRow>()
+                .fetch_all(pool.inner())
+                .await
+                .map_err(|e| (Status::InternalServerError, format!("Erro de banco de dados: {}", e)))?;
+            if rows.len() as i64 > MAX_XLSX_EXPORT_ROWS {
+                return Err(bad_request(format!(
+                    "A exportação em XLSX é limitada a {} transações. Use CSV ou filtre o período",
+                    MAX_XLSX_EXPORT_ROWS
+                )));
+            }
+            let workbook = xlsx_workbook(&rows, locale)
+                .map_err(|e| (Status::InternalServerError, format!("Erro ao gerar planilha: {}", e)))?;
+            (
+                ExportBody::Buffer(workbook),
+                ContentType::new("application", "vnd.openxmlformats-officedocument.spreadsheetml.sheet"),
+            )
+        }
+        other => return Err(bad_request(format!("Formato inválido: {} (use csv, ofx ou xlsx)", other))),
+    };
+
+    Ok(TransactionExport { body, content_type, disposition })
 }
 
 #[post("/transactions", format = "json", data = "<new_transaction>")]
@@ -92,24 +595,38 @@ pub async fn create_transaction(
     new_transaction: Json<NewTransaction>,
     pool: &State<PgPool>,
 ) -> Result<Json<Transaction>, Status> {
+    let mut conn = pool.acquire().await.map_err(|_| Status::InternalServerError)?;
+
+    let merchant_id = merchant_matching::resolve(&mut conn, user.id, Some(&new_transaction.description), None)
+        .await
+        .map_err(|e| {
+            eprintln!("Erro ao associar estabelecimento: {}", e);
+            Status::InternalServerError
+        })?;
+
     let transaction = sqlx::query_as!(
         Transaction,
         r#"
-        INSERT INTO transactions (user_id, amount, date, description, category, currency)
-        VALUES ($1, $2, $3, $4, $5, $6)
+        INSERT INTO transactions (user_id, amount, date, description, category, subcategory, currency, merchant_id, merchant_key)
+        VALUES ($1, $2, $3, $4, $5, NULLIF(BTRIM($6), ''), $7, $8, $9)
         RETURNING 
             id, pluggy_transaction_id, account_id, item_id, amount, date, 
-            description, category, subcategory, currency, status, merchant, balance, 
-            created_at, updated_at
+            description, category, subcategory, currency, status, merchant, balance, notes, rule_id,
+            recurring_transaction_id, installment_purchase_id, installment_number, installment_total, merchant_id,
+            predicted_category, prediction_confidence,
+            ARRAY[]::TEXT[] AS "tags!", NULL::UUID AS "transfer_id?", created_at, updated_at
         "#,
         user.id,
         new_transaction.amount,
         new_transaction.date,
         new_transaction.description,
         new_transaction.category,
-        new_transaction.currency
+        new_transaction.subcategory,
+        new_transaction.currency,
+        merchant_id,
+        merchant_matching::alias_key(Some(&new_transaction.description))
     )
-    .fetch_one(pool.inner())
+    .fetch_one(&mut *conn)
     .await
     .map_err(|e| {
         eprintln!("Erro ao criar transação: {}", e);
@@ -148,15 +665,52 @@ pub async fn update_transaction(
     updated_transaction: Json<NewTransaction>,
     pool: &State<PgPool>,
 ) -> Result<Json<Transaction>, Status> {
+    // Mudar o valor de uma transação dividida deixaria as partes sem somar o total
+    let split_total = sqlx::query_scalar!(
+        "SELECT SUM(amount) FROM transaction_splits WHERE transaction_id = $1 AND user_id = $2",
+        id,
+        user.id
+    )
+    .fetch_one(pool.inner())
+    .await
+    .map_err(|_| Status::InternalServerError)?;
+
+    if matches!(split_total, Some(total) if total != updated_transaction.amount) {
+        return Err(Status::Conflict);
+    }
+
     let transaction = sqlx::query_as!(
         Transaction,
         r#"
         UPDATE transactions 
-        SET amount = $1, date = $2, description = $3, category = $4, currency = $5
+        SET amount = $1, date = $2, description = $3, category = $4, currency = $5,
+            -- A subcategoria pertence à categoria: sem uma nova, sai junto quando a categoria muda
+            subcategory = CASE WHEN $8::VARCHAR IS NOT NULL THEN NULLIF(BTRIM($8), '')
+                WHEN category IS DISTINCT FROM $4::VARCHAR THEN NULL ELSE subcategory END,
+            -- Descrição, categoria e subcategoria alteradas pelo usuário não são sobrescritas pela sincronização
+            user_modified_fields = ARRAY(
+                SELECT DISTINCT field FROM unnest(
+                    user_modified_fields
+                    || CASE WHEN description IS DISTINCT FROM $3 THEN ARRAY['description'] ELSE ARRAY[]::TEXT[] END
+                    || CASE WHEN category IS DISTINCT FROM $4::VARCHAR THEN ARRAY['category', 'subcategory'] ELSE ARRAY[]::TEXT[] END
+                    || CASE WHEN $8::VARCHAR IS NOT NULL AND subcategory IS DISTINCT FROM NULLIF(BTRIM($8), '')
+                        THEN ARRAY['subcategory'] ELSE ARRAY[]::TEXT[] END
+                ) AS field
+            )
         WHERE id = $6 AND user_id = $7
         RETURNING 
             id, pluggy_transaction_id, account_id, item_id, amount, date, 
-            description, category, subcategory, currency, status, merchant, balance, 
+            description, category, subcategory, currency, status, merchant, balance, notes, rule_id,
+            recurring_transaction_id, installment_purchase_id, installment_number, installment_total, merchant_id,
+            predicted_category, prediction_confidence,
+            ARRAY(
+                SELECT g.name FROM transaction_tags tt INNER JOIN tags g ON tt.tag_id = g.id
+                WHERE tt.transaction_id = transactions.id ORDER BY g.name
+            ) AS "tags!",
+            (
+                SELECT tr.id FROM transfers tr
+                WHERE transactions.id IN (tr.outgoing_transaction_id, tr.incoming_transaction_id)
+            ) AS transfer_id,
             created_at, updated_at
         "#,
         updated_transaction.amount,
@@ -165,7 +719,8 @@ pub async fn update_transaction(
         updated_transaction.category,
         updated_transaction.currency,
         id,
-        user.id
+        user.id,
+        updated_transaction.subcategory
     )
     .fetch_optional(pool.inner())
     .await
@@ -177,4 +732,98 @@ pub async fn update_transaction(
     }
 }
 
+/// Atualiza anotação e tags de uma transação (manual ou sincronizada)
+#[patch("/transactions/<id>", format = "json", data = "<annotations>")]
+pub async fn annotate_transaction(
+    user: AuthenticatedUser,
+    id: Uuid,
+    annotations: Json<TransactionAnnotations>,
+    pool: &State<PgPool>,
+) -> Result<Json<Transaction>, ApiError> {
+    let db_error = |e: sqlx::Error| (Status::InternalServerError, format!("Erro de banco de dados: {}", e));
 
+    let tags = match &annotations.tags {
+        Some(tags) if tags.len() > MAX_TAGS_PER_TRANSACTION => {
+            return Err(bad_request(format!("Máximo de {} tags por transação", MAX_TAGS_PER_TRANSACTION)));
+        }
+        Some(tags) => {
+            let mut names = Vec::with_capacity(tags.len());
+            for tag in tags {
+                let name = Tag::normalize_name(tag).ok_or_else(|| bad_request(format!("Tag inválida: {}", tag)))?;
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+            Some(names)
+        }
+        None => None,
+    };
+
+    let mut tx = pool.begin().await.map_err(db_error)?;
+
+    let updated = sqlx::query!(
+        r#"
+        UPDATE transactions
+        SET notes = CASE WHEN $1::BOOLEAN THEN NULLIF(BTRIM($2), '') ELSE notes END
+        WHERE id = $3 AND user_id = $4
+        "#,
+        annotations.notes.is_some(),
+        annotations.notes,
+        id,
+        user.id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(db_error)?;
+
+    if updated.rows_affected() == 0 {
+        return Err((Status::NotFound, "Transação não encontrada".to_string()));
+    }
+
+    if let Some(names) = tags {
+        sqlx::query!("DELETE FROM transaction_tags WHERE transaction_id = $1", id)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error)?;
+
+        // Cria as tags que ainda não existem e associa todas à transação
+        sqlx::query!(
+            r#"
+            INSERT INTO tags (user_id, name)
+            SELECT $1, name FROM unnest($2::TEXT[]) AS name
+            ON CONFLICT (user_id, name) DO NOTHING
+            "#,
+            user.id,
+            &names
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO transaction_tags (transaction_id, tag_id)
+            SELECT $1, id FROM tags WHERE user_id = $2 AND name = ANY($3)
+            "#,
+            id,
+            user.id,
+            &names
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    }
+
+    tx.commit().await.map_err(db_error)?;
+
+    let transaction = sqlx::query_as::<_, Transaction>(&format!(
+        "SELECT {} FROM transactions t WHERE t.id = $1",
+        TRANSACTION_COLUMNS
+    ))
+    .bind(id)
+    .fetch_one(pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(transaction))
+}
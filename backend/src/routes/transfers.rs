@@ -0,0 +1,212 @@
+use crate::models::transfer::{DetectTransfersResult, NewTransfer, Transfer, DETECTION_MANUAL};
+use crate::routes::transactions::AuthenticatedUser;
+use crate::transfer_detection;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, State};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+type ApiError = (Status, String);
+
+fn db_error(e: sqlx::Error) -> ApiError {
+    match e {
+        sqlx::Error::Database(ref db) if db.is_unique_violation() => {
+            (Status::Conflict, "A transação já faz parte de uma transferência".to_string())
+        }
+        e => {
+            eprintln!("Erro de banco de dados nas transferências: {}", e);
+            (Status::InternalServerError, format!("Erro de banco de dados: {}", e))
+        }
+    }
+}
+
+async fn fetch_transfer(pool: &PgPool, user_id: Uuid, id: Uuid) -> Result<Option<Transfer>, ApiError> {
+    sqlx::query_as!(
+        Transfer,
+        r#"
+        SELECT tr.id, tr.detection, ABS(o.amount) AS "amount!", o.currency,
+               o.id AS outgoing_transaction_id, o.account_id AS outgoing_account_id,
+               o.date AS outgoing_date, o.description AS outgoing_description,
+               i.id AS incoming_transaction_id, i.account_id AS incoming_account_id,
+               i.date AS incoming_date, i.description AS incoming_description,
+               tr.created_at
+        FROM transfers tr
+        INNER JOIN transactions o ON tr.outgoing_transaction_id = o.id
+        INNER JOIN transactions i ON tr.incoming_transaction_id = i.id
+        WHERE tr.id = $1 AND tr.user_id = $2
+        "#,
+        id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(db_error)
+}
+
+#[get("/transfers")]
+pub async fn get_transfers(
+    user: AuthenticatedUser,
+    pool: &State<PgPool>,
+) -> Result<Json<Vec<Transfer>>, ApiError> {
+    let transfers = sqlx::query_as!(
+        Transfer,
+        r#"
+        SELECT tr.id, tr.detection, ABS(o.amount) AS "amount!", o.currency,
+               o.id AS outgoing_transaction_id, o.account_id AS outgoing_account_id,
+               o.date AS outgoing_date, o.description AS outgoing_description,
+               i.id AS incoming_transaction_id, i.account_id AS incoming_account_id,
+               i.date AS incoming_date, i.description AS incoming_description,
+               tr.created_at
+        FROM transfers tr
+        INNER JOIN transactions o ON tr.outgoing_transaction_id = o.id
+        INNER JOIN transactions i ON tr.incoming_transaction_id = i.id
+        WHERE tr.user_id = $1
+        ORDER BY o.date DESC, tr.created_at DESC
+        "#,
+        user.id
+    )
+    .fetch_all(pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(transfers))
+}
+
+/// Pareia manualmente uma saída e uma entrada como transferência
+#[post("/transfers", format = "json", data = "<transfer>")]
+pub async fn create_transfer(
+    user: AuthenticatedUser,
+    transfer: Json<NewTransfer>,
+    pool: &State<PgPool>,
+) -> Result<Json<Transfer>, ApiError> {
+    if transfer.outgoing_transaction_id == transfer.incoming_transaction_id {
+        return Err((Status::BadRequest, "Informe duas transações diferentes".to_string()));
+    }
+
+    let transactions = sqlx::query!(
+        "SELECT id, amount, account_id FROM transactions WHERE id IN ($1, $2) AND user_id = $3",
+        transfer.outgoing_transaction_id,
+        transfer.incoming_transaction_id,
+        user.id
+    )
+    .fetch_all(pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    let find = |id: Uuid| transactions.iter().find(|t| t.id == id);
+    let (Some(outgoing), Some(incoming)) =
+        (find(transfer.outgoing_transaction_id), find(transfer.incoming_transaction_id))
+    else {
+        return Err((Status::NotFound, "Transação não encontrada".to_string()));
+    };
+
+    if !outgoing.amount.is_sign_negative()
+        || outgoing.amount.is_zero()
+        || !incoming.amount.is_sign_positive()
+        || incoming.amount.is_zero()
+    {
+        return Err((
+            Status::BadRequest,
+            "A transação de saída precisa ter valor negativo e a de entrada, valor positivo".to_string(),
+        ));
+    }
+
+    if outgoing.amount.abs() != incoming.amount.abs() {
+        return Err((Status::BadRequest, "A saída e a entrada precisam ter o mesmo valor".to_string()));
+    }
+
+    if outgoing.account_id.is_some() && outgoing.account_id == incoming.account_id {
+        return Err((Status::BadRequest, "A saída e a entrada precisam ser de contas diferentes".to_string()));
+    }
+
+    let mut tx = pool.begin().await.map_err(db_error)?;
+
+    // Um pareamento manual desfaz a recusa anterior do mesmo par
+    sqlx::query!(
+        "DELETE FROM transfer_dismissals WHERE outgoing_transaction_id = $1 AND incoming_transaction_id = $2",
+        transfer.outgoing_transaction_id,
+        transfer.incoming_transaction_id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(db_error)?;
+
+    let id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO transfers (user_id, outgoing_transaction_id, incoming_transaction_id, detection)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id
+        "#,
+        user.id,
+        transfer.outgoing_transaction_id,
+        transfer.incoming_transaction_id,
+        DETECTION_MANUAL
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(db_error)?;
+
+    tx.commit().await.map_err(db_error)?;
+
+    let created = fetch_transfer(pool.inner(), user.id, id)
+        .await?
+        .ok_or((Status::InternalServerError, "Transferência não encontrada após criação".to_string()))?;
+
+    Ok(Json(created))
+}
+
+/// Desfaz a transferência; as transações voltam a contar como despesa e receita
+/// e a detecção automática não volta a pareá-las
+#[delete("/transfers/<id>")]
+pub async fn delete_transfer(
+    user: AuthenticatedUser,
+    id: Uuid,
+    pool: &State<PgPool>,
+) -> Result<Status, ApiError> {
+    let mut tx = pool.begin().await.map_err(db_error)?;
+
+    let removed = sqlx::query!(
+        r#"
+        DELETE FROM transfers
+        WHERE id = $1 AND user_id = $2
+        RETURNING outgoing_transaction_id, incoming_transaction_id
+        "#,
+        id,
+        user.id
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(db_error)?
+    .ok_or((Status::NotFound, "Transferência não encontrada".to_string()))?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO transfer_dismissals (outgoing_transaction_id, incoming_transaction_id)
+        VALUES ($1, $2)
+        ON CONFLICT DO NOTHING
+        "#,
+        removed.outgoing_transaction_id,
+        removed.incoming_transaction_id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(db_error)?;
+
+    tx.commit().await.map_err(db_error)?;
+
+    Ok(Status::NoContent)
+}
+
+/// Procura transferências entre as transações já existentes do usuário
+#[post("/transfers/detect")]
+pub async fn detect_transfers(
+    user: AuthenticatedUser,
+    pool: &State<PgPool>,
+) -> Result<Json<DetectTransfersResult>, ApiError> {
+    let detected = transfer_detection::detect_transfers(pool.inner(), user.id)
+        .await
+        .map_err(db_error)?;
+
+    Ok(Json(DetectTransfersResult { detected }))
+}
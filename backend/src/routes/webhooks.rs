@@ -1,5 +1,10 @@
+use crate::alerts;
 use crate::config::AppConfig;
+use crate::ingest::{self, IncomingTransaction, IngestContext};
+use crate::notifier::Notifier;
+use crate::pluggy::models::Item;
 use crate::routes::items::sync_item_data;
+use crate::transfer_detection;
 use rocket::http::Status;
 use rocket::serde::json::Json;
 use rocket::{post, State};
@@ -47,6 +52,7 @@ pub async fn handle_pluggy_webhook(
     payload: Json<PluggyWebhookPayload>,
     pool: &State<PgPool>,
     config: &State<Arc<AppConfig>>,
+    notifier: &State<Arc<Notifier>>,
 ) -> Result<Json<WebhookResponse>, Status> {
     let event = payload.event.clone();
     let item_id = payload
@@ -63,6 +69,7 @@ pub async fn handle_pluggy_webhook(
     // Processar em background após responder
     let config_clone = config.inner().clone();
     let pool_clone = pool.inner().clone();
+    let notifier_clone = notifier.inner().clone();
     let event_clone = event.clone();
     let item_id_clone = item_id.map(|s| s.to_string());
     let account_id_clone = account_id.map(|s| s.to_string());
@@ -72,6 +79,7 @@ pub async fn handle_pluggy_webhook(
         if let Err(e) = process_webhook_event(
             config_clone,
             pool_clone,
+            notifier_clone,
             &event_clone,
             item_id_clone.as_deref(),
             account_id_clone.as_deref(),
@@ -91,6 +99,7 @@ pub async fn handle_pluggy_webhook(
 async fn process_webhook_event(
     config: Arc<AppConfig>,
     pool: PgPool,
+    notifier: Arc<Notifier>,
     event: &str,
     item_id: Option<&str>,
     account_id: Option<&str>,
@@ -103,10 +112,16 @@ async fn process_webhook_event(
         
         eprintln!("Processando evento de transação {} para item {} e account {}", event, item_id, account_id);
         
-        process_transaction_event(config.clone(), pool.clone(), event, item_id, account_id, transaction_ids).await?;
+        let user_id =
+            process_transaction_event(config.clone(), pool.clone(), event, item_id, account_id, transaction_ids).await?;
         
         // Após processar eventos de transações, buscar e salvar saldos
-        fetch_and_save_balances(config, pool, Some(item_id), Some(account_id)).await?;
+        fetch_and_save_balances(config, pool.clone(), Some(item_id), Some(account_id)).await?;
+
+        // Com transações e saldos atualizados, avaliar os alertas do usuário
+        if let Some(user_id) = user_id {
+            alerts::evaluate(&pool, &notifier, user_id).await?;
+        }
         
         return Ok(());
     }
@@ -124,17 +139,7 @@ async fn process_webhook_event(
     match event {
         "item/created" | "item/updated" => {
             // Conforme documentação: fazer GET /items/{id} para recuperar informações mais recentes
-            let app_config = AppConfig {
-                client_id: config.client_id.clone(),
-                client_secret: config.client_secret.clone(),
-                environment: config.environment.clone(),
-                base_url: config.base_url.clone(),
-                database_url: config.database_url.clone(),
-                jwt_secret: config.jwt_secret.clone(),
-                admin_email: config.admin_email.clone(),
-                admin_password: config.admin_password.clone(),
-                admin_name: config.admin_name.clone(),
-            };
+            let app_config = config.as_ref().clone();
 
             use crate::pluggy::client::PluggyClient;
             let mut client = PluggyClient::new(app_config);
@@ -164,21 +169,12 @@ async fn process_webhook_event(
                         item_id
                     );
 
-                    let config_for_sync = Arc::new(AppConfig {
-                        client_id: config.client_id.clone(),
-                        client_secret: config.client_secret.clone(),
-                        environment: config.environment.clone(),
-                        base_url: config.base_url.clone(),
-                        database_url: config.database_url.clone(),
-                        jwt_secret: config.jwt_secret.clone(),
-                        admin_email: config.admin_email.clone(),
-                        admin_password: config.admin_password.clone(),
-                        admin_name: config.admin_name.clone(),
-                    });
+                    let config_for_sync = config.clone();
 
                     if let Err(e) = sync_item_data(
                         config_for_sync.clone(),
                         pool.clone(),
+                        notifier,
                         item_id,
                         id,
                         user_id,
@@ -210,14 +206,20 @@ async fn process_webhook_event(
                     "Item {} ainda não está UPDATED (status: {}), ignorando sincronização",
                     item_id, pluggy_item.status
                 );
+                refresh_item_status(&pool, &notifier, &pluggy_item).await?;
             }
         }
-        "item/error" => {
-            eprintln!("Item {} encontrou um erro. Verificar logs da Pluggy.", item_id);
-            // Poderia atualizar status no banco se necessário
-        }
-        "item/waiting_user_input" => {
-            eprintln!("Item {} está aguardando input do usuário.", item_id);
+        "item/error" | "item/waiting_user_input" => {
+            if event == "item/error" {
+                eprintln!("Item {} encontrou um erro. Verificar logs da Pluggy.", item_id);
+            } else {
+                eprintln!("Item {} está aguardando input do usuário.", item_id);
+            }
+
+            use crate::pluggy::client::PluggyClient;
+            let mut client = PluggyClient::new(config.as_ref().clone());
+            let pluggy_item = client.get_item_by_id(item_id).await?;
+            refresh_item_status(&pool, &notifier, &pluggy_item).await?;
         }
         "item/login_succeeded" => {
             eprintln!("Item {} fez login com sucesso e está coletando dados.", item_id);
@@ -234,6 +236,30 @@ async fn process_webhook_event(
     Ok(())
 }
 
+// Salva o status da conexão informado pela Pluggy e avalia os alertas do dono do item
+async fn refresh_item_status(pool: &PgPool, notifier: &Arc<Notifier>, item: &Item) -> anyhow::Result<()> {
+    let item_record = sqlx::query(
+        r#"
+        UPDATE items SET status = $1, execution_status = $2, error = $3
+        WHERE pluggy_item_id = $4
+        RETURNING user_id
+        "#,
+    )
+    .bind(&item.status)
+    .bind(&item.execution_status)
+    .bind(&item.error)
+    .bind(&item.id)
+    .fetch_optional(pool)
+    .await?;
+
+    if let Some(user_id) = item_record.and_then(|row| row.get::<Option<uuid::Uuid>, _>("user_id")) {
+        alerts::evaluate(pool, notifier, user_id).await?;
+    }
+
+    Ok(())
+}
+
+/// Processa um evento de transações e retorna o usuário dono da conta, se ela existir no banco
 async fn process_transaction_event(
     config: Arc<AppConfig>,
     pool: PgPool,
@@ -241,36 +267,28 @@ async fn process_transaction_event(
     item_id: &str,
     account_id: &str,
     transaction_ids: Option<&[String]>,
-) -> anyhow::Result<()> {
+) -> anyhow::Result<Option<uuid::Uuid>> {
     use crate::pluggy::client::PluggyClient;
-    use rust_decimal::Decimal;
-    use rust_decimal::prelude::FromPrimitive;
 
-    let app_config = AppConfig {
-        client_id: config.client_id.clone(),
-        client_secret: config.client_secret.clone(),
-        environment: config.environment.clone(),
-        base_url: config.base_url.clone(),
-        database_url: config.database_url.clone(),
-        jwt_secret: config.jwt_secret.clone(),
-        admin_email: config.admin_email.clone(),
-        admin_password: config.admin_password.clone(),
-        admin_name: config.admin_name.clone(),
-    };
+    let app_config = config.as_ref().clone();
 
     let mut client = PluggyClient::new(app_config);
 
     // Buscar o account_id local no banco
-    let account_record = sqlx::query("SELECT id, item_id FROM accounts WHERE pluggy_account_id = $1")
+    let account_record = sqlx::query("SELECT id, item_id, type FROM accounts WHERE pluggy_account_id = $1")
         .bind(account_id)
         .fetch_optional(&pool)
         .await?;
 
-    let (db_account_id, db_item_id) = match account_record {
-        Some(row) => (row.get::<uuid::Uuid, _>("id"), row.get::<uuid::Uuid, _>("item_id")),
+    let (db_account_id, db_item_id, account_type) = match account_record {
+        Some(row) => (
+            row.get::<uuid::Uuid, _>("id"),
+            row.get::<uuid::Uuid, _>("item_id"),
+            row.get::<Option<String>, _>("type"),
+        ),
         None => {
             eprintln!("Account {} não encontrado no banco de dados", account_id);
-            return Ok(());
+            return Ok(None);
         }
     };
 
@@ -284,7 +302,7 @@ async fn process_transaction_event(
         Some(row) => row.get::<uuid::Uuid, _>("user_id"),
         None => {
             eprintln!("Item {} não encontrado no banco de dados", db_item_id);
-            return Ok(());
+            return Ok(None);
         }
     };
 
@@ -295,46 +313,15 @@ async fn process_transaction_event(
 
             eprintln!("Encontradas {} transações para processar", transactions.len());
 
+            let mut context = IngestContext::load(&pool, user_id).await?;
+
             for tx in transactions {
-                let tx_amount = Decimal::from_f64(tx.amount).unwrap_or(Decimal::ZERO);
-                let tx_date = chrono::NaiveDate::parse_from_str(&tx.date[0..10], "%Y-%m-%d")
-                    .unwrap_or_else(|_| chrono::Utc::now().date_naive());
-                
-                let tx_balance = tx.balance.map(|b| Decimal::from_f64(b).unwrap_or(Decimal::ZERO));
-
-                sqlx::query(
-                    r#"
-                    INSERT INTO transactions (
-                        pluggy_transaction_id, account_id, item_id, user_id, 
-                        amount, date, description, category, subcategory, currency, status, balance
-                    )
-                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
-                    ON CONFLICT (pluggy_transaction_id) DO UPDATE SET
-                        amount = EXCLUDED.amount,
-                        date = EXCLUDED.date,
-                        description = EXCLUDED.description,
-                        category = EXCLUDED.category,
-                        subcategory = EXCLUDED.subcategory,
-                        currency = EXCLUDED.currency,
-                        status = EXCLUDED.status,
-                        balance = EXCLUDED.balance,
-                        updated_at = CURRENT_TIMESTAMP
-                    "#
-                )
-                .bind(&tx.id)
-                .bind(db_account_id)
-                .bind(db_item_id)
-                .bind(user_id)
-                .bind(tx_amount)
-                .bind(tx_date)
-                .bind(&tx.description)
-                .bind(&tx.category)
-                .bind(&tx.subcategory)
-                .bind(&tx.currency_code)
-                .bind(tx.status.unwrap_or_else(|| "PENDING".to_string()))
-                .bind(tx_balance)
-                .execute(&pool)
-                .await?;
+                let incoming = IncomingTransaction::from_pluggy(tx, db_account_id, account_type.as_deref(), db_item_id, user_id);
+                ingest::upsert_transaction(&pool, &mut context, incoming).await?;
+            }
+
+            if let Err(e) = transfer_detection::detect_transfers(&pool, user_id).await {
+                eprintln!("Erro ao detectar transferências: {}", e);
             }
 
             eprintln!("Transações processadas com sucesso para account {}", account_id);
@@ -358,7 +345,7 @@ async fn process_transaction_event(
         }
     }
 
-    Ok(())
+    Ok(Some(user_id))
 }
 
 async fn fetch_and_save_balances(
@@ -371,17 +358,7 @@ async fn fetch_and_save_balances(
     use rust_decimal::Decimal;
     use rust_decimal::prelude::FromPrimitive;
 
-    let app_config = AppConfig {
-        client_id: config.client_id.clone(),
-        client_secret: config.client_secret.clone(),
-        environment: config.environment.clone(),
-        base_url: config.base_url.clone(),
-        database_url: config.database_url.clone(),
-        jwt_secret: config.jwt_secret.clone(),
-        admin_email: config.admin_email.clone(),
-        admin_password: config.admin_password.clone(),
-        admin_name: config.admin_name.clone(),
-    };
+    let app_config = config.as_ref().clone();
 
     let mut client = PluggyClient::new(app_config);
 
@@ -392,7 +369,7 @@ async fn fetch_and_save_balances(
 
     for balance in balances {
         // Buscar o account_id local no banco
-        let account_record = sqlx::query("SELECT id, item_id FROM accounts WHERE pluggy_account_id = $1")
+        let account_record = sqlx::query("SELECT id, item_id, type FROM accounts WHERE pluggy_account_id = $1")
             .bind(&balance.account_id)
             .fetch_optional(&pool)
             .await?;
@@ -2,8 +2,15 @@ use std::sync::Arc;
 use std::time::Duration;
 use sqlx::{PgPool, FromRow};
 use uuid::Uuid;
+use crate::balance_history::snapshot_balances;
+use crate::budget_planning::copy_forward;
 use crate::config::AppConfig;
+use crate::data_export::cleanup_expired_exports;
+use crate::jwt_keys::KeyStore;
+use crate::notifier::Notifier;
+use crate::recurrence::materialize_due;
 use crate::routes::items::sync_item_data;
+use crate::storage::{purge_deleted_attachments, Storage};
 
 #[derive(FromRow)]
 struct ItemToSync {
@@ -12,7 +19,13 @@ struct ItemToSync {
     user_id: Uuid,
 }
 
-pub fn start_scheduler(pool: PgPool, config: Arc<AppConfig>) {
+pub fn start_scheduler(
+    pool: PgPool,
+    config: Arc<AppConfig>,
+    keys: Arc<KeyStore>,
+    storage: Arc<dyn Storage>,
+    notifier: Arc<Notifier>,
+) {
     tokio::spawn(async move {
         eprintln!("Iniciando agendador de atualizações...");
         let mut interval = tokio::time::interval(Duration::from_secs(60));
@@ -21,15 +34,45 @@ pub fn start_scheduler(pool: PgPool, config: Arc<AppConfig>) {
             interval.tick().await;
             eprintln!("Executando atualização agendada de items...");
 
-            match update_all_items(&pool, &config).await {
+            match update_all_items(&pool, &config, &notifier).await {
                 Ok(_) => eprintln!("Atualização agendada concluída com sucesso."),
                 Err(e) => eprintln!("Erro na atualização agendada: {}", e),
             }
+
+            match materialize_due(&pool).await {
+                Ok(0) => {}
+                Ok(created) => eprintln!("{} transações recorrentes criadas.", created),
+                Err(e) => eprintln!("Erro ao criar transações recorrentes: {}", e),
+            }
+
+            match snapshot_balances(&pool).await {
+                Ok(0) => {}
+                Ok(created) => eprintln!("{} saldos diários de contas registrados.", created),
+                Err(e) => eprintln!("Erro ao registrar saldos diários: {}", e),
+            }
+
+            match copy_forward(&pool).await {
+                Ok(0) => {}
+                Ok(copied) => eprintln!("{} orçamentos copiados para o mês atual.", copied),
+                Err(e) => eprintln!("Erro ao copiar orçamentos: {}", e),
+            }
+
+            if let Err(e) = cleanup_expired_exports(&pool).await {
+                eprintln!("Erro ao limpar exportações expiradas: {}", e);
+            }
+
+            if let Err(e) = purge_deleted_attachments(&pool, storage.as_ref()).await {
+                eprintln!("Erro ao remover arquivos de anexos excluídos: {}", e);
+            }
+
+            if let Err(e) = keys.rotate_if_needed(&pool).await {
+                eprintln!("Erro ao rotacionar chaves JWT: {}", e);
+            }
         }
     });
 }
 
-async fn update_all_items(pool: &PgPool, config: &Arc<AppConfig>) -> anyhow::Result<()> {
+async fn update_all_items(pool: &PgPool, config: &Arc<AppConfig>, notifier: &Arc<Notifier>) -> anyhow::Result<()> {
     // Buscar todos os items
     let items = sqlx::query_as::<_, ItemToSync>(
         r#"
@@ -45,6 +88,7 @@ async fn update_all_items(pool: &PgPool, config: &Arc<AppConfig>) -> anyhow::Res
     for item in items {
         let pool_clone = pool.clone();
         let config_clone = config.clone();
+        let notifier_clone = notifier.clone();
         let item_id = item.id;
         let pluggy_id = item.pluggy_item_id.clone();
         
@@ -58,7 +102,7 @@ async fn update_all_items(pool: &PgPool, config: &Arc<AppConfig>) -> anyhow::Res
         
         // Spawnar task individual para não parar se um der erro
         tokio::spawn(async move {
-            match sync_item_data(config_clone, pool_clone, &pluggy_id, item_id, item.user_id).await {
+            match sync_item_data(config_clone, pool_clone, notifier_clone, &pluggy_id, item_id, item.user_id).await {
                 Ok(_) => eprintln!("Item {} atualizado com sucesso.", pluggy_id),
                 Err(e) => eprintln!("Erro ao atualizar item {}: {}", pluggy_id, e),
             }
@@ -0,0 +1,57 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+
+// Prefixo do formato cifrado, para permitir trocar o algoritmo sem perder os valores antigos
+const VERSION_PREFIX: &str = "v1:";
+const NONCE_LEN: usize = 12;
+
+/// Cifra segredos guardados no banco (AES-256-GCM) com a chave `SECRETS_KEY`.
+/// O valor salvo é "v1:" seguido do nonce e do texto cifrado em base64.
+pub struct SecretCipher {
+    cipher: Aes256Gcm,
+}
+
+impl SecretCipher {
+    pub fn from_base64(key: &str) -> Result<Self> {
+        let key = STANDARD.decode(key.trim())?;
+        let cipher = Aes256Gcm::new_from_slice(&key).map_err(|_| anyhow!("a chave deve ter 32 bytes"))?;
+        Ok(SecretCipher { cipher })
+    }
+
+    pub fn encrypt(&self, plaintext: &str) -> Result<String> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|_| anyhow!("falha ao cifrar o segredo"))?;
+
+        let mut payload = nonce.to_vec();
+        payload.extend_from_slice(&ciphertext);
+        Ok(format!("{}{}", VERSION_PREFIX, STANDARD.encode(payload)))
+    }
+
+    /// Se o valor está no formato cifrado; valores gravados antes da cifra ficam em texto puro
+    pub fn is_encrypted(value: &str) -> bool {
+        value.starts_with(VERSION_PREFIX)
+    }
+
+    /// Falha se o valor não foi cifrado com esta chave (ex.: `SECRETS_KEY` trocada)
+    pub fn decrypt(&self, value: &str) -> Result<String> {
+        let payload = value
+            .strip_prefix(VERSION_PREFIX)
+            .ok_or_else(|| anyhow!("segredo em formato desconhecido"))?;
+        let payload = STANDARD.decode(payload)?;
+        if payload.len() <= NONCE_LEN {
+            return Err(anyhow!("segredo cifrado incompleto"));
+        }
+
+        let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+        let plaintext = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| anyhow!("segredo não pôde ser decifrado com a chave atual"))?;
+        Ok(String::from_utf8(plaintext)?)
+    }
+}
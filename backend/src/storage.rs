@@ -0,0 +1,231 @@
+use crate::config::{AppConfig, S3Config};
+use anyhow::{anyhow, Context};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use reqwest::{Client, Method};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Armazenamento dos arquivos anexados às transações
+#[rocket::async_trait]
+pub trait Storage: Send + Sync {
+    async fn put(&self, key: &str, bytes: Vec<u8>, content_type: &str) -> anyhow::Result<()>;
+    async fn get(&self, key: &str) -> anyhow::Result<Vec<u8>>;
+    /// Remove o objeto; remover um objeto inexistente não é erro
+    async fn delete(&self, key: &str) -> anyhow::Result<()>;
+}
+
+/// Usa S3 quando há bucket configurado, senão o disco local
+pub fn from_config(config: &AppConfig) -> Arc<dyn Storage> {
+    match &config.s3 {
+        Some(s3) => Arc::new(S3Storage::new(s3.clone())),
+        None => Arc::new(LocalStorage::new(&config.attachments_dir)),
+    }
+}
+
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: &str) -> Self {
+        LocalStorage { root: PathBuf::from(root) }
+    }
+
+    fn path(&self, key: &str) -> anyhow::Result<PathBuf> {
+        // As chaves são geradas pelo servidor, mas nunca devem sair do diretório raiz
+        if key.split('/').any(|part| part.is_empty() || part == "." || part == "..") {
+            return Err(anyhow!("Chave de armazenamento inválida: {}", key));
+        }
+        Ok(self.root.join(key))
+    }
+}
+
+#[rocket::async_trait]
+impl Storage for LocalStorage {
+    async fn put(&self, key: &str, bytes: Vec<u8>, _content_type: &str) -> anyhow::Result<()> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&path, bytes)
+            .await
+            .with_context(|| format!("Erro ao gravar {}", path.display()))
+    }
+
+    async fn get(&self, key: &str) -> anyhow::Result<Vec<u8>> {
+        let path = self.path(key)?;
+        tokio::fs::read(&path)
+            .await
+            .with_context(|| format!("Erro ao ler {}", path.display()))
+    }
+
+    async fn delete(&self, key: &str) -> anyhow::Result<()> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Backend compatível com S3 (AWS, MinIO, R2...), com requisições assinadas via SigV4
+pub struct S3Storage {
+    config: S3Config,
+    client: Client,
+}
+
+impl S3Storage {
+    pub fn new(config: S3Config) -> Self {
+        S3Storage { config, client: Client::new() }
+    }
+
+    // Caminho no estilo path (/bucket/chave), com cada segmento codificado
+    fn object_path(&self, key: &str) -> String {
+        let mut path = format!("/{}", uri_encode(&self.config.bucket));
+        for segment in key.split('/') {
+            path.push('/');
+            path.push_str(&uri_encode(segment));
+        }
+        path
+    }
+
+    async fn send(
+        &self,
+        method: Method,
+        key: &str,
+        body: Vec<u8>,
+        content_type: Option<&str>,
+    ) -> anyhow::Result<reqwest::Response> {
+        let path = self.object_path(key);
+        let url = reqwest::Url::parse(&format!("{}{}", self.config.endpoint, path))?;
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            _ => return Err(anyhow!("Endpoint S3 inválido: {}", self.config.endpoint)),
+        };
+
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let payload_hash = format!("{:x}", Sha256::digest(&body));
+
+        let canonical_request = format!(
+            "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            method.as_str(),
+            path,
+            host,
+            payload_hash,
+            amz_date,
+            SIGNED_HEADERS,
+            payload_hash
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, self.config.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{:x}",
+            amz_date,
+            scope,
+            Sha256::digest(canonical_request.as_bytes())
+        );
+
+        let signing_key = [self.config.region.as_str(), "s3", "aws4_request"].iter().fold(
+            hmac_sha256(format!("AWS4{}", self.config.secret_access_key).as_bytes(), date.as_bytes()),
+            |key, part| hmac_sha256(&key, part.as_bytes()),
+        );
+        let signature = hex(&hmac_sha256(&signing_key, string_to_sign.as_bytes()));
+
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.config.access_key_id, scope, SIGNED_HEADERS, signature
+        );
+
+        let mut request = self
+            .client
+            .request(method, url)
+            .header("x-amz-date", amz_date)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("Authorization", authorization);
+        if let Some(content_type) = content_type {
+            request = request.header("Content-Type", content_type);
+        }
+
+        Ok(request.body(body).send().await?)
+    }
+}
+
+const SIGNED_HEADERS: &str = "host;x-amz-content-sha256;x-amz-date";
+
+pub fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC aceita chaves de qualquer tamanho");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Codificação de URI exigida pelo SigV4: apenas A-Z a-z 0-9 - _ . ~ ficam intactos
+fn uri_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[rocket::async_trait]
+impl Storage for S3Storage {
+    async fn put(&self, key: &str, bytes: Vec<u8>, content_type: &str) -> anyhow::Result<()> {
+        let response = self.send(Method::PUT, key, bytes, Some(content_type)).await?;
+        if !response.status().is_success() {
+            return Err(anyhow!("S3 PUT {} retornou {}: {}", key, response.status(), response.text().await?));
+        }
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> anyhow::Result<Vec<u8>> {
+        let response = self.send(Method::GET, key, Vec::new(), None).await?;
+        if !response.status().is_success() {
+            return Err(anyhow!("S3 GET {} retornou {}", key, response.status()));
+        }
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    async fn delete(&self, key: &str) -> anyhow::Result<()> {
+        let response = self.send(Method::DELETE, key, Vec::new(), None).await?;
+        if !response.status().is_success() && response.status() != reqwest::StatusCode::NOT_FOUND {
+            return Err(anyhow!("S3 DELETE {} retornou {}", key, response.status()));
+        }
+        Ok(())
+    }
+}
+
+/// Remove do armazenamento os arquivos de anexos excluídos.
+///
+/// A fila `attachment_deletions` é preenchida por trigger, então anexos removidos em cascata
+/// (transação, importação desfeita ou conta excluída) também têm seus arquivos apagados.
+pub async fn purge_deleted_attachments(pool: &PgPool, storage: &dyn Storage) -> anyhow::Result<()> {
+    let keys = sqlx::query_scalar!(
+        "SELECT storage_key FROM attachment_deletions ORDER BY created_at LIMIT 500"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for key in keys {
+        if let Err(e) = storage.delete(&key).await {
+            // Fica na fila para a próxima execução
+            eprintln!("Erro ao remover anexo {}: {}", key, e);
+            continue;
+        }
+
+        sqlx::query!("DELETE FROM attachment_deletions WHERE storage_key = $1", key)
+            .execute(pool)
+            .await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,265 @@
+use crate::models::category::{CategoryPath, KIND_EXPENSE, KIND_INCOME, KIND_TRANSFER};
+use rust_decimal::Decimal;
+use sqlx::{PgConnection, PgPool};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+// Taxonomia inicial de cada usuário: (nome, tipo, subcategorias)
+const DEFAULT_CATEGORIES: &[(&str, &str, &[&str])] = &[
+    ("Alimentação", KIND_EXPENSE, &["Supermercado", "Restaurantes", "Delivery"]),
+    ("Transporte", KIND_EXPENSE, &["Combustível", "Aplicativos de transporte", "Transporte público"]),
+    ("Moradia", KIND_EXPENSE, &["Aluguel", "Contas da casa", "Manutenção"]),
+    ("Saúde", KIND_EXPENSE, &["Farmácia", "Plano de saúde", "Consultas"]),
+    ("Educação", KIND_EXPENSE, &[]),
+    ("Lazer", KIND_EXPENSE, &["Streaming", "Viagens"]),
+    ("Compras", KIND_EXPENSE, &["Vestuário", "Eletrônicos"]),
+    ("Serviços", KIND_EXPENSE, &[]),
+    ("Impostos e taxas", KIND_EXPENSE, &["Tarifas bancárias", "Juros"]),
+    ("Salário", KIND_INCOME, &[]),
+    ("Rendimentos", KIND_INCOME, &[]),
+    ("Outras receitas", KIND_INCOME, &[]),
+    ("Transferências", KIND_TRANSFER, &["Pagamento de cartão"]),
+];
+
+/// Cria a taxonomia padrão para usuários que ainda não têm nenhuma categoria
+pub async fn ensure_default_categories(pool: &PgPool, user_id: Uuid) -> sqlx::Result<()> {
+    let exists = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM categories WHERE user_id = $1) AS "exists!""#,
+        user_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    if exists {
+        return Ok(());
+    }
+
+    let mut tx = pool.begin().await?;
+    for (name, kind, children) in DEFAULT_CATEGORIES {
+        let parent_id = find_or_create(&mut tx, user_id, None, name, kind).await?;
+        for child in *children {
+            find_or_create(&mut tx, user_id, Some(parent_id), child, kind).await?;
+        }
+    }
+    tx.commit().await
+}
+
+/// Categoria com o nome informado (sem diferenciar maiúsculas) sob o mesmo pai, criando se não existir
+async fn find_or_create(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    parent_id: Option<Uuid>,
+    name: &str,
+    kind: &str,
+) -> sqlx::Result<Uuid> {
+    // ON CONFLICT cobre sincronizações simultâneas do mesmo usuário
+    sqlx::query!(
+        r#"
+        INSERT INTO categories (user_id, parent_id, name, kind)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT DO NOTHING
+        "#,
+        user_id,
+        parent_id,
+        name,
+        kind
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query_scalar!(
+        r#"
+        SELECT id FROM categories
+        WHERE user_id = $1 AND parent_id IS NOT DISTINCT FROM $2 AND LOWER(name) = LOWER($3)
+        "#,
+        user_id,
+        parent_id,
+        name
+    )
+    .fetch_one(&mut *conn)
+    .await
+}
+
+/// Categoria do usuário associada a um id da Pluggy
+#[derive(Debug, Clone)]
+pub struct MappedCategory {
+    pub category_id: Uuid,
+    pub parent_id: Option<Uuid>,
+    pub path: CategoryPath,
+}
+
+pub async fn load_mappings(pool: &PgPool, user_id: Uuid) -> sqlx::Result<HashMap<String, MappedCategory>> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT m.pluggy_category_id, c.id, c.parent_id, c.name, p.name AS "parent_name?"
+        FROM category_mappings m
+        INNER JOIN categories c ON m.category_id = c.id
+        LEFT JOIN categories p ON c.parent_id = p.id
+        WHERE m.user_id = $1
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let path = match row.parent_name {
+                Some(parent) => CategoryPath { category: parent, subcategory: Some(row.name) },
+                None => CategoryPath { category: row.name, subcategory: None },
+            };
+            (row.pluggy_category_id, MappedCategory { category_id: row.id, parent_id: row.parent_id, path })
+        })
+        .collect())
+}
+
+/// Id da categoria principal na hierarquia da Pluggy: 8 dígitos, 2 por nível ("05010000" → "05000000")
+fn pluggy_parent_id(pluggy_category_id: &str) -> Option<String> {
+    let is_code = pluggy_category_id.len() == 8 && pluggy_category_id.chars().all(|c| c.is_ascii_digit());
+    if is_code && &pluggy_category_id[2..] != "000000" {
+        Some(format!("{}000000", &pluggy_category_id[..2]))
+    } else {
+        None
+    }
+}
+
+/// Aprende a associação de um id da Pluggy ainda não mapeado: usa (ou cria) uma categoria com o
+/// nome que a Pluggy deu, como subcategoria quando a categoria principal do mesmo prefixo já está mapeada.
+pub async fn learn_mapping(
+    pool: &PgPool,
+    user_id: Uuid,
+    mappings: &HashMap<String, MappedCategory>,
+    pluggy_category_id: &str,
+    pluggy_name: &str,
+    amount: Decimal,
+) -> sqlx::Result<MappedCategory> {
+    let parent = pluggy_parent_id(pluggy_category_id)
+        .and_then(|id| mappings.get(&id))
+        .map(|mapped| mapped.parent_id.unwrap_or(mapped.category_id));
+
+    let mut tx = pool.begin().await?;
+
+    let (parent_id, parent_name, kind) = match parent {
+        Some(parent_id) => {
+            let parent = sqlx::query!("SELECT name, kind FROM categories WHERE id = $1", parent_id)
+                .fetch_one(&mut *tx)
+                .await?;
+            (Some(parent_id), Some(parent.name), parent.kind)
+        }
+        None => {
+            let kind = if amount > Decimal::ZERO { KIND_INCOME } else { KIND_EXPENSE };
+            (None, None, kind.to_string())
+        }
+    };
+
+    let category_id = find_or_create(&mut tx, user_id, parent_id, pluggy_name, &kind).await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO category_mappings (user_id, pluggy_category_id, category_id)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (user_id, pluggy_category_id) DO NOTHING
+        "#,
+        user_id,
+        pluggy_category_id,
+        category_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    let path = match parent_name {
+        Some(parent) => CategoryPath { category: parent, subcategory: Some(pluggy_name.to_string()) },
+        None => CategoryPath { category: pluggy_name.to_string(), subcategory: None },
+    };
+    Ok(MappedCategory { category_id, parent_id, path })
+}
+
+/// Como a categoria aparece nas transações
+pub async fn category_path(conn: &mut PgConnection, user_id: Uuid, category_id: Uuid) -> sqlx::Result<Option<CategoryPath>> {
+    let row = sqlx::query!(
+        r#"
+        SELECT c.name, p.name AS "parent_name?"
+        FROM categories c
+        LEFT JOIN categories p ON c.parent_id = p.id
+        WHERE c.id = $1 AND c.user_id = $2
+        "#,
+        category_id,
+        user_id
+    )
+    .fetch_optional(conn)
+    .await?;
+
+    Ok(row.map(|row| match row.parent_name {
+        Some(parent) => CategoryPath { category: parent, subcategory: Some(row.name) },
+        None => CategoryPath { category: row.name, subcategory: None },
+    }))
+}
+
+/// Reescreve transações, divisões, regras e lançamentos recorrentes que usam `from` para usar `to`.
+///
+/// Uma categoria principal leva junto as subcategorias das transações, a menos que
+/// o destino seja uma subcategoria. Retorna quantas transações mudaram.
+pub async fn rewrite_path(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    from: &CategoryPath,
+    to: &CategoryPath,
+) -> sqlx::Result<u64> {
+    let transactions = sqlx::query!(
+        r#"
+        UPDATE transactions
+        SET category = $4,
+            subcategory = CASE WHEN $3::VARCHAR IS NULL AND $5::VARCHAR IS NULL THEN subcategory ELSE $5::VARCHAR END
+        WHERE user_id = $1 AND category = $2 AND ($3::VARCHAR IS NULL OR subcategory = $3::VARCHAR)
+        "#,
+        user_id,
+        from.category,
+        from.subcategory,
+        to.category,
+        to.subcategory
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE categorization_rules
+        SET set_category = $4,
+            set_subcategory = CASE WHEN $3::VARCHAR IS NULL AND $5::VARCHAR IS NULL THEN set_subcategory ELSE $5::VARCHAR END
+        WHERE user_id = $1 AND set_category = $2 AND ($3::VARCHAR IS NULL OR set_subcategory = $3::VARCHAR)
+        "#,
+        user_id,
+        from.category,
+        from.subcategory,
+        to.category,
+        to.subcategory
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    // Divisões e lançamentos recorrentes só têm a categoria principal
+    if from.subcategory.is_none() {
+        sqlx::query!(
+            "UPDATE transaction_splits SET category = $3 WHERE user_id = $1 AND category = $2",
+            user_id,
+            from.category,
+            to.category
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query!(
+            "UPDATE recurring_transactions SET category = $3 WHERE user_id = $1 AND category = $2",
+            user_id,
+            from.category,
+            to.category
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(transactions.rows_affected())
+}
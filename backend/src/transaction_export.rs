@@ -0,0 +1,247 @@
+use chrono::{Datelike, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use rust_xlsxwriter::{ExcelDateTime, Format, Workbook};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+pub const FORMAT_CSV: &str = "csv";
+pub const FORMAT_OFX: &str = "ofx";
+pub const FORMAT_XLSX: &str = "xlsx";
+
+/// Linha exportada, com conta e instituição já resolvidas
+#[derive(Debug, FromRow)]
+pub struct ExportRow {
+    pub id: Uuid,
+    pub date: NaiveDate,
+    pub description: Option<String>,
+    pub amount: Decimal,
+    pub currency: String,
+    pub category: Option<String>,
+    pub subcategory: Option<String>,
+    pub status: Option<String>,
+    pub account_name: Option<String>,
+    pub institution: Option<String>,
+    pub notes: Option<String>,
+    pub tags: String,
+    pub external_id: Option<String>,
+}
+
+/// Colunas de `ExportRow`; a consulta deve usar os aliases `t`, `a` (accounts) e `i` (items)
+pub const EXPORT_COLUMNS: &str = r#"
+    t.id, t.date, t.description, t.amount, t.currency, t.category, t.subcategory, t.status,
+    a.name AS account_name, i.connector->>'name' AS institution, t.notes,
+    ARRAY_TO_STRING(ARRAY(
+        SELECT g.name FROM transaction_tags tt INNER JOIN tags g ON tt.tag_id = g.id
+        WHERE tt.transaction_id = t.id ORDER BY g.name
+    ), ', ') AS tags,
+    COALESCE(t.pluggy_transaction_id, t.import_external_id) AS external_id
+"#;
+
+/// Convenções de data, número e separador de colunas do idioma do usuário
+#[derive(Debug, Clone, Copy)]
+pub struct ExportLocale {
+    pub portuguese: bool,
+    pub date_format: &'static str,
+    pub decimal_separator: char,
+    pub delimiter: u8,
+}
+
+impl ExportLocale {
+    pub fn from_tag(locale: &str) -> Self {
+        let language = locale.split(['-', '_']).next().unwrap_or_default().to_lowercase();
+        let decimal_comma = matches!(language.as_str(), "pt" | "es" | "fr" | "de" | "it" | "nl");
+
+        let date_format = match language.as_str() {
+            "pt" | "es" | "fr" | "it" | "nl" => "%d/%m/%Y",
+            "de" => "%d.%m.%Y",
+            "en" if locale.eq_ignore_ascii_case("en-US") => "%m/%d/%Y",
+            _ => "%Y-%m-%d",
+        };
+
+        ExportLocale {
+            portuguese: language == "pt",
+            date_format,
+            decimal_separator: if decimal_comma { ',' } else { '.' },
+            // Com vírgula decimal o Excel espera ';' entre as colunas
+            delimiter: if decimal_comma { b';' } else { b',' },
+        }
+    }
+
+    fn headers(&self) -> [&'static str; 12] {
+        if self.portuguese {
+            [
+                "Data", "Descrição", "Valor", "Moeda", "Categoria", "Subcategoria", "Status",
+                "Conta", "Instituição", "Anotações", "Tags", "ID",
+            ]
+        } else {
+            [
+                "Date", "Description", "Amount", "Currency", "Category", "Subcategory", "Status",
+                "Account", "Institution", "Notes", "Tags", "ID",
+            ]
+        }
+    }
+
+    fn format_amount(&self, amount: Decimal) -> String {
+        let value = amount.round_dp(2).to_string();
+        if self.decimal_separator == ',' {
+            value.replace('.', ",")
+        } else {
+            value
+        }
+    }
+
+    // Formato de data equivalente para as células do Excel
+    fn excel_date_format(&self) -> &'static str {
+        match self.date_format {
+            "%d/%m/%Y" => "dd/mm/yyyy",
+            "%d.%m.%Y" => "dd.mm.yyyy",
+            "%m/%d/%Y" => "mm/dd/yyyy",
+            _ => "yyyy-mm-dd",
+        }
+    }
+}
+
+// Caracteres com que o Excel e o Google Planilhas iniciam uma fórmula
+const FORMULA_PREFIXES: &[char] = &['=', '+', '-', '@', '\t', '\r'];
+
+/// Neutraliza texto que a planilha executaria como fórmula (descrições de banco e PIX vêm da contraparte),
+/// prefixando-o com `'`. Números como "-45.90" ficam como estão.
+pub fn escape_formula(value: &str) -> String {
+    let is_formula = value.starts_with(FORMULA_PREFIXES) && value.trim().parse::<Decimal>().is_err();
+    if is_formula {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    }
+}
+
+/// Gera linhas de CSV aos poucos para que a resposta seja transmitida em partes
+pub struct CsvEncoder {
+    locale: ExportLocale,
+}
+
+impl CsvEncoder {
+    pub fn new(locale: ExportLocale) -> Self {
+        CsvEncoder { locale }
+    }
+
+    fn encode<I, T>(&self, record: I) -> csv::Result<Vec<u8>>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<[u8]>,
+    {
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(self.locale.delimiter)
+            .from_writer(Vec::new());
+        writer.write_record(record)?;
+        writer.into_inner().map_err(|e| csv::Error::from(e.into_error()))
+    }
+
+    /// BOM UTF-8 (para o Excel reconhecer acentos) e cabeçalho
+    pub fn header(&self) -> csv::Result<Vec<u8>> {
+        let mut bytes = "\u{feff}".as_bytes().to_vec();
+        bytes.extend(self.encode(self.locale.headers())?);
+        Ok(bytes)
+    }
+
+    pub fn row(&self, row: &ExportRow) -> csv::Result<Vec<u8>> {
+        let locale = self.locale;
+        let text = |value: Option<&str>| escape_formula(value.unwrap_or_default());
+        self.encode([
+            row.date.format(locale.date_format).to_string(),
+            text(row.description.as_deref()),
+            locale.format_amount(row.amount),
+            text(Some(&row.currency)),
+            text(row.category.as_deref()),
+            text(row.subcategory.as_deref()),
+            text(row.status.as_deref()),
+            text(row.account_name.as_deref()),
+            text(row.institution.as_deref()),
+            text(row.notes.as_deref()),
+            text(Some(&row.tags)),
+            row.id.to_string(),
+        ])
+    }
+}
+
+fn ofx_escape(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Cabeçalho OFX 1.02 (SGML) até a abertura da lista de transações
+pub fn ofx_header(currency: &str, start: Option<NaiveDate>, end: Option<NaiveDate>) -> String {
+    let now = Utc::now().format("%Y%m%d%H%M%S");
+    let today = Utc::now().date_naive();
+    format!(
+        "OFXHEADER:100\r\nDATA:OFXSGML\r\nVERSION:102\r\nSECURITY:NONE\r\nENCODING:UTF-8\r\nCHARSET:NONE\r\n\
+         COMPRESSION:NONE\r\nOLDFILEUID:NONE\r\nNEWFILEUID:NONE\r\n\r\n\
+         <OFX>\r\n<SIGNONMSGSRSV1><SONRS><STATUS><CODE>0<SEVERITY>INFO</STATUS><DTSERVER>{now}<LANGUAGE>POR</SONRS></SIGNONMSGSRSV1>\r\n\
+         <BANKMSGSRSV1><STMTTRNRS><TRNUID>1<STATUS><CODE>0<SEVERITY>INFO</STATUS>\r\n\
+         <STMTRS><CURDEF>{currency}<BANKACCTFROM><BANKID>0000<ACCTID>FIREBUDGET<ACCTTYPE>CHECKING</BANKACCTFROM>\r\n\
+         <BANKTRANLIST><DTSTART>{start}<DTEND>{end}\r\n",
+        now = now,
+        currency = ofx_escape(currency),
+        start = start.unwrap_or(today).format("%Y%m%d"),
+        end = end.unwrap_or(today).format("%Y%m%d"),
+    )
+}
+
+pub fn ofx_transaction(row: &ExportRow) -> String {
+    let trntype = if row.amount < Decimal::ZERO { "DEBIT" } else { "CREDIT" };
+    let description = row.description.as_deref().unwrap_or_default();
+    // NAME é limitado a 32 caracteres pela especificação; o texto completo vai no MEMO
+    let name: String = description.chars().take(32).collect();
+    format!(
+        "<STMTTRN><TRNTYPE>{}<DTPOSTED>{}<TRNAMT>{}<FITID>{}<NAME>{}<MEMO>{}</STMTTRN>\r\n",
+        trntype,
+        row.date.format("%Y%m%d"),
+        row.amount.round_dp(2),
+        ofx_escape(row.external_id.as_deref().unwrap_or(&row.id.to_string())),
+        ofx_escape(&name),
+        ofx_escape(description),
+    )
+}
+
+pub fn ofx_footer() -> &'static str {
+    "</BANKTRANLIST></STMTRS></STMTTRNRS></BANKMSGSRSV1></OFX>\r\n"
+}
+
+/// Planilha XLSX com datas e valores como células numéricas (gerada em memória)
+pub fn xlsx_workbook(rows: &[ExportRow], locale: ExportLocale) -> anyhow::Result<Vec<u8>> {
+    let mut workbook = Workbook::new();
+    let sheet = workbook.add_worksheet();
+    sheet.set_name(if locale.portuguese { "Transações" } else { "Transactions" })?;
+
+    let bold = Format::new().set_bold();
+    let date_format = Format::new().set_num_format(locale.excel_date_format());
+    let amount_format = Format::new().set_num_format("#,##0.00");
+
+    for (col, header) in locale.headers().iter().enumerate() {
+        sheet.write_string_with_format(0, col as u16, *header, &bold)?;
+    }
+
+    let text = |value: Option<&str>| escape_formula(value.unwrap_or_default());
+    for (index, row) in rows.iter().enumerate() {
+        let r = index as u32 + 1;
+        let date = ExcelDateTime::from_ymd(row.date.year() as u16, row.date.month() as u8, row.date.day() as u8)?;
+        sheet.write_datetime_with_format(r, 0, &date, &date_format)?;
+        sheet.write_string(r, 1, text(row.description.as_deref()))?;
+        let amount: f64 = row.amount.round_dp(2).try_into().unwrap_or_default();
+        sheet.write_number_with_format(r, 2, amount, &amount_format)?;
+        sheet.write_string(r, 3, text(Some(&row.currency)))?;
+        sheet.write_string(r, 4, text(row.category.as_deref()))?;
+        sheet.write_string(r, 5, text(row.subcategory.as_deref()))?;
+        sheet.write_string(r, 6, text(row.status.as_deref()))?;
+        sheet.write_string(r, 7, text(row.account_name.as_deref()))?;
+        sheet.write_string(r, 8, text(row.institution.as_deref()))?;
+        sheet.write_string(r, 9, text(row.notes.as_deref()))?;
+        sheet.write_string(r, 10, text(Some(&row.tags)))?;
+        sheet.write_string(r, 11, row.id.to_string())?;
+    }
+
+    sheet.autofilter(0, 0, rows.len() as u32, 11)?;
+    sheet.set_freeze_panes(1, 0)?;
+    sheet.autofit();
+
+    Ok(workbook.save_to_buffer()?)
+}
@@ -0,0 +1,101 @@
+use crate::models::categorization_rule::fold_text;
+use crate::models::transfer::DETECTION_AUTO;
+use sqlx::PgPool;
+use std::collections::HashSet;
+use uuid::Uuid;
+
+/// Diferença máxima, em dias, entre a saída e a entrada de uma transferência identificada pela descrição
+pub const TRANSFER_WINDOW_DAYS: i32 = 3;
+
+// Palavras (já sem acentos) que indicam movimentação entre contas
+const TRANSFER_WORDS: &[&str] = &["pix", "ted", "doc", "tef", "aplicacao", "resgate"];
+
+/// Descrição típica de transferência: PIX, TED, DOC, "transf"/"transferência", aplicação, resgate
+/// ou pagamento de fatura
+pub fn looks_like_transfer(description: Option<&str>) -> bool {
+    fold_text(description.unwrap_or_default())
+        .split(|c: char| !c.is_alphanumeric())
+        .any(|word| TRANSFER_WORDS.contains(&word) || word.starts_with("transf"))
+        || looks_like_bill_payment(description)
+}
+
+/// Pagamento da fatura do cartão: "PAGAMENTO FATURA", "PGTO FATURA", "PAG CARTAO CREDITO"
+pub fn looks_like_bill_payment(description: Option<&str>) -> bool {
+    let folded = fold_text(description.unwrap_or_default());
+    let words: Vec<&str> = folded.split(|c: char| !c.is_alphanumeric()).collect();
+    words.contains(&"fatura") || (words.contains(&"cartao") && words.iter().any(|w| w.starts_with("pag") || *w == "pgto"))
+}
+
+/// Pareia saídas e entradas de mesmo valor entre contas diferentes do usuário e as marca como transferências.
+///
+/// Só pareia quando a descrição de uma das pontas indica transferência, com até `TRANSFER_WINDOW_DAYS` dias
+/// de diferença; valores iguais sem essa indicação (ex.: uma compra e um reembolso) ficam para o pareamento
+/// manual. Pares desfeitos pelo usuário não são pareados de novo. Retorna quantas transferências foram criadas.
+pub async fn detect_transfers(pool: &PgPool, user_id: Uuid) -> sqlx::Result<u64> {
+    let candidates = sqlx::query!(
+        r#"
+        SELECT o.id AS outgoing_id, i.id AS incoming_id,
+               o.description AS outgoing_description, i.description AS incoming_description,
+               ABS(o.date - i.date) AS "day_gap!"
+        FROM transactions o
+        INNER JOIN transactions i
+            ON i.user_id = o.user_id
+           AND i.amount = -o.amount
+           AND i.currency = o.currency
+           AND i.account_id <> o.account_id
+           AND i.date BETWEEN o.date - $2::INTEGER AND o.date + $2::INTEGER
+        WHERE o.user_id = $1 AND o.amount < 0
+          AND NOT EXISTS (
+              SELECT 1 FROM transfers tr
+              WHERE tr.outgoing_transaction_id IN (o.id, i.id) OR tr.incoming_transaction_id IN (o.id, i.id)
+          )
+          AND NOT EXISTS (
+              SELECT 1 FROM transfer_dismissals d
+              WHERE d.outgoing_transaction_id = o.id AND d.incoming_transaction_id = i.id
+          )
+        "#,
+        user_id,
+        TRANSFER_WINDOW_DAYS
+    )
+    .fetch_all(pool)
+    .await?;
+
+    // Pares de datas mais próximas primeiro
+    let mut candidates: Vec<_> = candidates
+        .into_iter()
+        .filter(|c| {
+            looks_like_transfer(c.outgoing_description.as_deref())
+                || looks_like_transfer(c.incoming_description.as_deref())
+        })
+        .collect();
+    candidates.sort_by_key(|c| c.day_gap);
+
+    let mut paired = HashSet::new();
+    let mut created = 0;
+
+    for candidate in candidates {
+        if paired.contains(&candidate.outgoing_id) || paired.contains(&candidate.incoming_id) {
+            continue;
+        }
+
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO transfers (user_id, outgoing_transaction_id, incoming_transaction_id, detection)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT DO NOTHING
+            "#,
+            user_id,
+            candidate.outgoing_id,
+            candidate.incoming_id,
+            DETECTION_AUTO
+        )
+        .execute(pool)
+        .await?;
+
+        paired.insert(candidate.outgoing_id);
+        paired.insert(candidate.incoming_id);
+        created += result.rows_affected();
+    }
+
+    Ok(created)
+}
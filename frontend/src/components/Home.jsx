@@ -49,7 +49,7 @@ function Home() {
         accountService.getTotalBalance(),
         accountService.getTotalExpenses(),
         accountService.getMonthlyExpenses(parseInt(chartPeriod)),
-        transactionService.getTransactions(),
+        transactionService.getTransactions({ max_amount: 0, limit: 50 }),
         accountService.getAccounts()
       ])
 
@@ -65,7 +65,7 @@ function Home() {
       setAccounts(accountsData || [])
 
       // Processar transações
-      const processedTransactions = (transactionsData || [])
+      const processedTransactions = (transactionsData?.items || [])
         .filter(t => parseFloat(t.amount) < 0) // Apenas despesas
         .slice(0, 10) // Limitar a 10 transações
         .map(t => {
@@ -3,12 +3,17 @@ import { useNavigate } from 'react-router-dom'
 import { useAuth } from '../contexts/AuthContext'
 import { transactionService } from '../services/api'
 
+const PAGE_SIZE = 50
+
 function Transactions() {
   const navigate = useNavigate()
   const { user, logout } = useAuth()
   const [transactions, setTransactions] = useState([])
+  const [nextCursor, setNextCursor] = useState(null)
   const [loading, setLoading] = useState(true)
+  const [loadingMore, setLoadingMore] = useState(false)
   const [error, setError] = useState(null)
+  const [loadMoreError, setLoadMoreError] = useState(null)
 
   useEffect(() => {
     fetchTransactions()
@@ -16,8 +21,9 @@ function Transactions() {
 
   const fetchTransactions = async () => {
     try {
-      const data = await transactionService.getTransactions()
-      setTransactions(data)
+      const data = await transactionService.getTransactions({ limit: PAGE_SIZE })
+      setTransactions(data.items)
+      setNextCursor(data.next_cursor)
     } catch (err) {
       console.error('Erro ao buscar transações:', err)
       setError('Não foi possível carregar as transações.')
@@ -26,6 +32,23 @@ function Transactions() {
     }
   }
 
+  // Próxima página a partir do cursor da última resposta
+  const loadMore = async () => {
+    if (!nextCursor || loadingMore) return
+    setLoadingMore(true)
+    setLoadMoreError(null)
+    try {
+      const data = await transactionService.getTransactions({ limit: PAGE_SIZE, cursor: nextCursor })
+      setTransactions((current) => [...current, ...data.items])
+      setNextCursor(data.next_cursor)
+    } catch (err) {
+      console.error('Erro ao buscar mais transações:', err)
+      setLoadMoreError('Não foi possível carregar mais transações.')
+    } finally {
+      setLoadingMore(false)
+    }
+  }
+
   const handleLogout = () => {
     logout()
     navigate('/login')
@@ -201,6 +224,21 @@ function Transactions() {
                     </tbody>
                   </table>
                 </div>
+
+                {loadMoreError && (
+                  <div className="text-red-500 text-sm text-center mt-4">{loadMoreError}</div>
+                )}
+                {nextCursor && (
+                  <div className="flex justify-center mt-6">
+                    <button
+                      onClick={loadMore}
+                      disabled={loadingMore}
+                      className="px-6 py-2 bg-[#7802D6] text-white rounded-lg font-medium hover:opacity-90 transition disabled:opacity-50"
+                    >
+                      {loadingMore ? 'Carregando...' : 'Carregar mais'}
+                    </button>
+                  </div>
+                )}
               </div>
             </div>
 
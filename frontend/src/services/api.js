@@ -71,8 +71,9 @@ export const authService = {
 
 // Serviços de transações
 export const transactionService = {
-  async getTransactions() {
-    const response = await api.get('/transactions')
+  // Retorna uma página { items, next_cursor }; params aceita filtros, sort, cursor e limit
+  async getTransactions(params = {}) {
+    const response = await api.get('/transactions', { params })
     return response.data
   },
 